@@ -0,0 +1,78 @@
+/**
+ * mix.rs
+ * L-SAMP 100 | Mixing Benchmarks
+ *
+ * Cost of one 512-frame callback with 1, 8, 32 and 64 looping voices, from
+ * mono and from stereo sounds, on the offline backend. The sounds are
+ * written as WAV files and loaded the way the app loads them, so a voice
+ * here is the same as a voice on a pad. Run with `cargo bench -p
+ * lsamp-engine --no-default-features`.
+ */
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use lsamp_engine::{AudioEngine, LoadOptions, OfflineBackend, PartialPlayParams};
+use std::path::{Path, PathBuf};
+
+const RATE: u32 = 48_000;
+const CALLBACK_FRAMES: usize = 512;
+
+/// A two-second sine at `channels`, at 44.1 kHz so every voice resamples
+fn write_tone(dir: &Path, channels: u16) -> PathBuf {
+    let path = dir.join(format!("tone-{}.wav", channels));
+    let spec = hound::WavSpec {
+        channels,
+        sample_rate: 44_100,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+    let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+    for n in 0..88_200 {
+        let sample = 0.01 * (n as f32 * 0.05).sin();
+        for _ in 0..channels {
+            writer.write_sample(sample).unwrap();
+        }
+    }
+    writer.finalize().unwrap();
+    path
+}
+
+fn mix(c: &mut Criterion) {
+    let dir = std::env::temp_dir().join(format!("lsamp-bench-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let mut group = c.benchmark_group("mix");
+    for (name, channels) in [("mono", 1), ("stereo", 2)] {
+        let path = write_tone(&dir, channels);
+        for voices in [1, 8, 32, 64] {
+            let (backend, output) = OfflineBackend::new(RATE, 2);
+            let engine = AudioEngine::with_backend(backend).unwrap();
+            for voice in 0..voices {
+                let key = format!("v{}", voice);
+                let path = path.to_str().unwrap();
+                runtime
+                    .block_on(engine.load_sound(
+                        key.clone(),
+                        path,
+                        Some(120.0),
+                        LoadOptions::default(),
+                        |_| {},
+                    ))
+                    .unwrap();
+                let mut params = engine
+                    .resolve_play_params(&key, PartialPlayParams::default())
+                    .unwrap();
+                params.looping = true;
+                engine.play_sound(key, params).unwrap();
+            }
+            group.bench_with_input(BenchmarkId::new(name, voices), &voices, |b, _| {
+                b.iter(|| output.render(CALLBACK_FRAMES))
+            });
+        }
+    }
+    group.finish();
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+criterion_group!(benches, mix);
+criterion_main!(benches);
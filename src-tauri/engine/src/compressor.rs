@@ -0,0 +1,216 @@
+/**
+ * compressor.rs
+ * L-SAMP 100 | Master Compressor
+ *
+ * A feed-forward compressor on the summed pad mix, ahead of the master
+ * volume so the master fader never changes how hard it works. The detector
+ * follows the louder channel's peak; above the threshold the gain comes
+ * down by (1 - 1/ratio) dB per dB, reached over the attack time and let go
+ * over the release time, with the makeup gain on top. The detector lives in
+ * the mixer, so a rebuilt stream starts with none of it. At a 1:1 ratio the
+ * stage (makeup included) is skipped.
+ */
+use serde::{Deserialize, Serialize};
+
+use crate::flush_denormal;
+
+/// Master compressor settings, as `set_compressor` takes them
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CompressorSettings {
+    pub threshold_db: f32, // -60 to 0
+    pub ratio: f32,        // 1 (off) to 20
+    pub attack: f32,       // Seconds, 0.1 ms to 1 s
+    pub release: f32,      // Seconds, 1 ms to 5 s
+    pub makeup_db: f32,    // 0 to 24
+}
+
+impl Default for CompressorSettings {
+    fn default() -> Self {
+        Self {
+            threshold_db: -12.0,
+            ratio: 1.0,
+            attack: 0.01,
+            release: 0.1,
+            makeup_db: 0.0,
+        }
+    }
+}
+
+impl CompressorSettings {
+    pub(crate) fn check(&self) -> Result<(), String> {
+        let ranges = [
+            ("threshold", self.threshold_db, -60.0, 0.0),
+            ("ratio", self.ratio, 1.0, 20.0),
+            ("attack", self.attack, 0.0001, 1.0),
+            ("release", self.release, 0.001, 5.0),
+            ("makeup", self.makeup_db, 0.0, 24.0),
+        ];
+        for (name, value, min, max) in ranges {
+            if !(min..=max).contains(&value) {
+                return Err(format!(
+                    "[Social Noise] A compressor {} of {} is outside {}..={}",
+                    name, value, min, max
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Gain reduction in dB for a detector level of `level_db`
+    fn reduction(&self, level_db: f32) -> f32 {
+        (level_db - self.threshold_db).max(0.0) * (1.0 - 1.0 / self.ratio)
+    }
+}
+
+/// The compressor's part of the mixer
+pub(crate) struct Compressor {
+    settings: CompressorSettings,
+    sample_rate: f32,
+    attack: f32,      // Smoothing weight per frame while the reduction grows
+    release: f32,     // And while it shrinks
+    makeup: f32,      // Linear
+    reduction: f32,   // dB, smoothed
+    window_peak: f32, // Most reduction in the current meter window, dB
+}
+
+impl Compressor {
+    pub(crate) fn new(sample_rate: u32) -> Self {
+        let mut compressor = Self {
+            settings: CompressorSettings::default(),
+            sample_rate: sample_rate as f32,
+            attack: 0.0,
+            release: 0.0,
+            makeup: 1.0,
+            reduction: 0.0,
+            window_peak: 0.0,
+        };
+        compressor.set(CompressorSettings::default());
+        compressor
+    }
+
+    pub(crate) fn set(&mut self, settings: CompressorSettings) {
+        let weight = |seconds: f32| 1.0 - (-1.0 / (seconds * self.sample_rate)).exp();
+        self.attack = weight(settings.attack);
+        self.release = weight(settings.release);
+        self.makeup = 10f32.powf(settings.makeup_db / 20.0);
+        self.settings = settings;
+        if self.bypassed() {
+            self.rest();
+        }
+    }
+
+    pub(crate) fn bypassed(&self) -> bool {
+        self.settings.ratio <= 1.0
+    }
+
+    /// Lets go of any reduction at once, e.g. over silence
+    pub(crate) fn rest(&mut self) {
+        self.reduction = 0.0;
+    }
+
+    /// Compresses a block of the mix in place
+    pub(crate) fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            let peak = l.abs().max(r.abs()).max(f32::MIN_POSITIVE);
+            let target = self.settings.reduction(20.0 * peak.log10());
+            let weight = if target > self.reduction {
+                self.attack
+            } else {
+                self.release
+            };
+            self.reduction = flush_denormal(self.reduction + (target - self.reduction) * weight);
+            self.window_peak = self.window_peak.max(self.reduction);
+            let gain = 10f32.powf(-self.reduction / 20.0) * self.makeup;
+            *l *= gain;
+            *r *= gain;
+        }
+    }
+
+    /// Most reduction since the last call, in dB, for the meter window
+    pub(crate) fn take_reduction(&mut self) -> f32 {
+        std::mem::take(&mut self.window_peak)
+    }
+
+    /// The smoothed reduction
+    #[cfg(test)]
+    pub(crate) fn state(&self) -> f32 {
+        self.reduction
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 48_000;
+
+    fn db(ratio: f32) -> f32 {
+        20.0 * ratio.log10()
+    }
+
+    /// Runs `frames` of a constant `level` through, returning the last output
+    fn step(compressor: &mut Compressor, level: f32, frames: usize) -> f32 {
+        let mut left = vec![level; frames];
+        let mut right = vec![level; frames];
+        compressor.process(&mut left, &mut right);
+        assert_eq!(left, right);
+        left[frames - 1]
+    }
+
+    #[test]
+    fn settled_step_matches_the_static_curve() {
+        let settings = CompressorSettings {
+            threshold_db: -20.0,
+            ratio: 4.0,
+            attack: 0.005,
+            release: 0.05,
+            makeup_db: 3.0,
+        };
+        let mut compressor = Compressor::new(RATE);
+        compressor.set(settings);
+        for level_db in [-30.0f32, -20.0, -12.0, -6.0, 0.0] {
+            let level = 10f32.powf(level_db / 20.0);
+            let out = step(&mut compressor, level, RATE as usize);
+            let expected = level_db - settings.reduction(level_db) + settings.makeup_db;
+            assert!(
+                (db(out) - expected).abs() < 0.05,
+                "{} dB in: {} dB out, expected {}",
+                level_db,
+                db(out),
+                expected
+            );
+        }
+        assert_eq!(settings.reduction(-6.0), 10.5);
+        assert!((compressor.take_reduction() - 15.0).abs() < 0.05);
+        assert_eq!(compressor.take_reduction(), 0.0);
+    }
+
+    #[test]
+    fn attack_reaches_most_of_the_step_in_its_time() {
+        let settings = CompressorSettings {
+            threshold_db: -20.0,
+            ratio: 10.0,
+            attack: 0.01,
+            release: 0.1,
+            makeup_db: 0.0,
+        };
+        let mut compressor = Compressor::new(RATE);
+        compressor.set(settings);
+        step(&mut compressor, 1.0, (0.01 * RATE as f32) as usize);
+        // One time constant gets 1 - 1/e of the way
+        let reached = compressor.reduction / settings.reduction(0.0);
+        assert!((reached - 0.632).abs() < 0.01, "{}", reached);
+    }
+
+    #[test]
+    fn a_1_to_1_ratio_is_bypassed_makeup_and_all() {
+        let mut compressor = Compressor::new(RATE);
+        compressor.set(CompressorSettings {
+            makeup_db: 12.0,
+            ..CompressorSettings::default()
+        });
+        assert!(compressor.bypassed());
+        assert_eq!(CompressorSettings::default().reduction(0.0), 0.0);
+    }
+}
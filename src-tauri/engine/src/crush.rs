@@ -0,0 +1,195 @@
+/**
+ * crush.rs
+ * L-SAMP 100 | Bitcrusher
+ *
+ * Every voice's lo-fi stage, ahead of its filter and gain: sample-and-hold
+ * decimation by `crushDownsample` and quantization to `crushBits`. The
+ * factor glides to a new value instead of jumping, and a fractional factor
+ * holds for uneven runs of frames, so sweeping it does not click. At 16
+ * bits and a factor of 1 (the defaults) the voice skips the stage.
+ */
+use crate::{PlayParams, Slew};
+
+/// Bits of a voice that is not crushed, the most there are
+pub const MAX_CRUSH_BITS: u8 = 16;
+
+/// Highest downsample factor
+pub const MAX_CRUSH_DOWNSAMPLE: u32 = 32;
+
+/// Time a downsample factor change glides over
+const CRUSH_SLEW_MS: f32 = 50.0;
+
+/// The crusher part of a pad's `PlayParams`, as the callback gets it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct CrushSettings {
+    bits: u8,
+    downsample: u32,
+}
+
+impl CrushSettings {
+    /// No crushing, for voices outside the sound bank
+    pub(crate) const OFF: Self = Self {
+        bits: MAX_CRUSH_BITS,
+        downsample: 1,
+    };
+
+    pub(crate) fn of(params: &PlayParams) -> Self {
+        Self {
+            bits: params.crush_bits,
+            downsample: params.crush_downsample,
+        }
+    }
+
+    /// Quantization steps per polarity, 0 for none
+    fn levels(&self) -> f32 {
+        if self.bits >= MAX_CRUSH_BITS {
+            0.0
+        } else {
+            (1u32 << (self.bits.max(1) - 1)) as f32
+        }
+    }
+}
+
+/// A voice's crusher
+pub(crate) struct Crusher {
+    levels: f32,
+    factor: Slew,   // Frames each held sample lasts
+    hold: f32,      // Frames the held sample has left, counting the next one
+    held: [f32; 2], // Left (or mono) and right
+}
+
+impl Crusher {
+    pub(crate) fn new(settings: CrushSettings, sample_rate: u32) -> Self {
+        let factor = settings.downsample as f32;
+        Self {
+            levels: settings.levels(),
+            factor: Slew::new(factor, sample_rate, CRUSH_SLEW_MS),
+            hold: 1.0,
+            held: [0.0; 2],
+        }
+    }
+
+    /// New settings for a playing voice; the factor glides to its new value
+    pub(crate) fn set(&mut self, settings: CrushSettings) {
+        self.levels = settings.levels();
+        self.factor.set(settings.downsample as f32);
+    }
+
+    pub(crate) fn retime(&mut self, sample_rate: u32) {
+        self.factor.retime(sample_rate, CRUSH_SLEW_MS);
+    }
+
+    /// Whether the voice can skip the stage, checked once per block
+    pub(crate) fn bypassed(&self) -> bool {
+        self.levels == 0.0 && self.factor.settled() && self.factor.current <= 1.0
+    }
+
+    /// Moves on a frame; true when it takes a new sample
+    pub(crate) fn advance(&mut self) -> bool {
+        let factor = self.factor.next();
+        self.hold = self.hold.min(factor) - 1.0;
+        if self.hold > 0.0 {
+            return false;
+        }
+        self.hold += factor;
+        true
+    }
+
+    /// The crushed sample of `channel` (0 or 1) for this frame; `input` only
+    /// counts when `advance` took a new sample
+    pub(crate) fn process(&mut self, channel: usize, input: f32, take: bool) -> f32 {
+        if take {
+            self.held[channel] = if self.levels > 0.0 {
+                (input * self.levels).round() / self.levels
+            } else {
+                input
+            };
+        }
+        self.held[channel]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 48_000;
+
+    fn settings(bits: u8, downsample: u32) -> CrushSettings {
+        CrushSettings { bits, downsample }
+    }
+
+    /// Frames between the samples `crusher` takes over `frames` frames
+    fn runs(crusher: &mut Crusher, frames: usize) -> Vec<usize> {
+        let mut runs = Vec::new();
+        let mut run = 0;
+        for _ in 0..frames {
+            if crusher.advance() && run > 0 {
+                runs.push(run);
+                run = 0;
+            }
+            run += 1;
+        }
+        runs
+    }
+
+    #[test]
+    fn the_defaults_are_bypassed() {
+        assert!(Crusher::new(CrushSettings::OFF, RATE).bypassed());
+        assert!(!Crusher::new(settings(8, 1), RATE).bypassed());
+        assert!(!Crusher::new(settings(MAX_CRUSH_BITS, 2), RATE).bypassed());
+        // Only once a factor gliding back to 1 gets there
+        let mut crusher = Crusher::new(settings(MAX_CRUSH_BITS, 8), RATE);
+        crusher.set(CrushSettings::OFF);
+        assert!(!crusher.bypassed());
+        runs(&mut crusher, RATE as usize * CRUSH_SLEW_MS as usize / 1000);
+        assert!(crusher.bypassed());
+    }
+
+    #[test]
+    fn bits_snap_to_their_levels() {
+        let mut crusher = Crusher::new(settings(3, 1), RATE);
+        let crushed: Vec<f32> = [0.3, 0.4, -0.1, -0.2, 1.0]
+            .iter()
+            .map(|&input| {
+                let take = crusher.advance();
+                crusher.process(0, input, take)
+            })
+            .collect();
+        assert_eq!(crushed, [0.25, 0.5, 0.0, -0.25, 1.0]);
+        let mut crusher = Crusher::new(settings(1, 1), RATE);
+        assert_eq!(crusher.process(1, -0.7, true), -1.0);
+        assert_eq!(crusher.process(1, 0.2, true), 0.0);
+    }
+
+    #[test]
+    fn a_factor_holds_each_sample_that_long() {
+        let mut crusher = Crusher::new(settings(MAX_CRUSH_BITS, 4), RATE);
+        let held: Vec<f32> = (0..12)
+            .map(|n| {
+                let take = crusher.advance();
+                crusher.process(0, n as f32, take)
+            })
+            .collect();
+        assert_eq!(held, [0., 0., 0., 0., 4., 4., 4., 4., 8., 8., 8., 8.]);
+        // Both channels hold on the same frames
+        assert_eq!(crusher.process(1, 0.5, true), 0.5);
+        assert_eq!(crusher.process(0, 0.0, false), 8.0);
+    }
+
+    #[test]
+    fn a_factor_change_glides() {
+        let mut crusher = Crusher::new(settings(MAX_CRUSH_BITS, 1), RATE);
+        crusher.set(settings(MAX_CRUSH_BITS, 16));
+        let runs = runs(&mut crusher, RATE as usize / 10);
+        assert!(runs[0] <= 2, "{:?}", &runs[..4]);
+        for pair in runs.windows(2) {
+            assert!(
+                pair[1] + 1 >= pair[0] && pair[1] <= pair[0] + 2,
+                "{:?}",
+                pair
+            );
+        }
+        assert!(runs[runs.len() - 20..].iter().all(|&run| run == 16));
+    }
+}
@@ -0,0 +1,141 @@
+/**
+ * delay.rs
+ * L-SAMP 100 | Delay Bus
+ *
+ * A tempo-synced stereo echo the pads send into. Each voice taps its output
+ * (after volume, envelope and crossfader) into the bus at its send level;
+ * the bus repeats it every `division` of a beat at the transport's tempo,
+ * each repeat `feedback` times the one before, and adds only the echoes to
+ * the mix. A tempo change glides the delay time like tape instead of
+ * jumping. The whole line is allocated with the mixer, for the longest
+ * delay there can be, and the bus keeps running after the last voice until
+ * its tail dies out.
+ */
+use serde::{Deserialize, Serialize};
+
+use crate::{flush_denormal, Slew, StepDivision};
+
+/// Longest delay time, a quarter note at 20 BPM with room to spare
+const MAX_DELAY_SECONDS: f32 = 4.0;
+
+/// Highest feedback, short of a repeat that never dies
+pub const MAX_DELAY_FEEDBACK: f32 = 0.95;
+
+/// Time a new delay time glides over
+const DELAY_GLIDE_MS: f32 = 100.0;
+
+/// Level below which a repeat counts as gone (-100 dBFS)
+const SILENCE: f32 = 1e-5;
+
+/// Buses a pad can send to
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SendBus {
+    Delay,
+}
+
+/// Delay bus settings, as `set_delay` takes them
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DelaySettings {
+    pub division: StepDivision, // Note value of one repeat
+    pub feedback: f32,          // 0 to MAX_DELAY_FEEDBACK
+}
+
+impl Default for DelaySettings {
+    fn default() -> Self {
+        Self {
+            division: StepDivision::Eighth,
+            feedback: 0.4,
+        }
+    }
+}
+
+/// The delay bus's part of the mixer
+pub(crate) struct DelayBus {
+    settings: DelaySettings,
+    sample_rate: f32,
+    line: Vec<f32>,      // Stereo interleaved, MAX_DELAY_SECONDS long
+    write: usize,        // Frame written next
+    time: Slew,          // Delay in frames
+    quiet_frames: usize, // Frames written below SILENCE in a row
+}
+
+impl DelayBus {
+    pub(crate) fn new(sample_rate: u32) -> Self {
+        let frames = (sample_rate as f32 * MAX_DELAY_SECONDS) as usize + 2;
+        Self {
+            settings: DelaySettings::default(),
+            sample_rate: sample_rate as f32,
+            line: vec![0.0; frames * 2],
+            write: 0,
+            time: Slew::new(0.0, sample_rate, DELAY_GLIDE_MS),
+            quiet_frames: frames,
+        }
+    }
+
+    pub(crate) fn set(&mut self, settings: DelaySettings) {
+        self.settings = settings;
+    }
+
+    fn frames(&self) -> usize {
+        self.line.len() / 2
+    }
+
+    /// True once every echo has died out
+    pub(crate) fn idle(&self) -> bool {
+        self.quiet_frames >= self.frames()
+    }
+
+    /// Runs a block: `send` goes in, the echoes are added to `left`/`right`
+    pub(crate) fn process(
+        &mut self,
+        send: (&[f32], &[f32]),
+        left: &mut [f32],
+        right: &mut [f32],
+        bpm: f64,
+    ) {
+        let frames = self.frames();
+        let seconds = 60.0 / bpm / self.settings.division.steps_per_beat();
+        let target = (seconds as f32 * self.sample_rate).clamp(1.0, (frames - 2) as f32);
+        if self.time.target != target {
+            if self.idle() || self.time.target == 0.0 {
+                self.time.jump(target);
+            } else {
+                self.time.set(target);
+            }
+        }
+        let feedback = self.settings.feedback;
+        for i in 0..left.len() {
+            let delay = self.time.next() as f64;
+            // Linear interpolation between the two frames around the read point
+            let read = self.write as f64 + frames as f64 - delay;
+            let before = read.floor() as usize % frames;
+            let after = (before + 1) % frames;
+            let frac = read.fract() as f32;
+            let line = &self.line;
+            let tap = |channel: usize| {
+                line[before * 2 + channel] * (1.0 - frac) + line[after * 2 + channel] * frac
+            };
+            let (echo_l, echo_r) = (tap(0), tap(1));
+            let in_l = flush_denormal(send.0[i] + echo_l * feedback);
+            let in_r = flush_denormal(send.1[i] + echo_r * feedback);
+            self.line[self.write * 2] = in_l;
+            self.line[self.write * 2 + 1] = in_r;
+            self.write = (self.write + 1) % frames;
+            if in_l.abs().max(in_r.abs()) < SILENCE {
+                self.quiet_frames = self.quiet_frames.saturating_add(1);
+            } else {
+                self.quiet_frames = 0;
+            }
+            left[i] += echo_l;
+            right[i] += echo_r;
+        }
+    }
+
+    /// The delay line
+    #[cfg(test)]
+    pub(crate) fn state(&self) -> &[f32] {
+        &self.line
+    }
+}
@@ -0,0 +1,173 @@
+/**
+ * drive.rs
+ * L-SAMP 100 | Drive
+ *
+ * Every voice's saturation: `drive` (0-1) sets the gain into a waveshaper,
+ * after the crusher and filter and ahead of the volume and envelope, so a
+ * fade or a volume change never changes the tone. The output follows the
+ * input's loudness (both tracked as a running RMS), which keeps a driven pad
+ * as loud as it was instead of louder. Both means start at 0 and grow at
+ * the same rate, so their ratio is right from a note's first frames, and
+ * before anything but silence the makeup is the shaper's small-signal gain.
+ * There is no oversampling yet; `shape`
+ * is the one place a better curve goes. A drive of 0 skips the stage.
+ */
+use crate::{flush_denormal, Slew};
+
+/// Gain into the shaper at full drive (+20 dB)
+const MAX_DRIVE_GAIN: f32 = 10.0;
+
+/// How quickly the loudness match follows, slow enough not to pump
+const DRIVE_RMS_MS: f32 = 150.0;
+
+/// Time a drive change glides over
+const DRIVE_SLEW_MS: f32 = 30.0;
+
+/// The waveshaper
+fn shape(x: f32) -> f32 {
+    x.tanh()
+}
+
+/// A voice's drive
+pub(crate) struct Drive {
+    gain: Slew,     // Into the shaper, 1 for a drive of 0
+    in_power: f32,  // Running mean square of what goes in
+    out_power: f32, // And of what the shaper makes of it
+    smoothing: f32, // Weight of the running means per frame
+}
+
+impl Drive {
+    pub(crate) fn new(drive: f32, sample_rate: u32) -> Self {
+        Self {
+            gain: Slew::new(drive_gain(drive), sample_rate, DRIVE_SLEW_MS),
+            in_power: 0.0,
+            out_power: 0.0,
+            smoothing: smoothing(sample_rate),
+        }
+    }
+
+    /// A new drive for a playing voice; the gain glides to it. Means left
+    /// over from before a bypass would be stale, so they start again.
+    pub(crate) fn set(&mut self, drive: f32) {
+        if self.bypassed() {
+            (self.in_power, self.out_power) = (0.0, 0.0);
+        }
+        self.gain.set(drive_gain(drive));
+    }
+
+    pub(crate) fn retime(&mut self, sample_rate: u32) {
+        self.gain.retime(sample_rate, DRIVE_SLEW_MS);
+        self.smoothing = smoothing(sample_rate);
+    }
+
+    /// Whether the voice can skip the stage, checked once per block
+    pub(crate) fn bypassed(&self) -> bool {
+        self.gain.settled() && self.gain.current <= 1.0
+    }
+
+    /// Saturates the samples of one frame (one, or left and right) in place
+    pub(crate) fn process(&mut self, frame: &mut [f32]) {
+        let gain = self.gain.next();
+        let (mut in_power, mut out_power) = (0.0, 0.0);
+        for sample in frame.iter_mut() {
+            in_power += *sample * *sample;
+            *sample = shape(*sample * gain);
+            out_power += *sample * *sample;
+        }
+        self.in_power = flush_denormal(self.in_power + (in_power - self.in_power) * self.smoothing);
+        self.out_power =
+            flush_denormal(self.out_power + (out_power - self.out_power) * self.smoothing);
+        let makeup = if self.out_power > f32::EPSILON * self.in_power {
+            (self.in_power / self.out_power).sqrt()
+        } else {
+            // Nothing but silence yet: near zero the shaper is just its gain
+            1.0 / gain
+        };
+        for sample in frame.iter_mut() {
+            *sample *= makeup;
+        }
+    }
+
+    /// The running means
+    #[cfg(test)]
+    pub(crate) fn state(&self) -> [f32; 2] {
+        [self.in_power, self.out_power]
+    }
+}
+
+fn drive_gain(drive: f32) -> f32 {
+    1.0 + drive * (MAX_DRIVE_GAIN - 1.0)
+}
+
+fn smoothing(sample_rate: u32) -> f32 {
+    1.0 - (-1000.0 / (DRIVE_RMS_MS * sample_rate as f32)).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::TAU;
+
+    const RATE: u32 = 48_000;
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    fn db(ratio: f32) -> f32 {
+        20.0 * ratio.log10()
+    }
+
+    /// `seconds` of a sine of peak `level`, after `silence` seconds of nothing
+    fn sine(level: f32, silence: f32, seconds: f32) -> Vec<f32> {
+        let lead = (silence * RATE as f32) as usize;
+        let frames = (seconds * RATE as f32) as usize;
+        (0..lead + frames)
+            .map(|n| match n.checked_sub(lead) {
+                Some(n) => level * (n as f32 * TAU * 220.0 / RATE as f32).sin(),
+                None => 0.0,
+            })
+            .collect()
+    }
+
+    fn driven(drive: f32, input: &[f32]) -> Vec<f32> {
+        let mut stage = Drive::new(drive, RATE);
+        input
+            .iter()
+            .map(|&sample| {
+                let mut frame = [sample];
+                stage.process(&mut frame);
+                frame[0]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn moderate_drive_keeps_rms_within_1db_of_bypass() {
+        let input = sine(0.5, 0.0, 1.0);
+        for drive in [0.25, 0.5, 0.75] {
+            let output = driven(drive, &input);
+            let change = db(rms(&output) / rms(&input));
+            assert!(change.abs() < 1.0, "drive {}: {} dB", drive, change);
+        }
+    }
+
+    #[test]
+    fn attack_after_silence_is_matched_too() {
+        let input = sine(0.5, 0.05, 0.5);
+        let output = driven(0.5, &input);
+        let lead = (0.05 * RATE as f32) as usize;
+        assert!(output[..lead].iter().all(|&s| s == 0.0));
+        // The first 10 ms of the note, before a 150 ms mean could settle
+        let attack = lead..lead + RATE as usize / 100;
+        let change = db(rms(&output[attack.clone()]) / rms(&input[attack]));
+        assert!(change.abs() < 1.0, "attack: {} dB", change);
+    }
+
+    #[test]
+    fn zero_drive_is_bypassed() {
+        let stage = Drive::new(0.0, RATE);
+        assert!(stage.bypassed());
+        assert!(!Drive::new(0.5, RATE).bypassed());
+    }
+}
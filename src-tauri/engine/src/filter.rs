@@ -0,0 +1,240 @@
+/**
+ * filter.rs
+ * L-SAMP 100 | Voice Filter
+ *
+ * Every voice's low-pass: a state-variable filter with a flat (Butterworth)
+ * response at the pad's `filterCutoff`, moved by an attack/decay envelope of
+ * `filterEnvAmount` octaves. The envelope belongs to the voice, so every
+ * trigger (pad, sequencer step or take replay) starts it over. The cutoff is
+ * worked out per frame while the envelope moves and always stays between
+ * MIN_FILTER_CUTOFF and just under Nyquist. A voice with the cutoff fully
+ * open and no envelope amount skips the filter altogether.
+ */
+use crate::{flush_denormal, PlayParams};
+
+/// Lowest cutoff, for the base and the envelope alike
+pub const MIN_FILTER_CUTOFF: f32 = 20.0;
+
+/// Base cutoff of a filter that is fully open, the default
+pub const FILTER_OPEN: f32 = 20_000.0;
+
+/// Envelope amount either way, in octaves
+pub const MAX_FILTER_ENV_OCTAVES: f32 = 10.0;
+
+/// Highest cutoff as a share of the sample rate, short of Nyquist (0.5)
+/// where the filter's coefficients run off
+const MAX_CUTOFF_RATIO: f32 = 0.49;
+
+/// Damping of a Butterworth response (1/Q)
+const DAMPING: f32 = std::f32::consts::SQRT_2;
+
+/// The filter part of a pad's `PlayParams`, as the callback gets it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct FilterSettings {
+    cutoff: f32,     // Hz
+    env_amount: f32, // Octaves at the envelope's peak, negative sweeps down
+    env_attack: f32, // Seconds
+    env_decay: f32,  // Seconds
+}
+
+impl FilterSettings {
+    /// No filtering, for voices outside the sound bank
+    pub(crate) const OPEN: Self = Self {
+        cutoff: FILTER_OPEN,
+        env_amount: 0.0,
+        env_attack: 0.0,
+        env_decay: 0.0,
+    };
+
+    pub(crate) fn of(params: &PlayParams) -> Self {
+        Self {
+            cutoff: params.filter_cutoff,
+            env_amount: params.filter_env_amount,
+            env_attack: params.filter_env_attack,
+            env_decay: params.filter_env_decay,
+        }
+    }
+
+    fn bypassed(&self) -> bool {
+        self.env_amount == 0.0 && self.cutoff >= FILTER_OPEN
+    }
+}
+
+/// Integrator state of one channel
+#[derive(Debug, Clone, Copy, Default)]
+struct Stage {
+    ic1: f32,
+    ic2: f32,
+}
+
+/// A voice's filter and envelope
+pub(crate) struct VoiceFilter {
+    settings: FilterSettings,
+    sample_rate: f32,
+    position: f64, // Frames since the trigger
+    coefficients: [f32; 3],
+    tuned_to: f32,      // Cutoff the coefficients are for, 0 before the first frame
+    stages: [Stage; 2], // Left (or mono) and right
+}
+
+impl VoiceFilter {
+    pub(crate) fn new(settings: FilterSettings, sample_rate: u32) -> Self {
+        Self {
+            settings,
+            sample_rate: sample_rate as f32,
+            position: 0.0,
+            coefficients: [0.0; 3],
+            tuned_to: 0.0,
+            stages: [Stage::default(); 2],
+        }
+    }
+
+    /// New settings for a playing voice; the envelope carries on where it is
+    pub(crate) fn set(&mut self, settings: FilterSettings) {
+        self.settings = settings;
+    }
+
+    /// Keeps the envelope's progress at a new sample rate
+    pub(crate) fn retime(&mut self, sample_rate: u32) {
+        let sample_rate = sample_rate as f32;
+        self.position *= (sample_rate / self.sample_rate) as f64;
+        self.sample_rate = sample_rate;
+        self.tuned_to = 0.0;
+    }
+
+    /// Moves the envelope on a frame; false when this frame skips the filter
+    pub(crate) fn advance(&mut self) -> bool {
+        let position = self.position;
+        self.position += 1.0;
+        if self.settings.bypassed() {
+            // Starts from silence if it comes back on
+            self.stages = [Stage::default(); 2];
+            return false;
+        }
+        let mut cutoff = self.settings.cutoff;
+        if self.settings.env_amount != 0.0 {
+            cutoff *= (self.settings.env_amount * self.envelope(position)).exp2();
+        }
+        let cutoff = cutoff.clamp(MIN_FILTER_CUTOFF, self.sample_rate * MAX_CUTOFF_RATIO);
+        if cutoff != self.tuned_to {
+            let g = (std::f32::consts::PI * cutoff / self.sample_rate).tan();
+            let a1 = 1.0 / (1.0 + g * (g + DAMPING));
+            self.coefficients = [a1, g * a1, g * g * a1];
+            self.tuned_to = cutoff;
+        }
+        true
+    }
+
+    /// Attack/decay level (0-1) `position` frames after the trigger
+    fn envelope(&self, position: f64) -> f32 {
+        let attack = (self.settings.env_attack * self.sample_rate) as f64;
+        if position < attack {
+            return (position / attack) as f32;
+        }
+        let decay = (self.settings.env_decay * self.sample_rate) as f64;
+        if decay <= 0.0 {
+            return 0.0;
+        }
+        (1.0 - (position - attack) / decay).max(0.0) as f32
+    }
+
+    /// Low-passes one sample of `channel` (0 or 1) for the current frame
+    pub(crate) fn process(&mut self, channel: usize, input: f32) -> f32 {
+        let [a1, a2, a3] = self.coefficients;
+        let stage = &mut self.stages[channel];
+        let v3 = input - stage.ic2;
+        let v1 = a1 * stage.ic1 + a2 * v3;
+        let v2 = stage.ic2 + a2 * stage.ic1 + a3 * v3;
+        stage.ic1 = flush_denormal(2.0 * v1 - stage.ic1);
+        stage.ic2 = flush_denormal(2.0 * v2 - stage.ic2);
+        v2
+    }
+
+    /// Integrator state of both channels
+    #[cfg(test)]
+    pub(crate) fn state(&self) -> [f32; 4] {
+        let [left, right] = self.stages;
+        [left.ic1, left.ic2, right.ic1, right.ic2]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 48_000;
+
+    fn settings(cutoff: f32, env_amount: f32, env_attack: f32, env_decay: f32) -> FilterSettings {
+        FilterSettings {
+            cutoff,
+            env_amount,
+            env_attack,
+            env_decay,
+        }
+    }
+
+    /// The cutoff the filter is tuned to on each of `frames` frames
+    fn cutoffs(settings: FilterSettings, frames: usize) -> Vec<f32> {
+        let mut filter = VoiceFilter::new(settings, RATE);
+        (0..frames)
+            .map(|_| {
+                filter.advance();
+                filter.tuned_to
+            })
+            .collect()
+    }
+
+    #[test]
+    fn the_envelope_sweeps_the_cutoff_up_and_back() {
+        // Two octaves over 10 ms, back over 20 ms
+        let swept = cutoffs(settings(500.0, 2.0, 0.01, 0.02), 3000);
+        assert_eq!(swept[0], 500.0);
+        assert!((swept[240] - 1000.0).abs() < 0.1, "{}", swept[240]);
+        assert!((swept[480] - 2000.0).abs() < 0.1, "{}", swept[480]);
+        assert!((swept[960] - 1000.0).abs() < 0.1, "{}", swept[960]);
+        assert!(swept[..480].windows(2).all(|pair| pair[1] > pair[0]));
+        assert!(swept[480..1440].windows(2).all(|pair| pair[1] < pair[0]));
+        assert!(swept[1440..].iter().all(|&cutoff| cutoff == 500.0));
+        // A negative amount sweeps down instead
+        let swept = cutoffs(settings(2000.0, -2.0, 0.01, 0.02), 481);
+        assert!((swept[480] - 500.0).abs() < 0.1, "{}", swept[480]);
+    }
+
+    #[test]
+    fn the_cutoff_stays_between_20_hz_and_nyquist() {
+        let top = RATE as f32 * MAX_CUTOFF_RATIO;
+        let up = cutoffs(settings(10_000.0, MAX_FILTER_ENV_OCTAVES, 0.01, 0.0), 481);
+        assert_eq!(up[479], top);
+        assert!(up.iter().all(|&cutoff| cutoff <= top));
+        let down = cutoffs(settings(100.0, -MAX_FILTER_ENV_OCTAVES, 0.01, 0.0), 481);
+        assert_eq!(down[479], MIN_FILTER_CUTOFF);
+        assert!(down.iter().all(|&cutoff| cutoff >= MIN_FILTER_CUTOFF));
+        // Tuned right up there, it still holds a full-scale input
+        let mut filter =
+            VoiceFilter::new(settings(10_000.0, MAX_FILTER_ENV_OCTAVES, 0.0, 1.0), RATE);
+        for n in 0..RATE {
+            filter.advance();
+            let input = if n % 2 == 0 { 1.0 } else { -1.0 };
+            assert!(filter.process(0, input).abs() < 4.0);
+        }
+    }
+
+    #[test]
+    fn no_amount_with_the_filter_open_is_bypassed() {
+        let mut filter = VoiceFilter::new(FilterSettings::OPEN, RATE);
+        assert!(!filter.advance());
+        assert!(VoiceFilter::new(settings(FILTER_OPEN, 1.0, 0.01, 0.1), RATE).advance());
+        assert!(VoiceFilter::new(settings(1000.0, 0.0, 0.0, 0.0), RATE).advance());
+        // Turned on mid-voice, the envelope is where the voice is
+        for _ in 0..479 {
+            filter.advance();
+        }
+        filter.set(settings(500.0, 2.0, 0.01, 0.02));
+        assert!(filter.advance());
+        assert!(
+            (filter.tuned_to - 2000.0).abs() < 0.1,
+            "{}",
+            filter.tuned_to
+        );
+    }
+}
@@ -0,0 +1,106 @@
+/**
+ * launch.rs
+ * L-SAMP 100 | Quantized Launches
+ *
+ * With a launch grid set, a pad trigger waits in the callback for the next
+ * step of the master clock's grid instead of sounding at once. Steps count
+ * from the bar line and swing through `Grid`, so a launch lands where a
+ * sequencer step of the same note value would. Stopping the pad calls a
+ * waiting launch off, reported as stopped; a stream rebuilt while launches
+ * wait starts them at once on the new one. The blip never waits.
+ */
+use crate::{
+    retire_voice, Clock, Grid, StepDivision, Voice, VoiceEndReason, VoiceEnded, VoicePool,
+    BEATS_PER_BAR, VOICE_CAPACITY,
+};
+
+/// The launches' part of the mixer
+pub(crate) struct Launches {
+    waiting: Vec<(f64, Voice)>, // Beat each starts on, in trigger order, up to VOICE_CAPACITY
+    division: Option<StepDivision>,
+    swing: f32,
+}
+
+impl Launches {
+    pub(crate) fn new() -> Self {
+        Self {
+            waiting: Vec::with_capacity(VOICE_CAPACITY),
+            division: None,
+            swing: 0.0,
+        }
+    }
+
+    /// Grid of the launches from now on; those waiting keep their step
+    pub(crate) fn set_division(&mut self, division: Option<StepDivision>) {
+        self.division = division;
+    }
+
+    pub(crate) fn set_swing(&mut self, swing: f32) {
+        self.swing = swing;
+    }
+
+    pub(crate) fn idle(&self) -> bool {
+        self.waiting.is_empty()
+    }
+
+    /// Plays `voice` on the next step of the grid, or right away without
+    /// one (or with every launch slot taken)
+    pub(crate) fn launch(
+        &mut self,
+        voice: Voice,
+        clock: &Clock,
+        voices: &mut VoicePool,
+        retired: &mut Vec<VoiceEnded>,
+    ) {
+        match self.division {
+            Some(division) if self.waiting.len() < VOICE_CAPACITY => {
+                let beat = clock.beat();
+                let bar = (beat / BEATS_PER_BAR).floor() * BEATS_PER_BAR;
+                let due = bar + Grid::new(division, self.swing).next(beat - bar);
+                self.waiting.push((due, voice));
+            }
+            _ => voices.insert(voice, retired),
+        }
+    }
+
+    /// Calls off the waiting launches of `slot`, or all of them with None
+    pub(crate) fn cancel(&mut self, slot: Option<usize>, retired: &mut Vec<VoiceEnded>) {
+        self.waiting.retain_mut(|(_, voice)| {
+            if slot.is_some() && slot != Some(voice.slot) {
+                return true;
+            }
+            voice.end_reason = VoiceEndReason::Stopped;
+            retire_voice(voice, retired);
+            false
+        });
+    }
+
+    /// Frames from the clock's position to the next launch, None when none waits
+    pub(crate) fn frames_to_launch(&self, clock: &Clock) -> Option<usize> {
+        let due = self.waiting.iter().map(|&(due, _)| due).reduce(f64::min)?;
+        Some(clock.frames_to(due))
+    }
+
+    /// Starts every launch due at the clock's position
+    pub(crate) fn fire(
+        &mut self,
+        clock: &Clock,
+        voices: &mut VoicePool,
+        retired: &mut Vec<VoiceEnded>,
+    ) {
+        let mut index = 0;
+        while index < self.waiting.len() {
+            if clock.reached(self.waiting[index].0) {
+                let (_, voice) = self.waiting.remove(index);
+                voices.insert(voice, retired);
+            } else {
+                index += 1;
+            }
+        }
+    }
+
+    /// Every waiting voice, for the next mixer to start
+    pub(crate) fn take_all(&mut self) -> impl Iterator<Item = Voice> + '_ {
+        self.waiting.drain(..).map(|(_, voice)| voice)
+    }
+}
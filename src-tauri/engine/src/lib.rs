@@ -0,0 +1,5739 @@
+/**
+ * lib.rs
+ * L-SAMP 100 | Audio Engine
+ *
+ * Decoding, analysis and the voice mixer, with no Tauri in sight: the app's
+ * commands are thin wrappers over `AudioEngine`. Everything else here is
+ * private unless a caller needs it. Errors are `Result<_, String>` like the
+ * rest of the app, except loads, which return the typed `LoadError`.
+ *
+ * `AudioEngine::new` plays through cpal (feature `cpal`, on by default).
+ * Without the feature only `AudioEngine::with_backend(OfflineBackend)` is
+ * there, which needs no device.
+ */
+#[cfg(feature = "cpal")]
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use crossbeam_queue::ArrayQueue;
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use stratum_dsp::{analyze_audio, AnalysisConfig};
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::io::{MediaSource, MediaSourceStream};
+use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey};
+use symphonia::core::probe::{Hint, ProbeResult};
+use tokio::sync::mpsc::{self as async_mpsc, UnboundedReceiver, UnboundedSender};
+#[cfg(feature = "cpal")]
+use tracing::error; // Only the cpal error callback logs errors
+use tracing::{debug, info, warn};
+
+mod compressor;
+mod crush;
+mod delay;
+mod drive;
+mod filter;
+mod launch;
+mod limiter;
+mod looper;
+mod metronome;
+mod performance;
+mod recorder;
+mod render;
+mod repeat;
+mod scene;
+mod sequencer;
+use compressor::Compressor;
+pub use compressor::CompressorSettings;
+use crush::{CrushSettings, Crusher};
+pub use crush::{MAX_CRUSH_BITS, MAX_CRUSH_DOWNSAMPLE};
+use delay::DelayBus;
+pub use delay::{DelaySettings, SendBus, MAX_DELAY_FEEDBACK};
+use drive::Drive;
+use filter::{FilterSettings, VoiceFilter};
+pub use filter::{FILTER_OPEN, MAX_FILTER_ENV_OCTAVES, MIN_FILTER_CUTOFF};
+use launch::Launches;
+use limiter::Limiter;
+use looper::{
+    Layer, Looper, LooperInfo, ALL_PADS, LOOPER_KEY_PREFIX, MAX_LOOPER_BARS, MAX_LOOPER_LAYERS,
+};
+use metronome::Metronome;
+pub use metronome::{CountInTick, MAX_COUNT_IN_BARS};
+use performance::Performances;
+pub use performance::{Performance, PerformanceAction, PerformanceEvent};
+pub use recorder::MasterRecording;
+use recorder::{RecordLink, Recorder, RecordingHandle};
+pub use render::{OfflineRender, RenderProgress, RenderSource};
+use repeat::BeatRepeat;
+use scene::Scenes;
+pub use scene::{PadScene, Scene, SceneRecall};
+use sequencer::{Lane, SeqLink, Sequence, Sequencer, LANES};
+pub use sequencer::{SeqStep, StepDivision, StepEvent};
+
+/// Whatever keeps a backend's output running; dropped with the engine
+struct StreamHandle(#[allow(dead_code)] Box<dyn Send>);
+
+pub struct AudioBuffer {
+    pub data: Vec<f32>,
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub duration: f32,
+    pub waveform: Vec<f32>, // Downsampled peak magnitudes for UI
+    pub waveform_left: Vec<f32>,
+    pub waveform_right: Vec<f32>,
+    pub trim_start: f32,   // First audible moment (seconds), see detect_silence
+    pub trim_end: f32,     // Last audible moment (seconds)
+    pub lufs: Option<f32>, // Integrated loudness (None for silent files)
+    pub embedded_loop: Option<(f32, f32)>, // Loop from a WAV `smpl` chunk (seconds)
+    pub root_note: Option<u8>, // MIDI unity note from the `smpl` chunk
+    pub tags: FileTags,
+    pub truncated: bool,              // Decoding stopped at the decode limit
+    pub decode_warnings: Vec<String>, // Damage decoding skipped or stopped at
+}
+
+/// Textual tags from ID3, Vorbis comments or MP4 atoms. Missing tags are empty.
+#[derive(serde::Serialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FileTags {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub genre: String,
+    pub comment: String,
+    pub cover_art: Option<Vec<u8>>, // Not read yet
+}
+
+struct Voice {
+    id: u64, // Unique per trigger, reported in `voice-ended`
+    key: String,
+    slot: usize, // The pad's `PadSlots` index, what the mixer matches on
+    buffer: Arc<AudioBuffer>,
+    position: f64,      // Precise fractional position for resampling
+    playback_rate: f64, // Ratio of file SR to device SR
+    speed: f64,         // Pitch/sync factor on top of the rate ratio
+    device_rate: u32,   // Device rate the sample counts are in
+    looping: bool,
+    loop_start: f64,
+    loop_end: f64,
+    gain: Slew,       // Volume, velocity and makeup gain, ramped on updates
+    makeup_gain: f32, // Loudness normalization gain, already folded into `gain`
+    velocity: f32,    // Strike strength of the trigger, kept across parameter updates
+    attack: f32,      // Seconds, what `attack_samples` is rebuilt from
+    release: f32,     // Seconds, what `release_samples` is rebuilt from
+    attack_samples: usize,
+    release_samples: usize,
+    crush: Crusher,      // Lo-fi stage ahead of the filter
+    filter: VoiceFilter, // Low-pass and its envelope, from the trigger on
+    drive: Drive,        // Saturation after the filter, ahead of `gain`
+    send: Slew,          // Level into the delay bus, 0 while sends are muted
+    stopped: bool,
+    fade_position: usize, // Current position in the overall envelope
+    is_fading_out: bool,
+    stop_command: bool,         // Flag to trigger symmetric release
+    fade_start_gain: f32,       // Snapshot of gain when fade-out starts
+    fade_out_pos: usize,        // Progress of the fade-out specifically
+    current_peak: f32,          // Track peak level for visualizers
+    meter_peak: f32,            // Peak over the current meter window
+    meter_samples: Vec<f32>,    // Visual samples of the current window, up to METER_SAMPLES
+    custom_release_set: bool, // Flag to prevent symmetry override when frontend provides effective_release
+    declick_from: f32,        // Gain the declick ramp starts at
+    declick_left: usize,      // Frames left of the declick ramp, 0 when there is none
+    end_reason: VoiceEndReason, // Reported once the voice is retired
+}
+
+/// Why a voice was retired. Choke groups don't exist yet; their reason is
+/// reserved so the frontend can match on the full set.
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VoiceEndReason {
+    Natural, // Reached the end of its region (or finished its natural release)
+    Stopped, // stop_sound / stop_all
+    #[allow(dead_code)]
+    Choked,
+    Stolen, // Made room for a trigger while the voice pool was full
+}
+
+/// Payload of the `voice-ended` event
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VoiceEnded {
+    pub key: String,
+    pub voice_id: u64,
+    pub reason: VoiceEndReason,
+}
+
+/// Pending `voice-ended` events. Bounded so the callback never allocates or
+/// blocks; when the drain thread falls this far behind, events are dropped.
+const VOICE_EVENT_QUEUE: usize = 256;
+
+/// Error reported by the output stream (from the backend's error thread)
+#[derive(Clone, Debug)]
+pub struct StreamFault {
+    pub device_lost: bool, // The device went away; nothing plays until a restart
+    pub message: String,
+}
+
+/// Stream faults waiting for `take_stream_faults`; a failing device repeats
+/// itself, so overflow is dropped
+const STREAM_FAULT_QUEUE: usize = 16;
+
+/// Problems inside the audio callback, which must not log: it only counts
+/// them and `callback_errors` hands the totals to a monitor outside it
+#[derive(Default)]
+struct CallbackCounters {
+    dropped_voice_events: AtomicU64, // `voice-ended` events lost to a full queue
+    panics: AtomicU64,               // Buffers lost to a panic in the mixer
+    non_finite: AtomicU64,           // Voices killed for rendering NaN/infinity
+    late_patterns: AtomicU64,        // Sequencer loops that were not built in time
+}
+
+/// Locks taken over from a holder that panicked, across all engines
+static POISONED_LOCKS: AtomicU64 = AtomicU64::new(0);
+
+/// Locks the engine state, taking it over when a holder panicked. Every
+/// mutation of a locked section is a plain field or map update, so what a
+/// panic leaves behind is consistent; refusing the lock forever would
+/// brick the engine until a restart instead.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| {
+        POISONED_LOCKS.fetch_add(1, Ordering::Relaxed);
+        mutex.clear_poison();
+        poisoned.into_inner()
+    })
+}
+
+/// Snapshot of the callback counters, totals since the engine started
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CallbackErrors {
+    pub dropped_voice_events: u64,
+    pub panics: u64,            // Caught in the callback; the buffer went out silent
+    pub poisoned_locks: u64,    // Engine locks recovered after a panic elsewhere
+    pub non_finite_voices: u64, // Voices killed for rendering NaN or infinity
+    pub late_sequencer_loops: u64, // Loops the sequencer played silent, not built in time
+}
+
+/// Key of the UI blip voice, which never reports `voice-ended`
+const BLIP_KEY: &str = "__blip";
+
+/// A loaded pad: its decoded buffer and the waveforms drawn from it so far.
+/// Reloading a pad replaces the entry, which drops its cached waveforms.
+#[derive(Clone)]
+pub struct BankEntry {
+    pub buffer: Arc<AudioBuffer>,
+    waveforms: Arc<Mutex<WaveformCache>>,
+}
+
+/// Waveforms of one buffer keyed by (channel, points)
+type WaveformCache = HashMap<(WaveformChannel, usize), Arc<Vec<f32>>>;
+
+impl BankEntry {
+    fn new(buffer: AudioBuffer) -> Self {
+        // The waveforms decode computed anyway are the first cache entries
+        let mut waveforms = WaveformCache::new();
+        for (channel, waveform) in [
+            (WaveformChannel::Mixed, &buffer.waveform),
+            (WaveformChannel::Left, &buffer.waveform_left),
+            (WaveformChannel::Right, &buffer.waveform_right),
+        ] {
+            waveforms.insert((channel, WAVEFORM_POINTS), Arc::new(waveform.clone()));
+        }
+        Self {
+            buffer: Arc::new(buffer),
+            waveforms: Arc::new(Mutex::new(waveforms)),
+        }
+    }
+}
+
+pub struct AudioEngineState {
+    pub sound_bank: HashMap<String, BankEntry>,
+    master_volume: f32,                         // Last volume sent to the mixer
+    pub master_bpm: f32,                        // Global Master BPM
+    sample_rate: u32,                           // Device sample rate
+    pub bpm_overrides: HashMap<String, f32>,    // Half/double corrections applied per pad
+    pub bpm_range: (f32, f32),                  // Preferred BPM range for octave folding
+    analysis: HashMap<String, PadAnalysis>,     // Background BPM/key analysis per pad
+    makeup_gains: HashMap<String, f32>,         // Linear loudness normalization gain per pad
+    file_cache: HashMap<PathBuf, FileAnalysis>, // Known duration/BPM per decoded file
+    load_generation: u64,                       // Bumped on every load to spot stale analysis
+    loading: HashMap<String, PendingLoad>,      // Newest unfinished load per pad
+    pad_params: HashMap<String, PlayParams>,    // Last params each pad was played/updated with
+    pad_defaults: HashMap<String, PartialPlayParams>, // Stored defaults that fill omitted play fields
+    next_voice_id: u64,
+    pad_slots: PadSlots,
+    decode_limit: usize, // Most interleaved samples one file may decode to
+    stop_all_release: Option<f32>, // Seconds `stop_all` fades every voice over
+    sequence: Sequence,
+    performance: Performances,
+    looper: LooperInfo,
+    crossfader: f32, // 0 full side A, 1 full side B
+    crossfade_curve: CrossfadeCurve,
+    pad_groups: HashMap<String, CrossfadeGroup>, // Crossfader side per pad id
+    scenes: Scenes,
+    compressor: CompressorSettings,
+    delay: DelaySettings,
+    swing: f32, // Of every second step, as a share of a step (0 straight)
+    launch_quantize: Option<StepDivision>, // Grid pad triggers wait for, None to play at once
+    repeat: BeatRepeat,
+}
+
+impl AudioEngineState {
+    fn new(sample_rate: u32) -> Self {
+        Self {
+            sound_bank: HashMap::new(),
+            master_volume: 1.0,
+            master_bpm: 120.0,
+            sample_rate,
+            bpm_overrides: HashMap::new(),
+            bpm_range: DEFAULT_BPM_RANGE,
+            analysis: HashMap::new(),
+            makeup_gains: HashMap::new(),
+            file_cache: HashMap::new(),
+            load_generation: 0,
+            loading: HashMap::new(),
+            pad_params: HashMap::new(),
+            pad_defaults: HashMap::new(),
+            decode_limit: DEFAULT_DECODE_LIMIT_MB * 1024 * 1024 / std::mem::size_of::<f32>(),
+            next_voice_id: 0,
+            pad_slots: PadSlots::default(),
+            stop_all_release: None,
+            sequence: Sequence::default(),
+            performance: Performances::default(),
+            looper: LooperInfo::default(),
+            crossfader: 0.5,
+            crossfade_curve: CrossfadeCurve::default(),
+            pad_groups: HashMap::new(),
+            scenes: Scenes::default(),
+            compressor: CompressorSettings::default(),
+            delay: DelaySettings::default(),
+            swing: 0.0,
+            launch_quantize: None,
+            repeat: BeatRepeat::default(),
+        }
+    }
+}
+
+/// Requests for the audio callback. They are the only way in: the callback
+/// owns the voices and never locks `AudioEngineState`, so a long load or a
+/// burst of IPC calls can no longer hold up a buffer. Voices travel inline
+/// (see `SeqAction`).
+#[allow(clippy::large_enum_variant)]
+enum Command {
+    Play(Voice),
+    Launch(Voice), // A pad trigger, held for the launch grid when there is one
+    Stop {
+        slot: usize,
+        release: Option<f32>, // Seconds, overrides the voice's own release
+    },
+    Update {
+        slot: usize,
+        volume: f32,
+        looping: bool,
+        start_time: f32,
+        end_time: f32,
+        crush: CrushSettings,
+        filter: FilterSettings,
+        drive: f32,
+    },
+    StopAll {
+        release: Option<f32>, // Seconds for every voice, else each keeps its own
+    },
+    MasterVolume(f32),
+    Compressor(CompressorSettings),
+    Delay(DelaySettings),
+    Send {
+        slot: usize,
+        level: f32, // Into the delay bus, 0 to mute
+    },
+    Tempo(f32), // Master BPM for the transport clock
+    Swing(f32),
+    LaunchQuantize(Option<StepDivision>),
+    SeqStart {
+        lane: Lane,
+        beat: f64, // Where its first loop starts, already waiting in `SeqLink`
+    },
+    SeqStop(Lane),
+    LooperRecord {
+        layer: Layer,
+        beat: f64,             // Where it starts recording
+        count_in: Option<f64>, // Where the count-in up to `beat` starts
+    },
+    LooperOverdub(Layer),
+    RecordStart {
+        link: Arc<RecordLink>,
+        beat: Option<f64>,     // Where it starts recording, None for right away
+        count_in: Option<f64>, // Where the count-in up to `beat` starts
+    },
+    RecordEnd {
+        cancel: bool,
+    },
+    LooperUndo,
+    LooperClear,
+    Crossfade([f32; 2]), // Gains of sides A and B
+    PadGroup {
+        slot: usize,
+        group: Option<CrossfadeGroup>,
+    },
+}
+
+/// How long ending a recording waits for the callback to pass on its last frames
+const RECORD_END_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Pending commands. A burst beyond this fails the call instead of blocking.
+const COMMAND_QUEUE: usize = 1024;
+
+/// Voices that can play at once. The pool holds this many slots from the
+/// start; a trigger beyond it steals the oldest voice.
+const VOICE_CAPACITY: usize = 256;
+
+/// Pads that can ever play (eight banks of twelve, the blip and spare room).
+/// The mixer and every level snapshot hold one entry per slot up front.
+const PAD_SLOTS: usize = 128;
+
+/// Metering window. Levels cover this much audio whatever the device's
+/// buffer size, and a window may span several callbacks.
+const METER_WINDOW_MS: u64 = 16;
+
+/// Most visual samples per pad and window, decimated evenly from its frames
+const METER_SAMPLES: usize = 128;
+
+/// Small integer ids for pads, handed out on first play and never reused,
+/// so the callback meters by index instead of by pad id string
+#[derive(Default)]
+struct PadSlots {
+    ids: HashMap<String, usize>,
+    keys: Arc<Mutex<Vec<String>>>, // Pad id per slot, shared with the level meter
+}
+
+impl PadSlots {
+    fn get(&self, key: &str) -> Option<usize> {
+        self.ids.get(key).copied()
+    }
+
+    fn get_or_assign(&mut self, key: &str) -> Result<usize, String> {
+        if let Some(slot) = self.get(key) {
+            return Ok(slot);
+        }
+        let mut keys = lock(&self.keys);
+        if keys.len() == PAD_SLOTS {
+            return Err(format!("[Inner Cosmos] No mixer slot left for {}", key));
+        }
+        let slot = keys.len();
+        keys.push(key.to_string());
+        self.ids.insert(key.to_string(), slot);
+        Ok(slot)
+    }
+}
+
+/// Levels of one pad slot; `live` while a voice of the pad played in the window
+#[derive(Default)]
+struct SlotLevels {
+    live: bool,
+    visual: VisualData,
+}
+
+fn slot_table() -> Vec<SlotLevels> {
+    (0..PAD_SLOTS)
+        .map(|_| SlotLevels {
+            live: false,
+            visual: VisualData {
+                samples: Vec::with_capacity(METER_SAMPLES),
+                ..Default::default()
+            },
+        })
+        .collect()
+}
+
+/// Everything the audio callback reads and writes, owned by its closure
+struct Mixer {
+    voices: VoicePool,
+    master: Slew,
+    groups: Vec<Option<CrossfadeGroup>>, // Crossfader side per pad slot
+    crossfade: [Slew; 2],                // Gains of sides A and B
+    sample_rate: u32,                    // Device sample rate
+    levels: Vec<SlotLevels>,             // Levels of the last closed window, PAD_SLOTS entries
+    window_frames: usize,                // Frames per meter window
+    window_pos: usize,                   // Frames into the current window
+    meter_step: usize,                   // Frames per visual sample
+    commands: Arc<ArrayQueue<Command>>,
+    level_queues: Arc<LevelQueues>,
+    voice_ended: SyncSender<VoiceEnded>, // Filled by write_audio, drained outside the callback
+    retired: Vec<VoiceEnded>,            // Events of this window, sent after its levels
+    counters: Arc<CallbackCounters>,
+    handoff: Arc<Mutex<Handoff>>, // Where the voices and loop go when the mixer is dropped
+    clock: Clock,
+    transport: Arc<TransportShare>,
+    sequencer: Sequencer,
+    launches: Launches,
+    looper: Looper,
+    compressor: Compressor, // On the pad mix, ahead of `master`
+    limiter: Limiter,       // After `master`, ahead of the count-in clicks
+    delay: DelayBus,
+    metronome: Metronome,
+    recorder: Recorder,
+}
+
+/// What one mixer leaves to the next
+#[derive(Default)]
+struct Handoff {
+    voices: Vec<Voice>,
+    looper: Option<Looper>,
+}
+
+impl Mixer {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        sample_rate: u32,
+        commands: &Arc<ArrayQueue<Command>>,
+        level_queues: &Arc<LevelQueues>,
+        voice_ended: &SyncSender<VoiceEnded>,
+        counters: &Arc<CallbackCounters>,
+        handoff: &Arc<Mutex<Handoff>>,
+        transport: &Arc<TransportShare>,
+        seq: &SeqLink,
+        count_in: &SyncSender<CountInTick>,
+    ) -> Self {
+        let window_frames = (sample_rate as u64 * METER_WINDOW_MS / 1000).max(1) as usize;
+        Self {
+            voices: VoicePool::new(),
+            master: Slew::new(1.0, sample_rate, LEVEL_SLEW_MS),
+            groups: vec![None; PAD_SLOTS],
+            crossfade: [(); 2].map(|_| Slew::new(1.0, sample_rate, LEVEL_SLEW_MS)),
+            sample_rate,
+            levels: slot_table(),
+            window_frames,
+            window_pos: 0,
+            meter_step: window_frames.div_ceil(METER_SAMPLES),
+            commands: Arc::clone(commands),
+            level_queues: Arc::clone(level_queues),
+            voice_ended: voice_ended.clone(),
+            retired: Vec::with_capacity(VOICE_CAPACITY),
+            counters: Arc::clone(counters),
+            handoff: Arc::clone(handoff),
+            clock: Clock::resume(&transport.read(), sample_rate),
+            transport: Arc::clone(transport),
+            sequencer: Sequencer::new(seq),
+            launches: Launches::new(),
+            looper: Looper::new(sample_rate),
+            compressor: Compressor::new(sample_rate),
+            limiter: Limiter::new(sample_rate),
+            delay: DelayBus::new(sample_rate),
+            metronome: Metronome::new(sample_rate, count_in),
+            recorder: Recorder::new(),
+        }
+    }
+}
+
+impl Mixer {
+    /// Takes over the engine's levels without a ramp, for a stream that is
+    /// not playing yet
+    fn restore(&mut self, state: &mut AudioEngineState) {
+        self.master.jump(state.master_volume);
+        self.compressor.set(state.compressor);
+        self.delay.set(state.delay);
+        self.sequencer.set_swing(state.swing);
+        self.launches.set_swing(state.swing);
+        self.launches.set_division(state.launch_quantize);
+        let gains = state.crossfade_curve.gains(state.crossfader);
+        for (slew, gain) in self.crossfade.iter_mut().zip(gains) {
+            slew.jump(gain);
+        }
+        for (key, &group) in &state.pad_groups {
+            if let Ok(slot) = state.pad_slots.get_or_assign(key) {
+                self.groups[slot] = Some(group);
+            }
+        }
+    }
+}
+
+/// A mixer is dropped with its stream, never on the audio thread while it
+/// runs. Its voices (launches still waiting among them) and loop wait in
+/// `handoff` for the next stream to take them over.
+impl Drop for Mixer {
+    fn drop(&mut self) {
+        let mut handoff = lock(&self.handoff);
+        handoff.voices.extend(self.voices.take_all());
+        handoff.voices.extend(self.launches.take_all());
+        self.recorder.end(false);
+        handoff.looper = Some(std::mem::replace(
+            &mut self.looper,
+            Looper::new(self.sample_rate),
+        ));
+    }
+}
+
+/// Fixed-capacity voice storage, allocated once with the mixer. A trigger
+/// fills a free slot and a voice keeps its slot index until it is retired;
+/// `order` lists the occupied slots in trigger order, so voices still mix
+/// and meter the way they did in a plain list. When every slot is taken the
+/// oldest voice is retired as `stolen` and its slot reused.
+struct VoicePool {
+    slots: Vec<Option<Voice>>, // VOICE_CAPACITY entries, None when free
+    order: Vec<usize>,         // Occupied slots, oldest first
+    free: Vec<usize>,          // Free slots, next to use last
+}
+
+impl VoicePool {
+    fn new() -> Self {
+        Self {
+            slots: (0..VOICE_CAPACITY).map(|_| None).collect(),
+            order: Vec::with_capacity(VOICE_CAPACITY),
+            free: (0..VOICE_CAPACITY).rev().collect(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    /// Starts a voice, stealing the oldest one when the pool is full
+    fn insert(&mut self, voice: Voice, retired: &mut Vec<VoiceEnded>) {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                let oldest = self.order.remove(0);
+                if let Some(mut stolen) = self.slots[oldest].take() {
+                    stolen.end_reason = VoiceEndReason::Stolen;
+                    retire_voice(&mut stolen, retired);
+                }
+                oldest
+            }
+        };
+        self.slots[index] = Some(voice);
+        self.order.push(index);
+    }
+
+    /// Empties the pool, returning the voices in trigger order
+    fn take_all(&mut self) -> Vec<Voice> {
+        let VoicePool { slots, order, free } = self;
+        free.extend(order.iter().rev());
+        order
+            .drain(..)
+            .filter_map(|index| slots[index].take())
+            .collect()
+    }
+
+    /// Every voice, in slot order
+    fn iter_mut(&mut self) -> impl Iterator<Item = &mut Voice> {
+        self.slots.iter_mut().flatten()
+    }
+
+    /// Every voice, in trigger order
+    fn ordered(&self) -> impl Iterator<Item = &Voice> {
+        self.order
+            .iter()
+            .filter_map(|&index| self.slots[index].as_ref())
+    }
+
+    /// Calls `f` on every voice in trigger order
+    fn for_each_ordered(&mut self, mut f: impl FnMut(&mut Voice)) {
+        for &index in &self.order {
+            if let Some(voice) = self.slots[index].as_mut() {
+                f(voice);
+            }
+        }
+    }
+
+    /// Visits every voice in trigger order and frees the slots of those
+    /// `keep` returns false for
+    fn retain(&mut self, mut keep: impl FnMut(&mut Voice) -> bool) {
+        let VoicePool { slots, order, free } = self;
+        order.retain(|&index| {
+            let kept = slots[index].as_mut().is_some_and(&mut keep);
+            if !kept {
+                slots[index] = None;
+                free.push(index);
+            }
+            kept
+        });
+    }
+}
+
+/// Levels and playing pads as of one meter window
+struct MixerLevels {
+    levels: Vec<SlotLevels>,   // PAD_SLOTS entries
+    active: Vec<usize>,        // Slot of every voice in trigger order, then the looper's layers
+    compressor_reduction: f32, // Most gain reduction in the window, dB
+    limiter_reduction: f32,    // Likewise for the limiter
+}
+
+impl MixerLevels {
+    fn new() -> Self {
+        Self {
+            levels: slot_table(),
+            active: Vec::with_capacity(VOICE_CAPACITY + MAX_LOOPER_LAYERS),
+            compressor_reduction: 0.0,
+            limiter_reduction: 0.0,
+        }
+    }
+}
+
+/// Time a live level change (master volume, crossfader) is spread over, so
+/// dragging a fader does not zipper
+const LEVEL_SLEW_MS: f32 = 30.0;
+
+/// Side of the crossfader a pad plays on
+#[derive(serde::Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CrossfadeGroup {
+    A,
+    B,
+}
+
+/// How the crossfader's travel maps to the gains of its two sides
+#[derive(serde::Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CrossfadeCurve {
+    #[default]
+    ConstantPower, // Equal loudness all the way across
+    SharpCut, // Both sides full but for the last few percent at either end
+}
+
+/// Travel at either end over which `SharpCut` takes the far side out
+const SHARP_CUT_WIDTH: f32 = 0.05;
+
+impl CrossfadeCurve {
+    /// Gains of sides A and B with the fader at `position`
+    fn gains(self, position: f32) -> [f32; 2] {
+        match self {
+            CrossfadeCurve::ConstantPower => {
+                let angle = position * std::f32::consts::FRAC_PI_2;
+                [angle.cos(), angle.sin()]
+            }
+            CrossfadeCurve::SharpCut => [
+                ((1.0 - position) / SHARP_CUT_WIDTH).min(1.0),
+                (position / SHARP_CUT_WIDTH).min(1.0),
+            ],
+        }
+    }
+}
+
+/// A gain the callback moves towards its target in a straight line instead
+/// of jumping to it. Meant for any output level changed while playing.
+struct Slew {
+    current: f32,
+    target: f32,
+    step: f32,   // Change per frame of the ramp under way
+    frames: f32, // Frames a ramp takes
+}
+
+impl Slew {
+    fn new(value: f32, sample_rate: u32, ms: f32) -> Self {
+        Self {
+            current: value,
+            target: value,
+            step: 0.0,
+            frames: (sample_rate as f32 * ms / 1000.0).max(1.0),
+        }
+    }
+
+    /// Ramps from wherever the gain is now, taking the full ramp time
+    fn set(&mut self, target: f32) {
+        self.target = target;
+        self.step = (target - self.current).abs() / self.frames;
+    }
+
+    /// Sets the gain with no ramp, for a stream that is not playing yet
+    fn jump(&mut self, value: f32) {
+        self.current = value;
+        self.target = value;
+    }
+
+    /// Keeps the gain and whatever ramp is under way at a new sample rate
+    fn retime(&mut self, sample_rate: u32, ms: f32) {
+        let frames = (sample_rate as f32 * ms / 1000.0).max(1.0);
+        self.step *= self.frames / frames;
+        self.frames = frames;
+    }
+
+    fn settled(&self) -> bool {
+        self.current == self.target
+    }
+
+    /// The gain for the next frame, snapped to the target once within a step
+    fn next(&mut self) -> f32 {
+        let remaining = self.target - self.current;
+        if remaining.abs() <= self.step {
+            self.current = self.target;
+        } else {
+            self.current += self.step.copysign(remaining);
+        }
+        self.current
+    }
+}
+
+/// Beats per bar of the transport; there are no time signatures yet
+const BEATS_PER_BAR: f64 = 4.0;
+
+/// Highest swing: the late step three quarters of the way to the next
+pub const MAX_SWING: f32 = 0.75;
+
+/// The clock's step grid at one note value, with every second step (counted
+/// from the grid's start) late by `swing` of a step. Whatever lands on the
+/// grid goes through here (sequencer steps, launches, beat repeat and
+/// quantized recording), so they all swing alike.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Grid {
+    step_beats: f64,
+    swing: f64,
+}
+
+impl Grid {
+    pub(crate) fn new(division: StepDivision, swing: f32) -> Self {
+        Self {
+            step_beats: 1.0 / division.steps_per_beat(),
+            swing: swing as f64,
+        }
+    }
+
+    /// Where `beat`, on the straight grid, lands once swung
+    pub(crate) fn swung(&self, beat: f64) -> f64 {
+        self.step((beat / self.step_beats).round().max(0.0) as usize)
+    }
+
+    /// Beat of `step`
+    pub(crate) fn step(&self, step: usize) -> f64 {
+        let late = if step % 2 == 1 { self.swing } else { 0.0 };
+        (step as f64 + late) * self.step_beats
+    }
+
+    /// The first step at or after `beat`, as a beat
+    pub(crate) fn next(&self, beat: f64) -> f64 {
+        let step = (beat / self.step_beats).floor().max(0.0) as usize;
+        if self.step(step) >= beat {
+            self.step(step)
+        } else {
+            self.step(step + 1)
+        }
+    }
+
+    /// The step nearest to `beat`, as a beat
+    pub(crate) fn nearest(&self, beat: f64) -> f64 {
+        let pair = (beat / (2.0 * self.step_beats)).floor().max(0.0) as usize * 2;
+        let (on, late, next) = (self.step(pair), self.step(pair + 1), self.step(pair + 2));
+        if beat < (on + late) / 2.0 {
+            on
+        } else if beat < (late + next) / 2.0 {
+            late
+        } else {
+            next
+        }
+    }
+}
+
+/// Frames a beat may be off by and still land on a frame
+const FRAME_TOLERANCE: f64 = 1e-6;
+
+/// The callback's transport clock. `frame` counts every output frame since
+/// the engine started; beats and seconds are derived from it and the last
+/// tempo (or sample rate) change instead of summed per buffer, so they never
+/// drift and a new tempo only changes how fast the beat moves on.
+struct Clock {
+    frame: f64,
+    sample_rate: f64,
+    bpm: f64,
+    anchor_frame: f64, // Frame of the last tempo or rate change
+    anchor_beat: f64,
+    anchor_seconds: f64,
+}
+
+impl Clock {
+    /// Carries on from a published position at a possibly new sample rate
+    fn resume(from: &Transport, sample_rate: u32) -> Self {
+        Self {
+            frame: from.frame,
+            sample_rate: sample_rate as f64,
+            bpm: from.bpm as f64,
+            anchor_frame: from.frame,
+            anchor_beat: from.beat,
+            anchor_seconds: from.seconds,
+        }
+    }
+
+    fn seconds(&self) -> f64 {
+        self.anchor_seconds + (self.frame - self.anchor_frame) / self.sample_rate
+    }
+
+    fn beat(&self) -> f64 {
+        self.anchor_beat + (self.frame - self.anchor_frame) / self.sample_rate * self.bpm / 60.0
+    }
+
+    /// Frames from the clock's position to `beat`, rounded up. A beat within
+    /// a hair of a frame counts as on it, so float error never makes
+    /// something on the grid a frame late.
+    fn frames_to(&self, beat: f64) -> usize {
+        let frames = (beat - self.beat()) * 60.0 / self.bpm * self.sample_rate;
+        (frames - FRAME_TOLERANCE).ceil().max(0.0) as usize
+    }
+
+    /// Whether `beat` has come, within the same hair
+    fn reached(&self, beat: f64) -> bool {
+        self.frames_to(beat) == 0
+    }
+
+    fn set_bpm(&mut self, bpm: f64) {
+        self.anchor_beat = self.beat();
+        self.anchor_seconds = self.seconds();
+        self.anchor_frame = self.frame;
+        self.bpm = bpm;
+    }
+
+    fn advance(&mut self, frames: usize) {
+        self.frame += frames as f64;
+    }
+}
+
+/// The clock as of the last buffer, published by the callback without
+/// locking. A sequence number (odd while a write is under way) lets readers
+/// retry until they get all fields from the same buffer.
+struct TransportShare {
+    sequence: AtomicU64,
+    frame: AtomicU64, // f64 bits, like the other three
+    seconds: AtomicU64,
+    beat: AtomicU64,
+    bpm: AtomicU64,
+}
+
+impl TransportShare {
+    fn new(bpm: f32) -> Self {
+        Self {
+            sequence: AtomicU64::new(0),
+            frame: AtomicU64::new(0.0f64.to_bits()),
+            seconds: AtomicU64::new(0.0f64.to_bits()),
+            beat: AtomicU64::new(0.0f64.to_bits()),
+            bpm: AtomicU64::new((bpm as f64).to_bits()),
+        }
+    }
+
+    /// Only ever called by the one running mixer
+    fn publish(&self, clock: &Clock) {
+        let sequence = self.sequence.load(Ordering::Relaxed);
+        self.sequence.store(sequence + 1, Ordering::Relaxed);
+        std::sync::atomic::fence(Ordering::Release);
+        self.frame.store(clock.frame.to_bits(), Ordering::Relaxed);
+        self.seconds
+            .store(clock.seconds().to_bits(), Ordering::Relaxed);
+        self.beat.store(clock.beat().to_bits(), Ordering::Relaxed);
+        self.bpm.store(clock.bpm.to_bits(), Ordering::Relaxed);
+        self.sequence.store(sequence + 2, Ordering::Release);
+    }
+
+    fn read(&self) -> Transport {
+        loop {
+            let before = self.sequence.load(Ordering::Acquire);
+            let load = |value: &AtomicU64| f64::from_bits(value.load(Ordering::Relaxed));
+            let (frame, seconds, beat, bpm) = (
+                load(&self.frame),
+                load(&self.seconds),
+                load(&self.beat),
+                load(&self.bpm),
+            );
+            std::sync::atomic::fence(Ordering::Acquire);
+            if before % 2 == 0 && self.sequence.load(Ordering::Relaxed) == before {
+                return Transport {
+                    frame,
+                    seconds,
+                    beat,
+                    bar: beat / BEATS_PER_BAR,
+                    bpm: bpm as f32,
+                };
+            }
+            std::hint::spin_loop();
+        }
+    }
+}
+
+/// Result of `transport`: where the master clock is, as of the last buffer.
+/// `beat` and `bar` count from 0 since the engine started; their fractions
+/// are the phase within the current beat and bar.
+#[derive(serde::Serialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Transport {
+    pub frame: f64, // Output frames rendered
+    pub seconds: f64,
+    pub beat: f64,
+    pub bar: f64,
+    pub bpm: f32,
+}
+
+/// Level snapshots on their way out of the callback (`ready`, newest last)
+/// and back in (`free`), so the callback refills old ones instead of allocating
+struct LevelQueues {
+    ready: ArrayQueue<MixerLevels>,
+    free: ArrayQueue<MixerLevels>,
+}
+
+/// Snapshots in circulation, counting the one `fill_levels` holds
+const LEVEL_SNAPSHOTS: usize = 4;
+
+#[derive(Clone)]
+struct PadAnalysis {
+    generation: u64,
+    path: PathBuf, // File the pad was loaded from, for the per-file cache
+    status: AnalysisStatus,
+}
+
+/// Number of selectable pad banks
+pub const BANK_COUNT: u32 = 8;
+
+/// Master volume ceiling (x2, about +6 dB)
+pub const MAX_MASTER_VOLUME: f32 = 2.0;
+
+/// Internal pad id: every bank holds its own copy of the twelve pad keys
+pub fn pad_id(bank: u32, key: &str) -> String {
+    format!("{}:{}", bank, key)
+}
+
+/// Splits "{bank}:{key}" back into its parts. Plain keys have no bank.
+pub fn split_pad_id(id: &str) -> (Option<u32>, &str) {
+    match id.split_once(':') {
+        Some((bank, key)) => match bank.parse() {
+            Ok(bank) => (Some(bank), key),
+            Err(_) => (None, id),
+        },
+        None => (None, id),
+    }
+}
+
+/// What a loaded pad currently holds, for session snapshots
+#[derive(Debug, Clone)]
+pub struct PadSnapshot {
+    pub key: String,
+    pub path: PathBuf,
+    pub params: Option<PlayParams>,
+    pub defaults: Option<PartialPlayParams>,
+    pub bpm: Option<f32>, // Effective BPM (override or analysis), None while pending
+}
+
+/// Decode/analysis results remembered per file so harbor listings can show
+/// duration and BPM without decoding anything
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct FileAnalysis {
+    pub duration: f32,
+    pub bpm: Option<f32>,
+}
+
+// ========================================================================
+// Output Backends
+// ========================================================================
+
+/// Sample rate and channel count the mixer renders at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputFormat {
+    pub sample_rate: u32,
+    pub channels: usize,
+}
+
+/// Where the mixer's output goes. `start` hands the backend the renderer
+/// and returns what keeps the output running.
+pub trait AudioBackend {
+    type Stream: Send + 'static;
+
+    fn format(&self) -> OutputFormat;
+
+    /// Faults go to `faults` with `try_send`; a full queue drops them
+    fn start(
+        self,
+        renderer: Renderer,
+        faults: SyncSender<StreamFault>,
+    ) -> Result<Self::Stream, String>;
+}
+
+/// The mixer end of the engine. Backends call `render` from their output thread.
+pub struct Renderer {
+    mixer: Mixer,
+    channels: usize,
+}
+
+impl Renderer {
+    /// Fills an interleaved buffer of whole frames. A panic in the mixer
+    /// costs one silent buffer and every playing voice, not the stream.
+    pub fn render(&mut self, data: &mut [f32]) {
+        let (mixer, channels) = (&mut self.mixer, self.channels);
+        let mixed = panic::catch_unwind(AssertUnwindSafe(|| write_audio(data, mixer, channels)));
+        if mixed.is_err() {
+            data.fill(0.0);
+            self.mixer.counters.panics.fetch_add(1, Ordering::Relaxed);
+            // Whichever voice tripped it would trip again next buffer
+            for voice in self.mixer.voices.iter_mut() {
+                voice.stopped = true;
+            }
+        }
+    }
+}
+
+/// The system's default output device, through cpal
+#[cfg(feature = "cpal")]
+pub struct CpalBackend {
+    device: cpal::Device,
+    config: cpal::SupportedStreamConfig,
+}
+
+/// A running cpal stream
+#[cfg(feature = "cpal")]
+pub struct CpalStream(#[allow(dead_code)] cpal::Stream);
+#[cfg(feature = "cpal")]
+unsafe impl Send for CpalStream {}
+
+#[cfg(feature = "cpal")]
+impl CpalBackend {
+    pub fn open_default() -> Result<Self, String> {
+        let host = cpal::default_host();
+        let device = host
+            .default_output_device()
+            .ok_or("No output device found")?;
+        let config = device.default_output_config().map_err(|e| e.to_string())?;
+        Ok(Self { device, config })
+    }
+
+    /// Opens the output device called `name`, as `output_device_names` lists it
+    pub fn open_named(name: &str) -> Result<Self, String> {
+        let device = cpal::default_host()
+            .output_devices()
+            .map_err(|e| e.to_string())?
+            .find(|device| device.name().is_ok_and(|device| device == name))
+            .ok_or_else(|| format!("[Social Noise] No output device called {:?}", name))?;
+        let config = device.default_output_config().map_err(|e| e.to_string())?;
+        Ok(Self { device, config })
+    }
+}
+
+/// Names of the output devices there are
+#[cfg(feature = "cpal")]
+pub fn output_device_names() -> Result<Vec<String>, String> {
+    let devices = cpal::default_host()
+        .output_devices()
+        .map_err(|e| e.to_string())?;
+    Ok(devices.filter_map(|device| device.name().ok()).collect())
+}
+
+#[cfg(feature = "cpal")]
+impl AudioBackend for CpalBackend {
+    type Stream = CpalStream;
+
+    fn format(&self) -> OutputFormat {
+        OutputFormat {
+            sample_rate: self.config.sample_rate().0,
+            channels: self.config.channels() as usize,
+        }
+    }
+
+    fn start(
+        self,
+        mut renderer: Renderer,
+        stream_fault: SyncSender<StreamFault>,
+    ) -> Result<CpalStream, String> {
+        let CpalBackend { device, config } = self;
+        let device_sample_rate = config.sample_rate().0;
+        let channels = config.channels() as usize;
+        info!(
+            device = %device.name().unwrap_or_default(),
+            sample_rate = device_sample_rate,
+            channels,
+            "Opening output stream"
+        );
+
+        let stream = match config.sample_format() {
+            cpal::SampleFormat::F32 => device.build_output_stream(
+                &config.into(),
+                move |data: &mut [f32], _| {
+                    // Cheap enough to redo every buffer, whichever thread cpal calls from
+                    disable_denormals();
+                    renderer.render(data)
+                },
+                move |err| {
+                    let fault = StreamFault {
+                        device_lost: matches!(err, cpal::StreamError::DeviceNotAvailable),
+                        message: err.to_string(),
+                    };
+                    // Nobody draining (or a flood): at least keep it in the log
+                    if let Err(
+                        mpsc::TrySendError::Full(fault) | mpsc::TrySendError::Disconnected(fault),
+                    ) = stream_fault.try_send(fault)
+                    {
+                        error!("Audio stream error: {}", fault.message);
+                    }
+                },
+                None,
+            ),
+            _ => return Err("Unsupported sample format".into()),
+        }
+        .map_err(|e| e.to_string())?;
+
+        stream.play().map_err(|e| e.to_string())?;
+
+        Ok(CpalStream(stream))
+    }
+}
+
+/// Makes the calling thread's FPU flush subnormal floats to zero, which a
+/// long decay would otherwise sink into at a heavy cost per operation on x86:
+/// FTZ and DAZ on x86/x86_64 (SSE), FZ on aarch64. Elsewhere this does
+/// nothing and only the clamps in the stateful DSP (`flush_denormal`) help.
+#[cfg(feature = "cpal")]
+fn disable_denormals() {
+    #[cfg(any(
+        target_arch = "x86_64",
+        all(target_arch = "x86", target_feature = "sse2")
+    ))]
+    // SAFETY: only the FTZ (bit 15) and DAZ (bit 6) MXCSR flags change
+    unsafe {
+        let mut csr: u32 = 0;
+        std::arch::asm!("stmxcsr [{}]", in(reg) &mut csr as *mut u32, options(nostack));
+        csr |= 0x8040;
+        std::arch::asm!("ldmxcsr [{}]", in(reg) &csr as *const u32, options(nostack, readonly));
+    }
+    #[cfg(target_arch = "aarch64")]
+    // SAFETY: only the FZ (bit 24) FPCR flag changes
+    unsafe {
+        let mut fpcr: u64;
+        std::arch::asm!("mrs {}, fpcr", out(reg) fpcr, options(nomem, nostack));
+        fpcr |= 1 << 24;
+        std::arch::asm!("msr fpcr, {}", in(reg) fpcr, options(nomem, nostack));
+    }
+}
+
+/// Callback state below this is flushed to zero: far under anything audible,
+/// far over the subnormals, which the offline backend does not flush
+const STATE_FLOOR: f32 = 1e-20;
+
+/// A value of recursive callback state (a filter, a mean, a delay line)
+/// with anything below `STATE_FLOOR` flushed to zero
+pub(crate) fn flush_denormal(value: f32) -> f32 {
+    if value.abs() < STATE_FLOOR {
+        0.0
+    } else {
+        value
+    }
+}
+
+/// No device: audio is only rendered when `OfflineOutput::render` asks, so
+/// the engine runs deterministically where there is no sound card
+pub struct OfflineBackend {
+    format: OutputFormat,
+    renderer: Arc<Mutex<Option<Renderer>>>,
+}
+
+/// Pulls frames from an engine built on an `OfflineBackend`
+pub struct OfflineOutput {
+    renderer: Arc<Mutex<Option<Renderer>>>,
+    channels: usize,
+}
+
+impl OfflineBackend {
+    pub fn new(sample_rate: u32, channels: usize) -> (Self, OfflineOutput) {
+        let renderer = Arc::new(Mutex::new(None));
+        let backend = Self {
+            format: OutputFormat {
+                sample_rate,
+                channels,
+            },
+            renderer: Arc::clone(&renderer),
+        };
+        (backend, OfflineOutput { renderer, channels })
+    }
+}
+
+impl AudioBackend for OfflineBackend {
+    type Stream = ();
+
+    fn format(&self) -> OutputFormat {
+        self.format
+    }
+
+    fn start(self, renderer: Renderer, _: SyncSender<StreamFault>) -> Result<(), String> {
+        let mut slot = self.renderer.lock().map_err(|e| e.to_string())?;
+        *slot = Some(renderer);
+        Ok(())
+    }
+}
+
+impl OfflineOutput {
+    /// Renders `frames` interleaved frames, as one callback of that size would.
+    /// Silent until the engine has started.
+    pub fn render(&self, frames: usize) -> Vec<f32> {
+        let mut data = vec![0.0; frames * self.channels];
+        if let Ok(mut renderer) = self.renderer.lock() {
+            if let Some(renderer) = renderer.as_mut() {
+                renderer.render(&mut data);
+            }
+        }
+        data
+    }
+}
+
+pub struct AudioEngine {
+    state: Arc<Mutex<AudioEngineState>>,
+    stream: Arc<Mutex<Option<StreamHandle>>>,
+    voice_ended: SyncSender<VoiceEnded>, // Handed to every new mixer
+    stream_fault: SyncSender<StreamFault>, // Handed to every new stream
+    handoff: Arc<Mutex<Handoff>>,        // Voices and loop between two streams
+    transport: Arc<TransportShare>,
+    seq: SeqLink,
+    seq_steps: Mutex<Option<Receiver<SeqStep>>>,
+    count_in_tick: SyncSender<CountInTick>, // Handed to every new mixer
+    count_in_ticks: Mutex<Option<Receiver<CountInTick>>>,
+    recording: Mutex<Option<RecordingHandle>>, // The master recording under way
+    voice_events: Mutex<Option<Receiver<VoiceEnded>>>,
+    stream_faults: Mutex<Option<Receiver<StreamFault>>>,
+    meter: LevelMeter,
+    counters: Arc<CallbackCounters>,
+    commands: Arc<ArrayQueue<Command>>,
+    level_queues: Arc<LevelQueues>,
+    mixer_levels: Mutex<MixerLevels>, // Newest snapshot taken from `level_queues`
+    decode_queue: Arc<DecodeQueue>,
+}
+
+// Tauri keeps the engine in managed state, shared across threads
+const _: fn() = || {
+    fn shared<T: Send + Sync>() {}
+    shared::<AudioEngine>();
+};
+
+/// Published levels, double-buffered: `publish_levels` refills the back
+/// snapshot and swaps it to the front, where readers take a reference to it.
+/// A back snapshot a reader still holds is replaced rather than refilled.
+/// Neither side touches the engine state.
+#[derive(Default)]
+struct LevelMeter {
+    front: Mutex<Arc<LevelsResponse>>,
+    back: Mutex<Arc<LevelsResponse>>, // Also keeps writers (metering thread vs. polling) apart
+    slot_keys: Arc<Mutex<Vec<String>>>, // `PadSlots::keys`
+}
+
+impl AudioEngine {
+    /// Opens the default output device
+    #[cfg(feature = "cpal")]
+    pub fn new() -> Result<Self, String> {
+        Self::with_backend(CpalBackend::open_default()?)
+    }
+
+    /// Builds the engine on any output, e.g. `OfflineBackend` where there is no device
+    pub fn with_backend<B: AudioBackend>(backend: B) -> Result<Self, String> {
+        let sample_rate = backend.format().sample_rate;
+        Self::with_state(backend, AudioEngineState::new(sample_rate))
+    }
+
+    /// Builds the engine on `backend` around an existing state, e.g. the
+    /// copy `render_offline` plays
+    fn with_state<B: AudioBackend>(
+        backend: B,
+        mut state: AudioEngineState,
+    ) -> Result<Self, String> {
+        let format = backend.format();
+        let device_sample_rate = format.sample_rate;
+        let (voice_ended, voice_events) = mpsc::sync_channel(VOICE_EVENT_QUEUE);
+        let (stream_fault, stream_faults) = mpsc::sync_channel(STREAM_FAULT_QUEUE);
+
+        state.sample_rate = device_sample_rate;
+        let meter = LevelMeter {
+            slot_keys: Arc::clone(&state.pad_slots.keys),
+            ..Default::default()
+        };
+        let state = Arc::new(Mutex::new(state));
+
+        let commands = Arc::new(ArrayQueue::new(COMMAND_QUEUE));
+        let level_queues = Arc::new(LevelQueues {
+            ready: ArrayQueue::new(LEVEL_SNAPSHOTS),
+            free: ArrayQueue::new(LEVEL_SNAPSHOTS),
+        });
+        for _ in 1..LEVEL_SNAPSHOTS {
+            let _ = level_queues.free.push(MixerLevels::new());
+        }
+        let counters = Arc::new(CallbackCounters::default());
+        let handoff = Arc::new(Mutex::new(Handoff::default()));
+        let transport = Arc::new(TransportShare::new(lock(&state).master_bpm));
+        let (seq_step, seq_steps) = mpsc::sync_channel(sequencer::SEQ_EVENT_QUEUE);
+        let seq = sequencer::start_feeder(Arc::clone(&state), seq_step)?;
+        let (count_in_tick, count_in_ticks) = mpsc::sync_channel(metronome::COUNT_IN_QUEUE);
+        let mut mixer = Mixer::new(
+            device_sample_rate,
+            &commands,
+            &level_queues,
+            &voice_ended,
+            &counters,
+            &handoff,
+            &transport,
+            &seq,
+            &count_in_tick,
+        );
+        mixer.restore(&mut lock(&state));
+        let renderer = Renderer {
+            mixer,
+            channels: format.channels,
+        };
+        let stream = backend.start(renderer, stream_fault.clone())?;
+
+        Ok(Self {
+            state,
+            stream: Arc::new(Mutex::new(Some(StreamHandle(Box::new(stream))))),
+            voice_ended,
+            stream_fault,
+            handoff,
+            transport,
+            seq,
+            seq_steps: Mutex::new(Some(seq_steps)),
+            count_in_tick,
+            count_in_ticks: Mutex::new(Some(count_in_ticks)),
+            recording: Mutex::new(None),
+            voice_events: Mutex::new(Some(voice_events)),
+            stream_faults: Mutex::new(Some(stream_faults)),
+            meter,
+            counters,
+            commands,
+            level_queues,
+            mixer_levels: Mutex::new(MixerLevels::new()),
+            decode_queue: Arc::new(DecodeQueue::new(DEFAULT_DECODE_CONCURRENCY)),
+        })
+    }
+
+    /// Moves the output to `backend`, e.g. after the device was lost. The
+    /// old stream is closed first; its voices carry over, retimed when the
+    /// sample rate changed, and so does everything already queued for it.
+    /// The loop carries over too, unless the rate changed.
+    /// Returns the previous rate if it differs from the new one.
+    pub fn restart_output<B: AudioBackend>(&self, backend: B) -> Result<Option<u32>, String> {
+        let format = backend.format();
+        // Dropping the stream drops its mixer, which leaves the voices in `handoff`
+        drop(lock(&self.stream).take());
+        let previous = lock(&self.state).sample_rate;
+
+        let mut mixer = Mixer::new(
+            format.sample_rate,
+            &self.commands,
+            &self.level_queues,
+            &self.voice_ended,
+            &self.counters,
+            &self.handoff,
+            &self.transport,
+            &self.seq,
+            &self.count_in_tick,
+        );
+        mixer.restore(&mut lock(&self.state));
+        let handoff = std::mem::take(&mut *lock(&self.handoff));
+        for mut voice in handoff.voices {
+            voice.retime(format.sample_rate);
+            mixer.voices.insert(voice, &mut mixer.retired);
+        }
+        match handoff.looper {
+            // A loop is so many frames long, which only fits its own rate
+            Some(looper) if previous == format.sample_rate => mixer.looper = looper,
+            Some(looper) if !looper.idle() => {
+                info!("Looper cleared: the output sample rate changed");
+                lock(&self.state).looper = LooperInfo::default();
+            }
+            _ => {}
+        }
+        let renderer = Renderer {
+            mixer,
+            channels: format.channels,
+        };
+        // A failed start drops the mixer, so the voices wait for the next try
+        let stream = backend.start(renderer, self.stream_fault.clone())?;
+        *lock(&self.stream) = Some(StreamHandle(Box::new(stream)));
+        lock(&self.state).sample_rate = format.sample_rate;
+        // The new mixer's lanes start over, each on its next grid line
+        for lane in LANES {
+            if *lane.running(&mut lock(&self.state)) {
+                self.start_lane(lane)?;
+            }
+        }
+        if previous != format.sample_rate {
+            info!(
+                previous,
+                sample_rate = format.sample_rate,
+                "Output sample rate changed"
+            );
+        }
+        Ok((previous != format.sample_rate).then_some(previous))
+    }
+
+    /// Rate of the current output
+    pub fn sample_rate(&self) -> u32 {
+        lock(&self.state).sample_rate
+    }
+
+    /// `restart_output` on the system's current default device
+    #[cfg(feature = "cpal")]
+    pub fn reopen_default_output(&self) -> Result<Option<u32>, String> {
+        self.restart_output(CpalBackend::open_default()?)
+    }
+
+    /// Like `reopen_default_output`, onto the device called `name`. An
+    /// unknown name leaves the output as it is.
+    #[cfg(feature = "cpal")]
+    pub fn reopen_output_named(&self, name: &str) -> Result<Option<u32>, String> {
+        self.restart_output(CpalBackend::open_named(name)?)
+    }
+
+    // REPLACED THIS BLOCK WITH THE ONE BELOW THIS ONE FOR OPTIMIZATION VIA BPM CACHING
+    /*
+    pub async fn load_sound(&self, key: String, path: &str) -> Result<LoadResult, String> {
+        let path_clone = path.to_string();
+        let buffer = tokio::task::spawn_blocking(move || decode_file(&path_clone))
+            .await
+            .map_err(|e| e.to_string())??;
+
+        let result = LoadResult {
+            duration: buffer.duration,
+            bpm: buffer.bpm,
+            waveform: buffer.waveform.clone(),
+        };
+        let mut state = lock(&self.state);
+        state.sound_bank.insert(key, Arc::new(buffer));
+        Ok(result)
+    }
+    */
+    // END OF REPLACED BLOCK
+
+    #[tracing::instrument(skip_all, fields(pad = %key, path = %path))]
+    pub async fn load_sound(
+        &self,
+        key: String,
+        path: &str,
+        cached_bpm: Option<f32>,
+        options: LoadOptions,
+        on_progress: impl Fn(LoadProgress) + Send + 'static,
+    ) -> Result<LoadResult, LoadError> {
+        let path_clone = path.to_string();
+        let cancel = Arc::new(AtomicU8::new(LOAD_RUNNING));
+        let (generation, decode_limit) = {
+            let mut state = lock(&self.state);
+            state.load_generation += 1;
+            let generation = state.load_generation;
+            let pending = PendingLoad {
+                generation,
+                cancel: Arc::clone(&cancel),
+            };
+            // The pad's previous load stops at its next packet
+            if let Some(previous) = state.loading.insert(key.clone(), pending) {
+                previous.cancel.store(LOAD_SUPERSEDED, Ordering::Relaxed);
+            }
+            (generation, state.decode_limit)
+        };
+        let mut reporter = LoadReporter {
+            state: Arc::clone(&self.state),
+            key: key.clone(),
+            generation,
+            on_progress,
+            last: None,
+        };
+
+        // Wait for a decode slot so a whole kit loading at once cannot starve the callback
+        let mut line = self.decode_queue.enter(options.jump_queue);
+        let turn = loop {
+            match line.recv().await {
+                Some(Turn::Queued(position)) => reporter.queued(position),
+                Some(Turn::Go(turn)) => break turn,
+                None => return Err("[Inner Cosmos] Decode queue closed".to_string().into()),
+            }
+        };
+        if cancel.load(Ordering::Relaxed) != LOAD_RUNNING {
+            return Err(cancelled_load(&cancel));
+        }
+        let decode_cancel = Arc::clone(&cancel);
+
+        // 1. Fast phase: decode + waveform only. BPM analysis runs later via run_analysis.
+        let decoded = tokio::task::spawn_blocking(move || {
+            let _turn = turn; // Held until the decode is done, even if the load is dropped
+            decode_file(
+                &path_clone,
+                decode_limit,
+                &decode_cancel,
+                &mut |stage, percent| reporter.report(stage, percent),
+            )
+        })
+        .await
+        .map_err(|e| LoadError::from(e.to_string()));
+
+        let mut state = lock(&self.state);
+        // A superseded decode that finished anyway must not replace the newer pick
+        if state.loading.get(&key).map(|pending| pending.generation) != Some(generation) {
+            return Err(cancelled_load(&cancel));
+        }
+        state.loading.remove(&key);
+        let buffer = decoded??;
+
+        // 2. THE OVERRIDE: If the Bureau already knows the BPM, there is nothing to analyze.
+        let status = match cached_bpm {
+            Some(bpm) => {
+                debug!("Skipping Analysis for {}. Using Cache: {}", key, bpm);
+                AnalysisStatus::Complete {
+                    bpm,
+                    raw_bpm: bpm,
+                    bpm_confidence: 1.0,
+                    bpm_source: BpmSource::Cached,
+                    bpm_error: None,
+                    key_detected: None,
+                }
+            }
+            None => AnalysisStatus::Pending,
+        };
+
+        // With auto-trim the suggested pad region skips the silent head and tail
+        let (start_time, end_time) = if options.auto_trim {
+            (buffer.trim_start, buffer.trim_end)
+        } else {
+            (0.0, buffer.duration)
+        };
+
+        let mut result = LoadResult {
+            duration: buffer.duration,
+            bpm: cached_bpm,
+            raw_bpm: cached_bpm,
+            bpm_confidence: cached_bpm.map(|_| 1.0),
+            bpm_source: cached_bpm.map(|_| BpmSource::Cached),
+            waveform: buffer.waveform.clone(),
+            waveform_left: buffer.waveform_left.clone(),
+            waveform_right: buffer.waveform_right.clone(),
+            trim_start: buffer.trim_start,
+            trim_end: buffer.trim_end,
+            start_time,
+            end_time,
+            lufs: buffer.lufs,
+            loudness_gain_db: 0.0,
+            loudness_gain_clamped: false,
+            embedded_loop_start: buffer.embedded_loop.map(|(start, _)| start),
+            embedded_loop_end: buffer.embedded_loop.map(|(_, end)| end),
+            root_note: buffer.root_note,
+            settings: None,
+            tags: buffer.tags.clone(),
+            decode_mode: DecodeMode::Full,
+            warning: None,
+            decode_warnings: buffer.decode_warnings.clone(),
+        };
+        for warning in &buffer.decode_warnings {
+            warn!("{}: {}", key, warning);
+        }
+        if buffer.truncated {
+            let warning = format!(
+                "Only the first {:.0} s fit in the {} MB decode limit",
+                buffer.duration,
+                decode_limit * std::mem::size_of::<f32>() / (1024 * 1024)
+            );
+            warn!("{}: {}", key, warning);
+            result.decode_mode = DecodeMode::Truncated;
+            result.warning = Some(warning);
+        }
+
+        // 3. Loudness: store a makeup gain so the pad plays back at the requested LUFS
+        let mut gain_db = options.gain_trim_db;
+        if let (Some(target), Some(measured)) = (options.loudness_target, buffer.lufs) {
+            let (makeup_db, clamped) = loudness_makeup_db(target, measured);
+            if clamped {
+                info!(
+                    "Makeup gain for {} clamped to +{} dB (measured {:.1} LUFS)",
+                    key, MAX_LOUDNESS_GAIN_DB, measured
+                );
+            }
+            result.loudness_gain_db = makeup_db;
+            result.loudness_gain_clamped = clamped;
+            gain_db += makeup_db;
+        }
+        if gain_db != 0.0 {
+            state
+                .makeup_gains
+                .insert(key.clone(), 10f32.powf(gain_db / 20.0));
+        } else {
+            state.makeup_gains.remove(&key);
+        }
+
+        // A fresh load invalidates any half/double correction made on the previous sample
+        state.bpm_overrides.remove(&key);
+        state.file_cache.insert(
+            PathBuf::from(path),
+            FileAnalysis {
+                duration: buffer.duration,
+                bpm: cached_bpm,
+            },
+        );
+        state.analysis.insert(
+            key.clone(),
+            PadAnalysis {
+                generation,
+                path: PathBuf::from(path),
+                status,
+            },
+        );
+        state.sound_bank.insert(key, BankEntry::new(buffer));
+
+        Ok(result)
+    }
+
+    /// Stops the pad's unfinished load, which then fails with `Cancelled`.
+    /// Returns whether there was one.
+    pub fn cancel_load(&self, key: &str) -> Result<bool, String> {
+        let mut state = lock(&self.state);
+        Ok(match state.loading.remove(key) {
+            Some(pending) => {
+                pending.cancel.store(LOAD_CANCELLED, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        })
+    }
+
+    /// Background phase of a load: runs BPM/key analysis for a pad whose analysis is pending.
+    /// Returns `None` if nothing was pending or the pad was reloaded while analyzing,
+    /// in which case the stale result is discarded.
+    pub async fn run_analysis(&self, key: &str) -> Option<AnalysisComplete> {
+        let (generation, buffer, bpm_range) = {
+            let state = lock(&self.state);
+            let pad = state.analysis.get(key)?;
+            if !matches!(pad.status, AnalysisStatus::Pending) {
+                return None;
+            }
+            (
+                pad.generation,
+                Arc::clone(&state.sound_bank.get(key)?.buffer),
+                state.bpm_range,
+            )
+        };
+
+        let label = key.to_string();
+        let outcome = tokio::task::spawn_blocking(move || analyze_bpm(&buffer, bpm_range, &label))
+            .await
+            .ok()?;
+
+        let mut state = lock(&self.state);
+        let pad = state.analysis.get_mut(key)?;
+        if pad.generation != generation {
+            debug!("Discarding stale analysis for {}", key);
+            return None;
+        }
+
+        pad.status = AnalysisStatus::Complete {
+            bpm: outcome.bpm,
+            raw_bpm: outcome.raw_bpm,
+            bpm_confidence: outcome.confidence,
+            bpm_source: outcome.source,
+            bpm_error: outcome.error.clone(),
+            key_detected: outcome.key_detected.clone(),
+        };
+        // A placeholder is no BPM to show in the harbor
+        let path = pad.path.clone();
+        if let Some(file) = state.file_cache.get_mut(&path) {
+            file.bpm = (outcome.source != BpmSource::Fallback).then_some(outcome.bpm);
+        }
+
+        Some(AnalysisComplete {
+            key: key.to_string(),
+            bpm: outcome.bpm,
+            raw_bpm: outcome.raw_bpm,
+            bpm_confidence: outcome.confidence,
+            bpm_source: outcome.source,
+            bpm_error: outcome.error,
+            key_detected: outcome.key_detected,
+        })
+    }
+
+    pub fn get_analysis_status(&self, key: &str) -> Result<AnalysisStatus, String> {
+        let state = lock(&self.state);
+        state
+            .analysis
+            .get(key)
+            .map(|pad| pad.status.clone())
+            .ok_or_else(|| "Sound not found".to_string())
+    }
+
+    /// Peak waveform of a pad at `points` resolution (clamped to
+    /// `WAVEFORM_POINTS_RANGE`), computed on first request and cached with the
+    /// buffer. Empty when the pad is not loaded.
+    pub fn get_buffer_waveform(
+        &self,
+        key: &str,
+        channel: WaveformChannel,
+        points: usize,
+    ) -> Arc<Vec<f32>> {
+        let points = points.clamp(*WAVEFORM_POINTS_RANGE.start(), *WAVEFORM_POINTS_RANGE.end());
+        let (buffer, waveforms) = match lock(&self.state).sound_bank.get(key) {
+            Some(entry) => (Arc::clone(&entry.buffer), Arc::clone(&entry.waveforms)),
+            None => return Arc::default(),
+        };
+        if let Some(waveform) = waveforms
+            .lock()
+            .ok()
+            .and_then(|waveforms| waveforms.get(&(channel, points)).cloned())
+        {
+            return waveform;
+        }
+
+        // Outside both locks: a long file at a high resolution takes a while
+        let lane = match channel {
+            WaveformChannel::Mixed => None,
+            _ if buffer.channels < 2 => None,
+            WaveformChannel::Left => Some(0),
+            WaveformChannel::Right => Some(1),
+        };
+        let waveform = Arc::new(peak_waveform(&buffer.data, buffer.channels, lane, points));
+        // A reload meanwhile replaced the entry; this lands in the orphaned cache
+        if let Ok(mut waveforms) = waveforms.lock() {
+            waveforms.insert((channel, points), Arc::clone(&waveform));
+        }
+        waveform
+    }
+
+    /// Snaps `time` to the closest zero crossing within `ZERO_CROSSING_WINDOW` seconds,
+    /// returning the original time when the window has none.
+    pub fn find_zero_crossing(
+        &self,
+        key: &str,
+        time: f32,
+        direction: SearchDirection,
+    ) -> Result<f32, String> {
+        let buffer = {
+            let state = lock(&self.state);
+            Arc::clone(&state.sound_bank.get(key).ok_or("Sound not found")?.buffer)
+        };
+        Ok(zero_crossing(&buffer, time, direction))
+    }
+
+    /// Batch variant: snaps both loop markers in one call.
+    /// The start searches forward and the end backward so the region never grows.
+    pub fn snap_loop_points(
+        &self,
+        key: &str,
+        start_time: f32,
+        end_time: f32,
+    ) -> Result<LoopPoints, String> {
+        let buffer = {
+            let state = lock(&self.state);
+            Arc::clone(&state.sound_bank.get(key).ok_or("Sound not found")?.buffer)
+        };
+        Ok(LoopPoints {
+            start_time: zero_crossing(&buffer, start_time, SearchDirection::Forward),
+            end_time: zero_crossing(&buffer, end_time, SearchDirection::Backward),
+        })
+    }
+
+    /// Copies `start_time..end_time` of a pad with its volume and makeup gain baked
+    /// in, plus optional linear fades at both edges. Heavy work happens after the
+    /// lock is released, so callers should run this on a blocking worker.
+    pub fn render_region(
+        &self,
+        key: &str,
+        start_time: f32,
+        end_time: f32,
+        fade_ms: f32,
+    ) -> Result<RenderedRegion, String> {
+        let (buffer, gain) = {
+            let state = lock(&self.state);
+            let buffer = Arc::clone(&state.sound_bank.get(key).ok_or("Sound not found")?.buffer);
+            let volume = state.pad_params.get(key).map(|p| p.volume).unwrap_or(1.0);
+            let makeup = state.makeup_gains.get(key).copied().unwrap_or(1.0);
+            (buffer, volume * makeup)
+        };
+
+        let channels = buffer.channels as usize;
+        let frames = buffer.data.len() / channels;
+        let to_frame = |t: f32| ((t.max(0.0) * buffer.sample_rate as f32) as usize).min(frames);
+        let (first, last) = (to_frame(start_time), to_frame(end_time));
+        if last <= first {
+            return Err(format!("Empty region {}-{}", start_time, end_time));
+        }
+
+        let length = last - first;
+        let fade = ((fade_ms / 1000.0 * buffer.sample_rate as f32) as usize).min(length / 2);
+        let mut data = buffer.data[first * channels..last * channels].to_vec();
+        for (frame, samples) in data.chunks_mut(channels).enumerate() {
+            let edge = frame.min(length - 1 - frame);
+            let envelope = if edge < fade {
+                edge as f32 / fade as f32
+            } else {
+                1.0
+            };
+            for sample in samples {
+                *sample *= gain * envelope;
+            }
+        }
+
+        Ok(RenderedRegion {
+            data,
+            sample_rate: buffer.sample_rate,
+            channels: buffer.channels,
+        })
+    }
+
+    /// Bounces `bars` bars of the step patterns, the take or both from a copy
+    /// of the engine, faster than real time and without touching the live
+    /// output. `on_progress` hears of every bar; run this on a blocking worker.
+    pub fn render_offline(
+        &self,
+        source: RenderSource,
+        bars: u32,
+        on_progress: impl FnMut(RenderProgress),
+    ) -> Result<OfflineRender, String> {
+        let state = render::snapshot(&lock(&self.state))?;
+        render::render(state, source, bars, on_progress)
+    }
+
+    /// Applies a half/double tempo correction to a loaded pad and returns the new BPM.
+    /// The corrected value takes precedence over `sample_bpm` on the next synced trigger.
+    pub fn bpm_multiply(&self, key: &str, factor: f32) -> Result<f32, String> {
+        if factor != 0.5 && factor != 2.0 {
+            return Err(format!(
+                "Invalid BPM factor {} (expected 0.5 or 2.0)",
+                factor
+            ));
+        }
+
+        let mut state = lock(&self.state);
+        let current = match state.bpm_overrides.get(key) {
+            Some(bpm) => *bpm,
+            None => match &state.analysis.get(key).ok_or("Sound not found")?.status {
+                AnalysisStatus::Complete { bpm, .. } => *bpm,
+                AnalysisStatus::Pending => return Err("BPM analysis still running".to_string()),
+            },
+        };
+
+        let corrected = current * factor;
+        state.bpm_overrides.insert(key.to_string(), corrected);
+        if let Some(path) = state.analysis.get(key).map(|pad| pad.path.clone()) {
+            if let Some(file) = state.file_cache.get_mut(&path) {
+                file.bpm = Some(corrected);
+            }
+        }
+        Ok(corrected)
+    }
+
+    /// Drops cached analysis for a file that left the harbor and returns the pads
+    /// still playing it. Their buffers stay valid since the PCM is in memory.
+    pub fn forget_file(&self, path: &Path) -> Vec<String> {
+        let target = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let same =
+            |p: &PathBuf| p == path || p.canonicalize().map(|c| c == target).unwrap_or(false);
+
+        let mut state = lock(&self.state);
+        state.file_cache.retain(|p, _| !same(p));
+        let mut pads: Vec<String> = state
+            .analysis
+            .iter()
+            .filter(|(key, pad)| same(&pad.path) && state.sound_bank.contains_key(*key))
+            .map(|(key, _)| key.clone())
+            .collect();
+        pads.sort();
+        pads
+    }
+
+    /// Follows a file moved inside the harbor so cached analysis and pad
+    /// bookkeeping keep pointing at it.
+    pub fn rename_file(&self, from: &Path, to: &Path) {
+        let mut state = lock(&self.state);
+        if let Some(file) = state.file_cache.remove(from) {
+            state.file_cache.insert(to.to_path_buf(), file);
+        }
+        for pad in state.analysis.values_mut() {
+            if pad.path == from {
+                pad.path = to.to_path_buf();
+            }
+        }
+    }
+
+    /// Every loaded pad with its source file, last params and effective BPM
+    pub fn pad_snapshot(&self) -> Vec<PadSnapshot> {
+        let state = lock(&self.state);
+        let mut pads: Vec<PadSnapshot> = state
+            .analysis
+            .iter()
+            .filter(|(key, _)| state.sound_bank.contains_key(*key))
+            .map(|(key, pad)| PadSnapshot {
+                key: key.clone(),
+                path: pad.path.clone(),
+                params: state.pad_params.get(key).cloned(),
+                defaults: state.pad_defaults.get(key).cloned(),
+                // A placeholder saved here would come back as a trusted cached BPM
+                bpm: state.bpm_overrides.get(key).copied().or(match pad.status {
+                    AnalysisStatus::Complete {
+                        bpm, bpm_source, ..
+                    } if bpm_source != BpmSource::Fallback => Some(bpm),
+                    _ => None,
+                }),
+            })
+            .collect();
+        pads.sort_by(|a, b| a.key.cmp(&b.key));
+        pads
+    }
+
+    /// Restores the remembered params of a pad without playing it
+    pub fn set_pad_params(&self, key: &str, params: PlayParams) {
+        lock(&self.state).pad_params.insert(key.to_string(), params);
+    }
+
+    /// Last params a pad was played or updated with
+    pub fn pad_params(&self, key: &str) -> Option<PlayParams> {
+        lock(&self.state).pad_params.get(key).cloned()
+    }
+
+    /// Stores (or with None, drops) the defaults a pad's omitted play fields
+    /// come from. The sends stored with the old defaults are kept.
+    pub fn set_pad_defaults(&self, key: &str, params: Option<PartialPlayParams>) {
+        let mut state = lock(&self.state);
+        match params {
+            Some(mut params) => {
+                if let Some(stored) = state.pad_defaults.get(key) {
+                    params.delay_send = stored.delay_send.or(params.delay_send);
+                    params.sends_muted = stored.sends_muted.or(params.sends_muted);
+                }
+                state.pad_defaults.insert(key.to_string(), params)
+            }
+            None => state.pad_defaults.remove(key),
+        };
+    }
+
+    pub fn pad_defaults(&self) -> HashMap<String, PartialPlayParams> {
+        lock(&self.state).pad_defaults.clone()
+    }
+
+    /// Replaces every pad's defaults, e.g. with the persisted table at startup
+    pub fn replace_pad_defaults(&self, defaults: HashMap<String, PartialPlayParams>) {
+        lock(&self.state).pad_defaults = defaults;
+    }
+
+    /// Completes partial play params from the pad's stored defaults, or from
+    /// neutral values (full volume, no envelope, the whole file) without any
+    pub fn resolve_play_params(
+        &self,
+        key: &str,
+        partial: PartialPlayParams,
+    ) -> Result<PlayParams, String> {
+        resolve_params(&lock(&self.state), key, partial)
+    }
+
+    /// (master volume, master BPM)
+    pub fn master_settings(&self) -> (f32, f32) {
+        let state = lock(&self.state);
+        (state.master_volume, state.master_bpm)
+    }
+
+    /// Snapshot of everything known about previously decoded files, keyed by path
+    pub fn file_analysis_snapshot(&self) -> HashMap<PathBuf, FileAnalysis> {
+        lock(&self.state).file_cache.clone()
+    }
+
+    /// Plays a pad, on the next step of the launch grid when there is one
+    #[tracing::instrument(skip_all, fields(pad = %key))]
+    pub fn play_sound(&self, key: String, mut params: PlayParams) -> Result<(), String> {
+        let corrected = params.sanitize();
+        if !corrected.is_empty() {
+            warn!("{}: corrected play params {:?}", key, corrected);
+        }
+        let mut state = lock(&self.state);
+        let voice = pad_voice(&mut state, key.clone(), &mut params)?;
+        state.pad_params.insert(key.clone(), params.clone());
+        drop(state);
+        self.send(Command::Launch(voice))?;
+        self.record(&key, PerformanceAction::Play { params });
+        Ok(())
+    }
+
+    /// Short sine tick outside the sound bank, used as UI confirmation (e.g.
+    /// the listener toggle hotkey). Fades in and out so it never clicks.
+    pub fn play_blip(&self, frequency: f32) -> Result<(), String> {
+        const BLIP_SECONDS: f32 = 0.06;
+        const BLIP_GAIN: f32 = 0.25;
+        let mut state = lock(&self.state);
+        let sample_rate = state.sample_rate;
+        let frames = (sample_rate as f32 * BLIP_SECONDS) as usize;
+        let data: Vec<f32> = (0..frames)
+            .map(|i| {
+                let t = i as f32 / sample_rate as f32;
+                let envelope = (std::f32::consts::PI * i as f32 / frames as f32).sin();
+                (std::f32::consts::TAU * frequency * t).sin() * envelope * BLIP_GAIN
+            })
+            .collect();
+        let buffer = Arc::new(AudioBuffer {
+            data,
+            sample_rate,
+            channels: 1,
+            duration: BLIP_SECONDS,
+            waveform: Vec::new(),
+            waveform_left: Vec::new(),
+            waveform_right: Vec::new(),
+            trim_start: 0.0,
+            trim_end: BLIP_SECONDS,
+            lufs: None,
+            embedded_loop: None,
+            root_note: None,
+            tags: FileTags::default(),
+            truncated: false,
+            decode_warnings: Vec::new(),
+        });
+
+        let slot = state.pad_slots.get_or_assign(BLIP_KEY)?;
+        state.next_voice_id += 1;
+        let id = state.next_voice_id;
+        drop(state);
+
+        self.send(Command::Play(Voice {
+            id,
+            key: BLIP_KEY.to_string(),
+            slot,
+            buffer,
+            position: 0.0,
+            playback_rate: 1.0,
+            speed: 1.0,
+            device_rate: sample_rate,
+            looping: false,
+            loop_start: 0.0,
+            loop_end: frames as f64,
+            gain: Slew::new(1.0, sample_rate, LEVEL_SLEW_MS),
+            makeup_gain: 1.0,
+            velocity: 1.0,
+            attack: 0.0,
+            release: 0.0,
+            attack_samples: 0,
+            release_samples: 0,
+            crush: Crusher::new(CrushSettings::OFF, sample_rate),
+            filter: VoiceFilter::new(FilterSettings::OPEN, sample_rate),
+            drive: Drive::new(0.0, sample_rate),
+            send: Slew::new(0.0, sample_rate, LEVEL_SLEW_MS),
+            stopped: false,
+            fade_position: 0,
+            is_fading_out: false,
+            fade_start_gain: 1.0,
+            fade_out_pos: 0,
+            current_peak: 0.0,
+            meter_peak: 0.0,
+            meter_samples: Vec::with_capacity(METER_SAMPLES),
+            stop_command: false,
+            custom_release_set: false,
+            declick_from: 0.0,
+            declick_left: 0,
+            end_reason: VoiceEndReason::Natural,
+        }))
+    }
+
+    /// Fades out the pad's voices. A pad with no sound loaded is an error;
+    /// one that is loaded but silent matches nothing.
+    #[tracing::instrument(skip_all, fields(pad = %key, release = ?effective_release))]
+    pub fn stop_sound(
+        &self,
+        key: String,
+        effective_release: Option<f32>,
+    ) -> Result<VoiceMatch, String> {
+        let state = lock(&self.state);
+        if !state.sound_bank.contains_key(&key) {
+            return Err(not_loaded(&key));
+        }
+        // A pad that never played has no voice to stop
+        let slot = match state.pad_slots.get(&key) {
+            Some(slot) => slot,
+            None => return Ok(VoiceMatch::default()),
+        };
+        drop(state);
+        self.send(Command::Stop {
+            slot,
+            release: effective_release,
+        })?;
+        self.record(
+            &key,
+            PerformanceAction::Stop {
+                release: effective_release,
+            },
+        );
+        Ok(VoiceMatch {
+            matched: self.playing_voices(slot),
+            corrected: Vec::new(),
+        })
+    }
+
+    /// Applies new params to the pad's voices and keeps them for the pad.
+    /// Errors like `stop_sound`; `corrected` also lists a clamped region.
+    #[tracing::instrument(skip_all, fields(pad = %key))]
+    pub fn update_voice(&self, key: String, mut params: PlayParams) -> Result<VoiceMatch, String> {
+        let mut corrected = params.sanitize();
+        let mut state = lock(&self.state);
+        let duration = match state.sound_bank.get(&key) {
+            Some(entry) => entry.buffer.duration,
+            None => return Err(not_loaded(&key)),
+        };
+        let region = clamp_region(params.start_time, params.end_time, duration)
+            .map_err(|e| format!("{}: {}", key, e))?;
+        if region.0 != params.start_time {
+            corrected.push("startTime");
+        }
+        if region.1 != params.end_time && !corrected.contains(&"endTime") {
+            corrected.push("endTime");
+        }
+        (params.start_time, params.end_time) = region;
+        // Sends change through `set_send` and `mute_sends` only
+        if let Some(stored) = state.pad_params.get(&key) {
+            keep_sends(&mut params, stored);
+        }
+        state.pad_params.insert(key.clone(), params.clone());
+        let slot = state.pad_slots.get(&key);
+        drop(state);
+        if !corrected.is_empty() {
+            warn!("{}: corrected play params {:?}", key, corrected);
+        }
+        let slot = match slot {
+            Some(slot) => slot,
+            None => {
+                return Ok(VoiceMatch {
+                    matched: 0,
+                    corrected,
+                })
+            }
+        };
+
+        self.send(Command::Update {
+            slot,
+            volume: params.volume,
+            looping: params.looping,
+            start_time: params.start_time,
+            end_time: params.end_time,
+            crush: CrushSettings::of(&params),
+            filter: FilterSettings::of(&params),
+            drive: params.drive,
+        })?;
+        Ok(VoiceMatch {
+            matched: self.playing_voices(slot),
+            corrected,
+        })
+    }
+
+    /// Voices of a pad slot in the newest mixer snapshot
+    fn playing_voices(&self, slot: usize) -> usize {
+        let latest = self.mixer_levels();
+        latest
+            .active
+            .iter()
+            .filter(|&&active| active == slot)
+            .count()
+    }
+
+    /// Releases every voice the way `stop_sound` releases a pad's, with the
+    /// `set_stop_all_release` time if there is one
+    pub fn stop_all(&self) {
+        let release = lock(&self.state).stop_all_release;
+        if let Err(e) = self.send(Command::StopAll { release }) {
+            warn!("Stop all dropped: {}", e);
+        }
+    }
+
+    /// Release time `stop_all` uses for every voice; None keeps each voice's own
+    pub fn set_stop_all_release(&self, release: Option<f32>) -> Result<(), String> {
+        if let Some(release) = release {
+            if !(release.is_finite() && release >= 0.0) {
+                return Err(format!("{} s is not a usable release", release));
+            }
+        }
+        lock(&self.state).stop_all_release = release;
+        Ok(())
+    }
+
+    /// Clamped to 0..MAX_MASTER_VOLUME; NaN keeps the current volume
+    pub fn set_master_volume(&self, volume: f32) {
+        if volume.is_nan() {
+            return;
+        }
+        let volume = volume.clamp(0.0, MAX_MASTER_VOLUME);
+        lock(&self.state).master_volume = volume;
+        if let Err(e) = self.send(Command::MasterVolume(volume)) {
+            warn!("Master volume dropped: {}", e);
+        }
+    }
+
+    /// Sets the master compressor; a ratio of 1 turns it off
+    pub fn set_compressor(&self, settings: CompressorSettings) -> Result<(), String> {
+        settings.check()?;
+        lock(&self.state).compressor = settings;
+        self.send(Command::Compressor(settings))
+    }
+
+    /// Sets the delay bus the pads send into
+    pub fn set_delay(&self, settings: DelaySettings) -> Result<(), String> {
+        if !(0.0..=MAX_DELAY_FEEDBACK).contains(&settings.feedback) {
+            return Err(format!(
+                "[Social Noise] A delay feedback of {} is outside 0..={}",
+                settings.feedback, MAX_DELAY_FEEDBACK
+            ));
+        }
+        lock(&self.state).delay = settings;
+        self.send(Command::Delay(settings))
+    }
+
+    /// Sets how much of a pad goes to `bus` (0-1), for the voices playing
+    /// and every later one. The pad's params and defaults keep it.
+    pub fn set_send(&self, key: &str, bus: SendBus, amount: f32) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&amount) {
+            return Err(format!(
+                "[Social Noise] A send of {} is outside 0..=1",
+                amount
+            ));
+        }
+        self.update_sends(key, |params| match bus {
+            SendBus::Delay => params.delay_send = amount,
+        })
+    }
+
+    /// Silences (or brings back) every send of a pad, leaving its dry signal
+    /// alone. The send amounts are kept.
+    pub fn mute_sends(&self, key: &str, muted: bool) -> Result<(), String> {
+        self.update_sends(key, |params| params.sends_muted = muted)
+    }
+
+    fn update_sends(&self, key: &str, change: impl Fn(&mut PlayParams)) -> Result<(), String> {
+        let mut state = lock(&self.state);
+        if !state.sound_bank.contains_key(key) {
+            return Err(not_loaded(key));
+        }
+        let mut params = match state.pad_params.get(key) {
+            Some(params) => params.clone(),
+            None => resolve_params(&state, key, PartialPlayParams::default())?,
+        };
+        change(&mut params);
+        // Only the sends are stored, so a pad without defaults keeps its
+        // neutral values for everything else
+        let mut sends = resolve_params(&state, key, PartialPlayParams::default())?;
+        change(&mut sends);
+        let mut defaults = state.pad_defaults.get(key).cloned().unwrap_or_default();
+        defaults.delay_send = Some(sends.delay_send);
+        defaults.sends_muted = Some(sends.sends_muted);
+        let level = send_level(&params);
+        state.pad_params.insert(key.to_string(), params);
+        state.pad_defaults.insert(key.to_string(), defaults);
+        let slot = state.pad_slots.get_or_assign(key)?;
+        self.send(Command::Send { slot, level })
+    }
+
+    /// Moves the crossfader, from 0 (side A only) to 1 (side B only). Pads on
+    /// neither side are not affected.
+    pub fn set_crossfader(&self, position: f32) -> Result<(), String> {
+        if position.is_nan() {
+            return Err("[Social Noise] The crossfader position is not a number".to_string());
+        }
+        let mut state = lock(&self.state);
+        state.crossfader = position.clamp(0.0, 1.0);
+        self.send(Command::Crossfade(
+            state.crossfade_curve.gains(state.crossfader),
+        ))
+    }
+
+    pub fn set_crossfade_curve(&self, curve: CrossfadeCurve) -> Result<(), String> {
+        let mut state = lock(&self.state);
+        state.crossfade_curve = curve;
+        self.send(Command::Crossfade(curve.gains(state.crossfader)))
+    }
+
+    /// Puts a pad on side A or B of the crossfader, or on neither (None),
+    /// also while it plays
+    pub fn set_pad_group(&self, key: &str, group: Option<CrossfadeGroup>) -> Result<(), String> {
+        let mut state = lock(&self.state);
+        let slot = state.pad_slots.get_or_assign(key)?;
+        self.send(Command::PadGroup { slot, group })?;
+        match group {
+            Some(group) => state.pad_groups.insert(key.to_string(), group),
+            None => state.pad_groups.remove(key),
+        };
+        Ok(())
+    }
+
+    /// Keeps the mix as it is in scene `slot`, replacing what was there
+    pub fn scene_save(&self, slot: usize) -> Result<Scene, String> {
+        let mut state = lock(&self.state);
+        let scene = Scene::capture(&state, slot);
+        state.scenes.save(scene.clone())?;
+        info!(slot, pads = scene.pads.len(), "Scene saved");
+        Ok(scene)
+    }
+
+    /// Morphs from the mix as it is to scene `slot` over `fade_seconds`, as
+    /// `scene_tick` moves it on; 0 applies the scene at once. Pads of the
+    /// scene that are not loaded any more are skipped.
+    pub fn scene_recall(&self, slot: usize, fade_seconds: f32) -> Result<SceneRecall, String> {
+        if !(fade_seconds >= 0.0 && fade_seconds.is_finite()) {
+            return Err(format!(
+                "[Social Noise] A fade of {} seconds is not possible",
+                fade_seconds
+            ));
+        }
+        let mut state = lock(&self.state);
+        let mut scene = state.scenes.get(slot)?.clone();
+        let skipped: Vec<String> = scene
+            .pads
+            .keys()
+            .filter(|key| !state.sound_bank.contains_key(*key))
+            .cloned()
+            .collect();
+        for key in &skipped {
+            warn!("Scene {}: {} is not loaded, skipped", slot, key);
+            scene.pads.remove(key);
+        }
+        let fading = fade_seconds > 0.0;
+        if fading {
+            let from = Scene::capture(&state, slot);
+            state.scenes.start(from, scene, fade_seconds);
+        } else {
+            state.scenes.morph = None;
+            drop(state);
+            self.apply_scene(&scene);
+        }
+        info!(slot, fade_seconds, "Scene recalled");
+        Ok(SceneRecall { skipped, fading })
+    }
+
+    /// Moves the morph under way on; false once there is none. Meant to be
+    /// called every ~16 ms while a recall is `fading`. Extra calls do no
+    /// harm, as the morph goes by the time since the recall.
+    pub fn scene_tick(&self) -> bool {
+        let step = lock(&self.state).scenes.step();
+        match step {
+            Some((scene, more)) => {
+                self.apply_scene(&scene);
+                more
+            }
+            None => false,
+        }
+    }
+
+    /// Every saved scene, by slot
+    pub fn scene_list(&self) -> Vec<Scene> {
+        lock(&self.state).scenes.list()
+    }
+
+    /// Replaces every scene, e.g. from a session
+    pub fn set_scenes(&self, scenes: Vec<Scene>) -> Result<(), String> {
+        lock(&self.state).scenes.replace(scenes)
+    }
+
+    /// Sends a whole mix: a recalled scene, or a step of the morph to it
+    fn apply_scene(&self, scene: &Scene) {
+        self.set_master_volume(scene.master_volume);
+        if lock(&self.state).master_bpm != scene.master_bpm {
+            if let Err(e) = self.set_master_bpm(scene.master_bpm) {
+                warn!("Scene {}: {}", scene.slot, e);
+            }
+        }
+        if let Err(e) = self.set_crossfader(scene.crossfader) {
+            warn!("Scene {}: {}", scene.slot, e);
+        }
+        for (key, pad) in &scene.pads {
+            let (group, sends) = {
+                let state = lock(&self.state);
+                let sends = state
+                    .pad_params
+                    .get(key)
+                    .map(|params| (params.delay_send, params.sends_muted));
+                (state.pad_groups.get(key).copied(), sends)
+            };
+            let mut applied = self.set_pad_mix(key, pad.volume, pad.filter_cutoff);
+            if applied.is_ok() && group != pad.group {
+                applied = self.set_pad_group(key, pad.group);
+            }
+            if let Some(amount) = pad.delay_send {
+                if applied.is_ok() && sends.map(|sends| sends.0) != Some(amount) {
+                    applied = self.set_send(key, SendBus::Delay, amount);
+                }
+            }
+            if let Some(muted) = pad.sends_muted {
+                if applied.is_ok() && sends.map(|sends| sends.1) != Some(muted) {
+                    applied = self.mute_sends(key, muted);
+                }
+            }
+            if let Err(e) = applied {
+                warn!("Scene {}: {}: {}", scene.slot, key, e);
+            }
+        }
+    }
+
+    /// Changes only the volume and (with Some) the filter cutoff of a pad's
+    /// params, playing or not
+    fn set_pad_mix(&self, key: &str, volume: f32, cutoff: Option<f32>) -> Result<(), String> {
+        let params = lock(&self.state).pad_params.get(key).cloned();
+        match params {
+            Some(params)
+                if params.volume == volume
+                    && cutoff.map_or(true, |cutoff| cutoff == params.filter_cutoff) =>
+            {
+                Ok(())
+            }
+            Some(params) => {
+                let filter_cutoff = cutoff.unwrap_or(params.filter_cutoff);
+                let params = PlayParams {
+                    volume,
+                    filter_cutoff,
+                    ..params
+                };
+                self.update_voice(key.to_string(), params).map(|_| ())
+            }
+            None => {
+                let mut state = lock(&self.state);
+                let partial = PartialPlayParams {
+                    volume: Some(volume),
+                    filter_cutoff: cutoff,
+                    ..Default::default()
+                };
+                let params = resolve_params(&state, key, partial)?;
+                state.pad_params.insert(key.to_string(), params);
+                Ok(())
+            }
+        }
+    }
+
+    /// Queues a command for the audio callback, which applies it at the
+    /// start of its next buffer
+    fn send(&self, command: Command) -> Result<(), String> {
+        self.commands
+            .push(command)
+            .map_err(|_| "[Inner Cosmos] Audio command queue full".to_string())
+    }
+
+    /// How many files may decode at once; queued loads start as slots free up
+    pub fn set_decode_concurrency(&self, permits: usize) -> Result<(), String> {
+        if !(1..=MAX_DECODE_CONCURRENCY).contains(&permits) {
+            return Err(format!(
+                "{} is outside 1..{}",
+                permits, MAX_DECODE_CONCURRENCY
+            ));
+        }
+        self.decode_queue.set_permits(permits);
+        Ok(())
+    }
+
+    /// Caps how much decoded audio one file may occupy; later loads stop there
+    pub fn set_decode_limit_mb(&self, limit_mb: usize) -> Result<(), String> {
+        let bytes = limit_mb
+            .checked_mul(1024 * 1024)
+            .filter(|&bytes| bytes > 0)
+            .ok_or_else(|| format!("{} MB is not a usable limit", limit_mb))?;
+        let mut state = lock(&self.state);
+        state.decode_limit = bytes / std::mem::size_of::<f32>();
+        Ok(())
+    }
+
+    pub fn set_bpm_range(&self, min: f32, max: f32) -> Result<(), String> {
+        // Folding needs at least one full octave of room to always land in range
+        if !(min > 0.0 && max >= min * 2.0) {
+            return Err(format!("Invalid BPM range {}-{}", min, max));
+        }
+        let mut state = lock(&self.state);
+        state.bpm_range = (min, max);
+        Ok(())
+    }
+
+    /// Sets the transport tempo, which has to be within SAMPLE_BPM_RANGE
+    pub fn set_master_bpm(&self, bpm: f32) -> Result<(), String> {
+        if !SAMPLE_BPM_RANGE.contains(&bpm) {
+            return Err(format!(
+                "[Social Noise] A tempo of {} BPM is outside {}..={}",
+                bpm,
+                SAMPLE_BPM_RANGE.start(),
+                SAMPLE_BPM_RANGE.end()
+            ));
+        }
+        let mut state = lock(&self.state);
+        state.master_bpm = bpm;
+        // Synced voices of the waiting loops were built for the old tempo
+        for lane in LANES {
+            self.restock(&mut state, lane);
+        }
+        drop(state);
+        if let Err(e) = self.send(Command::Tempo(bpm)) {
+            warn!("Transport tempo dropped: {}", e);
+        }
+        Ok(())
+    }
+
+    /// Delays every second step of the sequencer, quantized launches, beat
+    /// repeat and quantized recording by `swing` (0 to MAX_SWING) of a step,
+    /// from the next step on
+    pub fn set_swing(&self, swing: f32) -> Result<(), String> {
+        if !(0.0..=MAX_SWING).contains(&swing) {
+            return Err(format!(
+                "[Social Noise] A swing of {} is outside 0..={}",
+                swing, MAX_SWING
+            ));
+        }
+        lock(&self.state).swing = swing;
+        self.send(Command::Swing(swing))
+    }
+
+    /// Makes pad triggers wait for the next step of `division` (counted from
+    /// the bar line), or play at once with None
+    pub fn set_launch_quantize(&self, division: Option<StepDivision>) -> Result<(), String> {
+        lock(&self.state).launch_quantize = division;
+        self.send(Command::LaunchQuantize(division))
+    }
+
+    /// Retriggers `key` on every step of `division`, from the next pair of
+    /// steps until `beat_repeat_stop`. Repeating while a repeat runs moves
+    /// it to the new pad and note value.
+    pub fn beat_repeat_start(&self, key: String, division: StepDivision) -> Result<(), String> {
+        let mut state = lock(&self.state);
+        if !state.sound_bank.contains_key(&key) {
+            return Err(not_loaded(&key));
+        }
+        let previous = std::mem::replace(
+            &mut state.repeat,
+            BeatRepeat {
+                key: Some(key),
+                division,
+                running: true,
+            },
+        );
+        drop(state);
+        if let Err(e) = self.start_lane(Lane::Repeat) {
+            lock(&self.state).repeat = previous;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Stops repeating; the last trigger rings out
+    pub fn beat_repeat_stop(&self) -> Result<(), String> {
+        self.stop_lane(Lane::Repeat)
+    }
+
+    /// The master clock as of the last buffer. Anything that schedules by
+    /// tempo reads this rather than the system time.
+    pub fn transport(&self) -> Transport {
+        self.transport.read()
+    }
+
+    /// Sets a pad's pattern (none clears it), heard from the next loop on
+    pub fn seq_set_pattern(&self, key: String, steps: Vec<StepEvent>) -> Result<(), String> {
+        let mut state = lock(&self.state);
+        if !steps.is_empty() && !state.sound_bank.contains_key(&key) {
+            return Err(not_loaded(&key));
+        }
+        state.sequence.set_pattern(key, steps)?;
+        self.restock(&mut state, Lane::Steps);
+        Ok(())
+    }
+
+    /// Steps per loop and the note value of a step, from the next loop on
+    pub fn seq_set_length(&self, steps: usize, division: StepDivision) -> Result<(), String> {
+        let mut state = lock(&self.state);
+        state.sequence.set_length(steps, division)?;
+        self.restock(&mut state, Lane::Steps);
+        Ok(())
+    }
+
+    /// Starts the sequence from its first step, on the next step of the
+    /// master clock's grid. Starting it while it runs starts it over.
+    pub fn seq_start(&self) -> Result<(), String> {
+        // Running first, or the feeder could skip the loop after the first
+        let was_running = std::mem::replace(Lane::Steps.running(&mut lock(&self.state)), true);
+        if let Err(e) = self.start_lane(Lane::Steps) {
+            lock(&self.state).sequence.running = was_running;
+            return Err(e);
+        }
+        info!("Sequencer started");
+        Ok(())
+    }
+
+    /// Stops triggering; voices already playing carry on
+    pub fn seq_stop(&self) -> Result<(), String> {
+        self.stop_lane(Lane::Steps)?;
+        info!("Sequencer stopped");
+        Ok(())
+    }
+
+    /// Records every pad trigger and stop from now on, from the start of the
+    /// current bar, optionally quantized to `quantize`. With `overdub` the
+    /// hits go into the playing take instead, heard from its next loop.
+    pub fn pattern_record_start(
+        &self,
+        quantize: Option<StepDivision>,
+        overdub: bool,
+    ) -> Result<(), String> {
+        let beat = self.transport.read().beat;
+        let mut state = lock(&self.state);
+        let grid = quantize.map(|division| Grid::new(division, state.swing));
+        state.performance.record_start(beat, grid, overdub)?;
+        drop(state);
+        info!(?quantize, overdub, "Recording started");
+        Ok(())
+    }
+
+    /// Ends the recording and returns the take, which `pattern_play` plays
+    /// when given none
+    pub fn pattern_record_stop(&self) -> Result<Performance, String> {
+        let beat = self.transport.read().beat;
+        let take = lock(&self.state).performance.record_stop(beat)?;
+        info!(
+            events = take.events.len(),
+            beats = take.length,
+            "Recording stopped"
+        );
+        Ok(take)
+    }
+
+    /// Plays `take` (or the last one recorded or played) from the next bar,
+    /// once or looping. Playing while a take plays starts over.
+    pub fn pattern_play(&self, take: Option<Performance>, looping: bool) -> Result<(), String> {
+        let mut state = lock(&self.state);
+        if take.is_some() {
+            state.performance.set_take(take)?;
+        }
+        let was_playing = state.performance.playing;
+        if !state.performance.play(looping) {
+            return Err("[Social Noise] No take to play".to_string());
+        }
+        drop(state);
+        if let Err(e) = self.start_lane(Lane::Performance) {
+            lock(&self.state).performance.playing = was_playing;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Stops the take; voices already playing carry on
+    pub fn pattern_stop(&self) -> Result<(), String> {
+        self.stop_lane(Lane::Performance)
+    }
+
+    /// The last take recorded or played, e.g. for a session
+    pub fn performance(&self) -> Option<Performance> {
+        lock(&self.state).performance.take()
+    }
+
+    /// Keeps a take for `pattern_play` without playing it
+    pub fn set_performance(&self, take: Option<Performance>) -> Result<(), String> {
+        lock(&self.state).performance.set_take(take)
+    }
+
+    /// Records `bars` bars of `pads` (every pad when None) from the next bar
+    /// and loops them as the looper's first layer, dropping any loop there is.
+    /// With `count_in_bars`, that many bars are clicked in from the next bar
+    /// first; pads still sound during the count but are not captured, and
+    /// `looper_undo` or `looper_clear` calls the recording off.
+    pub fn looper_record(
+        &self,
+        bars: u32,
+        pads: Option<Vec<String>>,
+        count_in_bars: Option<u32>,
+    ) -> Result<(), String> {
+        if !(1..=MAX_LOOPER_BARS).contains(&bars) {
+            return Err(format!(
+                "[Social Noise] A loop of {} bars is outside 1..={}",
+                bars, MAX_LOOPER_BARS
+            ));
+        }
+        let count_in_bars = check_count_in(count_in_bars)?;
+        let beat = self.transport.read().beat;
+        let mut state = lock(&self.state);
+        let sources = pad_mask(&mut state, pads)?;
+        let seconds = bars as f64 * BEATS_PER_BAR * 60.0 / state.master_bpm as f64;
+        let frames = (seconds * state.sample_rate as f64).round() as usize;
+        let slot = state.pad_slots.get_or_assign(&looper_key(0))?;
+        let bar = (beat / BEATS_PER_BAR).ceil() * BEATS_PER_BAR;
+        let count_in = count_in_bars as f64 * BEATS_PER_BAR;
+        self.send(Command::LooperRecord {
+            layer: Layer::new(slot, frames, sources),
+            beat: bar + count_in,
+            count_in: (count_in_bars > 0).then_some(bar),
+        })?;
+        state.looper = LooperInfo { frames, layers: 1 };
+        info!(bars, count_in_bars, "Looper recording");
+        Ok(())
+    }
+
+    /// Records one more layer of `pads` (every pad when None) over the loop,
+    /// from its next start once no other layer is recording
+    pub fn looper_overdub(&self, pads: Option<Vec<String>>) -> Result<(), String> {
+        let mut state = lock(&self.state);
+        let LooperInfo { frames, layers } = state.looper;
+        if frames == 0 {
+            return Err("[Social Noise] No loop to overdub".to_string());
+        }
+        if layers == MAX_LOOPER_LAYERS {
+            return Err(format!(
+                "[Social Noise] The looper holds {} layers at most",
+                MAX_LOOPER_LAYERS
+            ));
+        }
+        let sources = pad_mask(&mut state, pads)?;
+        let slot = state.pad_slots.get_or_assign(&looper_key(layers))?;
+        self.send(Command::LooperOverdub(Layer::new(slot, frames, sources)))?;
+        state.looper.layers += 1;
+        Ok(())
+    }
+
+    /// Drops the newest layer, also while it waits or records; undoing the
+    /// first one clears the loop
+    pub fn looper_undo(&self) -> Result<(), String> {
+        let mut state = lock(&self.state);
+        if state.looper.layers == 0 {
+            return Err("[Social Noise] No looper layer to undo".to_string());
+        }
+        self.send(Command::LooperUndo)?;
+        state.looper.layers -= 1;
+        if state.looper.layers == 0 {
+            state.looper = LooperInfo::default();
+        }
+        Ok(())
+    }
+
+    /// Drops the loop and every layer
+    pub fn looper_clear(&self) -> Result<(), String> {
+        let mut state = lock(&self.state);
+        self.send(Command::LooperClear)?;
+        state.looper = LooperInfo::default();
+        Ok(())
+    }
+
+    /// Records the master output to a WAV at `path` from now on. With
+    /// `count_in_bars`, that many bars are clicked in from the next bar and
+    /// the recording starts on the downbeat after them; pads still sound
+    /// during the count but are not recorded, and the file is only created
+    /// on that downbeat.
+    pub fn record_start(
+        &self,
+        path: PathBuf,
+        format: WavFormat,
+        count_in_bars: Option<u32>,
+    ) -> Result<(), String> {
+        let count_in_bars = check_count_in(count_in_bars)?;
+        let mut recording = lock(&self.recording);
+        if recording.is_some() {
+            return Err("[Social Noise] The master output is already recording".to_string());
+        }
+        let beat = self.transport.read().beat;
+        let sample_rate = lock(&self.state).sample_rate;
+        let handle = RecordingHandle::start(path, format, sample_rate)?;
+        let bar = (beat / BEATS_PER_BAR).ceil() * BEATS_PER_BAR;
+        let count_in = (count_in_bars > 0).then_some(bar);
+        let start = count_in.map(|bar| bar + count_in_bars as f64 * BEATS_PER_BAR);
+        let sent = self.send(Command::RecordStart {
+            link: Arc::clone(&handle.link),
+            beat: start,
+            count_in,
+        });
+        if let Err(e) = sent {
+            handle.link.end(true);
+            let _ = handle.finish();
+            return Err(e);
+        }
+        *recording = Some(handle);
+        info!(count_in_bars, "Master recording");
+        Ok(())
+    }
+
+    /// Ends the master recording and finishes its file. Stopped during its
+    /// count-in it returns None and leaves no file.
+    pub fn record_stop(&self) -> Result<Option<MasterRecording>, String> {
+        let recording = self.end_recording(false)?;
+        if let Some(recording) = &recording {
+            info!(seconds = recording.duration, "Master recording stopped");
+        }
+        Ok(recording)
+    }
+
+    /// Ends the master recording and removes whatever it wrote; during the
+    /// count-in there is no file to remove
+    pub fn record_cancel(&self) -> Result<(), String> {
+        self.end_recording(true)?;
+        info!("Master recording cancelled");
+        Ok(())
+    }
+
+    fn end_recording(&self, cancel: bool) -> Result<Option<MasterRecording>, String> {
+        let handle = lock(&self.recording)
+            .take()
+            .ok_or("[Social Noise] The master output is not recording")?;
+        if self.send(Command::RecordEnd { cancel }).is_ok() {
+            // A callback that has stopped running never gets to it
+            let asked = Instant::now();
+            while !handle.link.ended() && asked.elapsed() < RECORD_END_TIMEOUT {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        }
+        handle.link.end(cancel);
+        handle.finish()
+    }
+
+    /// Adds a pad trigger or stop to a recording under way. Its beat is the
+    /// clock as of the last buffer.
+    fn record(&self, key: &str, action: PerformanceAction) {
+        let beat = self.transport.read().beat;
+        let mut state = lock(&self.state);
+        if state.performance.record(beat, key, action) {
+            self.restock(&mut state, Lane::Performance);
+        }
+    }
+
+    /// Starts a lane the engine marked running over, with a fresh first
+    /// loop on the lane's next grid line
+    fn start_lane(&self, lane: Lane) -> Result<(), String> {
+        let beat = self.transport.read().beat;
+        let mut state = lock(&self.state);
+        let start = lane.start_beat(&state, beat);
+        if lane == Lane::Performance {
+            state.performance.origin = start;
+        }
+        self.seq.offer(lane, lane.build(&mut state));
+        drop(state);
+        self.send(Command::SeqStart { lane, beat: start })
+    }
+
+    fn stop_lane(&self, lane: Lane) -> Result<(), String> {
+        *lane.running(&mut lock(&self.state)) = false;
+        self.send(Command::SeqStop(lane))?;
+        self.seq.clear(lane);
+        Ok(())
+    }
+
+    /// Replaces the waiting loop of a running lane with what the engine holds now
+    fn restock(&self, state: &mut AudioEngineState, lane: Lane) {
+        if *lane.running(state) {
+            self.seq.offer(lane, lane.build(state));
+        }
+    }
+
+    /// Receiver of the steps the sequencer fired, handed out once like
+    /// `take_voice_events`
+    pub fn take_seq_steps(&self) -> Option<Receiver<SeqStep>> {
+        self.seq_steps.lock().ok()?.take()
+    }
+
+    /// Receiver of the count-in clicks as they start, handed out once like
+    /// `take_voice_events`
+    pub fn take_count_in_ticks(&self) -> Option<Receiver<CountInTick>> {
+        self.count_in_ticks.lock().ok()?.take()
+    }
+
+    /// Receiver of the `voice-ended` queue, handed out once to the thread that
+    /// emits the events. A voice is removed from the engine before its event is
+    /// queued, so a `get_levels` issued after the event arrives never lists it.
+    pub fn take_voice_events(&self) -> Option<Receiver<VoiceEnded>> {
+        self.voice_events.lock().ok()?.take()
+    }
+
+    /// Receiver of the stream's errors, handed out once like `take_voice_events`
+    pub fn take_stream_faults(&self) -> Option<Receiver<StreamFault>> {
+        self.stream_faults.lock().ok()?.take()
+    }
+
+    /// Totals of the problems the audio callback counted instead of logging
+    pub fn callback_errors(&self) -> CallbackErrors {
+        CallbackErrors {
+            dropped_voice_events: self.counters.dropped_voice_events.load(Ordering::Relaxed),
+            panics: self.counters.panics.load(Ordering::Relaxed),
+            poisoned_locks: POISONED_LOCKS.load(Ordering::Relaxed),
+            non_finite_voices: self.counters.non_finite.load(Ordering::Relaxed),
+            late_sequencer_loops: self.counters.late_patterns.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Publishes fresh levels and returns them, for callers that poll
+    pub fn get_levels(&self, active_bank: u32) -> Arc<LevelsResponse> {
+        self.publish_levels(active_bank);
+        self.latest_levels()
+    }
+
+    /// Takes a levels snapshot into the meter's back slot and makes it current
+    pub fn publish_levels(&self, active_bank: u32) {
+        let mut back = lock(&self.meter.back);
+        if Arc::get_mut(&mut back).is_none() {
+            *back = Arc::default();
+        }
+        self.fill_levels(active_bank, Arc::make_mut(&mut back));
+        std::mem::swap(&mut *lock(&self.meter.front), &mut *back);
+    }
+
+    /// The latest published snapshot, without publishing a new one
+    pub fn latest_levels(&self) -> Arc<LevelsResponse> {
+        Arc::clone(&lock(&self.meter.front))
+    }
+
+    /// Reads the latest published snapshot without cloning it
+    pub fn with_levels<R>(&self, read: impl FnOnce(&LevelsResponse) -> R) -> R {
+        read(&self.latest_levels())
+    }
+
+    /// The newest mixer snapshot. Windows closed since the last call are
+    /// drained: the newest one is kept, with the peaks of the others it
+    /// replaces folded in.
+    fn mixer_levels(&self) -> MutexGuard<'_, MixerLevels> {
+        let mut latest = lock(&self.mixer_levels);
+        let mut unread = false;
+        while let Some(mut snapshot) = self.level_queues.ready.pop() {
+            if unread {
+                for (level, older) in snapshot.levels.iter_mut().zip(latest.levels.iter()) {
+                    if level.live && older.live {
+                        level.visual.peak = level.visual.peak.max(older.visual.peak);
+                    }
+                }
+                snapshot.compressor_reduction = snapshot
+                    .compressor_reduction
+                    .max(latest.compressor_reduction);
+                snapshot.limiter_reduction =
+                    snapshot.limiter_reduction.max(latest.limiter_reduction);
+            }
+            let older = std::mem::replace(&mut *latest, snapshot);
+            let _ = self.level_queues.free.push(older);
+            unread = true;
+        }
+        latest
+    }
+
+    /// Levels of every bank. `data`/`active_keys` use plain keys of the active bank
+    /// (what the pads show); `all_data`/`all_active_keys` use full pad ids.
+    /// Refills `response` in place so the slot keeps its allocations.
+    fn fill_levels(&self, active_bank: u32, response: &mut LevelsResponse) {
+        response.data.clear();
+        response.active_keys.clear();
+        response.active_bank = active_bank;
+        response.all_data.clear();
+        response.all_active_keys.clear();
+        {
+            let latest = self.mixer_levels();
+            response.compressor_reduction_db = latest.compressor_reduction;
+            response.limiter_reduction_db = latest.limiter_reduction;
+            // Slots become pad ids again here, outside the callback
+            let keys = lock(&self.meter.slot_keys);
+            for (id, level) in keys.iter().zip(latest.levels.iter()) {
+                if !level.live {
+                    continue;
+                }
+                if let (Some(bank), key) = split_pad_id(id) {
+                    if bank == active_bank {
+                        response.data.insert(key.to_string(), level.visual.clone());
+                    }
+                }
+                response.all_data.insert(id.clone(), level.visual.clone());
+            }
+            for id in latest.active.iter().filter_map(|&slot| keys.get(slot)) {
+                if let (Some(bank), key) = split_pad_id(id) {
+                    if bank == active_bank {
+                        response.active_keys.push(key.to_string());
+                    }
+                }
+                response.all_active_keys.push(id.clone());
+            }
+        }
+    }
+}
+
+#[derive(serde::Serialize, Clone, Default)]
+pub struct VisualData {
+    pub peak: f32,
+    pub samples: Vec<f32>,
+    // Envelope of the pad's most recently triggered voice; omitted when unknown
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub envelope_stage: Option<EnvelopeStage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub envelope_gain: Option<f32>, // 0-1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remaining_secs: Option<f32>, // Until the voice ends; none while it loops
+}
+
+impl VisualData {
+    /// `clone_from` that keeps this entry's sample buffer
+    fn copy_from(&mut self, other: &VisualData) {
+        self.peak = other.peak;
+        self.samples.clear();
+        self.samples.extend_from_slice(&other.samples);
+        self.envelope_stage = other.envelope_stage;
+        self.envelope_gain = other.envelope_gain;
+        self.remaining_secs = other.remaining_secs;
+    }
+}
+
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvelopeStage {
+    Attack,
+    Sustain,
+    Release, // Natural release or fading out after a stop
+}
+
+#[derive(serde::Serialize, Clone, Default)]
+pub struct LevelsResponse {
+    pub data: HashMap<String, VisualData>,
+    pub active_keys: Vec<String>,
+    pub active_bank: u32,
+    pub all_data: HashMap<String, VisualData>, // Keyed by "{bank}:{key}"
+    pub all_active_keys: Vec<String>,
+    pub compressor_reduction_db: f32, // Master compressor, most in the last window
+    pub limiter_reduction_db: f32,    // Master limiter, likewise
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadResult {
+    pub duration: f32,
+    pub bpm: Option<f32>, // None while background analysis is still running
+    pub raw_bpm: Option<f32>,
+    pub bpm_confidence: Option<f32>, // 0.0-1.0, None until analysis completes
+    pub bpm_source: Option<BpmSource>,
+    pub waveform: Vec<f32>,
+    pub waveform_left: Vec<f32>,
+    pub waveform_right: Vec<f32>,
+    pub trim_start: f32,
+    pub trim_end: f32,
+    pub start_time: f32, // Suggested default region (trimmed when autoTrim is set)
+    pub end_time: f32,
+    pub lufs: Option<f32>,
+    pub loudness_gain_db: f32, // Makeup gain applied for loudnessTarget (0 when unused)
+    pub loudness_gain_clamped: bool,
+    pub embedded_loop_start: Option<f32>,
+    pub embedded_loop_end: Option<f32>,
+    pub root_note: Option<u8>,
+    pub settings: Option<SampleSettings>, // Sidecar values, already merged into the fields above
+    pub tags: FileTags,
+    pub decode_mode: DecodeMode,
+    pub warning: Option<String>, // Set when the pad holds less than the whole file
+    pub decode_warnings: Vec<String>, // Damaged parts of the file that were skipped
+}
+
+/// How much of a file a pad holds
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DecodeMode {
+    Full,      // The whole file
+    Truncated, // Only the start, up to the decode limit
+}
+
+/// Why a file could not be loaded. Serialized as-is so the UI can branch on `kind`.
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadErrorKind {
+    FileMissing,
+    UnsupportedCodec,
+    TruncatedStream,
+    ZeroChannels,
+    EmptyAudio,
+    Superseded, // A newer load of the same pad replaced this one
+    Cancelled,  // Stopped by `audio_load_cancel`
+    Other,      // Engine-side failures (poisoned lock, community build, ...)
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct LoadError {
+    pub kind: LoadErrorKind,
+    pub message: String, // Human readable, safe to show as-is
+}
+
+impl LoadError {
+    pub fn new(kind: LoadErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<String> for LoadError {
+    fn from(message: String) -> Self {
+        Self::new(LoadErrorKind::Other, message)
+    }
+}
+
+/// Interleaved audio produced by `render_region`
+pub struct RenderedRegion {
+    pub data: Vec<f32>,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+impl RenderedRegion {
+    pub fn duration(&self) -> f32 {
+        self.data.len() as f32 / (self.sample_rate as f32 * self.channels as f32)
+    }
+}
+
+/// Sample format for exported WAV files
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum WavFormat {
+    #[default]
+    Float32,
+    Pcm16,
+}
+
+/// Channel selector for `audio_get_waveform`
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum WaveformChannel {
+    #[default]
+    Mixed,
+    Left,
+    Right,
+}
+
+/// Which way `audio_find_zero_crossing` may move a marker
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchDirection {
+    #[default]
+    Nearest,
+    Forward,
+    Backward,
+}
+
+#[derive(serde::Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct LoopPoints {
+    pub start_time: f32,
+    pub end_time: f32,
+}
+
+/// Optional processing requested with `audio_load`
+#[derive(Debug, Clone, Default)]
+pub struct LoadOptions {
+    pub auto_trim: bool,
+    pub loudness_target: Option<f32>, // Target integrated loudness in LUFS
+    pub gain_trim_db: f32,            // Extra per-sample trim on top of loudness makeup
+    pub jump_queue: bool,             // Decode before loads already queued (a pad reload)
+}
+
+/// Payload of the `load-progress` event. `generation` increases with every
+/// load, so a listener can tell a pad's current load from a superseded one.
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadProgress {
+    pub key: String,
+    pub stage: LoadStage,
+    pub percent: f32, // 0-100 within the stage
+    pub generation: u64,
+    pub queue_position: Option<usize>, // 1 = next to decode; set in the queued stage only
+}
+
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LoadStage {
+    Queued,    // Waiting for a decode slot
+    Decoding,  // Estimated from bytes read vs. file size
+    Analyzing, // Silence, loudness and waveform passes over the decoded PCM
+}
+
+/// Upper bound on the `load-progress` rate per load (~5 Hz)
+const LOAD_PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Throttles a load's progress and goes quiet once a newer load of the same
+/// pad has started. Stage changes and 100% always get through.
+struct LoadReporter<F> {
+    state: Arc<Mutex<AudioEngineState>>,
+    key: String,
+    generation: u64,
+    on_progress: F,
+    last: Option<(LoadStage, Instant)>,
+}
+
+impl<F: Fn(LoadProgress)> LoadReporter<F> {
+    fn report(&mut self, stage: LoadStage, percent: f32) {
+        let now = Instant::now();
+        if let Some((last_stage, at)) = self.last {
+            if last_stage == stage
+                && percent < 100.0
+                && now.duration_since(at) < LOAD_PROGRESS_INTERVAL
+            {
+                return;
+            }
+        }
+        if self.emit(stage, percent, None) {
+            self.last = Some((stage, now));
+        }
+    }
+
+    /// Reported on every move of the queue, which is rare enough to skip throttling
+    fn queued(&mut self, position: usize) {
+        self.emit(LoadStage::Queued, 0.0, Some(position));
+    }
+
+    /// Emits unless a newer load of the pad took over; returns whether it did
+    fn emit(&self, stage: LoadStage, percent: f32, queue_position: Option<usize>) -> bool {
+        let current = lock(&self.state)
+            .loading
+            .get(&self.key)
+            .map(|pending| pending.generation)
+            == Some(self.generation);
+        if current {
+            (self.on_progress)(LoadProgress {
+                key: self.key.clone(),
+                stage,
+                percent: percent.clamp(0.0, 100.0),
+                generation: self.generation,
+                queue_position,
+            });
+        }
+        current
+    }
+}
+
+/// A load that has not finished yet. `cancel` is polled between packets.
+struct PendingLoad {
+    generation: u64,
+    cancel: Arc<AtomicU8>,
+}
+
+const LOAD_RUNNING: u8 = 0;
+const LOAD_SUPERSEDED: u8 = 1;
+const LOAD_CANCELLED: u8 = 2;
+
+/// Error for a load that stopped being its pad's newest
+fn cancelled_load(cancel: &AtomicU8) -> LoadError {
+    match cancel.load(Ordering::Relaxed) {
+        LOAD_CANCELLED => LoadError::new(LoadErrorKind::Cancelled, "Load cancelled"),
+        _ => LoadError::new(
+            LoadErrorKind::Superseded,
+            "Superseded by a newer load of the pad",
+        ),
+    }
+}
+
+// ========================================================================
+// Decode Queue
+// ========================================================================
+
+/// Files decoded at once unless `set_decode_concurrency` says otherwise
+pub const DEFAULT_DECODE_CONCURRENCY: usize = 2;
+const MAX_DECODE_CONCURRENCY: usize = 16;
+
+/// Hands out decode slots first come, first served. Loads flagged
+/// `jump_queue` go ahead of unflagged ones but stay in order among themselves.
+struct DecodeQueue {
+    line: Mutex<DecodeLine>,
+}
+
+struct DecodeLine {
+    permits: usize,
+    running: usize,
+    waiting: VecDeque<Waiter>,
+}
+
+struct Waiter {
+    jump_queue: bool,
+    turn: UnboundedSender<Turn>,
+}
+
+/// What a waiting load hears from the queue
+enum Turn {
+    Queued(usize), // Its new position, 1-based
+    Go(DecodeTurn),
+}
+
+/// A decode slot; dropping it lets the next load in
+struct DecodeTurn {
+    queue: Arc<DecodeQueue>,
+}
+
+impl Drop for DecodeTurn {
+    fn drop(&mut self) {
+        if let Ok(mut line) = self.queue.line.lock() {
+            line.running -= 1;
+        }
+        self.queue.dispatch();
+    }
+}
+
+impl DecodeQueue {
+    fn new(permits: usize) -> Self {
+        Self {
+            line: Mutex::new(DecodeLine {
+                permits,
+                running: 0,
+                waiting: VecDeque::new(),
+            }),
+        }
+    }
+
+    fn enter(self: &Arc<Self>, jump_queue: bool) -> UnboundedReceiver<Turn> {
+        let (turn, line) = async_mpsc::unbounded_channel();
+        if let Ok(mut queue) = self.line.lock() {
+            let position = if jump_queue {
+                queue.waiting.iter().take_while(|w| w.jump_queue).count()
+            } else {
+                queue.waiting.len()
+            };
+            queue.waiting.insert(position, Waiter { jump_queue, turn });
+        }
+        self.dispatch();
+        line
+    }
+
+    fn set_permits(self: &Arc<Self>, permits: usize) {
+        if let Ok(mut line) = self.line.lock() {
+            line.permits = permits;
+        }
+        self.dispatch();
+    }
+
+    /// Starts waiters while slots are free and tells the rest where they stand
+    fn dispatch(self: &Arc<Self>) {
+        // Dropped after the lock: a turn nobody received releases itself, which locks.
+        // A granted waiter's channel can still hold one, if its load just went away.
+        let mut unsent = Vec::new();
+        let mut granted = Vec::new();
+        if let Ok(mut line) = self.line.lock() {
+            line.waiting.retain(|w| !w.turn.is_closed()); // Loads dropped while queued
+            while line.running < line.permits {
+                let waiter = match line.waiting.pop_front() {
+                    Some(waiter) => waiter,
+                    None => break,
+                };
+                line.running += 1;
+                let turn = Turn::Go(DecodeTurn {
+                    queue: Arc::clone(self),
+                });
+                if let Err(e) = waiter.turn.send(turn) {
+                    unsent.push(e.0);
+                }
+                granted.push(waiter);
+            }
+            for (index, waiter) in line.waiting.iter().enumerate() {
+                let _ = waiter.turn.send(Turn::Queued(index + 1));
+            }
+        }
+        drop(granted);
+        drop(unsent);
+    }
+}
+
+/// Payload of the `analysis-complete` event
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalysisComplete {
+    pub key: String,
+    pub bpm: f32,
+    pub raw_bpm: f32,
+    pub bpm_confidence: f32,
+    pub bpm_source: BpmSource,
+    pub bpm_error: Option<String>, // Why detection failed when the source is `fallback`
+    pub key_detected: Option<String>,
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum AnalysisStatus {
+    Pending,
+    #[serde(rename_all = "camelCase")]
+    Complete {
+        bpm: f32,
+        raw_bpm: f32,
+        bpm_confidence: f32,
+        bpm_source: BpmSource,
+        bpm_error: Option<String>,
+        key_detected: Option<String>,
+    },
+}
+
+/// Where a pad's BPM came from, so the UI can avoid syncing on guesses
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BpmSource {
+    Detected, // stratum_dsp analysis succeeded
+    Cached,   // Supplied by the frontend's BPM cache
+    Fallback, // Analysis failed; 120 BPM placeholder
+}
+
+/// Result of `analyze_bpm`
+pub struct BpmAnalysis {
+    pub bpm: f32,
+    pub raw_bpm: f32,
+    pub confidence: f32,
+    pub source: BpmSource,
+    pub error: Option<String>, // Set with `BpmSource::Fallback`
+    pub key_detected: Option<String>,
+}
+
+/// Below this confidence the integer-snap heuristic is skipped
+const BPM_SNAP_CONFIDENCE: f32 = 0.5;
+
+/// Frames of the decimated signal per point of its energy envelope (about 21 ms)
+const ENVELOPE_FRAMES: usize = 256;
+
+/// An envelope whose energy spreads less than this around its mean (standard
+/// deviation over mean) has no onsets to time: noise, drones, held tones
+const MIN_ENVELOPE_SPREAD: f32 = 0.25;
+
+/// Default per-file decode limit: about 25 minutes of 44.1 kHz stereo
+pub const DEFAULT_DECODE_LIMIT_MB: usize = 512;
+
+/// Default preferred tempo window; detections outside it are folded by octaves.
+pub const DEFAULT_BPM_RANGE: (f32, f32) = (80.0, 170.0);
+
+/// True when the energy envelope of `mono` barely moves. Silence and
+/// signals too short for two envelope points are left to the analyzer.
+fn steady_level(mono: &[f32]) -> bool {
+    let energies: Vec<f32> = mono
+        .chunks_exact(ENVELOPE_FRAMES)
+        .map(|chunk| chunk.iter().map(|s| s * s).sum())
+        .collect();
+    if energies.len() < 2 {
+        return false;
+    }
+    let count = energies.len() as f32;
+    let mean = energies.iter().sum::<f32>() / count;
+    if mean <= 0.0 {
+        return false;
+    }
+    let variance = energies.iter().map(|e| (e - mean).powi(2)).sum::<f32>() / count;
+    variance.sqrt() < MIN_ENVELOPE_SPREAD * mean
+}
+
+/// Doubles or halves `bpm` until it lands inside `range`.
+/// stratum_dsp often locks onto half or double time (70 vs 140), so this
+/// folds the detection into the range where most loop material lives.
+fn fold_bpm(bpm: f32, range: (f32, f32)) -> f32 {
+    let (min, max) = range;
+    if bpm <= 0.0 || !bpm.is_finite() {
+        return bpm;
+    }
+
+    let mut folded = bpm;
+    while folded < min {
+        folded *= 2.0;
+    }
+    while folded > max {
+        folded /= 2.0;
+    }
+    folded
+}
+
+#[derive(serde::Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayParams {
+    pub volume: f32,
+    pub attack: f32,
+    pub release: f32,
+    pub looping: bool,
+    pub start_time: f32,
+    pub end_time: f32,
+    pub sync: bool,
+    pub sample_bpm: f32,
+    #[serde(default = "full_velocity")]
+    pub velocity: f32, // 0.0-1.0 trigger strength (MIDI note-on); keyboard triggers play at 1.0
+    #[serde(default)]
+    pub force_sync: bool, // Sync even on the 120 BPM placeholder of a failed detection
+    #[serde(default = "open_cutoff")]
+    pub filter_cutoff: f32, // Hz of the voice low-pass, FILTER_OPEN for none
+    #[serde(default)]
+    pub filter_env_amount: f32, // Octaves the envelope moves the cutoff, 0 for no envelope
+    #[serde(default)]
+    pub filter_env_attack: f32, // Seconds
+    #[serde(default)]
+    pub filter_env_decay: f32, // Seconds
+    #[serde(default = "full_bits")]
+    pub crush_bits: u8, // 1-16, MAX_CRUSH_BITS for none
+    #[serde(default = "no_downsample")]
+    pub crush_downsample: u32, // Frames per held sample, 1-32
+    #[serde(default)]
+    pub drive: f32, // 0-1 saturation, 0 for none
+    #[serde(default)]
+    pub delay_send: f32, // 0-1 into the delay bus, see `set_send`
+    #[serde(default)]
+    pub sends_muted: bool, // See `mute_sends`
+}
+
+fn full_velocity() -> f32 {
+    1.0
+}
+
+fn open_cutoff() -> f32 {
+    FILTER_OPEN
+}
+
+fn full_bits() -> u8 {
+    MAX_CRUSH_BITS
+}
+
+fn no_downsample() -> u32 {
+    1
+}
+
+/// Result of `stop_sound` and `update_voice`
+#[derive(serde::Serialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct VoiceMatch {
+    pub matched: usize, // Voices of the pad playing as of the last meter window
+    pub corrected: Vec<&'static str>, // Params that were corrected or clamped
+}
+
+/// Pad volume ceiling (x4, about +12 dB)
+pub const MAX_PAD_VOLUME: f32 = 4.0;
+
+/// Sample tempos sync accepts; anything else counts as unknown (0). The
+/// master tempo has to be in here too.
+pub const SAMPLE_BPM_RANGE: std::ops::RangeInclusive<f32> = 20.0..=400.0;
+
+impl PlayParams {
+    /// Replaces what the mixer cannot use: NaN or infinite numbers, negative
+    /// times, volumes outside 0..=MAX_PAD_VOLUME, velocities outside 0..=1,
+    /// cutoffs outside MIN_FILTER_CUTOFF..=FILTER_OPEN and filter envelopes
+    /// beyond MAX_FILTER_ENV_OCTAVES either way. Crusher settings are pulled
+    /// into 1..=MAX_CRUSH_BITS and 1..=MAX_CRUSH_DOWNSAMPLE, drive and the
+    /// delay send into 0..=1.
+    /// A sample BPM outside SAMPLE_BPM_RANGE becomes 0, which turns sync off.
+    /// Returns the names of the corrected fields, as the frontend spells them.
+    pub fn sanitize(&mut self) -> Vec<&'static str> {
+        let mut corrected = Vec::new();
+        let mut fix = |name, value: &mut f32, fallback: f32, max: f32| {
+            let fixed = if value.is_finite() {
+                value.clamp(0.0, max)
+            } else {
+                fallback
+            };
+            if fixed != *value {
+                *value = fixed;
+                corrected.push(name);
+            }
+        };
+        fix("volume", &mut self.volume, 1.0, MAX_PAD_VOLUME);
+        fix("attack", &mut self.attack, 0.0, f32::MAX);
+        fix("release", &mut self.release, 0.0, f32::MAX);
+        fix("startTime", &mut self.start_time, 0.0, f32::MAX);
+        // play_sound pulls it in to the file's duration
+        fix("endTime", &mut self.end_time, f32::MAX, f32::MAX);
+        fix("velocity", &mut self.velocity, 1.0, 1.0);
+        fix(
+            "filterCutoff",
+            &mut self.filter_cutoff,
+            FILTER_OPEN,
+            FILTER_OPEN,
+        );
+        fix(
+            "filterEnvAttack",
+            &mut self.filter_env_attack,
+            0.0,
+            f32::MAX,
+        );
+        fix("filterEnvDecay", &mut self.filter_env_decay, 0.0, f32::MAX);
+        fix("drive", &mut self.drive, 0.0, 1.0);
+        fix("delaySend", &mut self.delay_send, 0.0, 1.0);
+        if self.filter_cutoff < MIN_FILTER_CUTOFF {
+            self.filter_cutoff = MIN_FILTER_CUTOFF;
+            corrected.push("filterCutoff");
+        }
+        let amount = self.filter_env_amount;
+        let fixed = if amount.is_finite() {
+            amount.clamp(-MAX_FILTER_ENV_OCTAVES, MAX_FILTER_ENV_OCTAVES)
+        } else {
+            0.0
+        };
+        if fixed != amount {
+            self.filter_env_amount = fixed;
+            corrected.push("filterEnvAmount");
+        }
+        let bits = self.crush_bits.clamp(1, MAX_CRUSH_BITS);
+        if bits != self.crush_bits {
+            self.crush_bits = bits;
+            corrected.push("crushBits");
+        }
+        let downsample = self.crush_downsample.clamp(1, MAX_CRUSH_DOWNSAMPLE);
+        if downsample != self.crush_downsample {
+            self.crush_downsample = downsample;
+            corrected.push("crushDownsample");
+        }
+        if self.sample_bpm != 0.0 && !SAMPLE_BPM_RANGE.contains(&self.sample_bpm) {
+            self.sample_bpm = 0.0;
+            corrected.push("sampleBpm");
+        }
+        corrected
+    }
+}
+
+/// `PlayParams` as sent by `audio_play`: omitted fields come from the pad's
+/// defaults. A pad's stored defaults are one too.
+#[derive(serde::Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PartialPlayParams {
+    pub volume: Option<f32>,
+    pub attack: Option<f32>,
+    pub release: Option<f32>,
+    pub looping: Option<bool>,
+    pub start_time: Option<f32>,
+    pub end_time: Option<f32>,
+    pub sync: Option<bool>,
+    pub sample_bpm: Option<f32>,
+    pub velocity: Option<f32>,
+    pub force_sync: Option<bool>,
+    pub filter_cutoff: Option<f32>,
+    pub filter_env_amount: Option<f32>,
+    pub filter_env_attack: Option<f32>,
+    pub filter_env_decay: Option<f32>,
+    pub crush_bits: Option<u8>,
+    pub crush_downsample: Option<u32>,
+    pub drive: Option<f32>,
+    pub delay_send: Option<f32>,
+    pub sends_muted: Option<bool>,
+}
+
+impl From<PlayParams> for PartialPlayParams {
+    fn from(params: PlayParams) -> Self {
+        Self {
+            volume: Some(params.volume),
+            attack: Some(params.attack),
+            release: Some(params.release),
+            looping: Some(params.looping),
+            start_time: Some(params.start_time),
+            end_time: Some(params.end_time),
+            sync: Some(params.sync),
+            sample_bpm: Some(params.sample_bpm),
+            velocity: None, // Never a default, see `over`
+            force_sync: Some(params.force_sync),
+            filter_cutoff: Some(params.filter_cutoff),
+            filter_env_amount: Some(params.filter_env_amount),
+            filter_env_attack: Some(params.filter_env_attack),
+            filter_env_decay: Some(params.filter_env_decay),
+            crush_bits: Some(params.crush_bits),
+            crush_downsample: Some(params.crush_downsample),
+            drive: Some(params.drive),
+            delay_send: Some(params.delay_send),
+            sends_muted: Some(params.sends_muted),
+        }
+    }
+}
+
+impl PartialPlayParams {
+    /// These fields, with the omitted ones taken from `base`
+    pub fn or(self, base: PartialPlayParams) -> PartialPlayParams {
+        PartialPlayParams {
+            volume: self.volume.or(base.volume),
+            attack: self.attack.or(base.attack),
+            release: self.release.or(base.release),
+            looping: self.looping.or(base.looping),
+            start_time: self.start_time.or(base.start_time),
+            end_time: self.end_time.or(base.end_time),
+            sync: self.sync.or(base.sync),
+            sample_bpm: self.sample_bpm.or(base.sample_bpm),
+            velocity: self.velocity, // Older saved defaults may carry one; see `over`
+            force_sync: self.force_sync.or(base.force_sync),
+            filter_cutoff: self.filter_cutoff.or(base.filter_cutoff),
+            filter_env_amount: self.filter_env_amount.or(base.filter_env_amount),
+            filter_env_attack: self.filter_env_attack.or(base.filter_env_attack),
+            filter_env_decay: self.filter_env_decay.or(base.filter_env_decay),
+            crush_bits: self.crush_bits.or(base.crush_bits),
+            crush_downsample: self.crush_downsample.or(base.crush_downsample),
+            drive: self.drive.or(base.drive),
+            delay_send: self.delay_send.or(base.delay_send),
+            sends_muted: self.sends_muted.or(base.sends_muted),
+        }
+    }
+
+    pub fn over(self, base: PlayParams) -> PlayParams {
+        PlayParams {
+            volume: self.volume.unwrap_or(base.volume),
+            attack: self.attack.unwrap_or(base.attack),
+            release: self.release.unwrap_or(base.release),
+            looping: self.looping.unwrap_or(base.looping),
+            start_time: self.start_time.unwrap_or(base.start_time),
+            end_time: self.end_time.unwrap_or(base.end_time),
+            sync: self.sync.unwrap_or(base.sync),
+            sample_bpm: self.sample_bpm.unwrap_or(base.sample_bpm),
+            // Strike strength belongs to the trigger, never to the stored defaults
+            velocity: self.velocity.unwrap_or_else(full_velocity),
+            force_sync: self.force_sync.unwrap_or(base.force_sync),
+            filter_cutoff: self.filter_cutoff.unwrap_or(base.filter_cutoff),
+            filter_env_amount: self.filter_env_amount.unwrap_or(base.filter_env_amount),
+            filter_env_attack: self.filter_env_attack.unwrap_or(base.filter_env_attack),
+            filter_env_decay: self.filter_env_decay.unwrap_or(base.filter_env_decay),
+            crush_bits: self.crush_bits.unwrap_or(base.crush_bits),
+            crush_downsample: self.crush_downsample.unwrap_or(base.crush_downsample),
+            drive: self.drive.unwrap_or(base.drive),
+            delay_send: self.delay_send.unwrap_or(base.delay_send),
+            sends_muted: self.sends_muted.unwrap_or(base.sends_muted),
+        }
+    }
+}
+
+/// Per-sample pad settings persisted in a sidecar file next to the sample.
+/// Every field is optional so partial or older sidecars still load.
+#[derive(serde::Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SampleSettings {
+    pub volume: Option<f32>,
+    pub attack: Option<f32>,
+    pub release: Option<f32>,
+    pub looping: Option<bool>,
+    pub start_time: Option<f32>, // Loop/playback region
+    pub end_time: Option<f32>,
+    pub sync: Option<bool>,
+    pub trim_start: Option<f32>,
+    pub trim_end: Option<f32>,
+    pub bpm_override: Option<f32>,
+    pub gain_trim_db: Option<f32>,
+}
+
+/// Ramp that eases a voice back in after a position jump or a cancelled
+/// release, too short to hear as a fade
+const DECLICK_MS: f32 = 5.0;
+
+/// Frames mixed per block; each voice renders a block into stack scratch
+const MIX_BLOCK: usize = 64;
+
+fn write_audio(data: &mut [f32], mixer: &mut Mixer, channels: usize) {
+    mixer.apply_commands();
+
+    // THIS IS THE ADDED BLOCK FOR SILENT GUARD
+    // --- THE SILENT GUARD ---
+    // If no voices are active, zero out the buffer and rest the CPU.
+    if mixer.voices.is_empty()
+        && !mixer.sequencer.running()
+        && mixer.launches.idle()
+        && mixer.recorder.idle()
+        && mixer.looper.idle()
+        && mixer.delay.idle()
+        && mixer.metronome.idle()
+    {
+        data.fill(0.0);
+        mixer.master.jump(mixer.master.target); // Nothing to click
+        mixer.compressor.rest();
+        mixer.limiter.rest();
+        for slew in mixer.crossfade.iter_mut() {
+            slew.jump(slew.target);
+        }
+        mixer.close_window();
+        mixer.clock.advance(data.len() / channels);
+        mixer.transport.publish(&mixer.clock);
+        return;
+    }
+    // THIS IS THE ADDED BLOCK FOR SILENT GUARD - END
+
+    let frames = data.len() / channels;
+    let mut done = 0;
+    while done < frames {
+        // Steps fire on the first frame at or after their beat
+        mixer.sequencer.fire(
+            &mixer.clock,
+            &mut mixer.voices,
+            &mut mixer.retired,
+            &mixer.counters,
+        );
+        mixer
+            .launches
+            .fire(&mixer.clock, &mut mixer.voices, &mut mixer.retired);
+        mixer.metronome.fire(&mixer.clock);
+        mixer.recorder.fire(&mixer.clock);
+        // Blocks never straddle a meter window, which closes between them,
+        // a sequencer step, a launch, a count-in click or the start of a
+        // recording
+        let to_step = mixer.sequencer.frames_to_step(&mixer.clock);
+        let to_launch = mixer.launches.frames_to_launch(&mixer.clock);
+        let to_click = mixer.metronome.frames_to_click(&mixer.clock);
+        let to_record = mixer.recorder.frames_to_start(&mixer.clock);
+        let len = MIX_BLOCK
+            .min(frames - done)
+            .min(mixer.window_frames - mixer.window_pos)
+            .min(to_step.map_or(usize::MAX, |frames| frames.max(1)))
+            .min(to_launch.map_or(usize::MAX, |frames| frames.max(1)))
+            .min(to_click.map_or(usize::MAX, |frames| frames.max(1)))
+            .min(to_record.map_or(usize::MAX, |frames| frames.max(1)));
+        let mut left = [0.0f32; MIX_BLOCK];
+        let mut right = [0.0f32; MIX_BLOCK];
+        mixer.mix_block(&mut left[..len], &mut right[..len]);
+        if !mixer.compressor.bypassed() {
+            mixer
+                .compressor
+                .process(&mut left[..len], &mut right[..len]);
+        }
+
+        if !mixer.master.settled() {
+            for (l, r) in left[..len].iter_mut().zip(&mut right[..len]) {
+                let master = mixer.master.next();
+                *l *= master;
+                *r *= master;
+            }
+        } else {
+            let master = mixer.master.current;
+            for (l, r) in left[..len].iter_mut().zip(&mut right[..len]) {
+                *l *= master;
+                *r *= master;
+            }
+        }
+        mixer.limiter.process(&mut left[..len], &mut right[..len]);
+        mixer.recorder.capture(&left[..len], &right[..len]);
+        mixer.metronome.process(&mut left[..len], &mut right[..len]);
+        let out = &mut data[done * channels..(done + len) * channels];
+        if channels == 1 {
+            for (sample, (l, r)) in out.iter_mut().zip(left.iter().zip(&right)) {
+                *sample = (l + r) * 0.5;
+            }
+        } else {
+            for (frame, (l, r)) in out.chunks_exact_mut(channels).zip(left.iter().zip(&right)) {
+                frame[0] = *l;
+                frame[1] = *r;
+            }
+        }
+
+        done += len;
+        mixer.clock.advance(len);
+        mixer.window_pos += len;
+        if mixer.window_pos == mixer.window_frames {
+            mixer.close_window();
+        }
+    }
+    mixer.transport.publish(&mixer.clock);
+}
+
+/// Renders one voice into `left`/`right` (which start zeroed), frame by
+/// frame. Returns false once the voice is done; the frames after that stay
+/// silent.
+fn render_voice(
+    voice: &mut Voice,
+    left: &mut [f32],
+    right: &mut [f32],
+    window_pos: usize,
+    meter_step: usize,
+) -> bool {
+    let crushing = !voice.crush.bypassed();
+    let driving = !voice.drive.bypassed();
+    for i in 0..left.len() {
+        if voice.stopped {
+            return false;
+        }
+
+        // Reset per-voice peak for THIS frame calculation
+        voice.current_peak = 0.0;
+
+        let mut env_gain = 1.0f32;
+        let data_len = voice.buffer.data.len();
+        let b_channels = voice.buffer.channels as usize;
+
+        // 1. Calculate potential "Attack" gain (independent of fading state)
+        if voice.fade_position < voice.attack_samples {
+            env_gain = voice.fade_position as f32 / voice.attack_samples as f32;
+        }
+
+        // 1b. Ease in from a jump or a cancelled release (see `set_looping`)
+        if voice.declick_left > 0 && !voice.is_fading_out {
+            let progress = 1.0 - voice.declick_left as f32 / voice.declick_frames() as f32;
+            env_gain = voice.declick_from + (env_gain - voice.declick_from) * progress.max(0.0);
+            voice.declick_left -= 1;
+        }
+
+        // 2. Handle Stop Command (Manual) with Symmetry
+        if voice.stop_command && !voice.is_fading_out {
+            // Only apply symmetry if frontend hasn't already calculated effective release
+            if !voice.custom_release_set && voice.fade_position < voice.attack_samples {
+                // Symmetric Release: If stopped at 0.2 attack, fade out in 0.2 release
+                voice.release_samples = voice.fade_position;
+                voice.release = voice.fade_position as f32 / voice.device_rate as f32;
+            }
+            voice.is_fading_out = true;
+            voice.fade_start_gain = env_gain;
+            voice.fade_out_pos = 0;
+        }
+
+        // 3. Trigger "Natural Release" BEFORE reaching loop_end (One-Shot only)
+        if !voice.is_fading_out && !voice.looping {
+            let file_samples_remaining = voice.loop_end - voice.position;
+            let device_samples_remaining =
+                file_samples_remaining / (voice.playback_rate * b_channels as f64);
+
+            if device_samples_remaining <= voice.release_samples as f64 {
+                voice.is_fading_out = true;
+                voice.fade_start_gain = env_gain;
+                voice.fade_out_pos = 0;
+            }
+        }
+
+        // 4. Handle Fading (Manual OR Natural)
+        if voice.is_fading_out {
+            // Ensure we capture the "exit gain" at the moment fading starts
+            if voice.fade_out_pos == 0 {
+                voice.fade_start_gain = env_gain;
+            }
+
+            let release_progress = if voice.release_samples > 0 {
+                voice.fade_out_pos as f32 / voice.release_samples as f32
+            } else {
+                1.0
+            };
+
+            if release_progress >= 1.0 {
+                voice.stopped = true;
+                return false;
+            }
+            env_gain = voice.fade_start_gain * (1.0 - release_progress);
+            voice.fade_out_pos += 1;
+        } else {
+            voice.fade_position += 1;
+        }
+
+        // A one-shot ends at its region end (or the end of the file) even
+        // when its release has not run out, in both channel layouts
+        if !voice.looping && voice.position >= voice.loop_end.min(data_len as f64) {
+            voice.stopped = true;
+            return false;
+        }
+
+        let gain = voice.gain.next() * env_gain;
+        let take = crushing && voice.crush.advance();
+        let filtering = voice.filter.advance();
+
+        // Mix samples with Linear Interpolation
+
+        let mut s_visual = 0.0f32;
+
+        if b_channels == 1 {
+            let pos_idx = voice.position.floor() as usize;
+            let frac = (voice.position - pos_idx as f64) as f32;
+
+            if pos_idx >= data_len {
+                voice.stopped = true;
+                return false;
+            }
+
+            let s1 = voice.buffer.data[pos_idx];
+            let s2 = if pos_idx + 1 < data_len {
+                voice.buffer.data[pos_idx + 1]
+            } else {
+                0.0
+            };
+            let mut s_raw = s1 * (1.0 - frac) + s2 * frac;
+            if crushing {
+                s_raw = voice.crush.process(0, s_raw, take);
+            }
+            if filtering {
+                s_raw = voice.filter.process(0, s_raw);
+            }
+            if driving {
+                let mut frame = [s_raw];
+                voice.drive.process(&mut frame);
+                s_raw = frame[0];
+            }
+            let s = s_raw * gain;
+
+            voice.current_peak = f32::max(voice.current_peak, s_raw.abs());
+            s_visual = s_raw;
+
+            left[i] = s;
+            right[i] = s;
+            voice.position += voice.playback_rate;
+        } else if b_channels >= 2 {
+            // Interleaved Stereo: pos must be multiple of 2
+            let base_pos = (voice.position / 2.0).floor() * 2.0;
+            let pos_idx = base_pos as usize;
+            let frac = ((voice.position - base_pos) / 2.0) as f32;
+
+            if pos_idx + 1 < data_len {
+                // Left
+                let l1 = voice.buffer.data[pos_idx];
+                let l2 = if pos_idx + 2 < data_len {
+                    voice.buffer.data[pos_idx + 2]
+                } else {
+                    l1
+                };
+                let mut l_raw = l1 * (1.0 - frac) + l2 * frac;
+                if crushing {
+                    l_raw = voice.crush.process(0, l_raw, take);
+                }
+                if filtering {
+                    l_raw = voice.filter.process(0, l_raw);
+                }
+
+                // Right
+                let r1 = voice.buffer.data[pos_idx + 1];
+                let r2 = if pos_idx + 3 < data_len {
+                    voice.buffer.data[pos_idx + 3]
+                } else {
+                    r1
+                };
+                let mut r_raw = r1 * (1.0 - frac) + r2 * frac;
+                if crushing {
+                    r_raw = voice.crush.process(1, r_raw, take);
+                }
+                if filtering {
+                    r_raw = voice.filter.process(1, r_raw);
+                }
+
+                if driving {
+                    let mut frame = [l_raw, r_raw];
+                    voice.drive.process(&mut frame);
+                    [l_raw, r_raw] = frame;
+                }
+                left[i] = l_raw * gain;
+                right[i] = r_raw * gain;
+
+                voice.current_peak =
+                    f32::max(voice.current_peak, (l_raw.abs() + r_raw.abs()) * 0.5);
+                s_visual = (l_raw + r_raw) * 0.5;
+            }
+
+            voice.position += voice.playback_rate * 2.0;
+        }
+
+        // Record peak and sample for this voice
+        voice.meter_peak = f32::max(voice.meter_peak, voice.current_peak);
+        if let Some(mark) = meter_mark(window_pos + i, meter_step) {
+            // A voice that started mid-window is silent before it
+            voice.meter_samples.resize(mark, 0.0);
+            voice.meter_samples.push(s_visual);
+        }
+
+        // Handle Looping
+        if !voice.is_fading_out
+            && voice.looping
+            && (voice.position >= voice.loop_end || voice.position >= (data_len as f64))
+        {
+            // Carry the overshoot (and its offset within the frame) over so
+            // the loop keeps its exact length and channels stay in place
+            let end = voice.loop_end.min(data_len as f64);
+            let length = end - voice.loop_start;
+            voice.position = if length > 0.0 {
+                voice.loop_start + (voice.position - end) % length
+            } else {
+                voice.loop_start
+            };
+        }
+    }
+    true
+}
+
+impl Mixer {
+    /// Mixes a block of at most `MIX_BLOCK` frames into `left`/`right`. Each
+    /// voice renders the whole block into scratch, summed in with plain slice
+    /// loops the compiler vectorizes. Voices are summed in the order the
+    /// per-frame loop used, so the output does not change.
+    fn mix_block(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let len = left.len();
+        let (window_pos, meter_step) = (self.window_pos, self.meter_step);
+        let retired = &mut self.retired;
+        let counters = &self.counters;
+        let groups = &self.groups;
+        let mut fade = [[1.0f32; MIX_BLOCK]; 2];
+        for (gains, slew) in fade.iter_mut().zip(self.crossfade.iter_mut()) {
+            for gain in gains[..len].iter_mut() {
+                *gain = slew.next();
+            }
+        }
+        // What the looper records, before the master like the layers it plays
+        let sources = self.looper.capture_sources();
+        let mut capture_left = [0.0f32; MIX_BLOCK];
+        let mut capture_right = [0.0f32; MIX_BLOCK];
+        let mut send_left = [0.0f32; MIX_BLOCK];
+        let mut send_right = [0.0f32; MIX_BLOCK];
+        let mut sending = false;
+        self.voices.retain(|voice| {
+            let mut voice_left = [0.0f32; MIX_BLOCK];
+            let mut voice_right = [0.0f32; MIX_BLOCK];
+            let playing = render_voice(
+                voice,
+                &mut voice_left[..len],
+                &mut voice_right[..len],
+                window_pos,
+                meter_step,
+            );
+            // Last line of defense: one NaN would silence the whole mix for good
+            let rendered = voice_left[..len].iter().chain(&voice_right[..len]);
+            if !rendered.fold(true, |finite, sample| finite & sample.is_finite()) {
+                counters.non_finite.fetch_add(1, Ordering::Relaxed);
+                voice.end_reason = VoiceEndReason::Stopped;
+                retire_voice(voice, retired);
+                return false;
+            }
+            if let Some(group) = groups[voice.slot] {
+                let gains = &fade[group as usize][..len];
+                for (sample, gain) in voice_left[..len].iter_mut().zip(gains) {
+                    *sample *= gain;
+                }
+                for (sample, gain) in voice_right[..len].iter_mut().zip(gains) {
+                    *sample *= gain;
+                }
+            }
+            for (mixed, sample) in left.iter_mut().zip(&voice_left[..len]) {
+                *mixed += sample;
+            }
+            for (mixed, sample) in right.iter_mut().zip(&voice_right[..len]) {
+                *mixed += sample;
+            }
+            if voice.send.current > 0.0 || !voice.send.settled() {
+                sending = true;
+                let sends = send_left.iter_mut().zip(send_right.iter_mut());
+                for (i, (send_l, send_r)) in sends.take(len).enumerate() {
+                    let level = voice.send.next();
+                    *send_l += voice_left[i] * level;
+                    *send_r += voice_right[i] * level;
+                }
+            }
+            if sources.is_some_and(|sources| sources >> voice.slot & 1 == 1) {
+                for (captured, sample) in capture_left.iter_mut().zip(&voice_left[..len]) {
+                    *captured += sample;
+                }
+                for (captured, sample) in capture_right.iter_mut().zip(&voice_right[..len]) {
+                    *captured += sample;
+                }
+            }
+            if !playing {
+                retire_voice(voice, retired);
+            }
+            playing
+        });
+        let start_in = self.looper.frames_to_start(&self.clock);
+        self.looper.process(
+            left,
+            right,
+            (&capture_left[..len], &capture_right[..len]),
+            start_in,
+            window_pos,
+            meter_step,
+        );
+        if sending || !self.delay.idle() {
+            let send = (&send_left[..len], &send_right[..len]);
+            self.delay.process(send, left, right, self.clock.bpm);
+        }
+    }
+
+    /// Applies everything queued since the last buffer
+    fn apply_commands(&mut self) {
+        while let Some(command) = self.commands.pop() {
+            match command {
+                Command::Play(mut voice) => {
+                    // Sent before a device change, it is still in the old rate
+                    voice.retime(self.sample_rate);
+                    self.voices.insert(voice, &mut self.retired);
+                }
+                Command::Launch(mut voice) => {
+                    voice.retime(self.sample_rate);
+                    self.launches
+                        .launch(voice, &self.clock, &mut self.voices, &mut self.retired);
+                }
+                Command::Stop { slot, release } => {
+                    self.launches.cancel(Some(slot), &mut self.retired);
+                    for voice in self.voices.iter_mut().filter(|voice| voice.slot == slot) {
+                        voice.stop(release);
+                    }
+                }
+                Command::Update {
+                    slot,
+                    volume,
+                    looping,
+                    start_time,
+                    end_time,
+                    crush,
+                    filter,
+                    drive,
+                } => {
+                    for voice in self.voices.iter_mut() {
+                        if voice.slot == slot && !voice.stopped {
+                            voice.gain.set(volume * voice.velocity * voice.makeup_gain);
+                            voice.crush.set(crush);
+                            voice.filter.set(filter);
+                            voice.drive.set(drive);
+                            voice.loop_start = sample_index(start_time, &voice.buffer);
+                            voice.loop_end = sample_index(end_time, &voice.buffer);
+                            voice.set_looping(looping);
+                        }
+                    }
+                }
+                Command::StopAll { release } => {
+                    self.launches.cancel(None, &mut self.retired);
+                    for voice in self.voices.iter_mut() {
+                        voice.stop(release);
+                    }
+                }
+                Command::MasterVolume(volume) => self.master.set(volume),
+                Command::Compressor(settings) => self.compressor.set(settings),
+                Command::Delay(settings) => self.delay.set(settings),
+                Command::Send { slot, level } => {
+                    for voice in self.voices.iter_mut().filter(|voice| voice.slot == slot) {
+                        voice.send.set(level);
+                    }
+                }
+                Command::Tempo(bpm) => self.clock.set_bpm(bpm as f64),
+                Command::Swing(swing) => {
+                    self.sequencer.set_swing(swing);
+                    self.launches.set_swing(swing);
+                }
+                Command::LaunchQuantize(division) => self.launches.set_division(division),
+                Command::SeqStart { lane, beat } => {
+                    self.sequencer.start(lane, beat, &self.counters)
+                }
+                Command::SeqStop(lane) => self.sequencer.stop(lane),
+                Command::LooperRecord {
+                    layer,
+                    beat,
+                    count_in,
+                } => {
+                    self.looper.record(layer, beat);
+                    match count_in {
+                        Some(start) => self.metronome.count_in(start, beat),
+                        None => self.metronome.stop(),
+                    }
+                }
+                Command::LooperOverdub(layer) => self.looper.overdub(layer),
+                Command::RecordStart {
+                    link,
+                    beat,
+                    count_in,
+                } => {
+                    self.recorder.record(link, beat);
+                    if let (Some(start), Some(beat)) = (count_in, beat) {
+                        self.metronome.count_in(start, beat);
+                    }
+                }
+                Command::RecordEnd { cancel } => {
+                    if self.recorder.armed() {
+                        self.metronome.stop();
+                    }
+                    self.recorder.end(cancel);
+                }
+                Command::LooperUndo => {
+                    self.looper.undo();
+                    if self.looper.idle() {
+                        self.metronome.stop();
+                    }
+                }
+                Command::LooperClear => {
+                    self.looper.clear();
+                    self.metronome.stop();
+                }
+                Command::Crossfade(gains) => {
+                    for (slew, gain) in self.crossfade.iter_mut().zip(gains) {
+                        slew.set(gain);
+                    }
+                }
+                Command::PadGroup { slot, group } => self.groups[slot] = group,
+            }
+        }
+    }
+
+    /// Turns the voices' window into pad levels (Window-level peak tracking),
+    /// publishes them and starts the next window. A pad's samples are the sum
+    /// of its voices; voices stay in trigger order, so the newest voice of a
+    /// pad sets the envelope.
+    fn close_window(&mut self) {
+        self.clear_levels();
+        let device_sr = self.sample_rate as f64;
+        let levels = &mut self.levels;
+        self.voices.for_each_ordered(|voice| {
+            let level = &mut levels[voice.slot];
+            level.live = true;
+            let visual = &mut level.visual;
+            visual.peak = f32::max(visual.peak, voice.meter_peak);
+            if visual.samples.len() < voice.meter_samples.len() {
+                visual.samples.resize(voice.meter_samples.len(), 0.0);
+            }
+            for (mixed, sample) in visual.samples.iter_mut().zip(&voice.meter_samples) {
+                *mixed += sample;
+            }
+            voice.meter_peak = 0.0;
+            voice.meter_samples.clear();
+
+            let (stage, gain, remaining) = voice.envelope(device_sr);
+            visual.envelope_stage = Some(stage);
+            visual.envelope_gain = Some(gain);
+            visual.remaining_secs = remaining;
+        });
+        self.looper.meter(levels);
+        self.window_pos = 0;
+        self.publish_levels();
+        self.send_voice_events();
+    }
+
+    /// Resets every pad's levels for a new window, keeping the sample buffers
+    fn clear_levels(&mut self) {
+        for level in self.levels.iter_mut().filter(|level| level.live) {
+            level.live = false;
+            level.visual.peak = 0.0;
+            level.visual.samples.clear();
+            level.visual.envelope_stage = None;
+            level.visual.envelope_gain = None;
+            level.visual.remaining_secs = None;
+        }
+    }
+
+    /// Hands the window's levels to `fill_levels` in a recycled snapshot.
+    /// With no spare one, the oldest unread snapshot is overwritten instead.
+    fn publish_levels(&mut self) {
+        let queues = &self.level_queues;
+        let mut snapshot = match queues.free.pop().or_else(|| queues.ready.pop()) {
+            Some(snapshot) => snapshot,
+            None => return, // All in the reader's hands for the moment
+        };
+        for (copy, level) in snapshot.levels.iter_mut().zip(self.levels.iter()) {
+            copy.live = level.live;
+            if level.live {
+                copy.visual.copy_from(&level.visual);
+            }
+        }
+        snapshot.active.clear();
+        snapshot
+            .active
+            .extend(self.voices.ordered().map(|voice| voice.slot));
+        snapshot.active.extend(self.looper.slots());
+        snapshot.compressor_reduction = self.compressor.take_reduction();
+        snapshot.limiter_reduction = self.limiter.take_reduction();
+        // Room for every snapshot in circulation, so this never drops one
+        let _ = queues.ready.push(snapshot);
+    }
+
+    /// Sends the window's `voice-ended` events. The levels without those
+    /// voices are already published, so a `get_levels` issued once an event
+    /// arrives never lists its voice. A full queue drops (and counts) events
+    /// rather than blocking.
+    fn send_voice_events(&mut self) {
+        for event in self.retired.drain(..) {
+            if let Err(mpsc::TrySendError::Full(_)) = self.voice_ended.try_send(event) {
+                self.counters
+                    .dropped_voice_events
+                    .fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+impl Voice {
+    /// Asks for the release, which `render_voice` starts from the current
+    /// gain (symmetric to the attack when stopped during it, unless
+    /// `release` overrides it). Voices already fading are left alone.
+    fn stop(&mut self, release: Option<f32>) {
+        if self.stopped || self.is_fading_out {
+            return;
+        }
+        if let Some(release) = release {
+            self.release = release;
+            self.release_samples = (release as f64 * self.device_rate as f64) as usize;
+            self.custom_release_set = true; // Prevent symmetry override
+        }
+        self.stop_command = true;
+        self.end_reason = VoiceEndReason::Stopped;
+    }
+
+    /// Redoes everything kept in device samples for a new device rate from
+    /// the voice's seconds, keeping its progress through the envelope
+    fn retime(&mut self, sample_rate: u32) {
+        if sample_rate == self.device_rate {
+            return;
+        }
+        let scale = sample_rate as f64 / self.device_rate as f64;
+        let device_sr = sample_rate as f64;
+        self.playback_rate = self.buffer.sample_rate as f64 / device_sr * self.speed;
+        self.attack_samples = (self.attack as f64 * device_sr) as usize;
+        self.release_samples = (self.release as f64 * device_sr) as usize;
+        self.fade_position = (self.fade_position as f64 * scale) as usize;
+        self.fade_out_pos = (self.fade_out_pos as f64 * scale) as usize;
+        self.gain.retime(sample_rate, LEVEL_SLEW_MS);
+        self.crush.retime(sample_rate);
+        self.filter.retime(sample_rate);
+        self.drive.retime(sample_rate);
+        self.send.retime(sample_rate, LEVEL_SLEW_MS);
+        self.device_rate = sample_rate;
+    }
+
+    /// Applies a looping change, after the region change that came with it.
+    /// A voice past its region end when looping flips is brought back to the
+    /// start (the loop check only wraps voices already looping). Turning looping
+    /// on takes back a natural release the one-shot logic had started;
+    /// turning it off shortens the release to what is left of the region so
+    /// the voice fades out instead of being cut at the end.
+    fn set_looping(&mut self, looping: bool) {
+        let was_looping = std::mem::replace(&mut self.looping, looping);
+        if self.is_fading_out && (self.stop_command || !looping || was_looping) {
+            return; // A stop, or a natural release that still applies
+        }
+        if self.is_fading_out {
+            let (_, gain, _) = self.envelope(self.device_rate as f64);
+            self.is_fading_out = false;
+            self.fade_out_pos = 0;
+            self.ease_in(gain);
+        }
+        let end = self.loop_end.min(self.buffer.data.len() as f64);
+        if looping != was_looping && self.position >= end {
+            self.position = self.loop_start;
+            self.ease_in(0.0);
+        }
+        if was_looping && !looping {
+            let channels = self.buffer.channels as f64;
+            let remaining = (end - self.position) / (self.playback_rate * channels);
+            if remaining < self.release_samples as f64 {
+                self.release_samples = remaining as usize;
+                self.release = self.release_samples as f32 / self.device_rate as f32;
+            }
+        }
+    }
+
+    /// Starts the declick ramp from `gain` up to the envelope
+    fn ease_in(&mut self, gain: f32) {
+        self.declick_from = gain;
+        self.declick_left = self.declick_frames();
+    }
+
+    fn declick_frames(&self) -> usize {
+        ((DECLICK_MS / 1000.0 * self.device_rate as f32) as usize).max(1)
+    }
+
+    /// Stage, gain and seconds to the end, mirroring the envelope math of `write_audio`
+    fn envelope(&self, device_sr: f64) -> (EnvelopeStage, f32, Option<f32>) {
+        if self.is_fading_out {
+            let progress = if self.release_samples > 0 {
+                (self.fade_out_pos as f32 / self.release_samples as f32).min(1.0)
+            } else {
+                1.0
+            };
+            let remaining = self.release_samples.saturating_sub(self.fade_out_pos) as f64;
+            return (
+                EnvelopeStage::Release,
+                self.fade_start_gain * (1.0 - progress),
+                Some((remaining / device_sr) as f32),
+            );
+        }
+
+        let remaining = (!self.looping).then(|| {
+            let file_samples_remaining = (self.loop_end - self.position).max(0.0);
+            let device_samples_remaining =
+                file_samples_remaining / (self.playback_rate * self.buffer.channels as f64);
+            (device_samples_remaining / device_sr) as f32
+        });
+        if self.fade_position < self.attack_samples {
+            let gain = self.fade_position as f32 / self.attack_samples as f32;
+            (EnvelopeStage::Attack, gain, remaining)
+        } else {
+            (EnvelopeStage::Sustain, 1.0, remaining)
+        }
+    }
+}
+
+/// Holds back the `voice-ended` event of a voice `write_audio` is dropping
+/// until the buffer's levels are out (see `Mixer::send_voice_events`).
+/// Called from the audio callback: the key is moved out instead of cloned.
+fn retire_voice(voice: &mut Voice, retired: &mut Vec<VoiceEnded>) {
+    if voice.key == BLIP_KEY {
+        return;
+    }
+    retired.push(VoiceEnded {
+        key: std::mem::take(&mut voice.key),
+        voice_id: voice.id,
+        reason: voice.end_reason,
+    });
+}
+
+/// A one-shot's attack and release squeezed into the `region` seconds it
+/// plays for. When they don't fit, both shrink by the same factor: the
+/// attack still reaches full gain and the release starts right there,
+/// ending with the region instead of being cut off at it.
+fn fit_envelope(attack: f32, release: f32, region: f32) -> (f32, f32) {
+    let total = attack + release;
+    if total <= region || total <= 0.0 {
+        return (attack, release);
+    }
+    let scale = region.max(0.0) / total;
+    (attack * scale, release * scale)
+}
+
+/// Fits a playback region into a file of `duration` seconds. Times past
+/// either end are pulled in; a region that is inverted or empty once inside
+/// the file is refused, since its voice would end the moment it starts.
+fn clamp_region(start: f32, end: f32, duration: f32) -> Result<(f32, f32), String> {
+    if start > end {
+        return Err(format!(
+            "[Social Noise] Region starts after it ends ({:.3} s > {:.3} s)",
+            start, end
+        ));
+    }
+    let (clamped_start, clamped_end) = (start.clamp(0.0, duration), end.clamp(0.0, duration));
+    if clamped_end <= clamped_start {
+        return Err(format!(
+            "[Social Noise] Region {:.3}-{:.3} s is empty inside the {:.3} s file",
+            start, end, duration
+        ));
+    }
+    if (clamped_start, clamped_end) != (start, end) {
+        debug!(
+            "Region {}-{} s clamped to {}-{} s",
+            start, end, clamped_start, clamped_end
+        );
+    }
+    Ok((clamped_start, clamped_end))
+}
+
+/// A pad's voice for `params`, as `play_sound` sends it. Clamps the region
+/// of `params` to the file.
+fn pad_voice(
+    state: &mut AudioEngineState,
+    key: String,
+    params: &mut PlayParams,
+) -> Result<Voice, String> {
+    let buffer = state
+        .sound_bank
+        .get(&key)
+        .map(|entry| Arc::clone(&entry.buffer))
+        .ok_or("Sound not found")?;
+    (params.start_time, params.end_time) =
+        clamp_region(params.start_time, params.end_time, buffer.duration)
+            .map_err(|e| format!("{}: {}", key, e))?;
+
+    let device_rate = state.sample_rate;
+    let device_sr = device_rate as f64;
+    let file_sr = buffer.sample_rate as f64;
+    let mut speed = 1.0;
+
+    // A backend-side half/double correction wins over the frontend's value
+    let sample_bpm = state
+        .bpm_overrides
+        .get(&key)
+        .copied()
+        .unwrap_or(params.sample_bpm);
+
+    // A failed detection's placeholder would stretch the loop to a wrong tempo
+    let placeholder = matches!(
+        state.analysis.get(&key).map(|pad| &pad.status),
+        Some(AnalysisStatus::Complete {
+            bpm_source: BpmSource::Fallback,
+            ..
+        })
+    );
+    if params.sync && sample_bpm > 0.0 && (!placeholder || params.force_sync) {
+        let ratio = state.master_bpm / sample_bpm;
+        speed *= ratio as f64;
+    } else if params.sync {
+        debug!("Not syncing {}: no detected BPM", key);
+    }
+
+    // Convert time params to samples relative to the FILE's sample rate
+    // We track position as sample index in the interleaved buffer
+    let start_pos = sample_index(params.start_time, &buffer);
+    let end_pos = sample_index(params.end_time, &buffer);
+
+    // A one-shot's envelope has to fit the time its region plays for
+    let (attack, release) = if params.looping {
+        (params.attack, params.release)
+    } else {
+        let region = ((params.end_time - params.start_time) as f64 / speed) as f32;
+        fit_envelope(params.attack, params.release, region)
+    };
+
+    // Envelope is tracked in DEVICE samples for consistent timing
+    let attack_samples = (attack as f64 * device_sr) as usize;
+    let release_samples = (release as f64 * device_sr) as usize;
+
+    let makeup_gain = state.makeup_gains.get(&key).copied().unwrap_or(1.0);
+    let slot = state.pad_slots.get_or_assign(&key)?;
+    state.next_voice_id += 1;
+    let id = state.next_voice_id;
+
+    Ok(Voice {
+        id,
+        key,
+        slot,
+        buffer,
+        position: start_pos,
+        playback_rate: file_sr / device_sr * speed,
+        speed,
+        device_rate,
+        looping: params.looping,
+        loop_start: start_pos,
+        loop_end: end_pos,
+        gain: Slew::new(
+            params.volume * params.velocity * makeup_gain,
+            device_rate,
+            LEVEL_SLEW_MS,
+        ),
+        makeup_gain,
+        velocity: params.velocity,
+        attack,
+        release,
+        attack_samples,
+        release_samples,
+        crush: Crusher::new(CrushSettings::of(params), device_rate),
+        filter: VoiceFilter::new(FilterSettings::of(params), device_rate),
+        drive: Drive::new(params.drive, device_rate),
+        send: Slew::new(send_level(params), device_rate, LEVEL_SLEW_MS),
+        stopped: false,
+        fade_position: 0,
+        is_fading_out: false,
+        fade_start_gain: 1.0,
+        fade_out_pos: 0,
+        current_peak: 0.0,
+        meter_peak: 0.0,
+        meter_samples: Vec::with_capacity(METER_SAMPLES),
+        stop_command: false,
+        custom_release_set: false,
+        declick_from: 0.0,
+        declick_left: 0,
+        end_reason: VoiceEndReason::Natural,
+    })
+}
+
+/// `AudioEngine::resolve_play_params` on an already locked state
+fn resolve_params(
+    state: &AudioEngineState,
+    key: &str,
+    partial: PartialPlayParams,
+) -> Result<PlayParams, String> {
+    let partial = match state.pad_defaults.get(key) {
+        Some(defaults) => partial.or(defaults.clone()),
+        None => partial,
+    };
+    let end_time = match partial.end_time {
+        Some(end_time) => end_time,
+        None => {
+            state
+                .sound_bank
+                .get(key)
+                .ok_or("Sound not found")?
+                .buffer
+                .duration
+        }
+    };
+    Ok(partial.over(PlayParams {
+        volume: 1.0,
+        attack: 0.0,
+        release: 0.0,
+        looping: false,
+        start_time: 0.0,
+        end_time,
+        sync: false,
+        sample_bpm: 0.0,
+        velocity: full_velocity(),
+        force_sync: false,
+        filter_cutoff: FILTER_OPEN,
+        filter_env_amount: 0.0,
+        filter_env_attack: 0.0,
+        filter_env_decay: 0.0,
+        crush_bits: MAX_CRUSH_BITS,
+        crush_downsample: 1,
+        drive: 0.0,
+        delay_send: 0.0,
+        sends_muted: false,
+    }))
+}
+
+/// Index of the visual sample taken on frame `at` of the meter window, on
+/// every `meter_step`-th frame
+fn meter_mark(at: usize, meter_step: usize) -> Option<usize> {
+    (at % meter_step == 0)
+        .then_some(at / meter_step)
+        .filter(|&mark| mark < METER_SAMPLES)
+}
+
+/// Slot bits of the loaded `pads` for a looper layer, every pad when None
+fn pad_mask(state: &mut AudioEngineState, pads: Option<Vec<String>>) -> Result<u128, String> {
+    let pads = match pads {
+        Some(pads) => pads,
+        None => return Ok(ALL_PADS),
+    };
+    let mut mask = 0;
+    for key in pads {
+        if !state.sound_bank.contains_key(&key) {
+            return Err(not_loaded(&key));
+        }
+        mask |= 1 << state.pad_slots.get_or_assign(&key)?;
+    }
+    Ok(mask)
+}
+
+/// Reserved pad id of looper layer `layer`, for its levels
+fn looper_key(layer: usize) -> String {
+    format!("{}{}", LOOPER_KEY_PREFIX, layer)
+}
+
+/// Carries the sends of `stored` over to `params`
+fn keep_sends(params: &mut PlayParams, stored: &PlayParams) {
+    params.delay_send = stored.delay_send;
+    params.sends_muted = stored.sends_muted;
+}
+
+/// What a voice of `params` sends into the delay bus
+fn send_level(params: &PlayParams) -> f32 {
+    if params.sends_muted {
+        0.0
+    } else {
+        params.delay_send
+    }
+}
+
+/// Bars of a count-in, 0 for none
+fn check_count_in(bars: Option<u32>) -> Result<u32, String> {
+    let bars = bars.unwrap_or(0);
+    if bars > MAX_COUNT_IN_BARS {
+        return Err(format!(
+            "[Social Noise] A count-in of {} bars is outside 0..={}",
+            bars, MAX_COUNT_IN_BARS
+        ));
+    }
+    Ok(bars)
+}
+
+/// Error for a pad id with no sound loaded
+fn not_loaded(key: &str) -> String {
+    format!("[Social Noise] No sound loaded on {}", key)
+}
+
+/// Interleaved index of the frame at `seconds`. Always a multiple of the
+/// channel count, so a stereo position never lands on a right sample. A
+/// time that f32 rounding leaves a hair short of a frame still lands on it.
+fn sample_index(seconds: f32, buffer: &AudioBuffer) -> f64 {
+    let frame = seconds as f64 * buffer.sample_rate as f64;
+    (frame + 1e-3).floor() * buffer.channels as f64
+}
+
+// REPLACED THIS DECODE BLOCK WITH THE ONE BELLOW THIS ONE FOR OPTIMIZATION VIA SAMPLE DECIMATION
+/*
+fn decode_file(path: &str) -> Result<AudioBuffer, String> {
+    let src = File::open(path).map_err(|e| e.to_string())?;
+    let mss = MediaSourceStream::new(Box::new(src), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = Path::new(path).extension() {
+        hint.with_extension(&ext.to_string_lossy());
+    }
+
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            mss,
+            &symphonia::core::formats::FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let mut format_reader = probed.format;
+    let (track_id, codec_params) = {
+        let track = format_reader
+            .tracks()
+            .iter()
+            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or("No supported audio track found")?;
+        (track.id, track.codec_params.clone())
+    };
+
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&codec_params, &DecoderOptions::default())
+        .map_err(|e| e.to_string())?;
+
+    let mut pcm_data = Vec::new();
+    let sample_rate = codec_params.sample_rate.unwrap_or(44100);
+    let channels = codec_params.channels.map(|c| c.count() as u16).unwrap_or(2);
+
+    loop {
+        let packet = match format_reader.next_packet() {
+            Ok(packet) => packet,
+            Err(symphonia::core::errors::Error::IoError(ref e))
+                if e.kind() == std::io::ErrorKind::UnexpectedEof =>
+            {
+                break
+            }
+            Err(e) => return Err(e.to_string()),
+        };
+
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = decoder.decode(&packet).map_err(|e| e.to_string())?;
+        let mut sample_buf = SampleBuffer::<f32>::new(decoded.capacity() as u64, *decoded.spec());
+        sample_buf.copy_interleaved_ref(decoded);
+        pcm_data.extend_from_slice(sample_buf.samples());
+    }
+
+    let duration = pcm_data.len() as f32 / (sample_rate as f32 * channels as f32);
+
+    if channels == 0 {
+        return Err("Invalid audio: 0 channels".to_string());
+    }
+
+    // BPM Detection using stratum_dsp
+    // We typically want a mono signal for detection.
+    // PERFORMANCE FIX: Limit analysis to first 60 seconds (was 30) to catch tracks with longer intros.
+    let analysis_limit_samples = (sample_rate * 15) as usize; // Lowered from 60 to 15 furthermore
+    let mono_data: Vec<f32> = pcm_data
+        .chunks(channels as usize)
+        .take(analysis_limit_samples)
+        .map(|chunk| chunk.iter().sum::<f32>() / channels as f32)
+        .collect();
+
+    let mut config = AnalysisConfig::default();
+    config.bpm_resolution = 0.1; // Higher resolution for detection
+    config.enable_bpm_fusion = true; // Use consensus between tempogram and legacy
+
+    let detected_bpm = analyze_audio(&mono_data, sample_rate, config)
+        .map(|res| res.bpm)
+        .unwrap_or(120.0);
+
+    // Heuristic: Many loops are exact integers. If we are within 0.1 BPM of an integer, snap to it.
+    let bpm = if (detected_bpm - detected_bpm.round()).abs() < 0.1 {
+        detected_bpm.round()
+    } else {
+        detected_bpm
+    };
+
+    println!(
+        "[BackendBPM] Detected: {} (raw: {}) for file",
+        bpm, detected_bpm
+    );
+
+    // Generate downsampled waveform (e.g., 400 points)
+    let mut waveform = Vec::with_capacity(400);
+    if !pcm_data.is_empty() {
+        let step = (pcm_data.len() / (channels as usize)) / 400;
+        let step = if step == 0 { 1 } else { step };
+
+        for i in 0..400 {
+            let start = i * step * (channels as usize);
+            let end = (start + step * (channels as usize)).min(pcm_data.len());
+            if start >= pcm_data.len() {
+                break;
+            }
+
+            let mut peak = 0.0f32;
+            for j in start..end {
+                peak = peak.max(pcm_data[j].abs());
+            }
+            waveform.push(peak);
+        }
+    }
+
+    Ok(AudioBuffer {
+        data: pcm_data,
+        sample_rate,
+        channels,
+        duration,
+        bpm,
+        waveform,
+    })
+}
+// END OF REPLACED DECODE BLOCK
+*/
+
+/// File source that publishes how far it has been read, for decode progress
+struct TrackedFile {
+    file: File,
+    len: Option<u64>,
+    position: Arc<AtomicU64>,
+}
+
+impl Read for TrackedFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.file.read(buf)?;
+        self.position.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+impl Seek for TrackedFile {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let at = self.file.seek(pos)?;
+        self.position.store(at, Ordering::Relaxed);
+        Ok(at)
+    }
+}
+
+impl MediaSource for TrackedFile {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        self.len
+    }
+}
+
+/// Opens and probes `path`. `position` follows the bytes consumed from the file.
+fn probe_file(path: &str, position: &Arc<AtomicU64>) -> Result<ProbeResult, LoadError> {
+    let name = file_label(path);
+    let src = File::open(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => LoadError::new(
+            LoadErrorKind::FileMissing,
+            format!("{} no longer exists", name),
+        ),
+        _ => LoadError::new(
+            LoadErrorKind::Other,
+            format!("{} cannot be opened: {}", name, e),
+        ),
+    })?;
+    let len = src.metadata().map(|m| m.len()).ok();
+    if len == Some(0) {
+        return Err(LoadError::new(
+            LoadErrorKind::EmptyAudio,
+            format!("{} is empty (0 bytes)", name),
+        ));
+    }
+
+    let src = TrackedFile {
+        file: src,
+        len,
+        position: Arc::clone(position),
+    };
+    let mss = MediaSourceStream::new(Box::new(src), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = Path::new(path).extension() {
+        hint.with_extension(&ext.to_string_lossy().to_lowercase());
+    }
+
+    symphonia::default::get_probe()
+        .format(
+            &hint,
+            mss,
+            &symphonia::core::formats::FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| match e {
+            SymphoniaError::Unsupported(what) => LoadError::new(
+                LoadErrorKind::UnsupportedCodec,
+                format!("Container unsupported: {}", what),
+            ),
+            // The WAV/AIFF reader refuses a header of 0 (or over 32) channels itself
+            SymphoniaError::DecodeError(what) if what.ends_with("invalid channel count") => {
+                LoadError::new(
+                    LoadErrorKind::ZeroChannels,
+                    format!("{} declares no usable audio channels", name),
+                )
+            }
+            e => stream_error(&name, e),
+        })
+}
+
+fn file_label(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+/// Read/decode failures past the header mean the data itself is cut off or damaged
+fn stream_error(name: &str, e: SymphoniaError) -> LoadError {
+    match e {
+        SymphoniaError::IoError(_) | SymphoniaError::DecodeError(_) => LoadError::new(
+            LoadErrorKind::TruncatedStream,
+            format!("{} is truncated or damaged ({})", name, e),
+        ),
+        e => LoadError::new(LoadErrorKind::Other, format!("{}: {}", name, e)),
+    }
+}
+
+/// Reads title/artist/... without decoding any audio
+pub fn read_tags(path: &str) -> Result<FileTags, String> {
+    let mut probed = probe_file(path, &Arc::default()).map_err(|e| e.to_string())?;
+    Ok(collect_tags(&mut probed))
+}
+
+/// Merges tags found before the container (e.g. ID3v2) with the container's own
+/// (Vorbis comments, MP4 atoms). Container tags win when both are present.
+fn collect_tags(probed: &mut ProbeResult) -> FileTags {
+    let mut tags = FileTags::default();
+    let mut apply = |revision: &MetadataRevision| {
+        for tag in revision.tags() {
+            let slot = match tag.std_key {
+                Some(StandardTagKey::TrackTitle) => &mut tags.title,
+                Some(StandardTagKey::Artist) => &mut tags.artist,
+                Some(StandardTagKey::Album) => &mut tags.album,
+                Some(StandardTagKey::Genre) => &mut tags.genre,
+                Some(StandardTagKey::Comment) => &mut tags.comment,
+                _ => continue,
+            };
+            let value = tag.value.to_string().trim().to_string();
+            if !value.is_empty() {
+                *slot = value;
+            }
+        }
+    };
+
+    if let Some(metadata) = probed.metadata.get() {
+        if let Some(revision) = metadata.current() {
+            apply(revision);
+        }
+    }
+    if let Some(revision) = probed.format.metadata().current() {
+        apply(revision);
+    }
+    tags
+}
+
+/// Decodes a file up to `limit` interleaved samples, without the progress
+/// reports and cancellation of `load_sound`
+pub fn decode(path: &str, limit: usize) -> Result<AudioBuffer, LoadError> {
+    decode_file(path, limit, &AtomicU8::new(LOAD_RUNNING), &mut |_, _| {})
+}
+
+/// Damaged packets skipped before a decode gives up on the rest of the file
+const MAX_SKIPPED_PACKETS: usize = 100;
+
+/// Packets in a row that decode to nothing before the file counts as over
+const MAX_EMPTY_PACKETS: usize = 1000;
+
+/// Frames a container's count may overstate what it holds by (an AIFF's
+/// counts the SSND chunk's own header) before the file counts as cut short
+const FRAME_COUNT_SLACK: u64 = 64;
+
+/// Decodes a file up to `limit` interleaved samples (whole frames), reporting
+/// progress per stage as percent. Gives up once `cancel` leaves `LOAD_RUNNING`.
+/// Damage after the first frames is skipped or ends the decode early, noted
+/// in `decode_warnings`; only a file that yields no frames at all fails.
+fn decode_file(
+    path: &str,
+    limit: usize,
+    cancel: &AtomicU8,
+    progress: &mut dyn FnMut(LoadStage, f32),
+) -> Result<AudioBuffer, LoadError> {
+    let name = file_label(path);
+    let read_position = Arc::new(AtomicU64::new(0));
+    let mut probed = probe_file(path, &read_position)?;
+    let file_len = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    let tags = collect_tags(&mut probed);
+
+    let mut format_reader = probed.format;
+    let (track_id, codec_params) = {
+        let track = format_reader
+            .tracks()
+            .iter()
+            .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or_else(|| {
+                LoadError::new(
+                    LoadErrorKind::UnsupportedCodec,
+                    format!("{} has no supported audio track", name),
+                )
+            })?;
+        (track.id, track.codec_params.clone())
+    };
+
+    // e.g. an m4a holding a codec this build cannot decode
+    let codecs = symphonia::default::get_codecs();
+    let mut decoder = codecs
+        .make(&codec_params, &DecoderOptions::default())
+        .map_err(|e| match e {
+            SymphoniaError::Unsupported(_) => LoadError::new(
+                LoadErrorKind::UnsupportedCodec,
+                format!(
+                    "Codec unsupported: {}",
+                    codecs
+                        .get_codec(codec_params.codec)
+                        .map(|c| c.short_name.to_string())
+                        .unwrap_or_else(|| format!("{}", codec_params.codec))
+                ),
+            ),
+            e => stream_error(&name, e),
+        })?;
+
+    let mut pcm_data = Vec::new();
+    let mut sample_rate = codec_params.sample_rate.unwrap_or(44100);
+    let mut channels = codec_params.channels.map(|c| c.count() as u16).unwrap_or(2);
+    if channels == 0 {
+        return Err(LoadError::new(
+            LoadErrorKind::ZeroChannels,
+            format!("{} declares 0 audio channels", name),
+        ));
+    }
+
+    let requested = limit;
+    let mut limit = limit - limit % channels as usize;
+    let mut truncated = false;
+    let mut warnings = Vec::new();
+    let mut skipped = 0;
+    let mut empty_packets = 0;
+    loop {
+        if cancel.load(Ordering::Relaxed) != LOAD_RUNNING {
+            return Err(cancelled_load(cancel));
+        }
+        let packet = match format_reader.next_packet() {
+            Ok(packet) => packet,
+            Err(symphonia::core::errors::Error::IoError(ref e))
+                if e.kind() == std::io::ErrorKind::UnexpectedEof =>
+            {
+                break
+            }
+            // Whatever decoded before the damage is kept
+            Err(SymphoniaError::ResetRequired) => {
+                warnings.push("Stream changed format mid-file; decoding stopped there".into());
+                break;
+            }
+            Err(e) if !pcm_data.is_empty() => {
+                warnings.push(format!("Stopped at unreadable data: {}", e));
+                break;
+            }
+            Err(e) => return Err(stream_error(&name, e)),
+        };
+
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // A corrupt packet costs its own frames only
+            Err(SymphoniaError::DecodeError(e)) if skipped < MAX_SKIPPED_PACKETS => {
+                skipped += 1;
+                if skipped == 1 {
+                    warnings.push(format!("Skipped a damaged packet: {}", e));
+                }
+                continue;
+            }
+            Err(SymphoniaError::DecodeError(_)) => {
+                warnings.push(format!(
+                    "Gave up after {} damaged packets",
+                    MAX_SKIPPED_PACKETS
+                ));
+                break;
+            }
+            Err(SymphoniaError::ResetRequired) => {
+                warnings.push("Stream changed format mid-file; decoding stopped there".into());
+                break;
+            }
+            Err(e) if !pcm_data.is_empty() => {
+                warnings.push(format!("Stopped at undecodable data: {}", e));
+                break;
+            }
+            Err(e) => return Err(stream_error(&name, e)),
+        };
+        // Malformed files can yield packet after packet of nothing
+        if decoded.frames() == 0 {
+            empty_packets += 1;
+            if empty_packets == MAX_EMPTY_PACKETS {
+                warnings.push("Stopped after a run of empty packets".into());
+                break;
+            }
+            continue;
+        }
+        empty_packets = 0;
+        // The first frames settle what the container left out (an M4A track
+        // may carry no channel layout at all)
+        let spec = *decoded.spec();
+        if pcm_data.is_empty() && spec.channels.count() > 0 {
+            sample_rate = spec.rate;
+            channels = spec.channels.count() as u16;
+            limit = requested - requested % channels as usize;
+        }
+        let mut sample_buf = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        sample_buf.copy_interleaved_ref(decoded);
+        let samples = sample_buf.samples();
+        if pcm_data.len() + samples.len() > limit {
+            pcm_data.extend_from_slice(&samples[..limit - pcm_data.len()]);
+            truncated = true;
+            break;
+        }
+        pcm_data.extend_from_slice(samples);
+        if file_len > 0 {
+            let read = read_position.load(Ordering::Relaxed);
+            progress(LoadStage::Decoding, read as f32 / file_len as f32 * 100.0);
+        }
+    }
+    progress(LoadStage::Decoding, 100.0);
+    if skipped > 1 {
+        warnings.push(format!("{} damaged packets skipped in total", skipped));
+    }
+    // A file cut short just runs out, with no error to say so
+    let frames = (pcm_data.len() / channels as usize) as u64;
+    match codec_params.n_frames {
+        Some(expected) if !truncated && frames + FRAME_COUNT_SLACK < expected => {
+            warnings.push(format!(
+                "File ends after {} of its {} frames",
+                frames, expected
+            ));
+        }
+        _ => {}
+    }
+
+    // A header with no frames behind it would give a 0-duration buffer downstream
+    if pcm_data.is_empty() {
+        return Err(match warnings.first() {
+            Some(warning) => LoadError::new(
+                LoadErrorKind::TruncatedStream,
+                format!("{} has no decodable audio ({})", name, warning),
+            ),
+            None => LoadError::new(
+                LoadErrorKind::EmptyAudio,
+                format!("{} contains no audio frames", name),
+            ),
+        });
+    }
+
+    let duration = pcm_data.len() as f32 / (sample_rate as f32 * channels as f32);
+
+    progress(LoadStage::Analyzing, 0.0);
+    let (trim_start, trim_end) = detect_silence(&pcm_data, channels, sample_rate);
+    let lufs = measure_lufs(&pcm_data, channels, sample_rate);
+    progress(LoadStage::Analyzing, 50.0);
+
+    // ========================================================================
+    // Waveform Generation (Always happens for UI)
+    // ========================================================================
+    // Sampler metadata only exists in RIFF files; everything else skips the extra read
+    let smpl = read_smpl_chunk(path);
+    let embedded_loop = smpl.as_ref().and_then(|chunk| {
+        chunk.first_loop.map(|(start, end)| {
+            (
+                start as f32 / sample_rate as f32,
+                (end as f32 + 1.0) / sample_rate as f32,
+            )
+        })
+    });
+    let root_note = smpl.map(|chunk| chunk.unity_note);
+
+    let waveform = peak_waveform(&pcm_data, channels, None, WAVEFORM_POINTS);
+    // Per-channel peaks let the UI show L/R asymmetry; mono files mirror the merged data
+    let (waveform_left, waveform_right) = if channels >= 2 {
+        (
+            peak_waveform(&pcm_data, channels, Some(0), WAVEFORM_POINTS),
+            peak_waveform(&pcm_data, channels, Some(1), WAVEFORM_POINTS),
+        )
+    } else {
+        (waveform.clone(), waveform.clone())
+    };
+    progress(LoadStage::Analyzing, 100.0);
+
+    Ok(AudioBuffer {
+        data: pcm_data,
+        sample_rate,
+        channels,
+        duration,
+        waveform,
+        waveform_left,
+        waveform_right,
+        trim_start,
+        trim_end,
+        lufs,
+        embedded_loop,
+        root_note,
+        tags,
+        truncated,
+        decode_warnings: warnings,
+    })
+}
+
+// ========================================================================
+// WAV Sampler Chunk (`smpl`)
+// ========================================================================
+
+struct SmplChunk {
+    unity_note: u8,
+    first_loop: Option<(u32, u32)>, // Start and inclusive end, in sample frames
+}
+
+/// Walks the RIFF chunk list looking for `smpl`. Returns None for non-RIFF files,
+/// files without the chunk, or anything malformed, so those load exactly as before.
+fn read_smpl_chunk(path: &str) -> Option<SmplChunk> {
+    let mut file = File::open(path).ok()?;
+    let mut header = [0u8; 12];
+    file.read_exact(&mut header).ok()?;
+    if &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+        return None;
+    }
+
+    loop {
+        let mut chunk_header = [0u8; 8];
+        file.read_exact(&mut chunk_header).ok()?;
+        let size = u32::from_le_bytes(chunk_header[4..8].try_into().ok()?);
+
+        if &chunk_header[0..4] != b"smpl" {
+            // Chunks are padded to an even length
+            let skip = size as i64 + (size & 1) as i64;
+            file.seek(SeekFrom::Current(skip)).ok()?;
+            continue;
+        }
+
+        // 36-byte fixed header, then 24-byte loop records
+        if size < 36 {
+            return None;
+        }
+        let mut body = vec![0u8; size.min(36 + 24) as usize];
+        file.read_exact(&mut body).ok()?;
+        let field = |offset: usize| {
+            body.get(offset..offset + 4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        };
+
+        let unity_note = field(12)?.min(127) as u8;
+        let loop_count = field(28)?;
+        let first_loop = if loop_count > 0 {
+            match (field(36 + 8), field(36 + 12)) {
+                (Some(start), Some(end)) if end >= start => Some((start, end)),
+                _ => None,
+            }
+        } else {
+            None
+        };
+
+        return Some(SmplChunk {
+            unity_note,
+            first_loop,
+        });
+    }
+}
+
+/// Points in the waveform computed at decode time
+pub const WAVEFORM_POINTS: usize = 400;
+
+/// Resolutions `get_buffer_waveform` accepts
+const WAVEFORM_POINTS_RANGE: std::ops::RangeInclusive<usize> = 16..=16384;
+
+/// Downsamples to `points` peak magnitudes, either across all channels (`None`)
+/// or for a single interleaved channel index.
+fn peak_waveform(
+    pcm_data: &[f32],
+    channels: u16,
+    channel: Option<usize>,
+    points: usize,
+) -> Vec<f32> {
+    let channels = channels as usize;
+    let mut waveform = Vec::with_capacity(points);
+    if !pcm_data.is_empty() {
+        let step_wf = (pcm_data.len() / channels) / points;
+        let step_wf = if step_wf == 0 { 1 } else { step_wf };
+
+        for i in 0..points {
+            let start = i * step_wf * channels;
+            let end = (start + step_wf * channels).min(pcm_data.len());
+            if start >= pcm_data.len() {
+                break;
+            }
+
+            let window = &pcm_data[start..end];
+            let peak = match channel {
+                Some(ch) => window
+                    .iter()
+                    .skip(ch)
+                    .step_by(channels)
+                    .fold(0.0f32, |peak, s| peak.max(s.abs())),
+                None => window.iter().fold(0.0f32, |peak, s| peak.max(s.abs())),
+            };
+            waveform.push(peak);
+        }
+    }
+    waveform
+}
+
+/// Zero-crossing searches are bounded to ±50 ms around the requested time
+const ZERO_CROSSING_WINDOW: f32 = 0.05;
+
+/// Finds the zero crossing closest to `time` on the louder channel of the search window.
+/// A crossing sits between two frames of opposite sign; the quieter frame of the pair wins.
+fn zero_crossing(buffer: &AudioBuffer, time: f32, direction: SearchDirection) -> f32 {
+    let channels = buffer.channels as usize;
+    let frames = buffer.data.len() / channels;
+    if frames < 2 {
+        return time;
+    }
+
+    let sr = buffer.sample_rate as f32;
+    let window = (ZERO_CROSSING_WINDOW * sr) as usize;
+    let center = ((time * sr).max(0.0) as usize).min(frames - 1);
+    let (lo, hi) = match direction {
+        SearchDirection::Nearest => (center.saturating_sub(window), center + window),
+        SearchDirection::Forward => (center, center + window),
+        SearchDirection::Backward => (center.saturating_sub(window), center),
+    };
+    let hi = hi.min(frames - 1);
+
+    let sample = |frame: usize, ch: usize| buffer.data[frame * channels + ch];
+
+    // For stereo, follow whichever channel carries more energy in the window
+    let channel = (0..channels)
+        .map(|ch| {
+            let energy: f32 = (lo..=hi).map(|f| sample(f, ch).powi(2)).sum();
+            (ch, energy)
+        })
+        .fold(
+            (0, f32::MIN),
+            |best, cur| if cur.1 > best.1 { cur } else { best },
+        )
+        .0;
+
+    let mut best: Option<usize> = None;
+    for frame in lo..hi {
+        let (a, b) = (sample(frame, channel), sample(frame + 1, channel));
+        if a == 0.0 || (a < 0.0) != (b < 0.0) {
+            let candidate = if a.abs() <= b.abs() { frame } else { frame + 1 };
+            let closer = match best {
+                Some(prev) => candidate.abs_diff(center) < prev.abs_diff(center),
+                None => true,
+            };
+            if closer {
+                best = Some(candidate);
+            }
+        }
+    }
+
+    match best {
+        Some(frame) => frame as f32 / sr,
+        None => time,
+    }
+}
+
+/// -60 dBFS: anything quieter counts as silence for trim detection
+const SILENCE_THRESHOLD: f32 = 0.001;
+
+/// Finds the first and last frames with any channel above `SILENCE_THRESHOLD`
+/// and returns them as (start, end) seconds. A fully silent file keeps its whole range.
+fn detect_silence(pcm_data: &[f32], channels: u16, sample_rate: u32) -> (f32, f32) {
+    let channels = channels as usize;
+    let total_frames = pcm_data.len() / channels;
+    let duration = total_frames as f32 / sample_rate as f32;
+
+    let is_audible = |frame: &[f32]| frame.iter().any(|s| s.abs() > SILENCE_THRESHOLD);
+
+    let first = pcm_data.chunks(channels).position(is_audible);
+    let last = pcm_data.chunks(channels).rposition(is_audible);
+
+    match (first, last) {
+        (Some(first), Some(last)) => (
+            first as f32 / sample_rate as f32,
+            (last + 1) as f32 / sample_rate as f32,
+        ),
+        _ => (0.0, duration),
+    }
+}
+
+// ========================================================================
+// Loudness (ITU-R BS.1770 integrated LUFS)
+// ========================================================================
+
+/// Makeup gain ceiling so noisy, quiet recordings aren't blasted
+pub const MAX_LOUDNESS_GAIN_DB: f32 = 12.0;
+
+/// Returns the gain (dB) that brings `measured` to `target`, and whether it hit the ceiling.
+fn loudness_makeup_db(target: f32, measured: f32) -> (f32, bool) {
+    let gain_db = target - measured;
+    if gain_db > MAX_LOUDNESS_GAIN_DB {
+        (MAX_LOUDNESS_GAIN_DB, true)
+    } else {
+        (gain_db, false)
+    }
+}
+
+/// Filter state below this is flushed to zero, so a decaying tail never
+/// goes subnormal (running off the audio thread, it has no FTZ)
+const DENORMAL_FLOOR: f64 = 1e-30;
+
+/// Direct Form I biquad, run in f64 to keep the low shelf stable
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 3]) -> Self {
+        Self {
+            b: [b[0] / a[0], b[1] / a[0], b[2] / a[0]],
+            a: [a[1] / a[0], a[2] / a[0]],
+            x: [0.0; 2],
+            y: [0.0; 2],
+        }
+    }
+
+    fn process(&mut self, x0: f64) -> f64 {
+        let y0 = self.b[0] * x0 + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        let y0 = if y0.abs() < DENORMAL_FLOOR { 0.0 } else { y0 };
+        self.x = [x0, self.x[0]];
+        self.y = [y0, self.y[0]];
+        y0
+    }
+}
+
+/// The two K-weighting stages (head shelf + RLB high-pass) designed for any sample rate.
+/// Coefficients follow libebur128's bilinear designs of the 48 kHz reference filters.
+fn k_weighting(sample_rate: u32) -> (Biquad, Biquad) {
+    let fs = sample_rate as f64;
+
+    // Stage 1: high shelf modelling the acoustic effect of the head
+    let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (std::f64::consts::PI * f0 / fs).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let shelf = Biquad::new(
+        [
+            vh + vb * k / q + k * k,
+            2.0 * (k * k - vh),
+            vh - vb * k / q + k * k,
+        ],
+        [
+            1.0 + k / q + k * k,
+            2.0 * (k * k - 1.0),
+            1.0 - k / q + k * k,
+        ],
+    );
+
+    // Stage 2: RLB high-pass
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (std::f64::consts::PI * f0 / fs).tan();
+    let norm = 1.0 + k / q + k * k;
+    let high_pass = Biquad::new(
+        [1.0, -2.0, 1.0],
+        [
+            1.0,
+            2.0 * (k * k - 1.0) / norm,
+            (1.0 - k / q + k * k) / norm,
+        ],
+    );
+
+    (shelf, high_pass)
+}
+
+/// Integrated loudness with the BS.1770 absolute (-70 LUFS) and relative (-10 LU) gates,
+/// over 400 ms blocks with 75% overlap. Returns None when everything is gated away.
+fn measure_lufs(pcm_data: &[f32], channels: u16, sample_rate: u32) -> Option<f32> {
+    let channels = channels as usize;
+    let frames = pcm_data.len() / channels;
+    if frames == 0 {
+        return None;
+    }
+
+    // K-weighted, squared signal per channel
+    let mut squared: Vec<Vec<f64>> = Vec::with_capacity(channels);
+    for ch in 0..channels {
+        let (mut shelf, mut high_pass) = k_weighting(sample_rate);
+        squared.push(
+            pcm_data
+                .iter()
+                .skip(ch)
+                .step_by(channels)
+                .map(|&s| {
+                    let y = high_pass.process(shelf.process(s as f64));
+                    y * y
+                })
+                .collect(),
+        );
+    }
+
+    // Surround channels (beyond L/R/C) are weighted +1.5 dB
+    let weight = |ch: usize| if ch < 3 { 1.0 } else { 1.41 };
+
+    // Clips shorter than one block are measured as a single block
+    let block = ((sample_rate as f64 * 0.4) as usize).min(frames);
+    let hop = (block / 4).max(1);
+    let mut block_power = Vec::new();
+    let mut start = 0;
+    while start + block <= frames {
+        let power: f64 = (0..channels)
+            .map(|ch| {
+                let sum: f64 = squared[ch][start..start + block].iter().sum();
+                weight(ch) * sum / block as f64
+            })
+            .sum();
+        block_power.push(power);
+        start += hop;
+    }
+
+    let loudness = |power: f64| -0.691 + 10.0 * power.log10();
+    let gated_mean = |threshold: f64| {
+        let passing: Vec<f64> = block_power
+            .iter()
+            .copied()
+            .filter(|&p| p > 0.0 && loudness(p) > threshold)
+            .collect();
+        if passing.is_empty() {
+            None
+        } else {
+            Some(passing.iter().sum::<f64>() / passing.len() as f64)
+        }
+    };
+
+    let absolute = gated_mean(-70.0)?;
+    let integrated = gated_mean(loudness(absolute) - 10.0)?;
+    Some(loudness(integrated) as f32)
+}
+
+// ========================================================================
+// BPM Detection (background phase of a load)
+// ========================================================================
+/// BPM and key of a decoded buffer, folded into `bpm_range`; `label` names it in the log
+pub fn analyze_bpm(buffer: &AudioBuffer, bpm_range: (f32, f32), label: &str) -> BpmAnalysis {
+    let pcm_data = &buffer.data;
+    let sample_rate = buffer.sample_rate;
+    let channels = buffer.channels;
+
+    // We decimate by a factor of 4. At 48kHz, this gives us 12kHz—perfect for BPM.
+    let step = 4;
+    let analysis_limit_seconds = 15;
+    let max_frames = sample_rate as usize * analysis_limit_seconds;
+
+    let mono_data: Vec<f32> = pcm_data
+        .chunks(channels as usize * step)
+        .take(max_frames / step)
+        .map(|chunk| {
+            let sum: f32 = chunk.iter().take(channels as usize).sum();
+            sum / channels as f32
+        })
+        .collect();
+
+    let effective_sr = sample_rate / step as u32;
+    let config = AnalysisConfig {
+        bpm_resolution: 0.1,
+        enable_bpm_fusion: true,
+        ..AnalysisConfig::default()
+    };
+
+    // stratum_dsp finds a tempo in anything, noise included, so a signal with
+    // a steady level is a failed detection before it gets there
+    let analysis = if steady_level(&mono_data) {
+        Err("the level never rises or falls, so there are no beats to time".to_string())
+    } else {
+        analyze_audio(&mono_data, effective_sr, config).map_err(|e| e.to_string())
+    };
+    let (detected_bpm, confidence, source, key_detected, error) = match analysis {
+        Ok(res) => (
+            res.bpm,
+            res.bpm_confidence.clamp(0.0, 1.0),
+            BpmSource::Detected,
+            Some(res.key.name()),
+            None,
+        ),
+        Err(e) => {
+            warn!("BPM detection failed for {}: {}", label, e);
+            (120.0, 0.0, BpmSource::Fallback, None, Some(e))
+        }
+    };
+
+    // Heuristic: Many loops are exact integers, but only trust the snap when the detector is sure
+    let raw_bpm =
+        if confidence >= BPM_SNAP_CONFIDENCE && (detected_bpm - detected_bpm.round()).abs() < 0.1 {
+            detected_bpm.round()
+        } else {
+            detected_bpm
+        };
+    let bpm = fold_bpm(raw_bpm, bpm_range);
+
+    info!(
+        "Analysis complete for {}: {} BPM (raw: {}, confidence: {:.2})",
+        label, bpm, raw_bpm, confidence
+    );
+
+    BpmAnalysis {
+        bpm,
+        raw_bpm,
+        confidence,
+        source,
+        error,
+        key_detected,
+    }
+}
+
+// ========================================================================
+// WAV Export
+// ========================================================================
+
+pub fn write_wav(path: &Path, region: &RenderedRegion, format: WavFormat) -> Result<(), String> {
+    let spec = wav_spec(format, region.channels, region.sample_rate);
+    let mut writer = hound::WavWriter::create(path, spec).map_err(|e| e.to_string())?;
+    for &sample in &region.data {
+        write_wav_sample(&mut writer, sample, format)?;
+    }
+    writer.finalize().map_err(|e| e.to_string())
+}
+
+fn wav_spec(format: WavFormat, channels: u16, sample_rate: u32) -> hound::WavSpec {
+    let (bits_per_sample, sample_format) = match format {
+        WavFormat::Float32 => (32, hound::SampleFormat::Float),
+        WavFormat::Pcm16 => (16, hound::SampleFormat::Int),
+    };
+    hound::WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample,
+        sample_format,
+    }
+}
+
+fn write_wav_sample<W: std::io::Write + std::io::Seek>(
+    writer: &mut hound::WavWriter<W>,
+    sample: f32,
+    format: WavFormat,
+) -> Result<(), String> {
+    match format {
+        WavFormat::Float32 => writer.write_sample(sample),
+        WavFormat::Pcm16 => {
+            writer.write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16)
+        }
+    }
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,113 @@
+/**
+ * limiter.rs
+ * L-SAMP 100 | Master Limiter
+ *
+ * The last stage of the mix, after the compressor and the master volume:
+ * whenever the louder channel would pass the ceiling the gain drops at once
+ * to keep it there, then recovers over the release time. There is no
+ * lookahead, so a transient is caught on its first frame rather than
+ * before it. The count-in clicks join the output after it. Its gain
+ * reduction is reported in `LevelsResponse` next to the compressor's.
+ */
+use std::mem;
+
+/// Highest level the mix leaves at (-0.3 dBFS)
+const CEILING: f32 = 0.966;
+
+/// Time the gain takes to recover once the mix is back under the ceiling
+const RELEASE_MS: f32 = 50.0;
+
+/// The limiter's part of the mixer
+pub(crate) struct Limiter {
+    gain: f32,        // Linear, 1 when not limiting
+    release: f32,     // Recovery weight per frame
+    window_peak: f32, // Most reduction in the current meter window, dB
+}
+
+impl Limiter {
+    pub(crate) fn new(sample_rate: u32) -> Self {
+        Self {
+            gain: 1.0,
+            release: release(sample_rate),
+            window_peak: 0.0,
+        }
+    }
+
+    /// Lets go of any reduction at once, e.g. over silence
+    pub(crate) fn rest(&mut self) {
+        self.gain = 1.0;
+    }
+
+    /// Limits a block of the mix in place
+    pub(crate) fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        let mut lowest = self.gain;
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            self.gain += (1.0 - self.gain) * self.release;
+            let peak = l.abs().max(r.abs()) * self.gain;
+            if peak > CEILING {
+                self.gain *= CEILING / peak;
+            }
+            lowest = lowest.min(self.gain);
+            *l *= self.gain;
+            *r *= self.gain;
+        }
+        self.window_peak = self.window_peak.max(-20.0 * lowest.log10());
+    }
+
+    /// Most reduction since the last call, in dB, for the meter window
+    pub(crate) fn take_reduction(&mut self) -> f32 {
+        mem::take(&mut self.window_peak)
+    }
+}
+
+fn release(sample_rate: u32) -> f32 {
+    1.0 - (-1000.0 / (RELEASE_MS * sample_rate as f32)).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 48_000;
+
+    #[test]
+    fn loud_mix_never_passes_the_ceiling() {
+        let mut limiter = Limiter::new(RATE);
+        let mut left: Vec<f32> = (0..4800).map(|n| 4.0 * (n as f32 * 0.05).sin()).collect();
+        let mut right: Vec<f32> = left.iter().map(|s| -0.5 * s).collect();
+        limiter.process(&mut left, &mut right);
+        let peak = left
+            .iter()
+            .chain(&right)
+            .fold(0.0f32, |peak, s| peak.max(s.abs()));
+        assert!(peak <= CEILING + 1e-6, "{}", peak);
+        // About 12 dB over the ceiling
+        let reduction = limiter.take_reduction();
+        assert!((reduction - 12.34).abs() < 0.1, "{}", reduction);
+        assert_eq!(limiter.take_reduction(), 0.0);
+    }
+
+    #[test]
+    fn quiet_mix_is_untouched() {
+        let mut limiter = Limiter::new(RATE);
+        let input: Vec<f32> = (0..4800).map(|n| 0.9 * (n as f32 * 0.05).sin()).collect();
+        let (mut left, mut right) = (input.clone(), input.clone());
+        limiter.process(&mut left, &mut right);
+        assert_eq!(left, input);
+        assert_eq!(limiter.take_reduction(), 0.0);
+    }
+
+    #[test]
+    fn gain_recovers_over_the_release() {
+        let mut limiter = Limiter::new(RATE);
+        limiter.process(&mut [2.0], &mut [0.0]);
+        assert!((limiter.gain - CEILING / 2.0).abs() < 1e-6);
+        let frames = (RELEASE_MS / 1000.0 * RATE as f32) as usize;
+        limiter.process(&mut vec![0.0; frames], &mut vec![0.0; frames]);
+        // One time constant lets go of 1 - 1/e of the reduction
+        let left = (1.0 - limiter.gain) / (1.0 - CEILING / 2.0);
+        assert!((left - 0.368).abs() < 0.01, "{}", left);
+        limiter.rest();
+        assert_eq!(limiter.gain, 1.0);
+    }
+}
@@ -0,0 +1,275 @@
+/**
+ * looper.rs
+ * L-SAMP 100 | Looper Bus
+ *
+ * A classic looper on top of the pads: the first layer records the pad mix
+ * (or some of the pads) for a number of bars from the next bar line and
+ * loops straight away; overdubs record one more pass each, from the loop's
+ * next start, as layers of their own so they can be undone one by one.
+ * Layers capture before the master volume and play back under it.
+ *
+ * Every buffer a layer records into is allocated outside the callback, at
+ * the length the tempo and sample rate give when recording is asked for;
+ * the loop then keeps that length in frames whatever the tempo does. The
+ * first few milliseconds of every layer are crossfaded with what was played
+ * right after its end, so the loop point does not click. Playing layers
+ * meter under `LOOPER_KEY_PREFIX` keys.
+ */
+use crate::{meter_mark, Clock, SlotLevels, METER_SAMPLES};
+
+/// Prefix of the reserved pad ids the layers meter under ("__looper_0" is the first)
+pub(crate) const LOOPER_KEY_PREFIX: &str = "__looper_";
+
+/// Longest loop
+pub(crate) const MAX_LOOPER_BARS: u32 = 16;
+
+/// Layers (the first recording and its overdubs) at once
+pub(crate) const MAX_LOOPER_LAYERS: usize = 8;
+
+/// Crossfade at the loop point, far shorter than any loop
+pub(crate) const LOOPER_SEAM_MS: f64 = 10.0;
+
+/// Every pad slot, for a layer of the whole pad mix
+pub(crate) const ALL_PADS: u128 = u128::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    Armed,     // Waits for the next bar (the first layer) or the loop's start
+    Recording, // Written, not played
+    Seam,      // Played while its head is crossfaded with what follows its end
+    Playing,
+}
+
+/// One recorded pass, stereo interleaved
+pub(crate) struct Layer {
+    slot: usize,    // Meter slot of its reserved key
+    data: Vec<f32>, // Two samples per loop frame, zeroed
+    sources: u128,  // Bit per pad slot it records
+    stage: Stage,
+    meter_peak: f32,
+    meter_samples: Vec<f32>,
+}
+
+impl Layer {
+    /// Allocates the whole pass up front, off the audio thread
+    pub(crate) fn new(slot: usize, frames: usize, sources: u128) -> Self {
+        Self {
+            slot,
+            data: vec![0.0; frames * 2],
+            sources,
+            stage: Stage::Armed,
+            meter_peak: 0.0,
+            meter_samples: Vec::with_capacity(METER_SAMPLES),
+        }
+    }
+
+    fn frames(&self) -> usize {
+        self.data.len() / 2
+    }
+
+    fn audible(&self) -> bool {
+        matches!(self.stage, Stage::Seam | Stage::Playing)
+    }
+
+    fn capturing(&self) -> bool {
+        matches!(self.stage, Stage::Recording | Stage::Seam)
+    }
+}
+
+/// The looper as the engine sees it, to check requests before they reach
+/// the callback
+#[derive(Default)]
+pub(crate) struct LooperInfo {
+    pub(crate) frames: usize, // Loop length, 0 without a loop
+    pub(crate) layers: usize,
+}
+
+/// The looper's part of the mixer
+pub(crate) struct Looper {
+    layers: Vec<Layer>,      // MAX_LOOPER_LAYERS of room, in recording order
+    start_beat: Option<f64>, // Where the first layer starts recording, while it is armed
+    length: usize,           // Frames per loop, 0 until the first layer starts
+    position: usize,         // Frame into the loop
+    seam: usize,             // Crossfade frames
+}
+
+impl Looper {
+    pub(crate) fn new(sample_rate: u32) -> Self {
+        Self {
+            layers: Vec::with_capacity(MAX_LOOPER_LAYERS),
+            start_beat: None,
+            length: 0,
+            position: 0,
+            seam: (sample_rate as f64 * LOOPER_SEAM_MS / 1000.0) as usize,
+        }
+    }
+
+    pub(crate) fn idle(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Drops the loop and arms `layer` as the first one, from `beat`
+    pub(crate) fn record(&mut self, layer: Layer, beat: f64) {
+        self.clear();
+        self.layers.push(layer);
+        self.start_beat = Some(beat);
+    }
+
+    /// Arms `layer` for the loop's next start after any capture under way
+    pub(crate) fn overdub(&mut self, layer: Layer) {
+        if self.layers.len() < MAX_LOOPER_LAYERS && !self.layers.is_empty() {
+            self.layers.push(layer);
+        }
+    }
+
+    /// Drops the newest layer, recorded or not
+    pub(crate) fn undo(&mut self) {
+        self.layers.pop();
+        if self.layers.is_empty() {
+            self.clear();
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.layers.clear();
+        self.start_beat = None;
+        self.length = 0;
+        self.position = 0;
+    }
+
+    /// Pads the block's capture comes from, None when nothing records in it
+    pub(crate) fn capture_sources(&self) -> Option<u128> {
+        self.layers
+            .iter()
+            .find(|layer| layer.capturing())
+            .or_else(|| self.layers.iter().find(|layer| layer.stage == Stage::Armed))
+            .map(|layer| layer.sources)
+    }
+
+    /// Frame of the block on which the first layer starts recording
+    pub(crate) fn frames_to_start(&self, clock: &Clock) -> Option<usize> {
+        Some(clock.frames_to(self.start_beat?))
+    }
+
+    /// Records `input` into the capturing layer and adds the audible ones to
+    /// `left`/`right`, frame by frame. `start_in` is `frames_to_start`.
+    pub(crate) fn process(
+        &mut self,
+        left: &mut [f32],
+        right: &mut [f32],
+        input: (&[f32], &[f32]),
+        start_in: Option<usize>,
+        window_pos: usize,
+        meter_step: usize,
+    ) {
+        for i in 0..left.len() {
+            if start_in == Some(i) {
+                self.start();
+            }
+            if self.length == 0 {
+                continue;
+            }
+            let position = self.position;
+            let mark = meter_mark(window_pos + i, meter_step);
+            for layer in self.layers.iter_mut() {
+                let (l, r) = (position * 2, position * 2 + 1);
+                match layer.stage {
+                    Stage::Armed => continue,
+                    Stage::Recording => {
+                        layer.data[l] = input.0[i];
+                        layer.data[r] = input.1[i];
+                        continue;
+                    }
+                    Stage::Seam => {
+                        // From what followed the end into the head
+                        let head = position as f32 / self.seam as f32;
+                        layer.data[l] = layer.data[l] * head + input.0[i] * (1.0 - head);
+                        layer.data[r] = layer.data[r] * head + input.1[i] * (1.0 - head);
+                    }
+                    Stage::Playing => {}
+                }
+                let (sample_l, sample_r) = (layer.data[l], layer.data[r]);
+                left[i] += sample_l;
+                right[i] += sample_r;
+                layer.meter_peak = layer
+                    .meter_peak
+                    .max((sample_l.abs() + sample_r.abs()) * 0.5);
+                if let Some(mark) = mark {
+                    layer.meter_samples.resize(mark, 0.0);
+                    layer.meter_samples.push((sample_l + sample_r) * 0.5);
+                }
+            }
+
+            self.position += 1;
+            if self.position == self.seam {
+                for layer in self.layers.iter_mut() {
+                    if layer.stage == Stage::Seam {
+                        layer.stage = Stage::Playing;
+                    }
+                }
+            }
+            if self.position >= self.length {
+                self.position = 0;
+                self.wrap();
+            }
+        }
+    }
+
+    /// The armed first layer starts recording here
+    fn start(&mut self) {
+        self.start_beat = None;
+        if let Some(first) = self.layers.first_mut() {
+            self.length = first.frames();
+            self.position = 0;
+            first.stage = Stage::Recording;
+        }
+    }
+
+    /// At the loop's start: a finished pass goes to its seam, and with no
+    /// capture left, the next armed overdub starts
+    fn wrap(&mut self) {
+        let seam = self.seam;
+        for layer in self.layers.iter_mut() {
+            if layer.stage == Stage::Recording {
+                layer.stage = if seam > 0 {
+                    Stage::Seam
+                } else {
+                    Stage::Playing
+                };
+            }
+        }
+        if !self.layers.iter().any(Layer::capturing) {
+            let length = self.length;
+            if let Some(layer) = self
+                .layers
+                .iter_mut()
+                .find(|layer| layer.stage == Stage::Armed && layer.frames() == length)
+            {
+                layer.stage = Stage::Recording;
+            }
+        }
+    }
+
+    /// Adds the window's layer levels to the pad levels and starts the next window
+    pub(crate) fn meter(&mut self, levels: &mut [SlotLevels]) {
+        for layer in self.layers.iter_mut() {
+            if layer.audible() {
+                let level = &mut levels[layer.slot];
+                level.live = true;
+                level.visual.peak = level.visual.peak.max(layer.meter_peak);
+                level.visual.samples.clear();
+                level.visual.samples.extend_from_slice(&layer.meter_samples);
+            }
+            layer.meter_peak = 0.0;
+            layer.meter_samples.clear();
+        }
+    }
+
+    /// Slots of the layers that play
+    pub(crate) fn slots(&self) -> impl Iterator<Item = usize> + '_ {
+        self.layers
+            .iter()
+            .filter(|layer| layer.audible())
+            .map(|layer| layer.slot)
+    }
+}
@@ -0,0 +1,133 @@
+/**
+ * metronome.rs
+ * L-SAMP 100 | Count-In
+ *
+ * The clicks counted in ahead of a looper recording: one on every beat from
+ * a bar line up to the downbeat the recording starts on, higher on the
+ * first beat of each bar. The clicks are made in the callback (a short sine
+ * that dies away), join the output after the compressor, master volume and
+ * limiter so none of them changes them, and never reach the looper, which
+ * only captures pads. Each click posts a `CountInTick` for the UI. A stream
+ * rebuilt mid-count drops the rest of the clicks; the recording still
+ * starts on its downbeat.
+ */
+use serde::Serialize;
+use std::f32::consts::TAU;
+use std::sync::mpsc::SyncSender;
+
+use crate::{Clock, BEATS_PER_BAR};
+
+/// Most bars a count-in lasts
+pub const MAX_COUNT_IN_BARS: u32 = 2;
+
+/// Count-in ticks waiting for `take_count_in_ticks`; a full queue drops them
+pub(crate) const COUNT_IN_QUEUE: usize = 16;
+
+/// Length of one click
+const CLICK_MS: f32 = 30.0;
+
+/// Pitch of the click on a bar's first beat, and of the others
+const DOWNBEAT_HZ: f32 = 1500.0;
+const BEAT_HZ: f32 = 1000.0;
+
+/// Peak level of a click
+const CLICK_LEVEL: f32 = 0.5;
+
+/// Payload of `count-in-tick`, sent as each click starts
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct CountInTick {
+    pub bar: u32,        // Of the count-in, from 1
+    pub beat: u32,       // Of the bar, from 1
+    pub beats_left: u32, // Clicks still to come before the downbeat
+}
+
+/// The count-in's part of the mixer
+pub(crate) struct Metronome {
+    start: f64, // Beat of the first click
+    end: f64,   // The downbeat after the count; nothing clicks from here
+    next: f64,  // Beat of the next click
+    click_frames: usize,
+    sample_rate: f32,
+    pitch: f32,  // Phase step per frame of the sounding click
+    phase: f32,  // Of its sine
+    left: usize, // Frames of it still to play
+    ticks: SyncSender<CountInTick>,
+}
+
+impl Metronome {
+    pub(crate) fn new(sample_rate: u32, ticks: &SyncSender<CountInTick>) -> Self {
+        Self {
+            start: 0.0,
+            end: 0.0,
+            next: 0.0,
+            click_frames: (sample_rate as f32 * CLICK_MS / 1000.0) as usize,
+            sample_rate: sample_rate as f32,
+            pitch: 0.0,
+            phase: 0.0,
+            left: 0,
+            ticks: ticks.clone(),
+        }
+    }
+
+    /// Clicks every beat from `start` up to `end`
+    pub(crate) fn count_in(&mut self, start: f64, end: f64) {
+        self.start = start;
+        self.end = end;
+        self.next = start;
+    }
+
+    /// Stops counting; a click already sounding dies away as usual
+    pub(crate) fn stop(&mut self) {
+        self.end = self.next;
+    }
+
+    pub(crate) fn idle(&self) -> bool {
+        self.next >= self.end && self.left == 0
+    }
+
+    /// Frames from the clock's position to the next click, None when there is none
+    pub(crate) fn frames_to_click(&self, clock: &Clock) -> Option<usize> {
+        if self.next >= self.end {
+            return None;
+        }
+        Some(clock.frames_to(self.next))
+    }
+
+    /// Starts the click due at the clock's position, skipping any missed
+    pub(crate) fn fire(&mut self, clock: &Clock) {
+        if self.next >= self.end || !clock.reached(self.next) {
+            return;
+        }
+        let index = (self.next - self.start).round() as u32;
+        let per_bar = BEATS_PER_BAR as u32;
+        let bar_beat = index % per_bar;
+        let hz = if bar_beat == 0 { DOWNBEAT_HZ } else { BEAT_HZ };
+        self.pitch = TAU * hz / self.sample_rate;
+        self.phase = 0.0;
+        self.left = self.click_frames;
+        let _ = self.ticks.try_send(CountInTick {
+            bar: index / per_bar + 1,
+            beat: bar_beat + 1,
+            beats_left: (self.end - self.next).round() as u32 - 1,
+        });
+        while self.next < self.end && clock.reached(self.next) {
+            self.next += 1.0;
+        }
+    }
+
+    /// Adds the sounding click to a block of the output
+    pub(crate) fn process(&mut self, left: &mut [f32], right: &mut [f32]) {
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            if self.left == 0 {
+                return;
+            }
+            let fade = self.left as f32 / self.click_frames as f32;
+            let sample = self.phase.sin() * CLICK_LEVEL * fade * fade;
+            self.phase = (self.phase + self.pitch) % TAU;
+            self.left -= 1;
+            *l += sample;
+            *r += sample;
+        }
+    }
+}
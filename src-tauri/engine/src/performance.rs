@@ -0,0 +1,245 @@
+/**
+ * performance.rs
+ * L-SAMP 100 | Performance Capture
+ *
+ * Records pad triggers and stops (whatever sent them: hotkeys, MIDI, the
+ * pad grid) against the master clock and replays them on the sequencer's
+ * performance lane. A take is measured from the bar the recording started
+ * in and lasts whole bars, so it loops in time; the trigger beat is the
+ * clock as of the last buffer. Overdubbing records into the take while it
+ * plays, and each hit is heard from the next loop on. Takes are plain JSON
+ * (`Performance`), as sessions store them.
+ */
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::sequencer::{SeqAction, SeqPattern};
+use crate::{pad_voice, AudioEngineState, Grid, PlayParams, BEATS_PER_BAR};
+
+/// Longest take, in beats (64 bars)
+const MAX_PERFORMANCE_BEATS: f64 = 64.0 * BEATS_PER_BAR;
+
+/// A recorded take
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Performance {
+    pub length: f64, // Beats, whole bars when recorded
+    pub events: Vec<PerformanceEvent>,
+}
+
+/// One pad trigger or stop of a take
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PerformanceEvent {
+    pub beat: f64, // From the start of the take
+    pub key: String,
+    #[serde(flatten)]
+    pub action: PerformanceAction,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum PerformanceAction {
+    Play { params: PlayParams },   // As it was played, region already clamped
+    Stop { release: Option<f32> }, // Seconds, None for the voices' own release
+}
+
+impl Performance {
+    fn validate(&self) -> Result<(), String> {
+        if !(self.length > 0.0 && self.length <= MAX_PERFORMANCE_BEATS) {
+            return Err(format!(
+                "[Social Noise] A take of {} beats is outside 0..{}",
+                self.length, MAX_PERFORMANCE_BEATS
+            ));
+        }
+        if let Some(event) = self
+            .events
+            .iter()
+            .find(|event| !(event.beat >= 0.0 && event.beat < self.length))
+        {
+            return Err(format!(
+                "[Social Noise] {} at beat {} is outside the take",
+                event.key, event.beat
+            ));
+        }
+        Ok(())
+    }
+
+    fn insert(&mut self, event: PerformanceEvent) {
+        // After the events on the same beat, so they keep the order they came in
+        let at = self
+            .events
+            .partition_point(|other| other.beat <= event.beat);
+        self.events.insert(at, event);
+    }
+}
+
+/// A recording under way
+struct Recording {
+    origin: f64,        // Beat the take starts on
+    grid: Option<Grid>, // Quantize grid
+    overdub: bool,      // Into `Performances::take`, which is playing
+    events: Vec<PerformanceEvent>,
+}
+
+/// The engine's takes: the last one recorded or played, and what happens to it
+#[derive(Default)]
+pub(crate) struct Performances {
+    take: Option<Performance>,
+    looping: bool,
+    pub(crate) playing: bool,
+    pub(crate) origin: f64, // Beat the playing take's first loop started on
+    recording: Option<Recording>,
+}
+
+impl Performances {
+    /// Starts recording at `beat`, from its bar; with `overdub`, into the
+    /// playing take
+    pub(crate) fn record_start(
+        &mut self,
+        beat: f64,
+        quantize: Option<Grid>,
+        overdub: bool,
+    ) -> Result<(), String> {
+        if self.recording.is_some() {
+            return Err("[Social Noise] Already recording".to_string());
+        }
+        if overdub && !(self.playing && self.take.is_some()) {
+            return Err("[Social Noise] No take is playing to overdub".to_string());
+        }
+        self.recording = Some(Recording {
+            origin: if overdub {
+                self.origin
+            } else {
+                (beat / BEATS_PER_BAR).floor() * BEATS_PER_BAR
+            },
+            grid: quantize,
+            overdub,
+            events: Vec::new(),
+        });
+        Ok(())
+    }
+
+    /// Keeps a trigger or stop made at `beat`. True when it went into the
+    /// playing take, which then needs a new loop built.
+    pub(crate) fn record(&mut self, beat: f64, key: &str, action: PerformanceAction) -> bool {
+        let recording = match self.recording.as_mut() {
+            Some(recording) => recording,
+            None => return false,
+        };
+        let mut beat = beat - recording.origin;
+        if let Some(grid) = recording.grid {
+            beat = grid.nearest(beat);
+        }
+        let event = PerformanceEvent {
+            beat,
+            key: key.to_string(),
+            action,
+        };
+        match (recording.overdub, self.take.as_mut()) {
+            (true, Some(take)) => {
+                take.insert(PerformanceEvent {
+                    beat: event.beat.rem_euclid(take.length),
+                    ..event
+                });
+                true
+            }
+            _ => {
+                recording.events.push(event);
+                false
+            }
+        }
+    }
+
+    /// Ends the recording at `beat` and keeps the take (the overdubbed one,
+    /// or a new one of the whole bars recorded)
+    pub(crate) fn record_stop(&mut self, beat: f64) -> Result<Performance, String> {
+        let recording = self
+            .recording
+            .take()
+            .ok_or("[Social Noise] Not recording")?;
+        if !recording.overdub {
+            let bars = ((beat - recording.origin) / BEATS_PER_BAR).ceil().max(1.0);
+            let mut take = Performance {
+                length: (bars * BEATS_PER_BAR).min(MAX_PERFORMANCE_BEATS),
+                events: Vec::new(),
+            };
+            for event in recording.events {
+                // A hit quantized onto the end belongs to the downbeat
+                take.insert(PerformanceEvent {
+                    beat: event.beat.max(0.0).rem_euclid(take.length),
+                    ..event
+                });
+            }
+            self.take = Some(take);
+        }
+        self.take
+            .clone()
+            .ok_or_else(|| "[Social Noise] The take was cleared while recording".to_string())
+    }
+
+    /// Keeps `take` (if any) for `play`, e.g. from a session
+    pub(crate) fn set_take(&mut self, take: Option<Performance>) -> Result<(), String> {
+        if let Some(take) = &take {
+            take.validate()?;
+        }
+        self.take = take;
+        Ok(())
+    }
+
+    pub(crate) fn take(&self) -> Option<Performance> {
+        self.take.clone()
+    }
+
+    /// Marks the take as playing; false without one
+    pub(crate) fn play(&mut self, looping: bool) -> bool {
+        self.playing = self.take.is_some();
+        self.looping = looping;
+        self.playing
+    }
+}
+
+/// Builds the next loop of the take. Pads that are not loaded right now
+/// (or for a stop, never played) are left out.
+pub(crate) fn build_loop(state: &mut AudioEngineState) -> Box<SeqPattern> {
+    let looping = state.performance.looping;
+    let take = match state.performance.take.clone() {
+        Some(take) => take,
+        None => {
+            return Box::new(SeqPattern {
+                length: BEATS_PER_BAR,
+                looping: false,
+                actions: Vec::new(),
+                grid: None,
+            })
+        }
+    };
+    let mut actions = Vec::with_capacity(take.events.len());
+    for event in take.events {
+        let action = match event.action {
+            PerformanceAction::Play { mut params } => {
+                if !state.sound_bank.contains_key(&event.key) {
+                    continue;
+                }
+                match pad_voice(state, event.key.clone(), &mut params) {
+                    Ok(voice) => SeqAction::Play(voice),
+                    Err(e) => {
+                        warn!("Take at beat {}: {}", event.beat, e);
+                        continue;
+                    }
+                }
+            }
+            PerformanceAction::Stop { release } => match state.pad_slots.get(&event.key) {
+                Some(slot) => SeqAction::Stop { slot, release },
+                None => continue,
+            },
+        };
+        actions.push((event.beat, Some(action)));
+    }
+    Box::new(SeqPattern {
+        length: take.length,
+        looping,
+        actions,
+        grid: None,
+    })
+}
@@ -0,0 +1,261 @@
+/**
+ * recorder.rs
+ * L-SAMP 100 | Master Recorder
+ *
+ * Records the master output to a WAV file as it plays. The callback copies
+ * the mix (after the limiter, before the count-in clicks) into chunks it
+ * takes from a pool allocated up front, and a writer thread writes them out
+ * and hands them back. With a count-in the recording starts on the downbeat
+ * after it: pads played during the count sound but are not recorded, and
+ * nothing is written, not even the file, until that downbeat. Cancelling
+ * removes whatever was written. A stream rebuilt mid-recording ends the
+ * recording there, since the next one may run at another rate.
+ */
+use crossbeam_queue::ArrayQueue;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::{wav_spec, write_wav_sample, Clock, WavFormat};
+
+/// Frames per chunk, and chunks in the pool (about five seconds at 48 kHz);
+/// a writer this far behind loses frames rather than stalling the callback
+const CHUNK_FRAMES: usize = 4096;
+const CHUNKS: usize = 64;
+
+/// How often the writer looks for full chunks
+const WRITER_POLL: Duration = Duration::from_millis(5);
+
+/// What `record_stop` hands back
+#[derive(Debug, Clone)]
+pub struct MasterRecording {
+    pub path: PathBuf,
+    pub duration: f32,       // Seconds
+    pub peak: f32,           // Highest sample magnitude, 1.0 is full scale
+    pub dropped_frames: u64, // Lost to a writer that fell behind
+}
+
+/// One block of recorded frames, stereo interleaved
+struct Chunk {
+    data: Vec<f32>,
+}
+
+/// Shared by the callback and the writer of one recording
+pub(crate) struct RecordLink {
+    free: ArrayQueue<Chunk>,
+    full: ArrayQueue<Chunk>,
+    ended: AtomicBool,     // Set once the last chunk is in `full`
+    cancelled: AtomicBool, // The file goes too
+    dropped: AtomicU64,    // Frames with no free chunk to go in
+}
+
+impl RecordLink {
+    fn new() -> Arc<Self> {
+        let link = Arc::new(Self {
+            free: ArrayQueue::new(CHUNKS),
+            full: ArrayQueue::new(CHUNKS),
+            ended: AtomicBool::new(false),
+            cancelled: AtomicBool::new(false),
+            dropped: AtomicU64::new(0),
+        });
+        for _ in 0..CHUNKS {
+            let _ = link.free.push(Chunk {
+                data: Vec::with_capacity(CHUNK_FRAMES * 2),
+            });
+        }
+        link
+    }
+
+    /// Ends the recording from outside the callback, e.g. when it is not running
+    pub(crate) fn end(&self, cancel: bool) {
+        if cancel {
+            self.cancelled.store(true, Ordering::Release);
+        }
+        self.ended.store(true, Ordering::Release);
+    }
+
+    pub(crate) fn ended(&self) -> bool {
+        self.ended.load(Ordering::Acquire)
+    }
+}
+
+/// A recording under way, owned by the engine
+pub(crate) struct RecordingHandle {
+    pub(crate) link: Arc<RecordLink>,
+    writer: JoinHandle<Result<Option<MasterRecording>, String>>,
+}
+
+impl RecordingHandle {
+    /// Starts the writer for a recording to `path` at `sample_rate`
+    pub(crate) fn start(
+        path: PathBuf,
+        format: WavFormat,
+        sample_rate: u32,
+    ) -> Result<Self, String> {
+        let link = RecordLink::new();
+        let shared = Arc::clone(&link);
+        let writer = thread::Builder::new()
+            .name("lsamp-recorder".to_string())
+            .spawn(move || write(shared, path, format, sample_rate))
+            .map_err(|e| format!("[Inner Cosmos] Recorder thread failed to start: {}", e))?;
+        Ok(Self { link, writer })
+    }
+
+    /// Waits for the writer to finish the file. None when nothing was
+    /// recorded (or the recording was cancelled), and there is no file.
+    pub(crate) fn finish(self) -> Result<Option<MasterRecording>, String> {
+        self.writer
+            .join()
+            .map_err(|_| "[Inner Cosmos] The recorder thread panicked".to_string())?
+    }
+}
+
+/// The writer thread: creates the file with the first chunk, so a recording
+/// that never got past its count-in leaves none
+fn write(
+    link: Arc<RecordLink>,
+    path: PathBuf,
+    format: WavFormat,
+    sample_rate: u32,
+) -> Result<Option<MasterRecording>, String> {
+    let mut file = None;
+    let mut samples = 0usize;
+    let mut peak = 0.0f32;
+    loop {
+        let ended = link.ended();
+        if link.cancelled.load(Ordering::Acquire) {
+            break;
+        }
+        while let Some(mut chunk) = link.full.pop() {
+            if file.is_none() {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+                }
+                let spec = wav_spec(format, 2, sample_rate);
+                file = Some(hound::WavWriter::create(&path, spec).map_err(|e| e.to_string())?);
+            }
+            if let Some(writer) = file.as_mut() {
+                for &sample in &chunk.data {
+                    write_wav_sample(writer, sample, format)?;
+                    peak = peak.max(sample.abs());
+                }
+            }
+            samples += chunk.data.len();
+            chunk.data.clear();
+            let _ = link.free.push(chunk);
+        }
+        // Everything the callback pushed before ending is drained by now
+        if ended {
+            break;
+        }
+        thread::sleep(WRITER_POLL);
+    }
+    let writer = match file {
+        Some(writer) => writer,
+        None => return Ok(None),
+    };
+    if link.cancelled.load(Ordering::Acquire) {
+        drop(writer);
+        fs::remove_file(&path).map_err(|e| e.to_string())?;
+        return Ok(None);
+    }
+    writer.finalize().map_err(|e| e.to_string())?;
+    Ok(Some(MasterRecording {
+        path,
+        duration: samples as f32 / 2.0 / sample_rate as f32,
+        peak,
+        dropped_frames: link.dropped.load(Ordering::Relaxed),
+    }))
+}
+
+/// The recorder's part of the mixer
+pub(crate) struct Recorder {
+    link: Option<Arc<RecordLink>>,
+    start: Option<f64>, // Beat the capture starts on, while armed
+    chunk: Option<Chunk>,
+}
+
+impl Recorder {
+    pub(crate) fn new() -> Self {
+        Self {
+            link: None,
+            start: None,
+            chunk: None,
+        }
+    }
+
+    /// Records into `link` from `start`, or from now on with None
+    pub(crate) fn record(&mut self, link: Arc<RecordLink>, start: Option<f64>) {
+        self.end(false);
+        self.link = Some(link);
+        self.start = start;
+    }
+
+    /// Ends the recording, passing the last frames on unless `cancel`
+    pub(crate) fn end(&mut self, cancel: bool) {
+        if let Some(link) = self.link.take() {
+            // Either queue has room for every chunk there is
+            if let Some(chunk) = self.chunk.take() {
+                let _ = if cancel {
+                    link.free.push(chunk)
+                } else {
+                    link.full.push(chunk)
+                };
+            }
+            link.end(cancel);
+        }
+        self.start = None;
+    }
+
+    /// True when nothing records or waits to
+    pub(crate) fn idle(&self) -> bool {
+        self.link.is_none()
+    }
+
+    /// True while waiting for the start
+    pub(crate) fn armed(&self) -> bool {
+        self.start.is_some()
+    }
+
+    /// Frames from the clock's position to the start, None once capturing
+    pub(crate) fn frames_to_start(&self, clock: &Clock) -> Option<usize> {
+        Some(clock.frames_to(self.start?))
+    }
+
+    /// Starts capturing when the clock reaches the start
+    pub(crate) fn fire(&mut self, clock: &Clock) {
+        if self.start.is_some_and(|start| clock.reached(start)) {
+            self.start = None;
+        }
+    }
+
+    /// Copies a block of the mix into the recording
+    pub(crate) fn capture(&mut self, left: &[f32], right: &[f32]) {
+        let link = match &self.link {
+            Some(link) if self.start.is_none() => link,
+            _ => return,
+        };
+        for (&l, &r) in left.iter().zip(right) {
+            if self.chunk.is_none() {
+                self.chunk = link.free.pop();
+            }
+            let chunk = match self.chunk.as_mut() {
+                Some(chunk) => chunk,
+                None => {
+                    link.dropped.fetch_add(1, Ordering::Relaxed);
+                    continue;
+                }
+            };
+            chunk.data.push(l);
+            chunk.data.push(r);
+            if chunk.data.len() == CHUNK_FRAMES * 2 {
+                if let Some(full) = self.chunk.take() {
+                    let _ = link.full.push(full);
+                }
+            }
+        }
+    }
+}
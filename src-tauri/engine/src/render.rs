@@ -0,0 +1,166 @@
+/**
+ * render.rs
+ * L-SAMP 100 | Offline Render
+ *
+ * Bounces the step patterns, the take or both without playing them: a
+ * second engine on an `OfflineBackend` gets a copy of the live state (the
+ * loaded buffers are shared, not copied) and its mixer is pulled as fast
+ * as it goes. It is the same scheduler and mixer, so at the live sample
+ * rate the file is what playback sounds like. The live engine only gives
+ * up its lock for the copy.
+ *
+ * The offline engine's feeder still builds loops on its own thread, so the
+ * render waits for it before every chunk; a chunk is shorter than any loop,
+ * so no loop starts before its voices are ready.
+ */
+use serde::{Deserialize, Serialize};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::performance::Performances;
+use crate::sequencer::{Lane, LANES};
+use crate::{
+    lock, AudioEngine, AudioEngineState, OfflineBackend, RenderedRegion, BEATS_PER_BAR, MIX_BLOCK,
+};
+
+/// Longest render
+const MAX_RENDER_BARS: u32 = 64;
+
+/// Frames pulled at a time, well under the shortest loop (one step)
+const RENDER_CHUNK: usize = MIX_BLOCK;
+
+/// How long a render waits for the offline feeder before giving up
+const FEEDER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What `render_offline` plays
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum RenderSource {
+    Pattern, // The step patterns
+    Take,    // The last take recorded or played, looping
+    Session, // Both, started together
+}
+
+/// Payload of `render-progress`, once per bar rendered
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct RenderProgress {
+    pub bar: u32,
+    pub bars: u32,
+}
+
+/// Result of `render_offline`
+pub struct OfflineRender {
+    pub region: RenderedRegion,
+    pub peak: f32, // Highest sample magnitude, 1.0 is full scale
+}
+
+/// The part of the live state an offline engine plays from
+pub(crate) fn snapshot(live: &AudioEngineState) -> Result<AudioEngineState, String> {
+    let mut performance = Performances::default();
+    performance.set_take(live.performance.take())?;
+    let mut sequence = live.sequence.clone();
+    sequence.running = false;
+    Ok(AudioEngineState {
+        sound_bank: live.sound_bank.clone(), // Arcs to the same buffers
+        master_volume: live.master_volume,
+        master_bpm: live.master_bpm,
+        bpm_overrides: live.bpm_overrides.clone(),
+        bpm_range: live.bpm_range,
+        analysis: live.analysis.clone(),
+        makeup_gains: live.makeup_gains.clone(),
+        pad_params: live.pad_params.clone(),
+        pad_defaults: live.pad_defaults.clone(),
+        stop_all_release: live.stop_all_release,
+        crossfader: live.crossfader,
+        crossfade_curve: live.crossfade_curve,
+        pad_groups: live.pad_groups.clone(),
+        compressor: live.compressor,
+        delay: live.delay,
+        swing: live.swing,
+        sequence,
+        performance,
+        ..AudioEngineState::new(live.sample_rate)
+    })
+}
+
+/// Plays `source` on an offline engine around `state` for `bars` bars
+pub(crate) fn render(
+    state: AudioEngineState,
+    source: RenderSource,
+    bars: u32,
+    mut on_progress: impl FnMut(RenderProgress),
+) -> Result<OfflineRender, String> {
+    if !(1..=MAX_RENDER_BARS).contains(&bars) {
+        return Err(format!(
+            "[Social Noise] A render of {} bars is outside 1..={}",
+            bars, MAX_RENDER_BARS
+        ));
+    }
+    let lanes: &[Lane] = match source {
+        RenderSource::Pattern => &[Lane::Steps],
+        RenderSource::Take => &[Lane::Performance],
+        RenderSource::Session => &[Lane::Steps, Lane::Performance],
+    };
+    if source == RenderSource::Pattern && state.sequence.is_empty() {
+        return Err("[Social Noise] No pattern to render".to_string());
+    }
+    let sample_rate = state.sample_rate;
+    let seconds = bars as f64 * BEATS_PER_BAR * 60.0 / state.master_bpm as f64;
+    let frames = (seconds * sample_rate as f64).round() as usize;
+
+    let (backend, output) = OfflineBackend::new(sample_rate, 2);
+    let engine = AudioEngine::with_state(backend, state)?;
+    // The clock is on beat 0, so every lane starts right away
+    for &lane in lanes {
+        match lane {
+            Lane::Steps => engine.seq_start()?,
+            Lane::Performance => engine.pattern_play(None, true)?,
+            Lane::Repeat => {} // Only ever held live
+        }
+    }
+
+    let mut data = Vec::with_capacity(frames * 2);
+    let mut bar = 0;
+    while data.len() < frames * 2 {
+        wait_for_loops(&engine)?;
+        let chunk = RENDER_CHUNK.min(frames - data.len() / 2);
+        data.extend(output.render(chunk));
+        let done = (data.len() / 2 * bars as usize / frames) as u32;
+        if done > bar {
+            bar = done;
+            on_progress(RenderProgress { bar, bars });
+        }
+    }
+    let peak = data
+        .iter()
+        .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+    Ok(OfflineRender {
+        region: RenderedRegion {
+            data,
+            sample_rate,
+            channels: 2,
+        },
+        peak,
+    })
+}
+
+/// Waits until every lane `engine` plays has its next loop built
+pub(crate) fn wait_for_loops(engine: &AudioEngine) -> Result<(), String> {
+    let started = Instant::now();
+    loop {
+        let ready = {
+            let mut state = lock(&engine.state);
+            LANES
+                .into_iter()
+                .all(|lane| !*lane.running(&mut state) || engine.seq.waiting(lane))
+        };
+        if ready {
+            return Ok(());
+        }
+        if started.elapsed() > FEEDER_TIMEOUT {
+            return Err("[Inner Cosmos] The offline sequencer stalled".to_string());
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+}
@@ -0,0 +1,88 @@
+/**
+ * repeat.rs
+ * L-SAMP 100 | Beat Repeat
+ *
+ * Retriggers one pad on every step of the master clock's grid until it is
+ * stopped, the stutter of a drum machine's note repeat. It plays on the
+ * sequencer's repeat lane: each loop is a bar of steps, and every step cuts
+ * the pad's voices short and triggers it again with the params it was last
+ * played with. The steps swing through `Grid` like the sequencer's, and the
+ * repeat starts on the next pair of steps from the bar line, so its late
+ * steps fall where quantized launches' do. Stopping it lets the last
+ * trigger ring out.
+ */
+use tracing::warn;
+
+use crate::sequencer::{SeqAction, SeqPattern};
+use crate::{
+    pad_voice, resolve_params, AudioEngineState, PartialPlayParams, StepDivision, BEATS_PER_BAR,
+};
+
+/// Release of the voice a repeat cuts short, in seconds
+const REPEAT_CUT: f32 = 0.005;
+
+/// The pad being repeated, kept with the engine state
+#[derive(Default)]
+pub(crate) struct BeatRepeat {
+    pub(crate) key: Option<String>,
+    pub(crate) division: StepDivision,
+    pub(crate) running: bool,
+}
+
+/// Builds the next bar of the repeat. It is empty, and the lane ends with
+/// it, once the pad is no longer loaded.
+pub(crate) fn build_loop(state: &mut AudioEngineState) -> Box<SeqPattern> {
+    let division = state.repeat.division;
+    let key = state
+        .repeat
+        .key
+        .clone()
+        .filter(|key| state.sound_bank.contains_key(key));
+    let key = match key {
+        Some(key) => key,
+        None => {
+            return Box::new(SeqPattern {
+                length: BEATS_PER_BAR,
+                looping: false,
+                actions: Vec::new(),
+                grid: None,
+            })
+        }
+    };
+    let params = match state.pad_params.get(&key) {
+        Some(params) => Ok(params.clone()),
+        None => resolve_params(state, &key, PartialPlayParams::default()).map(|mut params| {
+            params.sanitize();
+            params
+        }),
+    };
+    let step_beats = 1.0 / division.steps_per_beat();
+    let steps = (BEATS_PER_BAR * division.steps_per_beat()) as usize;
+    let mut actions = Vec::with_capacity(steps * 2);
+    for step in 0..steps {
+        let voice = params
+            .clone()
+            .and_then(|mut params| pad_voice(state, key.clone(), &mut params));
+        match voice {
+            Ok(voice) => {
+                let beat = step as f64 * step_beats;
+                let stop = SeqAction::Stop {
+                    slot: voice.slot,
+                    release: Some(REPEAT_CUT),
+                };
+                actions.push((beat, Some(stop)));
+                actions.push((beat, Some(SeqAction::Play(voice))));
+            }
+            Err(e) => {
+                warn!("Beat repeat of {}: {}", key, e);
+                break;
+            }
+        }
+    }
+    Box::new(SeqPattern {
+        length: BEATS_PER_BAR,
+        looping: true,
+        actions,
+        grid: Some(division),
+    })
+}
@@ -0,0 +1,204 @@
+/**
+ * scene.rs
+ * L-SAMP 100 | Scenes
+ *
+ * Snapshots of the live mix: the master volume and tempo, the crossfader,
+ * and every played pad's volume, crossfader side, delay send, send mute and
+ * filter cutoff (the filter has no resonance to keep). Recalling a scene
+ * morphs from the mix as it is to the scene over a fade; the engine works
+ * the morph out on every `scene_tick` and sends it like any other change,
+ * so the mixer's ramps smooth the steps. The cutoff moves evenly in
+ * octaves; sides and send mutes switch halfway through. Scenes are plain
+ * JSON (`Scene`), as sessions store them.
+ */
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Instant;
+
+use crate::{AudioEngineState, CrossfadeGroup};
+
+/// Scenes kept at once
+pub(crate) const SCENE_SLOTS: usize = 8;
+
+/// A saved mix
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Scene {
+    pub slot: usize, // 0-based, below SCENE_SLOTS
+    pub master_volume: f32,
+    pub master_bpm: f32,
+    pub crossfader: f32,
+    pub pads: BTreeMap<String, PadScene>, // Per pad id
+}
+
+/// What a scene keeps of one pad
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PadScene {
+    pub volume: f32,
+    #[serde(default)]
+    pub group: Option<CrossfadeGroup>,
+    // None in scenes saved before these were kept; a recall leaves them be
+    #[serde(default)]
+    pub delay_send: Option<f32>,
+    #[serde(default)]
+    pub sends_muted: Option<bool>,
+    #[serde(default)]
+    pub filter_cutoff: Option<f32>, // Hz
+}
+
+/// Result of `scene_recall`
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SceneRecall {
+    pub skipped: Vec<String>, // Pads of the scene that are not loaded any more
+    pub fading: bool,         // False when the scene was applied at once
+}
+
+impl Scene {
+    /// The mix as it is, for the pads that have been played or set
+    pub(crate) fn capture(state: &AudioEngineState, slot: usize) -> Self {
+        let pads = state
+            .pad_params
+            .iter()
+            .filter(|(key, _)| state.sound_bank.contains_key(*key))
+            .map(|(key, params)| {
+                let pad = PadScene {
+                    volume: params.volume,
+                    group: state.pad_groups.get(key).copied(),
+                    delay_send: Some(params.delay_send),
+                    sends_muted: Some(params.sends_muted),
+                    filter_cutoff: Some(params.filter_cutoff),
+                };
+                (key.clone(), pad)
+            })
+            .collect();
+        Self {
+            slot,
+            master_volume: state.master_volume,
+            master_bpm: state.master_bpm,
+            crossfader: state.crossfader,
+            pads,
+        }
+    }
+
+    /// The mix `progress` (0-1) of the way from `self` to `to`. Pads only
+    /// `to` has start from their current volume.
+    fn towards(&self, to: &Scene, progress: f32) -> Scene {
+        let lerp = |from: f32, to: f32| from + (to - from) * progress;
+        let late = progress >= 0.5;
+        let pads = to
+            .pads
+            .iter()
+            .map(|(key, target)| {
+                let from = self.pads.get(key).unwrap_or(target);
+                let pad = PadScene {
+                    volume: lerp(from.volume, target.volume),
+                    group: if late { target.group } else { from.group },
+                    delay_send: match (from.delay_send, target.delay_send) {
+                        (Some(from), Some(to)) => Some(lerp(from, to)),
+                        (_, to) => to,
+                    },
+                    sends_muted: if late || from.sends_muted.is_none() {
+                        target.sends_muted
+                    } else {
+                        from.sends_muted
+                    },
+                    filter_cutoff: match (from.filter_cutoff, target.filter_cutoff) {
+                        (Some(from), Some(to)) if from > 0.0 && to > 0.0 => {
+                            Some(from * (to / from).powf(progress))
+                        }
+                        (_, to) => to,
+                    },
+                };
+                (key.clone(), pad)
+            })
+            .collect();
+        Scene {
+            slot: to.slot,
+            master_volume: lerp(self.master_volume, to.master_volume),
+            master_bpm: lerp(self.master_bpm, to.master_bpm),
+            crossfader: lerp(self.crossfader, to.crossfader),
+            pads,
+        }
+    }
+}
+
+/// A recall under way
+pub(crate) struct Morph {
+    from: Scene,
+    to: Scene, // Loaded pads only
+    started: Instant,
+    seconds: f32,
+}
+
+/// The engine's scenes and the morph between them
+#[derive(Default)]
+pub(crate) struct Scenes {
+    slots: BTreeMap<usize, Scene>,
+    pub(crate) morph: Option<Morph>,
+}
+
+impl Scenes {
+    pub(crate) fn save(&mut self, scene: Scene) -> Result<(), String> {
+        check_slot(scene.slot)?;
+        self.slots.insert(scene.slot, scene);
+        Ok(())
+    }
+
+    pub(crate) fn get(&self, slot: usize) -> Result<&Scene, String> {
+        check_slot(slot)?;
+        self.slots
+            .get(&slot)
+            .ok_or_else(|| format!("[Social Noise] Scene slot {} is empty", slot))
+    }
+
+    /// Every saved scene, by slot
+    pub(crate) fn list(&self) -> Vec<Scene> {
+        self.slots.values().cloned().collect()
+    }
+
+    /// Replaces every scene, e.g. from a session
+    pub(crate) fn replace(&mut self, scenes: Vec<Scene>) -> Result<(), String> {
+        if let Some(scene) = scenes.iter().find(|scene| scene.slot >= SCENE_SLOTS) {
+            check_slot(scene.slot)?;
+        }
+        self.slots = scenes
+            .into_iter()
+            .map(|scene| (scene.slot, scene))
+            .collect();
+        self.morph = None;
+        Ok(())
+    }
+
+    /// Starts morphing from `from` to `to` over `seconds`
+    pub(crate) fn start(&mut self, from: Scene, to: Scene, seconds: f32) {
+        self.morph = Some(Morph {
+            from,
+            to,
+            started: Instant::now(),
+            seconds,
+        });
+    }
+
+    /// Where the morph is now, and whether it goes on after this
+    pub(crate) fn step(&mut self) -> Option<(Scene, bool)> {
+        let morph = self.morph.as_ref()?;
+        let progress = (morph.started.elapsed().as_secs_f32() / morph.seconds).min(1.0);
+        let now = morph.from.towards(&morph.to, progress);
+        if progress >= 1.0 {
+            self.morph = None;
+        }
+        Some((now, progress < 1.0))
+    }
+}
+
+fn check_slot(slot: usize) -> Result<(), String> {
+    if slot >= SCENE_SLOTS {
+        return Err(format!(
+            "[Social Noise] Scene slot {} is outside 0..{}",
+            slot, SCENE_SLOTS
+        ));
+    }
+    Ok(())
+}
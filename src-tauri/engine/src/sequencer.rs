@@ -0,0 +1,486 @@
+/**
+ * sequencer.rs
+ * L-SAMP 100 | Step Sequencer
+ *
+ * A pattern of steps per pad, fired by the audio callback on the master
+ * clock's beat grid so triggers land on their frame whatever the UI is
+ * doing. Recorded performances (performance.rs) replay through the same
+ * scheduler, on a lane of their own, and beat repeat (repeat.rs) has a
+ * third.
+ *
+ * The callback cannot look up params or build voices, so every loop of a
+ * lane is built ahead as timed actions with ready voices (`SeqPattern`):
+ * the callback takes the waiting loop when it starts a new one, and the
+ * feeder thread builds the next as soon as the slot is empty. A change
+ * replaces the waiting loop, so it is heard from the next loop on. Swing
+ * is the exception: the callback swings each step as it comes due, so a
+ * new amount is heard from the next step.
+ */
+use crossbeam_queue::ArrayQueue;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::{Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tracing::warn;
+
+use crate::{
+    lock, pad_voice, resolve_params, AudioEngineState, CallbackCounters, Clock, Grid,
+    PartialPlayParams, Voice, VoiceEnded, VoicePool, BEATS_PER_BAR,
+};
+use crate::{performance, repeat};
+
+/// Longest pattern, in steps
+const MAX_STEPS: usize = 64;
+
+/// Pattern length before `seq_set_length`
+const DEFAULT_STEPS: usize = 16;
+
+/// Notices from the callback waiting for the feeder, and steps waiting for
+/// `take_seq_steps` after it. A full queue drops them; the feeder catches up
+/// on the next one and the playhead misses a step.
+pub(crate) const SEQ_EVENT_QUEUE: usize = 256;
+
+/// One trigger of a pad's pattern
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StepEvent {
+    pub step: usize, // 0-based, below MAX_STEPS; steps past the length are kept but silent
+    pub velocity: f32,
+    #[serde(default)]
+    pub params: Option<PartialPlayParams>, // Over the pad's last played params
+}
+
+/// Note value of one step
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum StepDivision {
+    Quarter,
+    Eighth,
+    #[default]
+    Sixteenth,
+    ThirtySecond,
+}
+
+impl StepDivision {
+    pub(crate) fn steps_per_beat(self) -> f64 {
+        match self {
+            StepDivision::Quarter => 1.0,
+            StepDivision::Eighth => 2.0,
+            StepDivision::Sixteenth => 4.0,
+            StepDivision::ThirtySecond => 8.0,
+        }
+    }
+}
+
+/// Payload of `seq-step`: the step that just fired
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct SeqStep {
+    pub step: usize,
+    pub length: usize,
+}
+
+/// What the sequencer plays, kept with the engine state
+#[derive(Clone)]
+pub(crate) struct Sequence {
+    patterns: HashMap<String, Vec<StepEvent>>, // Steps per pad id
+    length: usize,
+    division: StepDivision,
+    pub(crate) running: bool,
+}
+
+impl Default for Sequence {
+    fn default() -> Self {
+        Self {
+            patterns: HashMap::new(),
+            length: DEFAULT_STEPS,
+            division: StepDivision::default(),
+            running: false,
+        }
+    }
+}
+
+impl Sequence {
+    /// True when no pad has steps
+    pub(crate) fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Replaces a pad's steps; none removes the pad from the sequence
+    pub(crate) fn set_pattern(&mut self, key: String, steps: Vec<StepEvent>) -> Result<(), String> {
+        if let Some(event) = steps.iter().find(|event| event.step >= MAX_STEPS) {
+            return Err(format!(
+                "[Social Noise] Step {} is past the last step ({})",
+                event.step,
+                MAX_STEPS - 1
+            ));
+        }
+        if steps.is_empty() {
+            self.patterns.remove(&key);
+        } else {
+            self.patterns.insert(key, steps);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn set_length(
+        &mut self,
+        steps: usize,
+        division: StepDivision,
+    ) -> Result<(), String> {
+        if !(1..=MAX_STEPS).contains(&steps) {
+            return Err(format!(
+                "[Social Noise] {} steps is outside 1..{}",
+                steps, MAX_STEPS
+            ));
+        }
+        self.length = steps;
+        self.division = division;
+        Ok(())
+    }
+}
+
+/// What a lane plays: the step patterns, the recorded performance or the
+/// beat repeat
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Lane {
+    Steps,
+    Performance,
+    Repeat,
+}
+
+pub(crate) const LANES: [Lane; 3] = [Lane::Steps, Lane::Performance, Lane::Repeat];
+
+impl Lane {
+    fn index(self) -> usize {
+        self as usize
+    }
+
+    /// Whether the lane should be playing, as the engine sees it
+    pub(crate) fn running(self, state: &mut AudioEngineState) -> &mut bool {
+        match self {
+            Lane::Steps => &mut state.sequence.running,
+            Lane::Performance => &mut state.performance.playing,
+            Lane::Repeat => &mut state.repeat.running,
+        }
+    }
+
+    /// Beat a start asked for at `beat` begins on: the next step for the
+    /// step patterns, the next bar for a performance, the next pair of steps
+    /// for a beat repeat
+    pub(crate) fn start_beat(self, state: &AudioEngineState, beat: f64) -> f64 {
+        let grid = match self {
+            Lane::Steps => 1.0 / state.sequence.division.steps_per_beat(),
+            Lane::Performance => BEATS_PER_BAR,
+            Lane::Repeat => 2.0 / state.repeat.division.steps_per_beat(),
+        };
+        (beat / grid).ceil() * grid
+    }
+
+    /// The lane's next loop, from what the engine holds now
+    pub(crate) fn build(self, state: &mut AudioEngineState) -> Box<SeqPattern> {
+        match self {
+            Lane::Steps => build_pattern(state),
+            Lane::Performance => performance::build_loop(state),
+            Lane::Repeat => repeat::build_loop(state),
+        }
+    }
+}
+
+/// Something a loop does at its beat. Voices stay inline: a boxed one
+/// would be freed on the audio thread once it moves into the pool.
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum SeqAction {
+    Step(SeqStep), // Tells the playhead, fires nothing
+    Play(Voice),
+    Stop { slot: usize, release: Option<f32> },
+}
+
+/// One loop of a lane, with its voices ready to play
+pub(crate) struct SeqPattern {
+    pub(crate) length: f64, // Beats
+    pub(crate) looping: bool,
+    pub(crate) actions: Vec<(f64, Option<SeqAction>)>, // Beat into the loop, in order; taken as they fire
+    pub(crate) grid: Option<StepDivision>, // Steps the actions sit on, swung as they come due
+}
+
+/// Builds the next loop of the step patterns. Pads that are not loaded
+/// right now stay silent; their steps are kept for a reload.
+fn build_pattern(state: &mut AudioEngineState) -> Box<SeqPattern> {
+    let length = state.sequence.length;
+    let division = state.sequence.division;
+    let step_beats = 1.0 / division.steps_per_beat();
+    let patterns = state.sequence.patterns.clone();
+    let mut triggers: Vec<Vec<SeqAction>> = (0..length)
+        .map(|step| vec![SeqAction::Step(SeqStep { step, length })])
+        .collect();
+    for (key, events) in patterns {
+        if !state.sound_bank.contains_key(&key) {
+            continue;
+        }
+        for event in events.into_iter().filter(|event| event.step < length) {
+            let partial = PartialPlayParams {
+                velocity: Some(event.velocity),
+                ..event.params.unwrap_or_default()
+            };
+            let params = match state.pad_params.get(&key) {
+                Some(params) => Ok(partial.over(params.clone())),
+                None => resolve_params(state, &key, partial),
+            };
+            let voice = params.and_then(|mut params| {
+                params.sanitize();
+                pad_voice(state, key.clone(), &mut params)
+            });
+            match voice {
+                Ok(voice) => triggers[event.step].push(SeqAction::Play(voice)),
+                Err(e) => warn!("Sequencer step {} of {}: {}", event.step, key, e),
+            }
+        }
+    }
+    let actions = triggers
+        .into_iter()
+        .enumerate()
+        .flat_map(|(step, actions)| {
+            let beat = step as f64 * step_beats;
+            actions.into_iter().map(move |action| (beat, Some(action)))
+        })
+        .collect();
+    Box::new(SeqPattern {
+        length: length as f64 * step_beats,
+        looping: true,
+        actions,
+        grid: Some(division),
+    })
+}
+
+/// What the callback tells the feeder
+enum Fired {
+    Step(SeqStep),
+    Loop,        // A lane took its waiting loop (or found none)
+    Ended(Lane), // A lane played its last loop
+}
+
+/// The callback's ends of the lanes, handed to every new mixer
+#[derive(Clone)]
+pub(crate) struct SeqLink {
+    next: Arc<[ArrayQueue<Box<SeqPattern>>; 3]>, // The loop after the one playing, per lane
+    fired: SyncSender<Fired>,
+}
+
+impl SeqLink {
+    /// Puts `pattern` up as the lane's next loop, replacing a waiting one
+    pub(crate) fn offer(&self, lane: Lane, pattern: Box<SeqPattern>) {
+        drop(self.next[lane.index()].force_push(pattern));
+    }
+
+    /// Drops the lane's waiting loop
+    pub(crate) fn clear(&self, lane: Lane) {
+        drop(self.next[lane.index()].pop());
+    }
+
+    /// Whether the lane has a loop waiting
+    pub(crate) fn waiting(&self, lane: Lane) -> bool {
+        !self.next[lane.index()].is_empty()
+    }
+}
+
+/// Starts the feeder, which keeps a loop waiting for every lane that plays
+/// and passes the fired steps on to `steps`. It stops with the engine, when
+/// the last sender of its notices is gone.
+pub(crate) fn start_feeder(
+    state: Arc<Mutex<AudioEngineState>>,
+    steps: SyncSender<SeqStep>,
+) -> Result<SeqLink, String> {
+    let (fired, notices): (_, Receiver<Fired>) = std::sync::mpsc::sync_channel(SEQ_EVENT_QUEUE);
+    let link = SeqLink {
+        next: Arc::new([ArrayQueue::new(1), ArrayQueue::new(1), ArrayQueue::new(1)]),
+        fired,
+    };
+    let feeder = link.clone();
+    thread::Builder::new()
+        .name("lsamp-sequencer".to_string())
+        .spawn(move || feed(state, feeder, notices, steps))
+        .map_err(|e| format!("[Inner Cosmos] Sequencer thread failed to start: {}", e))?;
+    Ok(link)
+}
+
+fn feed(
+    state: Arc<Mutex<AudioEngineState>>,
+    link: SeqLink,
+    notices: Receiver<Fired>,
+    steps: SyncSender<SeqStep>,
+) {
+    // Holding a sender would keep this loop alive after the engine is gone
+    let SeqLink { next, fired } = link;
+    drop(fired);
+    for notice in notices {
+        let mut state = lock(&state);
+        if let Fired::Ended(lane) = notice {
+            *lane.running(&mut state) = false;
+            drop(next[lane.index()].pop());
+        }
+        for lane in LANES {
+            if next[lane.index()].is_empty() && *lane.running(&mut state) {
+                next[lane.index()].force_push(lane.build(&mut state));
+            }
+        }
+        drop(state);
+        if let Fired::Step(step) = notice {
+            // Nobody listening, or a listener behind, only costs playhead updates
+            let _ = steps.try_send(step);
+        }
+    }
+}
+
+/// One lane in the callback
+struct Track {
+    running: bool,
+    pattern: Option<Box<SeqPattern>>, // The loop playing; None when it was not ready in time
+    length: f64,                      // Of the loop playing, kept when there is none
+    looping: bool,
+    loop_beat: f64, // Beat the loop playing started on
+    cursor: usize,  // Next action of the loop
+}
+
+impl Track {
+    /// Beat of the next action, or of the loop's end after the last one
+    fn due(&self, swing: f32) -> f64 {
+        let offset = self.pattern.as_ref().and_then(|pattern| {
+            let (beat, _) = pattern.actions.get(self.cursor)?;
+            Some(match pattern.grid {
+                Some(division) => Grid::new(division, swing).swung(*beat),
+                None => *beat,
+            })
+        });
+        self.loop_beat + offset.unwrap_or(self.length)
+    }
+}
+
+/// The sequencer's part of the mixer
+pub(crate) struct Sequencer {
+    link: SeqLink,
+    tracks: [Track; 3],
+    swing: f32,
+}
+
+impl Sequencer {
+    pub(crate) fn new(link: &SeqLink) -> Self {
+        let track = || Track {
+            running: false,
+            pattern: None,
+            length: BEATS_PER_BAR,
+            looping: true,
+            loop_beat: 0.0,
+            cursor: 0,
+        };
+        Self {
+            link: link.clone(),
+            tracks: [track(), track(), track()],
+            swing: 0.0,
+        }
+    }
+
+    /// Applies to every step not fired yet
+    pub(crate) fn set_swing(&mut self, swing: f32) {
+        self.swing = swing;
+    }
+
+    pub(crate) fn running(&self) -> bool {
+        self.tracks.iter().any(|track| track.running)
+    }
+
+    /// Starts the lane's waiting loop on `beat`, over whatever it played
+    pub(crate) fn start(&mut self, lane: Lane, beat: f64, counters: &CallbackCounters) {
+        let track = &mut self.tracks[lane.index()];
+        track.running = true;
+        track.loop_beat = beat;
+        self.next_loop(lane, counters);
+    }
+
+    /// Voices already playing carry on
+    pub(crate) fn stop(&mut self, lane: Lane) {
+        let track = &mut self.tracks[lane.index()];
+        track.running = false;
+        track.pattern = None;
+    }
+
+    /// Frames from the clock's position to the next action, None while stopped
+    pub(crate) fn frames_to_step(&self, clock: &Clock) -> Option<usize> {
+        let due = self
+            .tracks
+            .iter()
+            .filter(|track| track.running)
+            .map(|track| track.due(self.swing))
+            .reduce(f64::min)?;
+        Some(clock.frames_to(due))
+    }
+
+    /// Fires every action due at the clock's position
+    pub(crate) fn fire(
+        &mut self,
+        clock: &Clock,
+        voices: &mut VoicePool,
+        retired: &mut Vec<VoiceEnded>,
+        counters: &CallbackCounters,
+    ) {
+        let swing = self.swing;
+        for lane in LANES {
+            loop {
+                let track = &mut self.tracks[lane.index()];
+                if !track.running || !clock.reached(track.due(swing)) {
+                    break;
+                }
+                let action = track
+                    .pattern
+                    .as_mut()
+                    .and_then(|pattern| pattern.actions.get_mut(track.cursor));
+                let action = match action {
+                    Some((_, action)) => action.take(),
+                    None => {
+                        // The loop is over
+                        track.loop_beat += track.length;
+                        if track.looping {
+                            self.next_loop(lane, counters);
+                        } else {
+                            self.stop(lane);
+                            let _ = self.link.fired.try_send(Fired::Ended(lane));
+                        }
+                        continue;
+                    }
+                };
+                track.cursor += 1;
+                match action {
+                    Some(SeqAction::Step(step)) => {
+                        let _ = self.link.fired.try_send(Fired::Step(step));
+                    }
+                    Some(SeqAction::Play(voice)) => voices.insert(voice, retired),
+                    Some(SeqAction::Stop { slot, release }) => {
+                        for voice in voices.iter_mut().filter(|voice| voice.slot == slot) {
+                            voice.stop(release);
+                        }
+                    }
+                    None => {}
+                }
+            }
+        }
+    }
+
+    /// Takes the loop the feeder built; without one the loop stays silent
+    fn next_loop(&mut self, lane: Lane, counters: &CallbackCounters) {
+        let track = &mut self.tracks[lane.index()];
+        track.cursor = 0;
+        track.pattern = self.link.next[lane.index()].pop();
+        match &track.pattern {
+            Some(pattern) => {
+                track.length = pattern.length;
+                track.looping = pattern.looping;
+            }
+            None => {
+                counters.late_patterns.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        let _ = self.link.fired.try_send(Fired::Loop);
+    }
+}
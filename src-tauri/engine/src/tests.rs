@@ -0,0 +1,158 @@
+/**
+ * tests.rs
+ * L-SAMP 100 | Engine Tests
+ *
+ * Whole-engine tests on an `OfflineBackend`: sounds go straight into the
+ * bank, the callback runs when a test renders, and the output is checked
+ * frame by frame. Stages with no engine around them are tested in their
+ * own modules.
+ */
+use super::*;
+
+mod alloc;
+mod bpm;
+mod crossfade;
+mod decode;
+mod decode_queue;
+mod denormals;
+mod envelope;
+mod looper;
+mod master;
+mod mix;
+mod offline;
+mod performance;
+mod recorder;
+mod scenes;
+mod sequencer;
+mod stress;
+mod swing;
+mod voices;
+mod waveform;
+
+/// Rate every test engine runs at
+pub(crate) const RATE: u32 = 48_000;
+
+/// A test engine at 120 BPM and the output that renders it
+pub(crate) fn engine() -> (AudioEngine, OfflineOutput) {
+    let (backend, output) = OfflineBackend::new(RATE, 2);
+    let engine = AudioEngine::with_backend(backend).unwrap();
+    (engine, output)
+}
+
+/// A buffer of `data`, interleaved over `channels`
+pub(crate) fn buffer(data: Vec<f32>, channels: u16, sample_rate: u32) -> AudioBuffer {
+    let duration = data.len() as f32 / channels as f32 / sample_rate as f32;
+    AudioBuffer {
+        data,
+        sample_rate,
+        channels,
+        duration,
+        waveform: Vec::new(),
+        waveform_left: Vec::new(),
+        waveform_right: Vec::new(),
+        trim_start: 0.0,
+        trim_end: duration,
+        lufs: None,
+        embedded_loop: None,
+        root_note: None,
+        tags: FileTags::default(),
+        truncated: false,
+        decode_warnings: Vec::new(),
+    }
+}
+
+/// Puts `buffer` on pad `key` as a finished load would
+pub(crate) fn load(engine: &AudioEngine, key: &str, buffer: AudioBuffer) {
+    lock(&engine.state)
+        .sound_bank
+        .insert(key.to_string(), BankEntry::new(buffer));
+}
+
+/// Plays `key` with neutral params over whatever `set` changes
+pub(crate) fn play(engine: &AudioEngine, key: &str, set: impl FnOnce(&mut PlayParams)) {
+    let mut params = engine
+        .resolve_play_params(key, PartialPlayParams::default())
+        .unwrap();
+    set(&mut params);
+    engine.play_sound(key.to_string(), params).unwrap();
+}
+
+/// Renders `frames` frames, letting the sequencer's feeder keep up
+pub(crate) fn render(engine: &AudioEngine, output: &OfflineOutput, frames: usize) -> Vec<f32> {
+    let mut data = Vec::with_capacity(frames * 2);
+    while data.len() < frames * 2 {
+        render::wait_for_loops(engine).unwrap();
+        let chunk = MIX_BLOCK.min(frames - data.len() / 2);
+        data.extend(output.render(chunk));
+    }
+    data
+}
+
+/// Runs `f` on another thread while the output keeps rendering, for calls
+/// that wait on the callback. Each block waits a little, roughly at device
+/// pace, so a busy machine cannot render seconds past the call's command.
+pub(crate) fn while_rendering<R: Send>(output: &OfflineOutput, f: impl FnOnce() -> R + Send) -> R {
+    std::thread::scope(|scope| {
+        let call = scope.spawn(f);
+        while !call.is_finished() {
+            output.render(MIX_BLOCK);
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        call.join().unwrap()
+    })
+}
+
+/// A path of its own for `name` in the temp directory, nothing there yet
+pub(crate) fn scratch(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("lsamp-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+/// A mono 16-bit WAV of `data` at the test rate, written to `path`
+pub(crate) fn write_mono_wav(path: &Path, data: &[f32]) {
+    let mut writer = hound::WavWriter::create(path, wav_spec(WavFormat::Pcm16, 1, RATE)).unwrap();
+    for &sample in data {
+        write_wav_sample(&mut writer, sample, WavFormat::Pcm16).unwrap();
+    }
+    writer.finalize().unwrap();
+}
+
+/// Loads `path` onto `key` through `load_sound`, as the app does
+pub(crate) fn load_path(engine: &AudioEngine, key: &str, path: &Path) -> LoadResult {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let load = engine.load_sound(
+        key.to_string(),
+        path.to_str().unwrap(),
+        Some(120.0),
+        LoadOptions::default(),
+        |_| {},
+    );
+    runtime.block_on(load).unwrap()
+}
+
+/// Path of a file in `tests/fixtures`
+pub(crate) fn fixture(name: &str) -> String {
+    format!("{}/src/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+/// One channel of interleaved stereo output
+pub(crate) fn channel(data: &[f32], index: usize) -> Vec<f32> {
+    data.chunks_exact(2).map(|frame| frame[index]).collect()
+}
+
+/// Frames where the left channel turns on after silence
+pub(crate) fn onsets(data: &[f32]) -> Vec<usize> {
+    let mut onsets = Vec::new();
+    let mut silent = true;
+    for (frame, pair) in data.chunks_exact(2).enumerate() {
+        let sounding = pair[0] != 0.0;
+        if sounding && silent {
+            onsets.push(frame);
+        }
+        silent = !sounding;
+    }
+    onsets
+}
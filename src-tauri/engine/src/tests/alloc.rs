@@ -0,0 +1,132 @@
+//! The callback never allocates. A counting allocator counts the
+//! allocations made on a thread while it is counting, and only the renderer
+//! runs then: commands are sent and the output buffer made outside it.
+//! Retired voices do free their buffers in the callback, so only
+//! allocations are counted.
+
+use super::*;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+struct Counting;
+
+thread_local! {
+    static COUNTING: Cell<bool> = const { Cell::new(false) };
+    static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+}
+
+fn count() {
+    // Without a thread local (as the thread exits) there is nothing to count
+    let _ = COUNTING.try_with(|counting| {
+        if counting.get() {
+            ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+        }
+    });
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count();
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count();
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count();
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// Allocations `f` makes on this thread
+fn allocations(f: impl FnOnce()) -> u64 {
+    ALLOCATIONS.with(|allocations| allocations.set(0));
+    COUNTING.with(|counting| counting.set(true));
+    f();
+    COUNTING.with(|counting| counting.set(false));
+    ALLOCATIONS.with(Cell::get)
+}
+
+/// Renders one 512-frame callback, returning what it allocated and its peak
+fn counted_render(engine: &AudioEngine, output: &OfflineOutput) -> (u64, f32) {
+    render::wait_for_loops(engine).unwrap();
+    let mut data = vec![0.0; 512 * 2];
+    let mut renderer = output.renderer.lock().unwrap();
+    let renderer = renderer.as_mut().unwrap();
+    let allocated = allocations(|| renderer.render(&mut data));
+    let peak = data.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+    (allocated, peak)
+}
+
+#[test]
+fn the_counter_counts() {
+    assert_eq!(allocations(|| drop(vec![0u8; 16])), 1);
+    let mut grown = Vec::<u8>::with_capacity(1);
+    assert_eq!(allocations(|| grown.extend([0; 64])), 1);
+}
+
+#[test]
+fn the_callback_never_allocates() {
+    let (engine, output) = engine();
+    let pads = ["a", "b", "c", "d"];
+    for (n, pad) in pads.iter().enumerate() {
+        let data = (0..10_000).map(|i| ((i * (n + 1)) as f32 * 0.01).sin() * 0.1);
+        load(
+            &engine,
+            pad,
+            buffer(data.collect(), 1 + (n % 2) as u16, 44_100),
+        );
+    }
+    engine.set_delay(DelaySettings::default()).unwrap();
+    engine.set_crossfader(0.3).unwrap();
+    engine.set_pad_group("b", Some(CrossfadeGroup::A)).unwrap();
+    let steps = (0..4)
+        .map(|step| StepEvent {
+            step,
+            velocity: 1.0,
+            params: None,
+        })
+        .collect();
+    engine.seq_set_pattern("d".to_string(), steps).unwrap();
+    engine.seq_start().unwrap();
+    let mut loudest = 0.0f32;
+    for callback in 0..400 {
+        // Plays, retriggers, updates and stops between the callbacks
+        let pad = pads[callback % 3];
+        match callback % 8 {
+            0 => play(&engine, pad, |params| {
+                params.looping = callback % 16 == 0;
+                params.attack = 0.01;
+                params.filter_cutoff = 2000.0;
+                params.drive = 0.3;
+                params.crush_bits = 8;
+                params.delay_send = 0.5;
+            }),
+            3 => {
+                let mut params = engine.pad_params(pad).unwrap();
+                params.volume = 0.5;
+                engine.update_voice(pad.to_string(), params).unwrap();
+            }
+            5 => {
+                engine.stop_sound(pad.to_string(), Some(0.02)).unwrap();
+            }
+            7 => engine.set_master_volume(0.5 + (callback % 3) as f32 * 0.2),
+            _ => {}
+        }
+        let (allocated, peak) = counted_render(&engine, &output);
+        assert_eq!(allocated, 0, "callback {}", callback);
+        loudest = loudest.max(peak);
+    }
+    assert!(loudest > 0.05, "nothing played");
+    assert_eq!(engine.callback_errors(), CallbackErrors::default());
+}
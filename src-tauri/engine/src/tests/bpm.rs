@@ -0,0 +1,88 @@
+//! Tempo analysis: a signal with no beats in it takes the fallback, and a
+//! fallback tempo is never synced to unless the caller forces it
+
+use super::*;
+use std::f32::consts::TAU;
+
+/// Uniform noise from a fixed xorshift seed
+fn white_noise(frames: usize) -> Vec<f32> {
+    let mut state = 0x2545_f491_u32;
+    (0..frames)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as f32 / u32::MAX as f32 - 0.5
+        })
+        .collect()
+}
+
+/// A decaying 60 Hz kick on every beat at `bpm`, over a little noise
+fn kicks(frames: usize, bpm: f32) -> Vec<f32> {
+    let beat = (RATE as f32 * 60.0 / bpm) as usize;
+    let noise = white_noise(frames);
+    (0..frames)
+        .map(|n| {
+            let t = (n % beat) as f32 / RATE as f32;
+            noise[n] * 0.05 + (-t * 30.0).exp() * (TAU * 60.0 * t).sin()
+        })
+        .collect()
+}
+
+/// Loads `path` with no cached tempo and runs its analysis
+fn load_and_analyze(engine: &AudioEngine, key: &str, path: &Path) -> AnalysisComplete {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let path = path.to_str().unwrap();
+    let load = engine.load_sound(key.to_string(), path, None, LoadOptions::default(), |_| {});
+    let result = runtime.block_on(load).unwrap();
+    assert_eq!(result.bpm_source, None);
+    runtime.block_on(engine.run_analysis(key)).unwrap()
+}
+
+#[test]
+fn white_noise_takes_the_fallback() {
+    for seconds in [0.5, 2.0, 10.0] {
+        let noise = buffer(white_noise((RATE as f32 * seconds) as usize), 1, RATE);
+        let analysis = analyze_bpm(&noise, DEFAULT_BPM_RANGE, "noise");
+        assert_eq!(analysis.source, BpmSource::Fallback, "{} s", seconds);
+        assert_eq!(analysis.confidence, 0.0);
+        assert!(analysis.error.is_some());
+    }
+    // While a beat is still found under the same noise
+    let beats = buffer(kicks(RATE as usize * 8, 120.0), 1, RATE);
+    let analysis = analyze_bpm(&beats, DEFAULT_BPM_RANGE, "kicks");
+    assert_eq!(analysis.source, BpmSource::Detected);
+    assert!((analysis.bpm - 120.0).abs() < 2.0, "{}", analysis.bpm);
+}
+
+#[test]
+fn a_fallback_tempo_is_only_synced_when_forced() {
+    let (engine, output) = engine();
+    let path = scratch("noise.wav");
+    write_mono_wav(&path, &white_noise(RATE as usize * 2));
+    let analysis = load_and_analyze(&engine, "a", &path);
+    assert_eq!(analysis.bpm_source, BpmSource::Fallback);
+    assert!(analysis.bpm_error.is_some());
+    let data = lock(&engine.state).sound_bank["a"].buffer.data.clone();
+    engine.set_master_bpm(90.0).unwrap();
+    let sync = |force_sync| {
+        move |params: &mut PlayParams| {
+            params.sync = true;
+            params.sample_bpm = analysis.bpm;
+            params.force_sync = force_sync;
+        }
+    };
+    // Played at its own speed, sync or not
+    play(&engine, "a", sync(false));
+    let left = channel(&render(&engine, &output, 2400), 0);
+    assert_eq!(left[..], data[..2400]);
+    engine.stop_all();
+    render(&engine, &output, RATE as usize / 10);
+    // and at 90/120 of it when forced
+    play(&engine, "a", sync(true));
+    let left = channel(&render(&engine, &output, 2400), 0);
+    assert!((left[1000] - data[750]).abs() < 1e-6);
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,102 @@
+//! The crossfader scales the pads on its two sides by the curve it is set
+//! to, glides there over `LEVEL_SLEW_MS` and leaves the other pads alone
+
+use super::*;
+
+/// Frames a fader move takes
+const RAMP: usize = (LEVEL_SLEW_MS as usize) * RATE as usize / 1000;
+
+/// Level of the pad a steady 0.5 is played on
+const LEVEL: f32 = 0.5;
+
+fn steady(engine: &AudioEngine) {
+    load(engine, "a", buffer(vec![LEVEL; RATE as usize], 1, RATE));
+}
+
+/// Gain a pad on `group` plays at with the fader settled at `position`
+fn heard(curve: CrossfadeCurve, group: Option<CrossfadeGroup>, position: f32) -> f32 {
+    let (engine, output) = engine();
+    steady(&engine);
+    engine.set_crossfade_curve(curve).unwrap();
+    engine.set_pad_group("a", group).unwrap();
+    engine.set_crossfader(position).unwrap();
+    render(&engine, &output, 2 * RAMP);
+    play(&engine, "a", |_| {});
+    channel(&render(&engine, &output, 100), 0)[99] / LEVEL
+}
+
+#[test]
+fn constant_power_keeps_the_loudness() {
+    let curve = CrossfadeCurve::ConstantPower;
+    for position in [0.0, 0.25, 0.5, 0.75, 1.0] {
+        let a = heard(curve, Some(CrossfadeGroup::A), position);
+        let b = heard(curve, Some(CrossfadeGroup::B), position);
+        assert!((a * a + b * b - 1.0).abs() < 1e-5, "{} and {}", a, b);
+        assert_eq!(heard(curve, None, position), 1.0);
+    }
+    assert_eq!(heard(curve, Some(CrossfadeGroup::A), 0.0), 1.0);
+    assert!(heard(curve, Some(CrossfadeGroup::B), 0.0).abs() < 1e-6);
+    assert!(heard(curve, Some(CrossfadeGroup::A), 1.0).abs() < 1e-6);
+    assert_eq!(heard(curve, Some(CrossfadeGroup::B), 1.0), 1.0);
+    // Past either end is the end
+    assert!(heard(curve, Some(CrossfadeGroup::A), 2.0).abs() < 1e-6);
+    let (engine, _output) = engine();
+    assert!(engine.set_crossfader(f32::NAN).is_err());
+}
+
+#[test]
+fn a_sharp_cut_only_moves_at_the_ends() {
+    let curve = CrossfadeCurve::SharpCut;
+    for position in [0.05, 0.5, 0.95] {
+        assert_eq!(heard(curve, Some(CrossfadeGroup::A), position), 1.0);
+        assert_eq!(heard(curve, Some(CrossfadeGroup::B), position), 1.0);
+    }
+    assert!((heard(curve, Some(CrossfadeGroup::B), 0.02) - 0.4).abs() < 1e-5);
+    assert_eq!(heard(curve, Some(CrossfadeGroup::B), 0.0), 0.0);
+    assert!((heard(curve, Some(CrossfadeGroup::A), 0.98) - 0.4).abs() < 1e-5);
+    assert_eq!(heard(curve, Some(CrossfadeGroup::A), 1.0), 0.0);
+}
+
+#[test]
+fn a_fader_move_glides() {
+    let (engine, output) = engine();
+    steady(&engine);
+    engine.set_pad_group("a", Some(CrossfadeGroup::A)).unwrap();
+    engine.set_crossfader(0.0).unwrap();
+    render(&engine, &output, 2 * RAMP);
+    play(&engine, "a", |_| {});
+    render(&engine, &output, 1000);
+    engine.set_crossfader(1.0).unwrap();
+    let left = channel(&render(&engine, &output, 2 * RAMP), 0);
+    // Down from full in equal steps, silent from one ramp on
+    let step = LEVEL / RAMP as f32;
+    let mut previous = LEVEL;
+    for (frame, &sample) in left.iter().enumerate() {
+        assert!(sample <= previous, "turned back at frame {}", frame);
+        assert!(
+            previous - sample <= step + 1e-6,
+            "jumped at frame {}",
+            frame
+        );
+        previous = sample;
+    }
+    assert!((left[RAMP / 2] - LEVEL / 2.0).abs() < 2.0 * step);
+    assert!(left[RAMP..].iter().all(|&sample| sample.abs() < 1e-6));
+}
+
+#[test]
+fn a_pad_changes_side_while_it_plays() {
+    let (engine, output) = engine();
+    steady(&engine);
+    engine.set_crossfader(1.0).unwrap();
+    render(&engine, &output, 2 * RAMP);
+    play(&engine, "a", |_| {});
+    let left = channel(&render(&engine, &output, 1000), 0);
+    assert_eq!(left[999], LEVEL);
+    engine.set_pad_group("a", Some(CrossfadeGroup::A)).unwrap();
+    let left = channel(&render(&engine, &output, 1000), 0);
+    assert!(left[999].abs() < 1e-6);
+    engine.set_pad_group("a", None).unwrap();
+    let left = channel(&render(&engine, &output, 1000), 0);
+    assert_eq!(left[999], LEVEL);
+}
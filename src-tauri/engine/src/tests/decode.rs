@@ -0,0 +1,131 @@
+//! Decoding each container the harbor lists. The fixtures are short 440 Hz
+//! sines at half scale: AIFF at 22.05 kHz, 16-bit mono and 24-bit stereo
+//! with the right channel inverted, and AAC-LC at 44.1 kHz, mono, as ADTS
+//! and inside an M4A (the encoder adds some silence around the tone). The
+//! broken files are a few bytes of hand-made WAV each, and the cut-short
+//! ones the first half of a half-second tone at 22.05 kHz, mono, as 16-bit
+//! WAV, FLAC and 64 kbps MP3 (with a LAME tag, so it knows its length).
+
+use super::*;
+use std::f32::consts::TAU;
+
+/// Zero crossings per second of the left channel over its loud part
+fn pitch(buffer: &AudioBuffer) -> f32 {
+    let left: Vec<f32> = buffer
+        .data
+        .iter()
+        .step_by(buffer.channels as usize)
+        .copied()
+        .collect();
+    let loud: Vec<usize> = (0..left.len()).filter(|&i| left[i].abs() > 0.25).collect();
+    let (first, last) = (loud[0], loud[loud.len() - 1]);
+    let crossings = left[first..last]
+        .windows(2)
+        .filter(|pair| (pair[0] < 0.0) != (pair[1] < 0.0))
+        .count();
+    crossings as f32 / 2.0 / ((last - first) as f32 / buffer.sample_rate as f32)
+}
+
+fn peak(buffer: &AudioBuffer) -> f32 {
+    buffer.data.iter().fold(0.0f32, |peak, s| peak.max(s.abs()))
+}
+
+#[test]
+fn aiff_decodes_sample_for_sample() {
+    let buffer = decode(&fixture("tone.aiff"), usize::MAX).unwrap();
+    assert_eq!((buffer.sample_rate, buffer.channels), (22_050, 1));
+    assert_eq!(buffer.data.len(), 2205);
+    for (n, &sample) in buffer.data.iter().enumerate() {
+        let expected = (n as f32 * 440.0 / 22_050.0 * TAU).sin() * 0.5;
+        assert!((sample - expected).abs() < 1e-4, "frame {}", n);
+    }
+}
+
+#[test]
+fn aif_decodes_both_channels() {
+    let buffer = decode(&fixture("tone.aif"), usize::MAX).unwrap();
+    assert_eq!((buffer.sample_rate, buffer.channels), (22_050, 2));
+    assert!((buffer.duration - 0.1).abs() < 1e-6);
+    for frame in buffer.data.chunks_exact(2) {
+        assert_eq!(frame[0], -frame[1]);
+    }
+    assert!((pitch(&buffer) - 440.0).abs() < 5.0);
+}
+
+#[test]
+fn aac_and_m4a_decode_the_tone() {
+    for name in ["tone.aac", "tone.m4a"] {
+        let buffer = decode(&fixture(name), usize::MAX).unwrap();
+        assert_eq!(
+            (buffer.sample_rate, buffer.channels),
+            (44_100, 1),
+            "{}",
+            name
+        );
+        assert!(buffer.duration >= 0.25 && buffer.duration < 0.4, "{}", name);
+        assert!(
+            (peak(&buffer) - 0.5).abs() < 0.05,
+            "{}: {}",
+            name,
+            peak(&buffer)
+        );
+        assert!(
+            (pitch(&buffer) - 440.0).abs() < 5.0,
+            "{}: {}",
+            name,
+            pitch(&buffer)
+        );
+        assert!(buffer.decode_warnings.is_empty(), "{}", name);
+    }
+}
+
+#[test]
+fn each_failure_has_its_kind() {
+    for (name, kind) in [
+        ("missing.wav", LoadErrorKind::FileMissing),
+        ("zero-bytes.wav", LoadErrorKind::EmptyAudio),
+        // A header and an empty data chunk
+        ("empty-data.wav", LoadErrorKind::EmptyAudio),
+        ("zero-channels.wav", LoadErrorKind::ZeroChannels),
+        // Format tag 0x1234
+        ("unknown-codec.wav", LoadErrorKind::UnsupportedCodec),
+        // Cut off inside the fmt chunk
+        ("cut-in-header.wav", LoadErrorKind::TruncatedStream),
+    ] {
+        let error = match decode(&fixture(name), usize::MAX) {
+            Ok(_) => panic!("{} decoded", name),
+            Err(error) => error,
+        };
+        assert_eq!(error.kind, kind, "{}: {}", name, error.message);
+        assert!(error.message.contains(name) || kind == LoadErrorKind::UnsupportedCodec);
+    }
+}
+
+#[test]
+fn a_file_cut_short_keeps_what_is_left() {
+    for name in ["cut-short.wav", "cut-short.flac", "cut-short.mp3"] {
+        let buffer = decode(&fixture(name), usize::MAX).unwrap();
+        assert_eq!(
+            (buffer.sample_rate, buffer.channels),
+            (22_050, 1),
+            "{}",
+            name
+        );
+        // About half of the half second, and the tone it was
+        assert!(buffer.duration > 0.2 && buffer.duration < 0.27, "{}", name);
+        assert!((peak(&buffer) - 0.5).abs() < 0.05, "{}", name);
+        assert!((pitch(&buffer) - 440.0).abs() < 5.0, "{}", name);
+        assert_eq!(buffer.decode_warnings.len(), 1, "{}", name);
+        assert!(
+            buffer.decode_warnings[0].starts_with("File ends after"),
+            "{}: {}",
+            name,
+            buffer.decode_warnings[0]
+        );
+    }
+    // Whole files carry no such warning
+    for name in ["tone.aiff", "tone.aif", "tone.aac", "tone.m4a"] {
+        let buffer = decode(&fixture(name), usize::MAX).unwrap();
+        assert!(buffer.decode_warnings.is_empty(), "{}", name);
+    }
+}
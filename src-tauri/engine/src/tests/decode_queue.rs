@@ -0,0 +1,84 @@
+//! The decode queue under a whole kit loading at once: never more decodes
+//! than permits, and slots handed out in the order the loads asked
+
+use super::*;
+use std::collections::HashSet;
+
+/// Loads `count` pads at once with `permits` decode slots, returning the
+/// progress reports in the order they came
+fn load_kit(count: usize, permits: usize) -> Vec<LoadProgress> {
+    let engine = Arc::new(engine().0);
+    engine.set_decode_concurrency(permits).unwrap();
+    let path = scratch(&format!("kit-{}-{}.wav", count, permits));
+    write_mono_wav(&path, &vec![0.25; RATE as usize / 4]);
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let loads: Vec<_> = (0..count)
+            .map(|pad| {
+                let (engine, path, reports) =
+                    (Arc::clone(&engine), path.clone(), Arc::clone(&reports));
+                tokio::spawn(async move {
+                    let on_progress = move |progress: LoadProgress| {
+                        let first = progress.stage == LoadStage::Decoding;
+                        lock(&reports).push(progress);
+                        // Holds the slot long enough for every other decode to pile up
+                        if first {
+                            std::thread::sleep(Duration::from_millis(20));
+                        }
+                    };
+                    let key = format!("p{:02}", pad);
+                    let path = path.to_str().unwrap();
+                    engine
+                        .load_sound(key, path, Some(120.0), LoadOptions::default(), on_progress)
+                        .await
+                })
+            })
+            .collect();
+        for load in loads {
+            load.await.unwrap().unwrap();
+        }
+    });
+    std::fs::remove_file(&path).unwrap();
+    let reports = lock(&reports).clone();
+    reports
+}
+
+#[test]
+fn decodes_never_exceed_the_permits() {
+    for (count, permits) in [(12, 1), (12, 2), (12, 4)] {
+        let reports = load_kit(count, permits);
+        let (mut decoding, mut most) = (HashSet::new(), 0);
+        let mut order = Vec::new();
+        for report in &reports {
+            match (report.stage, report.percent) {
+                (LoadStage::Queued, _) => continue,
+                (LoadStage::Analyzing, percent) if percent >= 100.0 => {
+                    decoding.remove(&report.key);
+                }
+                _ => {
+                    if decoding.insert(report.key.clone()) {
+                        order.push(report.key.clone());
+                    }
+                }
+            }
+            most = most.max(decoding.len());
+        }
+        assert_eq!(most, permits, "{} loads with {} permits", count, permits);
+        // First come, first served; loads let in together start in either order
+        assert_eq!(order.len(), count);
+        for (started, key) in order.iter().enumerate() {
+            let asked: usize = key[1..].parse().unwrap();
+            assert!(asked.abs_diff(started) < permits, "{:?}", order);
+        }
+        // Everyone past the first few waited and heard where they stood
+        let queued = reports
+            .iter()
+            .filter(|report| report.stage == LoadStage::Queued)
+            .map(|report| &report.key)
+            .collect::<HashSet<_>>();
+        assert_eq!(queued.len(), count - permits);
+    }
+}
@@ -0,0 +1,55 @@
+//! A decay left to run never sinks into subnormal floats. The offline
+//! backend has no flush-to-zero, so it is up to the DSP state itself.
+
+use super::*;
+
+/// Every voice's filter and drive state, the compressor's and the delay line
+fn subnormals(output: &OfflineOutput) -> usize {
+    let renderer = lock(&output.renderer);
+    let mixer = &renderer.as_ref().unwrap().mixer;
+    let mut state = vec![mixer.compressor.state()];
+    for voice in mixer.voices.slots.iter().flatten() {
+        state.extend(voice.filter.state());
+        state.extend(voice.drive.state());
+    }
+    state.extend(mixer.delay.state());
+    state.iter().filter(|value| value.is_subnormal()).count()
+}
+
+#[test]
+fn a_decay_never_goes_subnormal() {
+    let (engine, output) = engine();
+    // A burst, then silence long enough for the slowest mean to die out
+    let frames = RATE as usize * 16;
+    let mut data = vec![0.0; frames];
+    for (n, sample) in data.iter_mut().take(480).enumerate() {
+        *sample = 0.8 * (n as f32 * 0.3).sin();
+    }
+    load(&engine, "a", buffer(data, 1, RATE));
+    engine
+        .set_compressor(CompressorSettings {
+            threshold_db: -40.0,
+            ratio: 4.0,
+            attack: 0.001,
+            release: 0.001,
+            makeup_db: 0.0,
+        })
+        .unwrap();
+    engine
+        .set_delay(DelaySettings {
+            division: StepDivision::ThirtySecond,
+            feedback: 0.2,
+        })
+        .unwrap();
+    play(&engine, "a", |params| {
+        params.filter_cutoff = 200.0;
+        params.drive = 0.5;
+        params.delay_send = 1.0;
+    });
+    let mut rendered = 0;
+    while rendered < frames - RATE as usize {
+        render(&engine, &output, RATE as usize / 10);
+        rendered += RATE as usize / 10;
+        assert_eq!(subnormals(&output), 0, "after {} frames", rendered);
+    }
+}
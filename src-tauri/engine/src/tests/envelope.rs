@@ -0,0 +1,113 @@
+//! Envelopes that do not fit their region, and stops caught mid-attack.
+//! The pads hold a constant 0.5, so the output is the envelope at half scale.
+
+use super::*;
+
+/// Half a second of a constant 0.5
+fn half_second() -> AudioBuffer {
+    buffer(vec![0.5; RATE as usize / 2], 1, RATE)
+}
+
+/// Asserts no two frames of `left` are further apart than `step`
+fn assert_smooth(left: &[f32], step: f32) {
+    for frame in 1..left.len() {
+        let jump = (left[frame] - left[frame - 1]).abs();
+        assert!(jump <= step + 1e-6, "{} at frame {}", jump, frame);
+    }
+}
+
+#[test]
+fn an_attack_longer_than_the_region_rises_over_all_of_it() {
+    let (engine, output) = engine();
+    load(&engine, "a", half_second());
+    play(&engine, "a", |params| {
+        params.attack = 2.0;
+        params.release = 0.0;
+    });
+    let left = channel(&render(&engine, &output, RATE as usize), 0);
+    for (frame, &sample) in left[..24_000].iter().enumerate() {
+        let expected = 0.5 * frame as f32 / 24_000.0;
+        assert!((sample - expected).abs() < 1e-5, "frame {}", frame);
+    }
+    assert!(left[24_000..].iter().all(|&sample| sample == 0.0));
+}
+
+#[test]
+fn attack_and_release_both_too_long_share_the_region() {
+    let (engine, output) = engine();
+    load(&engine, "a", half_second());
+    play(&engine, "a", |params| {
+        params.attack = 2.0;
+        params.release = 2.0;
+    });
+    let left = channel(&render(&engine, &output, RATE as usize), 0);
+    // Up over the first half and down over the second, meeting at the top
+    for (frame, &sample) in left[..24_000].iter().enumerate() {
+        let expected = 0.5 * (1.0 - (frame as f32 - 12_000.0).abs() / 12_000.0);
+        assert!((sample - expected).abs() < 1e-4, "frame {}", frame);
+    }
+    assert!(left[24_000..].iter().all(|&sample| sample == 0.0));
+}
+
+#[test]
+fn a_long_attack_fades_out_from_where_it_got_to() {
+    for release in [0.1, 0.4, 1.0] {
+        let (engine, output) = engine();
+        load(&engine, "a", half_second());
+        play(&engine, "a", |params| {
+            params.attack = 2.0;
+            params.release = release;
+        });
+        let left = channel(&render(&engine, &output, RATE as usize), 0);
+        let top = left.iter().fold(0.0f32, |top, &s| top.max(s));
+        let peak = left.iter().position(|&s| s == top).unwrap();
+        // Up to the peak and down from it, with no dip or jump on the way
+        assert!(left[..=peak].windows(2).all(|pair| pair[1] >= pair[0]));
+        assert!(left[peak..].windows(2).all(|pair| pair[1] <= pair[0]));
+        let (attack, release) = fit_envelope(2.0, release, 0.5);
+        let steepest = 0.5 / (attack.min(release) * RATE as f32);
+        assert_smooth(&left, steepest);
+        assert!(left[24_000..].iter().all(|&sample| sample == 0.0));
+    }
+}
+
+/// Output of a pad 2400 frames into a 4800-frame attack, stopped by `stop`
+fn stopped_mid_attack<T>(stop: impl FnOnce(&AudioEngine) -> T) -> Vec<f32> {
+    let (engine, output) = engine();
+    load(&engine, "a", buffer(vec![0.5; RATE as usize], 1, RATE));
+    play(&engine, "a", |params| params.attack = 0.1);
+    let mut left = channel(&render(&engine, &output, 2400), 0);
+    stop(&engine);
+    left.extend(channel(&render(&engine, &output, 4800), 0));
+    left
+}
+
+#[test]
+fn stop_all_releases_mid_attack_like_a_single_stop() {
+    let single = stopped_mid_attack(|engine| engine.stop_sound("a".to_string(), None).unwrap());
+    let all = stopped_mid_attack(|engine| engine.stop_all());
+    assert_eq!(all, single);
+    // Down from the quarter it reached, over the 2400 frames it took
+    assert!((all[2399] - 0.25).abs() < 1e-3, "{}", all[2399]);
+    assert_smooth(&all, 0.5 / 4800.0);
+    assert!(all[4800..].iter().all(|&sample| sample == 0.0));
+}
+
+#[test]
+fn stop_all_with_its_own_release_starts_from_the_attack_gain() {
+    // 750 frames at 48 kHz
+    let release = 0.015625;
+    let single =
+        stopped_mid_attack(|engine| engine.stop_sound("a".to_string(), Some(release)).unwrap());
+    let all = stopped_mid_attack(|engine| {
+        engine.set_stop_all_release(Some(release)).unwrap();
+        engine.stop_all();
+    });
+    assert_eq!(all, single);
+    // A straight line down from the quarter the attack reached
+    for k in 0..750 {
+        let expected = 0.25 * (1.0 - k as f32 / 750.0);
+        assert!((all[2400 + k] - expected).abs() < 1e-5, "{} frames in", k);
+    }
+    assert!(all[3150..].iter().all(|&sample| sample == 0.0));
+}
@@ -0,0 +1,104 @@
+//! The looper bus, at 120 BPM and 48 kHz: a bar is 96000 frames, so a
+//! one-bar loop asked for before frame 96000 records 96000-192000
+
+use super::*;
+
+const BAR: usize = 96_000;
+
+/// 100 frames of DC, so every trigger shows as an onset
+fn click(engine: &AudioEngine) {
+    load(engine, "a", buffer(vec![0.5; 100], 1, RATE));
+}
+
+/// Renders on to `frame` (from `from`), returning the output
+fn render_to(
+    engine: &AudioEngine,
+    output: &OfflineOutput,
+    from: &mut usize,
+    frame: usize,
+) -> Vec<f32> {
+    let data = render(engine, output, frame - *from);
+    *from = frame;
+    data
+}
+
+/// A one-bar loop of a click 4000 frames into it, with the output so far
+fn one_bar_loop(engine: &AudioEngine, output: &OfflineOutput, frame: &mut usize) -> Vec<f32> {
+    click(engine);
+    let mut data = render_to(engine, output, frame, 1000);
+    engine.looper_record(1, None, None).unwrap();
+    data.extend(render_to(engine, output, frame, BAR + 4000));
+    play(engine, "a", |_| {});
+    data
+}
+
+#[test]
+fn a_recorded_bar_loops_straight_away() {
+    let (engine, output) = engine();
+    let mut frame = 0;
+    let mut data = one_bar_loop(&engine, &output, &mut frame);
+    data.extend(render_to(&engine, &output, &mut frame, 4 * BAR));
+    assert_eq!(onsets(&data), [BAR + 4000, 2 * BAR + 4000, 3 * BAR + 4000]);
+    // Each pass is the click as it was played
+    let left = channel(&data, 0);
+    for pass in 1..4 {
+        let click = &left[pass * BAR + 4000..pass * BAR + 4100];
+        assert!(click.iter().all(|&sample| sample == 0.5), "pass {}", pass);
+    }
+    let levels = engine.get_levels(0);
+    assert!(levels.all_active_keys.iter().any(|key| key == "__looper_0"));
+}
+
+#[test]
+fn an_overdub_adds_a_layer_and_undo_drops_it() {
+    let (engine, output) = engine();
+    let mut frame = 0;
+    let mut data = one_bar_loop(&engine, &output, &mut frame);
+    data.extend(render_to(&engine, &output, &mut frame, 2 * BAR + 10_000));
+    // Records the pass from 3 * BAR
+    engine.looper_overdub(None).unwrap();
+    data.extend(render_to(&engine, &output, &mut frame, 3 * BAR + 20_000));
+    play(&engine, "a", |_| {});
+    data.extend(render_to(&engine, &output, &mut frame, 4 * BAR + 50_000));
+    engine.looper_undo().unwrap();
+    data.extend(render_to(&engine, &output, &mut frame, 6 * BAR));
+    assert_eq!(
+        onsets(&data),
+        [
+            BAR + 4000,
+            2 * BAR + 4000,
+            3 * BAR + 4000,
+            3 * BAR + 20_000,
+            4 * BAR + 4000,
+            4 * BAR + 20_000,
+            5 * BAR + 4000,
+        ]
+    );
+    engine.looper_clear().unwrap();
+    assert!(onsets(&render(&engine, &output, BAR)).is_empty());
+}
+
+#[test]
+fn the_loop_point_does_not_click() {
+    let (engine, output) = engine();
+    // 220.25 periods to the bar, so a pass ends a quarter period out of
+    // where it started; eight seconds of it play through without a wrap
+    let data: Vec<f32> = (0..4 * BAR)
+        .map(|n| (0.25 * (n as f64 * 110.125 / RATE as f64 * std::f64::consts::TAU).sin()) as f32)
+        .collect();
+    load(&engine, "a", buffer(data, 1, RATE));
+    play(&engine, "a", |_| {});
+    let mut frame = 0;
+    render_to(&engine, &output, &mut frame, 1000);
+    engine.looper_record(1, None, None).unwrap();
+    // The pad plays on through the seam after the recording, then stops
+    render_to(&engine, &output, &mut frame, 2 * BAR + 1000);
+    engine.stop_sound("a".to_string(), Some(0.0)).unwrap();
+    render_to(&engine, &output, &mut frame, 3 * BAR - 1000);
+    let left = channel(&render_to(&engine, &output, &mut frame, 3 * BAR + 1000), 0);
+    // No step bigger than the sine's own around the wrap at 3 * BAR
+    let steepest = 0.25 * 110.125 / RATE as f32 * std::f32::consts::TAU;
+    for pair in left.windows(2) {
+        assert!((pair[1] - pair[0]).abs() <= steepest * 1.5);
+    }
+}
@@ -0,0 +1,44 @@
+//! Master volume changes glide over `LEVEL_SLEW_MS` instead of stepping
+
+use super::*;
+
+/// Frames a full level change takes
+const RAMP: usize = (LEVEL_SLEW_MS as usize) * RATE as usize / 1000;
+
+/// Left channel after `volume` is set on a steady 0.4 pad at full volume
+fn step_to(engine: &AudioEngine, output: &OfflineOutput, volume: f32) -> Vec<f32> {
+    engine.set_master_volume(volume);
+    channel(&render(engine, output, 2 * RAMP), 0)
+}
+
+/// Asserts `left` glides from `from` to `to` in equal steps over one ramp,
+/// then holds
+fn assert_ramp(left: &[f32], from: f32, to: f32) {
+    let step = (to - from).abs() / RAMP as f32;
+    let mut previous = from;
+    for (frame, &sample) in left.iter().enumerate() {
+        let moved = sample - previous;
+        assert!(moved.abs() <= step + 1e-6, "{} at frame {}", moved, frame);
+        assert!(
+            moved * (to - from) >= -1e-7,
+            "turned back at frame {}",
+            frame
+        );
+        previous = sample;
+    }
+    assert!((left[RAMP / 2] - (from + to) / 2.0).abs() < 2.0 * step);
+    assert!(left[RAMP..]
+        .iter()
+        .all(|&sample| (sample - to).abs() < 1e-6));
+}
+
+#[test]
+fn a_master_step_ramps_without_a_click() {
+    let (engine, output) = engine();
+    load(&engine, "a", buffer(vec![0.4; RATE as usize], 1, RATE));
+    play(&engine, "a", |params| params.looping = true);
+    render(&engine, &output, 1000);
+    assert_ramp(&step_to(&engine, &output, 1.5), 0.4, 0.6);
+    assert_ramp(&step_to(&engine, &output, 0.25), 0.6, 0.1);
+    assert_ramp(&step_to(&engine, &output, 0.0), 0.1, 0.0);
+}
@@ -0,0 +1,64 @@
+//! Block mixing against frame-by-frame mixing. A one-frame callback mixes
+//! every voice one frame at a time, as the scalar loop did, so rendering the
+//! same voices both ways compares the block path to the scalar one
+
+use super::*;
+
+/// A quiet sine, so 32 of them together stay under the limiter's ceiling
+fn tone(frames: usize, channels: u16, hz: f32, sample_rate: u32) -> AudioBuffer {
+    let data = (0..frames)
+        .flat_map(|n| {
+            let phase = n as f32 * hz / sample_rate as f32 * std::f32::consts::TAU;
+            let frame = [0.02 * phase.sin(), 0.02 * phase.cos()];
+            frame.into_iter().take(channels as usize)
+        })
+        .collect();
+    buffer(data, channels, sample_rate)
+}
+
+/// 32 voices, mono and stereo, at the device rate and resampled, one-shot
+/// and looped, some with an envelope or a filter
+fn mix(callback_frames: usize) -> Vec<f32> {
+    let (engine, output) = engine();
+    for voice in 0..32 {
+        let key = format!("v{}", voice);
+        let channels = 1 + (voice % 2) as u16;
+        let sample_rate = if voice % 3 == 0 { 44_100 } else { RATE };
+        let hz = 110.0 * (1.0 + voice as f32 * 0.37);
+        load(&engine, &key, tone(30_000, channels, hz, sample_rate));
+        play(&engine, &key, |params| {
+            params.looping = voice % 4 < 2;
+            params.attack = if voice % 5 == 0 { 0.05 } else { 0.0 };
+            params.release = 0.02 * (voice % 3) as f32;
+            if voice % 7 == 0 {
+                params.filter_cutoff = 800.0;
+            }
+        });
+    }
+    let frames = 48_000;
+    let mut data = Vec::with_capacity(frames * 2);
+    while data.len() < frames * 2 {
+        data.extend(output.render(callback_frames.min(frames - data.len() / 2)));
+    }
+    data
+}
+
+#[test]
+fn block_mix_matches_the_scalar_mix() {
+    let scalar = mix(1);
+    for callback_frames in [MIX_BLOCK, 441, 1024] {
+        let block = mix(callback_frames);
+        assert_eq!(block.len(), scalar.len());
+        for (sample, (&block, &scalar)) in block.iter().zip(&scalar).enumerate() {
+            assert!(
+                (block - scalar).abs() <= 1e-6,
+                "sample {} of {}-frame callbacks: {} vs {}",
+                sample,
+                callback_frames,
+                block,
+                scalar
+            );
+        }
+    }
+    assert!(scalar.iter().any(|&sample| sample.abs() > 0.1));
+}
@@ -0,0 +1,135 @@
+//! Offline rendering: a bounce is sample for sample what live playback at
+//! the same rate sounds like, and making one leaves the live engine alone
+
+use super::*;
+use std::f32::consts::TAU;
+
+/// Two bars at 120 BPM and 48 kHz
+const FRAMES: usize = 2 * 96_000;
+
+/// A session with most of the DSP in it: steps with their own params and a
+/// synced step, a take with a filter envelope, the delay and the compressor
+fn session(engine: &AudioEngine) {
+    let tone = |hz: f32| {
+        (0..RATE as usize / 4)
+            .map(|n| 0.6 * (TAU * hz * n as f32 / RATE as f32).sin())
+            .collect()
+    };
+    load(engine, "a", buffer(tone(220.0), 1, RATE));
+    load(engine, "b", buffer(tone(330.0), 1, RATE));
+    engine
+        .set_compressor(CompressorSettings {
+            threshold_db: -12.0,
+            ratio: 4.0,
+            attack: 0.005,
+            release: 0.05,
+            makeup_db: 0.0,
+        })
+        .unwrap();
+    engine
+        .set_delay(DelaySettings {
+            division: StepDivision::Eighth,
+            feedback: 0.4,
+        })
+        .unwrap();
+    let step = |step, params| StepEvent {
+        step,
+        velocity: 0.8,
+        params: Some(params),
+    };
+    let pattern = vec![
+        step(
+            0,
+            PartialPlayParams {
+                filter_cutoff: Some(800.0),
+                delay_send: Some(0.5),
+                ..PartialPlayParams::default()
+            },
+        ),
+        step(
+            6,
+            PartialPlayParams {
+                sync: Some(true),
+                sample_bpm: Some(100.0),
+                force_sync: Some(true),
+                attack: Some(0.01),
+                release: Some(0.05),
+                ..PartialPlayParams::default()
+            },
+        ),
+        step(
+            11,
+            PartialPlayParams {
+                drive: Some(0.7),
+                crush_bits: Some(6),
+                ..PartialPlayParams::default()
+            },
+        ),
+    ];
+    engine.seq_set_pattern("a".to_string(), pattern).unwrap();
+    let mut params = engine
+        .resolve_play_params("b", PartialPlayParams::default())
+        .unwrap();
+    params.filter_cutoff = 400.0;
+    params.filter_env_amount = 3.0;
+    params.filter_env_attack = 0.01;
+    params.filter_env_decay = 0.1;
+    let play = |beat| PerformanceEvent {
+        beat,
+        key: "b".to_string(),
+        action: PerformanceAction::Play {
+            params: params.clone(),
+        },
+    };
+    let take = Performance {
+        length: BEATS_PER_BAR,
+        events: vec![play(1.5), play(3.25)],
+    };
+    engine.set_performance(Some(take)).unwrap();
+}
+
+#[test]
+fn a_render_is_what_playback_sounds_like() {
+    let (engine, output) = engine();
+    session(&engine);
+    let mut progress = Vec::new();
+    let bounce = engine
+        .render_offline(RenderSource::Session, 2, |update| progress.push(update.bar))
+        .unwrap();
+    assert_eq!(progress, [1, 2]);
+    assert_eq!(bounce.region.sample_rate, RATE);
+    assert_eq!(bounce.region.channels, 2);
+
+    engine.seq_start().unwrap();
+    engine.pattern_play(None, true).unwrap();
+    let live = render(&engine, &output, FRAMES);
+    assert!(live.iter().any(|&sample| sample != 0.0));
+    assert_eq!(bounce.region.data, live);
+    let peak = live
+        .iter()
+        .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+    assert_eq!(bounce.peak, peak);
+}
+
+#[test]
+fn a_render_leaves_the_live_engine_alone() {
+    let playing = || {
+        let (engine, output) = engine();
+        session(&engine);
+        engine.seq_start().unwrap();
+        (engine, output)
+    };
+    let (engine, output) = playing();
+    let mut live = render(&engine, &output, 30_000);
+    let bounce = engine
+        .render_offline(RenderSource::Pattern, 1, |_| {})
+        .unwrap();
+    assert_eq!(bounce.region.data.len(), 96_000 * 2);
+    live.extend(render(&engine, &output, FRAMES - 30_000));
+
+    let (untouched, output) = playing();
+    assert_eq!(live, render(&untouched, &output, FRAMES));
+    // The pattern still runs and the take is still there to play
+    assert!(lock(&engine.state).sequence.running);
+    assert!(engine.performance().is_some());
+}
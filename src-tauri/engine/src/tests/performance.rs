@@ -0,0 +1,103 @@
+//! Recording pad hits into a take and replaying it, at 120 BPM and 48 kHz:
+//! a beat is 24000 frames and a bar 96000
+
+use super::*;
+
+/// 100 frames of DC, so every trigger shows as an onset
+fn click(engine: &AudioEngine) {
+    load(engine, "a", buffer(vec![0.5; 100], 1, RATE));
+}
+
+/// Renders on to `frame` (from `from`), returning the output
+fn render_to(
+    engine: &AudioEngine,
+    output: &OfflineOutput,
+    from: &mut usize,
+    frame: usize,
+) -> Vec<f32> {
+    let data = render(engine, output, frame - *from);
+    *from = frame;
+    data
+}
+
+/// Records hits on the frames of `hits` (from the start of the first bar)
+/// and returns the take
+fn record(
+    engine: &AudioEngine,
+    output: &OfflineOutput,
+    quantize: Option<StepDivision>,
+    hits: &[usize],
+) -> Performance {
+    let mut frame = 0;
+    engine.pattern_record_start(quantize, false).unwrap();
+    for &hit in hits {
+        render_to(engine, output, &mut frame, hit);
+        play(engine, "a", |_| {});
+    }
+    render_to(engine, output, &mut frame, 50_000);
+    engine.stop_sound("a".to_string(), None).unwrap();
+    engine.pattern_record_stop().unwrap()
+}
+
+#[test]
+fn a_take_survives_json_and_replays_from_the_next_bar() {
+    let (engine, output) = engine();
+    click(&engine);
+    let take = record(&engine, &output, None, &[6500, 30_500]);
+    assert_eq!(take.length, BEATS_PER_BAR);
+    let actions: Vec<bool> = take
+        .events
+        .iter()
+        .map(|event| matches!(event.action, PerformanceAction::Play { .. }))
+        .collect();
+    assert_eq!(actions, [true, true, false]);
+
+    let json = serde_json::to_string(&take).unwrap();
+    assert!(json.contains(r#""type":"play""#) && json.contains(r#""type":"stop""#));
+    let take: Performance = serde_json::from_str(&json).unwrap();
+    engine.pattern_play(Some(take), false).unwrap();
+    // From the bar after the one recorded in, and only once
+    let data = render(&engine, &output, 3 * 96_000);
+    let onsets: Vec<usize> = onsets(&data).iter().map(|onset| onset + 50_000).collect();
+    assert_eq!(onsets, [102_500, 126_500]);
+}
+
+#[test]
+fn a_quantized_take_snaps_to_the_grid() {
+    let (engine, output) = engine();
+    click(&engine);
+    let take = record(
+        &engine,
+        &output,
+        Some(StepDivision::Sixteenth),
+        &[6500, 17_000],
+    );
+    let beats: Vec<f64> = take.events.iter().map(|event| event.beat).collect();
+    // 6000 and 18000 frames in, then the stop at 48000
+    assert_eq!(beats, [0.25, 0.75, 2.0]);
+    engine.pattern_play(None, true).unwrap();
+    let data = render(&engine, &output, 2 * 96_000 + 46_000);
+    let onsets: Vec<usize> = onsets(&data).iter().map(|onset| onset + 50_000).collect();
+    assert_eq!(onsets, [102_000, 114_000, 198_000, 210_000]);
+}
+
+#[test]
+fn an_overdub_joins_the_playing_take_from_its_next_loop() {
+    let (engine, output) = engine();
+    click(&engine);
+    let take = record(&engine, &output, None, &[6000]);
+    engine.pattern_play(Some(take), true).unwrap();
+    // The take's first loop starts at frame 96000
+    let mut frame = 50_000;
+    let mut data = render_to(&engine, &output, &mut frame, 100_000);
+    engine.pattern_record_start(None, true).unwrap();
+    data.extend(render_to(&engine, &output, &mut frame, 150_000));
+    play(&engine, "a", |_| {});
+    data.extend(render_to(&engine, &output, &mut frame, 170_000));
+    let overdubbed = engine.pattern_record_stop().unwrap();
+    assert_eq!(overdubbed.events.len(), 3);
+    data.extend(render_to(&engine, &output, &mut frame, 288_000));
+    let onsets: Vec<usize> = onsets(&data).iter().map(|onset| onset + 50_000).collect();
+    // Live at 150000, then on the same beat of the next loop
+    assert_eq!(onsets, [102_000, 150_000, 198_000, 246_000]);
+}
@@ -0,0 +1,118 @@
+//! The master recorder's count-in, at 120 BPM and 48 kHz: a bar is 96000
+//! frames, and a one-bar count-in started on beat 0 ends on frame 96000
+
+use super::*;
+
+const BAR: usize = 96_000;
+
+fn engine_with_pad() -> (AudioEngine, OfflineOutput) {
+    let (engine, output) = engine();
+    load(&engine, "a", buffer(vec![0.5; 100], 1, RATE));
+    (engine, output)
+}
+
+/// Left channel of a recorded file
+fn recorded(path: &Path) -> Vec<f32> {
+    let mut reader = hound::WavReader::open(path).unwrap();
+    assert_eq!(reader.spec().channels, 2);
+    let samples: Vec<f32> = reader.samples::<f32>().map(Result::unwrap).collect();
+    samples.chunks_exact(2).map(|frame| frame[0]).collect()
+}
+
+#[test]
+fn recording_starts_on_the_downbeat_after_the_count() {
+    let (engine, output) = engine_with_pad();
+    let ticks = engine.take_count_in_ticks().unwrap();
+    let path = scratch("count-in.wav");
+    engine
+        .record_start(path.clone(), WavFormat::Float32, Some(1))
+        .unwrap();
+    render(&engine, &output, 1000);
+    // Heard during the count, but not recorded
+    play(&engine, "a", |_| {});
+    let counted = render(&engine, &output, BAR - 1000);
+    assert!(counted[..200].iter().any(|&sample| sample != 0.0));
+    assert!(!path.exists());
+    render(&engine, &output, 1000);
+    play(&engine, "a", |_| {});
+    render(&engine, &output, 2000);
+    let recording = while_rendering(&output, || engine.record_stop())
+        .unwrap()
+        .unwrap();
+    let left = recorded(&path);
+    assert!(left.len() >= 3000);
+    assert_eq!(recording.duration, left.len() as f32 / RATE as f32);
+    assert_eq!(onsets(&interleave(&left)), [1000]);
+    assert_eq!(ticks.try_iter().count(), 4);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn cancelling_during_the_count_leaves_no_file() {
+    let (engine, output) = engine_with_pad();
+    let ticks = engine.take_count_in_ticks().unwrap();
+    let path = scratch("cancelled.wav");
+    engine
+        .record_start(path.clone(), WavFormat::Pcm16, Some(2))
+        .unwrap();
+    render(&engine, &output, 30_000);
+    while_rendering(&output, || engine.record_cancel()).unwrap();
+    // The rest of the count and where the downbeat would have been
+    render(&engine, &output, 3 * BAR);
+    assert!(!path.exists());
+    // Beats 0 and 1 clicked before the cancel; no more after it
+    assert_eq!(ticks.try_iter().count(), 2);
+    assert!(engine.record_stop().is_err());
+}
+
+#[test]
+fn stopping_during_the_count_records_nothing() {
+    let (engine, output) = engine_with_pad();
+    let path = scratch("stopped.wav");
+    engine
+        .record_start(path.clone(), WavFormat::Float32, Some(1))
+        .unwrap();
+    render(&engine, &output, 1000);
+    let recording = while_rendering(&output, || engine.record_stop()).unwrap();
+    assert!(recording.is_none());
+    render(&engine, &output, 2 * BAR);
+    assert!(!path.exists());
+}
+
+#[test]
+fn cancelling_after_the_downbeat_removes_the_file() {
+    let (engine, output) = engine_with_pad();
+    let path = scratch("removed.wav");
+    engine
+        .record_start(path.clone(), WavFormat::Float32, None)
+        .unwrap();
+    render(&engine, &output, 20_000);
+    // The writer has the file open by now
+    let started = Instant::now();
+    while !path.exists() && started.elapsed() < Duration::from_secs(2) {
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    assert!(path.exists());
+    while_rendering(&output, || engine.record_cancel()).unwrap();
+    assert!(!path.exists());
+}
+
+#[test]
+fn a_second_recording_waits_for_the_first() {
+    let (engine, output) = engine_with_pad();
+    let path = scratch("twice.wav");
+    engine
+        .record_start(path.clone(), WavFormat::Float32, Some(1))
+        .unwrap();
+    assert!(engine
+        .record_start(path.clone(), WavFormat::Float32, None)
+        .is_err());
+    assert!(engine
+        .record_start(scratch("long.wav"), WavFormat::Float32, Some(3))
+        .is_err());
+    while_rendering(&output, || engine.record_cancel()).unwrap();
+}
+
+fn interleave(left: &[f32]) -> Vec<f32> {
+    left.iter().flat_map(|&sample| [sample, sample]).collect()
+}
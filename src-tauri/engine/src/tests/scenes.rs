@@ -0,0 +1,237 @@
+//! Scenes: saving the mix, recalling it at once or over a fade, sends and
+//! the filter with it, skipping pads that are gone, and a round trip through
+//! the session's JSON
+
+use super::*;
+use crate::scene::SCENE_SLOTS;
+use std::thread;
+
+/// Two steady pads, "a" played at `volume` on side A and "b" set but unplayed
+fn mix(engine: &AudioEngine, volume: f32) {
+    load(engine, "a", buffer(vec![0.5; RATE as usize * 4], 1, RATE));
+    load(engine, "b", buffer(vec![0.5; RATE as usize], 1, RATE));
+    play(engine, "a", |params| {
+        params.volume = volume;
+        params.looping = true;
+    });
+    let mut params = engine
+        .resolve_play_params("b", PartialPlayParams::default())
+        .unwrap();
+    params.volume = 0.3;
+    engine.set_pad_params("b", params);
+    engine.set_pad_group("a", Some(CrossfadeGroup::A)).unwrap();
+}
+
+fn volume(engine: &AudioEngine, key: &str) -> f32 {
+    engine.pad_params(key).unwrap().volume
+}
+
+#[test]
+fn a_recall_brings_the_mix_back() {
+    let (engine, output) = engine();
+    mix(&engine, 1.0);
+    engine.set_master_volume(0.8);
+    engine.set_master_bpm(100.0).unwrap();
+    engine.set_crossfader(0.0).unwrap();
+    let saved = engine.scene_save(2).unwrap();
+    assert_eq!(saved.pads.len(), 2);
+    assert_eq!(
+        saved.pads["a"],
+        PadScene {
+            volume: 1.0,
+            group: Some(CrossfadeGroup::A),
+            delay_send: Some(0.0),
+            sends_muted: Some(false),
+            filter_cutoff: Some(FILTER_OPEN),
+        }
+    );
+    let before = channel(&render(&engine, &output, 4000), 0)[3999];
+
+    engine.set_master_volume(0.5);
+    engine.set_master_bpm(140.0).unwrap();
+    engine.set_crossfader(1.0).unwrap();
+    engine.set_pad_group("a", Some(CrossfadeGroup::B)).unwrap();
+    engine
+        .update_voice(
+            "a".to_string(),
+            PlayParams {
+                volume: 0.25,
+                ..engine.pad_params("a").unwrap()
+            },
+        )
+        .unwrap();
+    engine.scene_save(0).unwrap();
+    render(&engine, &output, 4000);
+
+    let recall = engine.scene_recall(2, 0.0).unwrap();
+    assert!(recall.skipped.is_empty() && !recall.fading);
+    assert!(!engine.scene_tick());
+    assert_eq!(engine.master_settings(), (0.8, 100.0));
+    assert_eq!(volume(&engine, "a"), 1.0);
+    assert_eq!(volume(&engine, "b"), 0.3);
+    assert_eq!(lock(&engine.state).crossfader, 0.0);
+    assert_eq!(
+        lock(&engine.state).pad_groups.get("a"),
+        Some(&CrossfadeGroup::A)
+    );
+    // The playing pad is heard as it was
+    let after = channel(&render(&engine, &output, 4000), 0)[3999];
+    assert!((after - before).abs() < 1e-6, "{} then {}", before, after);
+
+    let slots: Vec<usize> = engine.scene_list().iter().map(|scene| scene.slot).collect();
+    assert_eq!(slots, [0, 2]);
+}
+
+#[test]
+fn a_fade_morphs_every_level_to_the_scene() {
+    let (engine, _output) = engine();
+    mix(&engine, 0.2);
+    engine.set_master_volume(0.2);
+    engine.scene_save(0).unwrap();
+    // The scene is louder all round, with "a" on side B
+    engine.set_master_volume(1.0);
+    engine.set_pad_group("a", Some(CrossfadeGroup::B)).unwrap();
+    engine
+        .update_voice(
+            "a".to_string(),
+            PlayParams {
+                volume: 1.0,
+                ..engine.pad_params("a").unwrap()
+            },
+        )
+        .unwrap();
+    engine.scene_save(1).unwrap();
+    engine.scene_recall(0, 0.0).unwrap();
+
+    assert!(engine.scene_recall(1, 0.3).unwrap().fading);
+    let mut previous = (0.2, 0.2);
+    let mut ticks = 0;
+    while engine.scene_tick() {
+        let now = (engine.master_settings().0, volume(&engine, "a"));
+        assert!(now.0 >= previous.0 && now.1 >= previous.1, "{:?}", now);
+        assert!(now.0 <= 1.0 && now.1 <= 1.0, "{:?}", now);
+        previous = now;
+        ticks += 1;
+        thread::sleep(Duration::from_millis(16));
+    }
+    assert!(ticks > 1);
+    assert_eq!(engine.master_settings().0, 1.0);
+    assert_eq!(volume(&engine, "a"), 1.0);
+    assert_eq!(
+        lock(&engine.state).pad_groups.get("a"),
+        Some(&CrossfadeGroup::B)
+    );
+    assert!(!engine.scene_tick());
+}
+
+/// Sets the filter cutoff of "a" and turns the volume to `volume` with it
+fn filter(engine: &AudioEngine, volume: f32, cutoff: f32) {
+    let params = PlayParams {
+        volume,
+        filter_cutoff: cutoff,
+        ..engine.pad_params("a").unwrap()
+    };
+    engine.update_voice("a".to_string(), params).unwrap();
+}
+
+#[test]
+fn the_sends_and_the_filter_come_back_too() {
+    let (engine, _output) = engine();
+    mix(&engine, 0.0);
+    engine.set_send("a", SendBus::Delay, 0.4).unwrap();
+    filter(&engine, 0.0, 4000.0);
+    engine.scene_save(0).unwrap();
+    engine.set_send("a", SendBus::Delay, 0.1).unwrap();
+    engine.mute_sends("a", true).unwrap();
+    filter(&engine, 1.0, 250.0);
+
+    // Over a fade, the cutoff moves as far in octaves as the volume does
+    engine.scene_recall(0, 0.3).unwrap();
+    while engine.scene_tick() {
+        let params = engine.pad_params("a").unwrap();
+        let progress = 1.0 - params.volume;
+        let octaves = (params.filter_cutoff / 250.0).log2();
+        assert!((octaves - 4.0 * progress).abs() < 1e-3, "{:?}", params);
+        assert!((0.1..=0.4).contains(&params.delay_send));
+        assert_eq!(params.sends_muted, progress < 0.5);
+        thread::sleep(Duration::from_millis(16));
+    }
+    let params = engine.pad_params("a").unwrap();
+    assert_eq!(params.delay_send, 0.4);
+    assert!(!params.sends_muted);
+    assert_eq!(params.filter_cutoff, 4000.0);
+    // What a later pad plays with keeps the sends
+    let defaults = &engine.pad_defaults()["a"];
+    assert_eq!(defaults.delay_send, Some(0.4));
+    assert_eq!(defaults.sends_muted, Some(false));
+
+    // A scene saved before sends and the filter were kept leaves them be
+    let mut old = engine.scene_list().remove(0);
+    for pad in old.pads.values_mut() {
+        pad.delay_send = None;
+        pad.sends_muted = None;
+        pad.filter_cutoff = None;
+    }
+    let json = serde_json::to_string(&vec![old]).unwrap().replace(
+        r#","delaySend":null,"sendsMuted":null,"filterCutoff":null"#,
+        "",
+    );
+    assert!(!json.contains("delaySend"));
+    engine
+        .set_scenes(serde_json::from_str(&json).unwrap())
+        .unwrap();
+    engine.set_send("a", SendBus::Delay, 0.7).unwrap();
+    filter(&engine, 1.0, 800.0);
+    engine.scene_recall(0, 0.0).unwrap();
+    let params = engine.pad_params("a").unwrap();
+    assert_eq!((params.volume, params.delay_send), (0.0, 0.7));
+    assert_eq!(params.filter_cutoff, 800.0);
+}
+
+#[test]
+fn pads_no_longer_loaded_are_skipped() {
+    let (engine, _output) = engine();
+    mix(&engine, 0.5);
+    engine.scene_save(0).unwrap();
+    lock(&engine.state).sound_bank.remove("b");
+    engine.set_master_volume(0.1);
+    let recall = engine.scene_recall(0, 0.0).unwrap();
+    assert_eq!(recall.skipped, ["b"]);
+    assert_eq!(engine.master_settings().0, 1.0);
+    assert_eq!(volume(&engine, "a"), 0.5);
+    // And a scene saved now leaves it out
+    assert!(!engine.scene_save(1).unwrap().pads.contains_key("b"));
+}
+
+#[test]
+fn scenes_survive_json() {
+    let (saved, _output) = engine();
+    mix(&saved, 0.5);
+    saved.scene_save(3).unwrap();
+    let json = serde_json::to_string(&saved.scene_list()).unwrap();
+    assert!(json.contains(r#""masterVolume""#) && json.contains(r#""group":"a""#));
+
+    let (restored, _output) = engine();
+    mix(&restored, 1.0);
+    restored
+        .set_scenes(serde_json::from_str(&json).unwrap())
+        .unwrap();
+    restored.scene_recall(3, 0.0).unwrap();
+    assert_eq!(volume(&restored, "a"), 0.5);
+}
+
+#[test]
+fn a_bad_recall_is_refused() {
+    let (engine, _output) = engine();
+    assert!(engine.scene_save(SCENE_SLOTS).is_err());
+    assert!(engine.scene_recall(1, 0.0).is_err());
+    engine.scene_save(1).unwrap();
+    assert!(engine.scene_recall(1, -1.0).is_err());
+    assert!(engine.scene_recall(1, f32::INFINITY).is_err());
+    let stray = Scene {
+        slot: SCENE_SLOTS,
+        ..engine.scene_save(0).unwrap()
+    };
+    assert!(engine.set_scenes(vec![stray]).is_err());
+    assert_eq!(engine.scene_list().len(), 2);
+}
@@ -0,0 +1,106 @@
+//! The step sequencer on the master clock, at 120 BPM and 48 kHz: a
+//! sixteenth is 6000 frames. Swung timing is in `swing`.
+
+use super::*;
+use std::time::Duration;
+
+/// 100 frames of DC, so every trigger shows as an onset
+fn click(engine: &AudioEngine) {
+    load(engine, "a", buffer(vec![0.5; 100], 1, RATE));
+}
+
+fn steps(steps: &[usize]) -> Vec<StepEvent> {
+    steps
+        .iter()
+        .map(|&step| StepEvent {
+            step,
+            velocity: 1.0,
+            params: None,
+        })
+        .collect()
+}
+
+#[test]
+fn steps_fire_on_their_frames_loop_after_loop() {
+    let (engine, output) = engine();
+    click(&engine);
+    engine
+        .seq_set_pattern("a".to_string(), steps(&[0, 4, 10]))
+        .unwrap();
+    engine.seq_start().unwrap();
+    // Two loops of 16 sixteenths
+    let data = render(&engine, &output, 2 * 16 * 6000);
+    assert_eq!(onsets(&data), [0, 24_000, 60_000, 96_000, 120_000, 156_000]);
+}
+
+#[test]
+fn a_new_pattern_is_heard_from_the_next_loop() {
+    let (engine, output) = engine();
+    click(&engine);
+    engine
+        .seq_set_pattern("a".to_string(), steps(&[0]))
+        .unwrap();
+    engine.seq_set_length(4, StepDivision::Sixteenth).unwrap();
+    engine.seq_start().unwrap();
+    let mut data = render(&engine, &output, 7000);
+    engine
+        .seq_set_pattern("a".to_string(), steps(&[1, 3]))
+        .unwrap();
+    data.extend(render(&engine, &output, 65_000));
+    assert_eq!(onsets(&data), [0, 30_000, 42_000, 54_000, 66_000]);
+}
+
+#[test]
+fn stopping_ends_the_triggers() {
+    let (engine, output) = engine();
+    click(&engine);
+    engine
+        .seq_set_pattern("a".to_string(), steps(&[0, 1, 2, 3]))
+        .unwrap();
+    engine.seq_set_length(4, StepDivision::Sixteenth).unwrap();
+    engine.seq_start().unwrap();
+    let mut data = render(&engine, &output, 13_000);
+    engine.seq_stop().unwrap();
+    data.extend(render(&engine, &output, 48_000));
+    assert_eq!(onsets(&data), [0, 6000, 12_000]);
+}
+
+#[test]
+fn the_playhead_hears_every_step() {
+    let (engine, output) = engine();
+    click(&engine);
+    let playhead = engine.take_seq_steps().unwrap();
+    engine
+        .seq_set_pattern("a".to_string(), steps(&[0]))
+        .unwrap();
+    engine.seq_set_length(4, StepDivision::Sixteenth).unwrap();
+    engine.seq_start().unwrap();
+    // Steps 0-3, then 0 of the second loop
+    render(&engine, &output, 24_001);
+    for expected in [0, 1, 2, 3, 0] {
+        let step = playhead.recv_timeout(Duration::from_secs(1)).unwrap();
+        assert_eq!((step.step, step.length), (expected, 4));
+    }
+    assert!(playhead.try_recv().is_err());
+}
+
+#[test]
+fn a_tempo_the_clock_cannot_run_at_is_refused() {
+    let (engine, output) = engine();
+    click(&engine);
+    engine
+        .seq_set_pattern("a".to_string(), steps(&[0, 4, 8, 12]))
+        .unwrap();
+    engine.seq_start().unwrap();
+    render(&engine, &output, 1000);
+    for bpm in [-120.0, 0.0, 1e-6, 19.9, 400.1, f32::NAN, f32::INFINITY] {
+        assert!(engine.set_master_bpm(bpm).is_err(), "{}", bpm);
+    }
+    assert_eq!(engine.master_settings().1, 120.0);
+    // Still on the beat at 120
+    let data = render(&engine, &output, 95_000);
+    assert_eq!(onsets(&data), [23_000, 47_000, 71_000]);
+    for bpm in [20.0, 400.0] {
+        assert!(engine.set_master_bpm(bpm).is_ok(), "{}", bpm);
+    }
+}
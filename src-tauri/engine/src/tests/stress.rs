@@ -0,0 +1,85 @@
+//! The command path under load: threads hammer plays, updates, stops and
+//! the master volume, and another holds the engine lock in stretches longer
+//! than a buffer, as a slow bank insert would, while the output renders at
+//! the device's pace. A callback that ran past its buffer's time would be
+//! an xrun on a real device.
+
+use super::*;
+use std::sync::atomic::AtomicBool;
+use std::thread;
+
+/// Frames per callback, about 21 ms at 48 kHz
+const CALLBACK_FRAMES: usize = 1024;
+const CALLBACKS: u32 = 150;
+
+/// How long the lock is held at a time, longer than a callback's budget
+const LOCK_HELD: Duration = Duration::from_millis(50);
+
+#[test]
+fn hammered_commands_never_make_the_callback_late() {
+    let (engine, output) = engine();
+    let keys: Vec<String> = (0..8).map(|pad| format!("p{}", pad)).collect();
+    for key in &keys {
+        load(&engine, key, buffer(vec![0.05; RATE as usize], 1, RATE));
+    }
+    let budget = Duration::from_secs_f64(CALLBACK_FRAMES as f64 / RATE as f64);
+    let done = AtomicBool::new(false);
+    let (mut xruns, mut commands) = (0, 0u64);
+    thread::scope(|scope| {
+        let hammers: Vec<_> = keys
+            .chunks(4)
+            .map(|keys| {
+                let (engine, done) = (&engine, &done);
+                scope.spawn(move || {
+                    let mut sent = 0u64;
+                    let mut round = 0u32;
+                    while !done.load(Ordering::Relaxed) {
+                        for key in keys {
+                            round += 1;
+                            let mut params = engine
+                                .resolve_play_params(key, PartialPlayParams::default())
+                                .unwrap();
+                            params.looping = true;
+                            params.volume = (round % 100) as f32 / 100.0;
+                            // A full queue fails the call, as it would for the UI
+                            let sent_ok = match round % 40 {
+                                0 => engine.play_sound(key.clone(), params).is_ok(),
+                                20 => engine.stop_sound(key.clone(), None).is_ok(),
+                                _ => engine.update_voice(key.clone(), params).is_ok(),
+                            };
+                            sent += sent_ok as u64;
+                        }
+                        engine.set_master_volume((round % 10) as f32 / 10.0);
+                        // A burst, then a breath, as a UI drag would send them
+                        thread::sleep(Duration::from_micros(200));
+                    }
+                    sent
+                })
+            })
+            .collect();
+        scope.spawn(|| {
+            while !done.load(Ordering::Relaxed) {
+                let state = lock(&engine.state);
+                thread::sleep(LOCK_HELD);
+                drop(state);
+                thread::sleep(Duration::from_millis(5));
+            }
+        });
+        let start = Instant::now();
+        for callback in 1..=CALLBACKS {
+            let began = Instant::now();
+            output.render(CALLBACK_FRAMES);
+            if began.elapsed() > budget {
+                xruns += 1;
+            }
+            if let Some(wait) = (budget * callback).checked_sub(start.elapsed()) {
+                thread::sleep(wait);
+            }
+        }
+        done.store(true, Ordering::Relaxed);
+        commands = hammers.into_iter().map(|h| h.join().unwrap()).sum();
+    });
+    assert_eq!(xruns, 0, "of {} callbacks", CALLBACKS);
+    assert!(commands > 1000, "only {} commands got through", commands);
+    assert_eq!(engine.callback_errors(), CallbackErrors::default());
+}
@@ -0,0 +1,147 @@
+//! Swung step timing, as frames, at 120 BPM and 48 kHz: a sixteenth is
+//! 6000 frames and a swing of 0.5 puts every second one 3000 late
+
+use super::*;
+
+/// Frames per beat at the test tempo
+const BEAT: f64 = RATE as f64 / 2.0;
+
+fn frame(beat: f64) -> f64 {
+    beat * BEAT
+}
+
+/// 100 frames of DC, so every trigger shows as an onset
+fn click(engine: &AudioEngine) {
+    load(engine, "a", buffer(vec![0.5; 100], 1, RATE));
+}
+
+#[test]
+fn grid_steps_land_on_their_frames() {
+    let straight = Grid::new(StepDivision::Sixteenth, 0.0);
+    let swung = Grid::new(StepDivision::Sixteenth, 0.5);
+    let steps: Vec<f64> = (0..5).map(|step| frame(straight.step(step))).collect();
+    assert_eq!(steps, [0.0, 6000.0, 12000.0, 18000.0, 24000.0]);
+    let steps: Vec<f64> = (0..5).map(|step| frame(swung.step(step))).collect();
+    assert_eq!(steps, [0.0, 9000.0, 12000.0, 21000.0, 24000.0]);
+    let eighths = Grid::new(StepDivision::Eighth, MAX_SWING);
+    assert_eq!(frame(eighths.step(1)), 21000.0);
+    assert_eq!(frame(eighths.swung(0.5)), 21000.0);
+}
+
+#[test]
+fn nearest_and_next_follow_the_swung_steps() {
+    let grid = Grid::new(StepDivision::Sixteenth, 0.5);
+    let beat = |frame: f64| frame / BEAT;
+    // Halfway between the on step (0) and the late one (9000) is 4500
+    assert_eq!(frame(grid.nearest(beat(4499.0))), 0.0);
+    assert_eq!(frame(grid.nearest(beat(4500.0))), 9000.0);
+    // And between the late one and the next on step (12000), 10500
+    assert_eq!(frame(grid.nearest(beat(10499.0))), 9000.0);
+    assert_eq!(frame(grid.nearest(beat(10500.0))), 12000.0);
+    assert_eq!(frame(grid.next(beat(1.0))), 9000.0);
+    assert_eq!(frame(grid.next(beat(9000.0))), 9000.0);
+    assert_eq!(frame(grid.next(beat(9001.0))), 12000.0);
+}
+
+#[test]
+fn sequencer_steps_fire_on_swung_frames() {
+    let (engine, output) = engine();
+    click(&engine);
+    engine.set_swing(0.5).unwrap();
+    let steps = (0..4)
+        .map(|step| StepEvent {
+            step,
+            velocity: 1.0,
+            params: None,
+        })
+        .collect();
+    engine.seq_set_pattern("a".to_string(), steps).unwrap();
+    engine.seq_set_length(4, StepDivision::Sixteenth).unwrap();
+    engine.seq_start().unwrap();
+    let data = render(&engine, &output, 48_000);
+    assert_eq!(
+        onsets(&data),
+        [0, 9000, 12000, 21000, 24000, 33000, 36000, 45000]
+    );
+}
+
+#[test]
+fn swing_changes_from_the_next_step() {
+    let (engine, output) = engine();
+    click(&engine);
+    let steps = (0..4)
+        .map(|step| StepEvent {
+            step,
+            velocity: 1.0,
+            params: None,
+        })
+        .collect();
+    engine.seq_set_pattern("a".to_string(), steps).unwrap();
+    engine.seq_set_length(4, StepDivision::Sixteenth).unwrap();
+    engine.seq_start().unwrap();
+    let mut data = render(&engine, &output, 7000);
+    engine.set_swing(0.5).unwrap();
+    data.extend(render(&engine, &output, 17000));
+    assert_eq!(onsets(&data), [0, 6000, 12000, 21000]);
+}
+
+#[test]
+fn launches_wait_for_the_next_swung_step() {
+    let (engine, output) = engine();
+    click(&engine);
+    engine.set_swing(0.5).unwrap();
+    engine
+        .set_launch_quantize(Some(StepDivision::Sixteenth))
+        .unwrap();
+    let mut data = render(&engine, &output, 1000);
+    play(&engine, "a", |_| {});
+    data.extend(render(&engine, &output, 10_000));
+    play(&engine, "a", |_| {});
+    data.extend(render(&engine, &output, 10_000));
+    assert_eq!(onsets(&data), [9000, 12000]);
+}
+
+#[test]
+fn stopping_a_pad_calls_its_launch_off() {
+    let (engine, output) = engine();
+    click(&engine);
+    let events = engine.take_voice_events().unwrap();
+    engine
+        .set_launch_quantize(Some(StepDivision::Quarter))
+        .unwrap();
+    render(&engine, &output, 100);
+    play(&engine, "a", |_| {});
+    render(&engine, &output, 100);
+    engine.stop_sound("a".to_string(), None).unwrap();
+    let data = render(&engine, &output, 48_000);
+    assert!(onsets(&data).is_empty());
+    let ended = events.try_recv().unwrap();
+    assert_eq!(ended.reason, VoiceEndReason::Stopped);
+}
+
+#[test]
+fn launches_play_at_once_without_a_grid() {
+    let (engine, output) = engine();
+    click(&engine);
+    let mut data = render(&engine, &output, 1000);
+    play(&engine, "a", |_| {});
+    data.extend(render(&engine, &output, 1000));
+    assert_eq!(onsets(&data), [1000]);
+}
+
+#[test]
+fn beat_repeat_retriggers_on_swung_steps() {
+    let (engine, output) = engine();
+    click(&engine);
+    engine.set_swing(0.5).unwrap();
+    // Asked for one frame in, it waits for the next pair of eighths: beat 1
+    let mut data = render(&engine, &output, 1);
+    engine
+        .beat_repeat_start("a".to_string(), StepDivision::Eighth)
+        .unwrap();
+    data.extend(render(&engine, &output, 96_000 - 1));
+    // An eighth is 12000 frames, the late ones 6000 late
+    assert_eq!(onsets(&data), [24000, 42000, 48000, 66000, 72000, 90000]);
+    engine.beat_repeat_stop().unwrap();
+    assert!(onsets(&render(&engine, &output, 48_000)).is_empty());
+}
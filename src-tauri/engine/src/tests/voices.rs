@@ -0,0 +1,397 @@
+//! The basic voice behaviours, pulled frame by frame through the offline
+//! backend: the envelopes, looping, stereo and stopping
+
+use super::*;
+
+fn ramp(frames: usize) -> Vec<f32> {
+    (0..frames)
+        .map(|n| n as f32 / frames as f32 * 0.5)
+        .collect()
+}
+
+#[test]
+fn stop_during_the_attack_releases_over_the_same_time() {
+    let (engine, output) = engine();
+    load(&engine, "a", buffer(vec![0.5; RATE as usize], 1, RATE));
+    play(&engine, "a", |params| params.attack = 0.1);
+    let mut left = channel(&render(&engine, &output, 2400), 0);
+    engine.stop_sound("a".to_string(), None).unwrap();
+    left.extend(channel(&render(&engine, &output, 4800), 0));
+    // Up to a quarter at the stop, halfway into the 4800-frame attack
+    assert!((left[2399] - 0.25).abs() < 1e-3, "{}", left[2399]);
+    // and down again over the 2400 frames it took
+    for k in 1..2400 {
+        let (up, down) = (left[2400 - k], left[2399 + k]);
+        assert!(
+            (up - down).abs() < 1e-3,
+            "{} frames out: {} vs {}",
+            k,
+            up,
+            down
+        );
+    }
+    assert!(left[4800..].iter().all(|&sample| sample == 0.0));
+}
+
+#[test]
+fn loops_repeat_the_region_exactly() {
+    let (engine, output) = engine();
+    let data = ramp(4800);
+    load(&engine, "a", buffer(data.clone(), 1, RATE));
+    let (start, end) = (1200, 3600);
+    play(&engine, "a", |params| {
+        params.looping = true;
+        params.start_time = start as f32 / RATE as f32;
+        params.end_time = end as f32 / RATE as f32;
+    });
+    let left = channel(&render(&engine, &output, 10 * (end - start)), 0);
+    for (frame, &sample) in left.iter().enumerate() {
+        let expected = data[start + frame % (end - start)];
+        assert!((sample - expected).abs() < 1e-6, "frame {}", frame);
+    }
+}
+
+#[test]
+fn stereo_channels_stay_in_their_place() {
+    let (engine, output) = engine();
+    let frames = 2000;
+    let data: Vec<f32> = (0..frames)
+        .flat_map(|n| [n as f32 / frames as f32 * 0.5, -0.25])
+        .collect();
+    load(&engine, "a", buffer(data.clone(), 2, RATE));
+    play(&engine, "a", |_| {});
+    let out = render(&engine, &output, frames + 100);
+    for frame in 0..frames {
+        assert_eq!(out[frame * 2], data[frame * 2], "left of frame {}", frame);
+        assert_eq!(out[frame * 2 + 1], -0.25, "right of frame {}", frame);
+    }
+    assert!(out[frames * 2..].iter().all(|&sample| sample == 0.0));
+}
+
+#[test]
+fn stop_fades_out_over_the_release_and_ends_the_voice() {
+    let (engine, output) = engine();
+    let events = engine.take_voice_events().unwrap();
+    load(&engine, "a", buffer(vec![0.5; RATE as usize], 1, RATE));
+    play(&engine, "a", |params| params.looping = true);
+    render(&engine, &output, 1000);
+    // 750 frames at 48 kHz
+    engine.stop_sound("a".to_string(), Some(0.015625)).unwrap();
+    let left = channel(&render(&engine, &output, 1000), 0);
+    // A straight line down from full level
+    for (frame, &sample) in left[..750].iter().enumerate() {
+        let expected = 0.5 * (1.0 - frame as f32 / 750.0);
+        assert!((sample - expected).abs() < 1e-5, "frame {}", frame);
+    }
+    assert!(left[750..].iter().all(|&sample| sample == 0.0));
+    for _ in 0..METER_WINDOW_MS {
+        render(&engine, &output, RATE as usize / 1000);
+    }
+    let ended = events.try_recv().unwrap();
+    assert_eq!(ended.key, "a");
+    assert_eq!(ended.reason, VoiceEndReason::Stopped);
+}
+
+#[test]
+fn buffer_size_does_not_change_the_output() {
+    let render_in = |size: usize| {
+        let (engine, output) = engine();
+        load(&engine, "a", buffer(ramp(20_000), 1, RATE));
+        play(&engine, "a", |params| {
+            params.attack = 0.05;
+            params.release = 0.05;
+        });
+        let mut data = Vec::new();
+        while data.len() < 20_000 * 2 {
+            data.extend(output.render(size.min(20_000 - data.len() / 2)));
+        }
+        data
+    };
+    let reference = render_in(1000);
+    for size in [1, 64, 500] {
+        assert_eq!(render_in(size), reference, "buffers of {}", size);
+    }
+}
+
+#[test]
+fn a_full_pool_steals_its_oldest_voice() {
+    let (engine, output) = engine();
+    let events = engine.take_voice_events().unwrap();
+    load(&engine, "a", buffer(vec![0.001; RATE as usize], 1, RATE));
+    for _ in 0..VOICE_CAPACITY {
+        play(&engine, "a", |params| params.looping = true);
+    }
+    render(&engine, &output, 64);
+    let ids = |output: &OfflineOutput| -> Vec<u64> {
+        let renderer = lock(&output.renderer);
+        let voices = &renderer.as_ref().unwrap().mixer.voices;
+        voices.ordered().map(|voice| voice.id).collect()
+    };
+    let before = ids(&output);
+    assert_eq!(before.len(), VOICE_CAPACITY);
+    // One more takes the slot of the first, which ends as stolen
+    play(&engine, "a", |params| params.looping = true);
+    render(&engine, &output, 64);
+    let after = ids(&output);
+    assert_eq!(after.len(), VOICE_CAPACITY);
+    assert_eq!(after[..VOICE_CAPACITY - 1], before[1..]);
+    assert!(!before.contains(&after[VOICE_CAPACITY - 1]));
+    for _ in 0..METER_WINDOW_MS {
+        render(&engine, &output, RATE as usize / 1000);
+    }
+    let stolen = events.try_recv().unwrap();
+    assert_eq!(stolen.voice_id, before[0]);
+    assert_eq!(stolen.reason, VoiceEndReason::Stolen);
+    assert!(events.try_recv().is_err());
+}
+
+#[test]
+fn a_stereo_loop_keeps_its_channels_and_length() {
+    let (engine, output) = engine();
+    let frames = 101;
+    // Left rises above zero, right falls below it
+    let data: Vec<f32> = (0..frames)
+        .flat_map(|n| [0.1 + n as f32 * 0.004, -0.1 - n as f32 * 0.002])
+        .collect();
+    load(&engine, "a", buffer(data.clone(), 2, RATE));
+    // An odd number of frames from an odd frame
+    let (start, end) = (7, 90);
+    play(&engine, "a", |params| {
+        params.looping = true;
+        params.start_time = start as f32 / RATE as f32;
+        params.end_time = end as f32 / RATE as f32;
+    });
+    let period = end - start;
+    let out = render(&engine, &output, 3000 * period);
+    for frame in 0..3000 * period {
+        let source = start + frame % period;
+        assert_eq!(out[frame * 2], data[source * 2], "left of frame {}", frame);
+        assert_eq!(
+            out[frame * 2 + 1],
+            data[source * 2 + 1],
+            "right of frame {}",
+            frame
+        );
+    }
+
+    // Resampled, every frame still reads left from left and right from right
+    let (engine, output) = super::engine();
+    load(&engine, "b", buffer(data, 2, 44_100));
+    play(&engine, "b", |params| {
+        params.looping = true;
+        params.start_time = start as f32 / 44_100.0;
+        params.end_time = end as f32 / 44_100.0;
+    });
+    let out = render(&engine, &output, 3000 * period);
+    for frame in 0..3000 * period {
+        assert!(out[frame * 2] > 0.09, "left of frame {}", frame);
+        assert!(out[frame * 2 + 1] < -0.09, "right of frame {}", frame);
+    }
+}
+
+#[test]
+fn a_one_shot_with_no_release_stops_at_its_end_time() {
+    for channels in [1, 2] {
+        let (engine, output) = engine();
+        let data: Vec<f32> = ramp(4800)
+            .into_iter()
+            .flat_map(|sample| vec![sample; channels])
+            .collect();
+        load(&engine, "a", buffer(data.clone(), channels as u16, RATE));
+        let (start, end) = (1200, 3600);
+        play(&engine, "a", |params| {
+            params.release = 0.0;
+            params.start_time = start as f32 / RATE as f32;
+            params.end_time = end as f32 / RATE as f32;
+        });
+        let out = render(&engine, &output, 4800);
+        for frame in 0..end - start {
+            let expected = data[(start + frame) * channels];
+            let (left, right) = (out[frame * 2], out[frame * 2 + 1]);
+            assert_eq!(
+                (left, right),
+                (expected, expected),
+                "{} channels, frame {}",
+                channels,
+                frame
+            );
+        }
+        assert!(out[(end - start) * 2..].iter().all(|&sample| sample == 0.0));
+    }
+}
+
+#[test]
+fn a_release_longer_than_the_region_fits_inside_it() {
+    let (engine, output) = engine();
+    load(&engine, "a", buffer(vec![0.5; 4800], 1, RATE));
+    // 2400 frames of region, a 4800-frame release
+    play(&engine, "a", |params| {
+        params.release = 0.1;
+        params.end_time = 0.05;
+    });
+    let left = channel(&render(&engine, &output, 4800), 0);
+    assert!((left[0] - 0.5).abs() < 1e-3, "{}", left[0]);
+    for frame in 1..2400 {
+        assert!(left[frame] <= left[frame - 1], "frame {}", frame);
+    }
+    assert!(left[2399] < 1e-3, "{}", left[2399]);
+    assert!(left[2400..].iter().all(|&sample| sample == 0.0));
+}
+
+#[test]
+fn an_end_time_past_the_file_plays_to_its_end() {
+    let (engine, output) = engine();
+    let data = ramp(2400);
+    load(&engine, "a", buffer(data.clone(), 1, RATE));
+    play(&engine, "a", |params| {
+        params.release = 0.0;
+        params.end_time = 10.0;
+    });
+    let left = channel(&render(&engine, &output, 4800), 0);
+    assert_eq!(left[..2400], data[..]);
+    assert!(left[2400..].iter().all(|&sample| sample == 0.0));
+}
+
+/// Updates `key` with neutral params over whatever `set` changes
+fn update(engine: &AudioEngine, key: &str, set: impl FnOnce(&mut PlayParams)) {
+    let mut params = engine
+        .resolve_play_params(key, PartialPlayParams::default())
+        .unwrap();
+    set(&mut params);
+    engine.update_voice(key.to_string(), params).unwrap();
+}
+
+#[test]
+fn looping_turned_on_past_the_region_wraps_back_into_it() {
+    let (engine, output) = engine();
+    let data = ramp(4800);
+    load(&engine, "a", buffer(data.clone(), 1, RATE));
+    play(&engine, "a", |params| params.release = 0.0);
+    render(&engine, &output, 3000);
+    // Frame 3000 is past the new region's end
+    update(&engine, "a", |params| {
+        params.looping = true;
+        params.end_time = 0.05;
+    });
+    let left = channel(&render(&engine, &output, 4 * 2400), 0);
+    // Back at the start, easing in from silence over the declick
+    let declick = (DECLICK_MS / 1000.0 * RATE as f32) as usize;
+    for frame in 0..declick {
+        assert!(left[frame] <= data[frame] + 1e-6, "frame {}", frame);
+    }
+    for (frame, &sample) in left.iter().enumerate().skip(declick) {
+        assert!(
+            (sample - data[frame % 2400]).abs() < 1e-6,
+            "frame {}",
+            frame
+        );
+    }
+}
+
+#[test]
+fn looping_turned_on_takes_back_a_natural_release() {
+    let (engine, output) = engine();
+    load(&engine, "a", buffer(vec![0.5; 4800], 1, RATE));
+    // The 960-frame release starts at frame 3840
+    play(&engine, "a", |params| params.release = 0.02);
+    let mut left = channel(&render(&engine, &output, 4200), 0);
+    assert!(left[4199] < 0.35, "{}", left[4199]);
+    update(&engine, "a", |params| params.looping = true);
+    left.extend(channel(&render(&engine, &output, 3 * 4800), 0));
+    let declick = (DECLICK_MS / 1000.0 * RATE as f32) as usize;
+    // Turning round within a frame, then back up over the declick
+    for frame in 4200..4200 + declick {
+        let moved = left[frame] - left[frame - 1];
+        assert!(moved.abs() <= 0.5 / declick as f32, "frame {}", frame);
+        assert!(frame == 4200 || moved > 0.0, "frame {}", frame);
+    }
+    // Full level again, through every wrap
+    assert!(left[4200 + declick..].iter().all(|&sample| sample == 0.5));
+}
+
+#[test]
+fn looping_turned_off_fades_out_by_the_region_end() {
+    let (engine, output) = engine();
+    load(&engine, "a", buffer(vec![0.5; 4800], 1, RATE));
+    // A 4800-frame release, longer than what is left of the pass
+    play(&engine, "a", |params| {
+        params.looping = true;
+        params.release = 0.1;
+    });
+    render(&engine, &output, 6000);
+    update(&engine, "a", |params| params.release = 0.1);
+    let left = channel(&render(&engine, &output, 4800), 0);
+    // Down from full level over the 3600 frames to the region end
+    for (frame, &sample) in left[..3600].iter().enumerate() {
+        let expected = 0.5 * (1.0 - frame as f32 / 3600.0);
+        assert!((sample - expected).abs() < 1e-3, "frame {}", frame);
+    }
+    assert!(left[3600..].iter().all(|&sample| sample == 0.0));
+}
+
+/// Two hits of a saw half a bar apart through a 300 Hz filter, and the
+/// envelope `amount` octaves over it
+fn filtered_hits(amount: f32) -> Vec<f32> {
+    let (engine, output) = engine();
+    let saw = (0..12_000)
+        .map(|n| (n % 100) as f32 / 200.0 - 0.25)
+        .collect();
+    load(&engine, "a", buffer(saw, 1, RATE));
+    let params = PartialPlayParams {
+        filter_cutoff: Some(300.0),
+        filter_env_amount: Some(amount),
+        filter_env_attack: Some(0.005),
+        filter_env_decay: Some(0.1),
+        ..PartialPlayParams::default()
+    };
+    let pattern = [0, 8]
+        .iter()
+        .map(|&step| StepEvent {
+            step,
+            velocity: 1.0,
+            params: Some(params.clone()),
+        })
+        .collect();
+    engine.seq_set_pattern("a".to_string(), pattern).unwrap();
+    engine.seq_start().unwrap();
+    channel(&render(&engine, &output, 2 * 96_000), 0)
+}
+
+#[test]
+fn every_trigger_starts_the_filter_envelope_over() {
+    let left = filtered_hits(4.0);
+    let first = &left[..12_000];
+    for start in [48_000, 96_000, 144_000] {
+        assert_eq!(&left[start..start + 12_000], first, "from {}", start);
+    }
+    // And it is the envelope that opens them up
+    let still = filtered_hits(0.0);
+    let energy = |hit: &[f32]| hit[..2400].iter().map(|s| s * s).sum::<f32>();
+    assert!(energy(first) > 2.0 * energy(&still[..12_000]));
+}
+
+#[test]
+fn the_crusher_goes_on_and_off_while_playing() {
+    let (engine, output) = engine();
+    let data = ramp(RATE as usize);
+    load(&engine, "a", buffer(data.clone(), 1, RATE));
+    play(&engine, "a", |_| {});
+    let left = channel(&render(&engine, &output, 4800), 0);
+    assert_eq!(left[..], data[..4800]);
+    // Three bits: every sample on a quarter step, each held for four frames
+    update(&engine, "a", |params| {
+        params.crush_bits = 3;
+        params.crush_downsample = 4;
+    });
+    let left = channel(&render(&engine, &output, 9600), 0);
+    let settled = &left[4800..];
+    assert!(settled
+        .iter()
+        .all(|&sample| sample == 0.0 || sample == 0.25));
+    assert!(settled.contains(&0.25) && settled.contains(&0.0));
+    // Back to the defaults, the voice is untouched once the factor is back
+    update(&engine, "a", |_| {});
+    render(&engine, &output, 4800);
+    let left = channel(&render(&engine, &output, 4800), 0);
+    assert_eq!(left[..], data[4800 * 4..4800 * 5]);
+}
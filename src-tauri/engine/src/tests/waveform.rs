@@ -0,0 +1,34 @@
+//! The per-pad waveform cache: filled as resolutions are asked for, and
+//! gone with the buffer when the pad is reloaded
+
+use super::*;
+
+#[test]
+fn reloading_a_pad_drops_its_cached_waveforms() {
+    let (engine, _output) = engine();
+    let (loud, quiet) = (scratch("loud.wav"), scratch("quiet.wav"));
+    write_mono_wav(&loud, &[0.5; 4800]);
+    write_mono_wav(&quiet, &[0.25; 4800]);
+
+    load_path(&engine, "a", &loud);
+    let first = engine.get_buffer_waveform("a", WaveformChannel::Mixed, 1000);
+    assert_eq!(first.len(), 1000);
+    assert!(first.iter().all(|&peak| (peak - 0.5).abs() < 1e-3));
+    // Asked again, the same vector comes back without a recompute
+    let again = engine.get_buffer_waveform("a", WaveformChannel::Mixed, 1000);
+    assert!(Arc::ptr_eq(&first, &again));
+    let decoded = engine.get_buffer_waveform("a", WaveformChannel::Mixed, WAVEFORM_POINTS);
+
+    load_path(&engine, "a", &quiet);
+    for points in [1000, WAVEFORM_POINTS] {
+        let reloaded = engine.get_buffer_waveform("a", WaveformChannel::Mixed, points);
+        assert!(!Arc::ptr_eq(&reloaded, &first) && !Arc::ptr_eq(&reloaded, &decoded));
+        assert!(
+            reloaded.iter().all(|&peak| (peak - 0.25).abs() < 1e-3),
+            "{} points",
+            points
+        );
+    }
+    std::fs::remove_file(&loud).unwrap();
+    std::fs::remove_file(&quiet).unwrap();
+}
@@ -0,0 +1,148 @@
+/**
+ * cli.rs
+ * L-SAMP 100 | Command Line
+ *
+ * `lsamp-100 ~/kits/house.json` opens a session file, `lsamp-100 --pad Q
+ * ~/loops/break.wav` preloads one pad of the active bank; lsamp:// links
+ * handed over by the OS are passed on to deeplink.rs. Arguments are
+ * parsed into `LaunchRequest`s without touching the filesystem beyond
+ * existence checks; main.rs runs them once the frontend listens, for the
+ * first launch and for launches forwarded by the single-instance plugin.
+ * Anything unusable becomes a `Warning` instead of stopping the start.
+ * `--headless SESSION.json [--midi PORT]` skips the window altogether and
+ * is picked out first, see headless.rs.
+ */
+use std::path::{Path, PathBuf};
+
+use crate::deeplink;
+use crate::keymap::PAD_KEYS;
+
+#[derive(Debug, Clone)]
+pub enum LaunchRequest {
+    Session(PathBuf),
+    Pad { pad: &'static str, path: PathBuf },
+    DeepLink(String), // lsamp:// URL as passed by the OS (Windows, Linux)
+    Warning(String),
+}
+
+/// Usage line repeated in argument warnings
+const USAGE: &str = "lsamp-100 [SESSION.json] [--pad KEY FILE]...";
+const HEADLESS_USAGE: &str = "lsamp-100 --headless SESSION.json [--midi PORT]";
+
+/// What `--headless` runs with
+#[derive(Debug, Clone)]
+pub struct HeadlessOptions {
+    pub session: PathBuf,
+    pub midi_port: Option<String>, // Pads come from this MIDI input instead of the keyboard
+}
+
+/// `Some` when the launch asks for headless mode; errors are usage mistakes
+/// that should end the process before anything starts
+pub fn headless(argv: &[String], cwd: &Path) -> Option<Result<HeadlessOptions, String>> {
+    if !argv.iter().skip(1).any(|arg| arg == "--headless") {
+        return None;
+    }
+    let mut session = None;
+    let mut midi_port = None;
+    let mut args = argv.iter().skip(1);
+    while let Some(arg) = args.next() {
+        let value = match arg.as_str() {
+            "--headless" => &mut session,
+            "--midi" => &mut midi_port,
+            _ => {
+                return Some(Err(format!(
+                    "[Social Noise] Unexpected argument {:?} ({})",
+                    arg, HEADLESS_USAGE
+                )))
+            }
+        };
+        match args.next() {
+            Some(next) if !next.starts_with("--") => *value = Some(next.clone()),
+            _ => {
+                return Some(Err(format!(
+                    "[Social Noise] {} needs a value ({})",
+                    arg, HEADLESS_USAGE
+                )))
+            }
+        }
+    }
+    let session = cwd.join(expand_home(session.as_deref().unwrap_or_default()));
+    Some(if session.is_file() {
+        Ok(HeadlessOptions { session, midi_port })
+    } else {
+        Err(format!(
+            "[Social Noise] Session file not found: {}",
+            session.display()
+        ))
+    })
+}
+
+/// Parses a full argv (`argv[0]` is skipped). Relative paths resolve
+/// against `cwd`, the working directory of the launch that passed them.
+pub fn parse(argv: &[String], cwd: &Path) -> Vec<LaunchRequest> {
+    let mut requests = Vec::new();
+    let mut args = argv.iter().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--pad" => match (args.next(), args.next()) {
+                (Some(pad), Some(file)) => requests.push(pad_request(cwd, pad, file)),
+                _ => {
+                    requests.push(warning(format!("--pad needs a key and a file ({})", USAGE)));
+                    break;
+                }
+            },
+            _ if deeplink::is_link(arg) => requests.push(LaunchRequest::DeepLink(arg.clone())),
+            // macOS Finder launches of older systems add a process serial number
+            _ if arg.starts_with("-psn_") => {}
+            _ if arg.starts_with('-') => {
+                requests.push(warning(format!("Unknown option {:?} ({})", arg, USAGE)));
+            }
+            _ if has_extension(arg, "json") => {
+                requests.push(existing(cwd, arg, LaunchRequest::Session));
+            }
+            _ => requests.push(warning(format!(
+                "{:?} is not a session file; use --pad KEY FILE for samples",
+                arg
+            ))),
+        }
+    }
+    requests
+}
+
+fn pad_request(cwd: &Path, pad: &str, file: &str) -> LaunchRequest {
+    match PAD_KEYS.iter().find(|key| key.eq_ignore_ascii_case(pad)) {
+        Some(pad) => existing(cwd, file, |path| LaunchRequest::Pad { pad, path }),
+        None => warning(format!("Unknown pad {:?} for --pad", pad)),
+    }
+}
+
+fn existing(
+    cwd: &Path,
+    file: &str,
+    request: impl FnOnce(PathBuf) -> LaunchRequest,
+) -> LaunchRequest {
+    let path = cwd.join(expand_home(file));
+    if path.is_file() {
+        request(path)
+    } else {
+        warning(format!("File not found: {}", path.display()))
+    }
+}
+
+fn warning(message: String) -> LaunchRequest {
+    LaunchRequest::Warning(format!("[Social Noise] {}", message))
+}
+
+/// Shells expand `~` themselves, but desktop launchers and forwarded args may not
+fn expand_home(file: &str) -> PathBuf {
+    match (file.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(file),
+    }
+}
+
+fn has_extension(file: &str, extension: &str) -> bool {
+    Path::new(file)
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case(extension))
+}
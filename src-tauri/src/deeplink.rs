@@ -0,0 +1,115 @@
+/**
+ * deeplink.rs
+ * L-SAMP 100 | lsamp:// Links
+ *
+ * `lsamp://load?pad=Q&file=breaks/amen.wav` loads a harbor file onto a pad
+ * (optionally `&bank=2`, 0-based like `audio_load`). Files are always
+ * harbor-relative and go through the same traversal checks as the harbor
+ * commands, so a link can never reach outside the harbor. main.rs runs the
+ * parsed link and reports every outcome as a `deeplink-handled` event.
+ */
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::Url;
+
+use crate::audio_engine::BANK_COUNT;
+use crate::harbor::{is_audio_file, resolve_in_harbor};
+use crate::keymap::PAD_KEYS;
+
+pub const SCHEME: &str = "lsamp";
+
+/// What a validated link asks for
+#[derive(Debug, Clone)]
+pub enum DeepLink {
+    Load {
+        pad: &'static str,
+        bank: Option<u32>,
+        file: String,  // As given, harbor-relative
+        path: PathBuf, // Resolved inside the harbor
+    },
+}
+
+/// Payload of `deeplink-handled`; `error` is set when the link was rejected
+#[derive(Debug, Clone, Serialize)]
+pub struct DeepLinkHandled {
+    pub url: String,
+    pub action: Option<String>,
+    pub pad: Option<String>, // Pad id ("{bank}:{key}") once the load ran
+    pub file: Option<String>,
+    pub error: Option<String>,
+}
+
+impl DeepLinkHandled {
+    pub fn rejected(url: &str, error: String) -> Self {
+        Self {
+            url: url.to_string(),
+            action: None,
+            pad: None,
+            file: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// Whether a command line argument is a link rather than a path
+pub fn is_link(arg: &str) -> bool {
+    arg.get(..SCHEME.len() + 1)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(&format!("{}:", SCHEME)))
+}
+
+/// Parses and validates a link against the harbor
+pub fn parse(url: &str, harbor: &Path) -> Result<DeepLink, String> {
+    let url = Url::parse(url).map_err(|e| format!("[Social Noise] Invalid link: {}", e))?;
+    if url.scheme() != SCHEME {
+        return Err(format!("[Social Noise] Not an {}:// link", SCHEME));
+    }
+    // "lsamp://load?..." carries the action as host, "lsamp:load?..." as path
+    let action = url
+        .host_str()
+        .unwrap_or_else(|| url.path().trim_matches('/'))
+        .to_ascii_lowercase();
+    let param = |name: &str| {
+        url.query_pairs()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    };
+
+    match action.as_str() {
+        "load" => {
+            let pad = param("pad").ok_or("[Social Noise] Link is missing pad=")?;
+            let pad = PAD_KEYS
+                .iter()
+                .copied()
+                .find(|key| key.eq_ignore_ascii_case(&pad))
+                .ok_or_else(|| format!("[Social Noise] Unknown pad: {:?}", pad))?;
+            let bank = param("bank")
+                .map(|bank| {
+                    bank.parse()
+                        .map_err(|_| format!("[Social Noise] Invalid bank: {:?}", bank))
+                })
+                .transpose()?;
+            if bank.is_some_and(|bank| bank >= BANK_COUNT) {
+                return Err(format!(
+                    "[Social Noise] Bank out of range (0-{})",
+                    BANK_COUNT - 1
+                ));
+            }
+            let file = param("file").ok_or("[Social Noise] Link is missing file=")?;
+            let path = resolve_in_harbor(harbor, &file)
+                .map_err(|e| format!("[Social Noise] {}: {:?}", e, file))?;
+            if !is_audio_file(&path) || !path.is_file() {
+                return Err(format!(
+                    "[Social Noise] {:?} is not an audio file in the harbor",
+                    file
+                ));
+            }
+            Ok(DeepLink::Load {
+                pad,
+                bank,
+                file,
+                path,
+            })
+        }
+        _ => Err(format!("[Social Noise] Unknown link action: {:?}", action)),
+    }
+}
@@ -0,0 +1,360 @@
+/**
+ * gamepad.rs
+ * L-SAMP 100 | Gamepad Input
+ *
+ * Optional input backend: a gilrs thread turns gamepad buttons into pad
+ * triggers, like the keyboard listener, and analog triggers or sticks into
+ * live control values, like MIDI CCs. Axis values are dead-zoned and rate
+ * limited before they reach the engine. Input is ignored until enabled via
+ * `apply_config`; the mapping is persisted as gamepad.json under the
+ * lsamp-100 config directory.
+ */
+use gilrs::{Axis, Button, Event, EventType, Gilrs};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::keymap::{PAD_KEYS, STOP_ALL};
+use crate::midi::MidiTarget;
+
+/// Inputs that fire pads, in default grid order (Start stops everything).
+/// The trailing analog triggers are only meant for controls.
+const BUTTONS: [Button; 15] = [
+    Button::South,
+    Button::East,
+    Button::West,
+    Button::North,
+    Button::LeftTrigger,
+    Button::RightTrigger,
+    Button::LeftThumb,
+    Button::RightThumb,
+    Button::DPadUp,
+    Button::DPadDown,
+    Button::DPadLeft,
+    Button::DPadRight,
+    Button::Start,
+    Button::LeftTrigger2,
+    Button::RightTrigger2,
+];
+
+/// Analog triggers, reported as button values from 0.0 to 1.0
+const TRIGGERS: [Button; 2] = [Button::LeftTrigger2, Button::RightTrigger2];
+
+/// Sticks, reported from -1.0 to 1.0 with rest at 0.0
+const AXES: [Axis; 6] = [
+    Axis::LeftStickX,
+    Axis::LeftStickY,
+    Axis::RightStickX,
+    Axis::RightStickY,
+    Axis::LeftZ,
+    Axis::RightZ,
+];
+
+/// Stick and trigger travel ignored around the rest position
+const DEAD_ZONE: f32 = 0.08;
+/// Fastest rate one analog input drives `update_voice` (50 Hz)
+const AXIS_INTERVAL: Duration = Duration::from_millis(20);
+/// Smaller moves than this are not worth a voice update
+const AXIS_EPSILON: f32 = 0.005;
+
+/// Input name ("South", "RightTrigger2", "LeftStickX") -> target. Targets are
+/// what MIDI bindings use: a pad slot, "SPACE", a bank key or a named control.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GamepadMapping {
+    pub inputs: BTreeMap<String, String>,
+}
+
+impl Default for GamepadMapping {
+    fn default() -> Self {
+        let targets = PAD_KEYS.iter().copied().chain(std::iter::once(STOP_ALL));
+        Self {
+            inputs: BUTTONS
+                .iter()
+                .zip(targets)
+                .map(|(button, target)| (button_name(*button), target.to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl GamepadMapping {
+    /// Binds `input` to `target`, or unbinds it when `target` is None. Pads
+    /// need a button; named controls need an analog trigger or a stick.
+    pub fn set(&mut self, input: &str, target: Option<&str>) -> Result<(), String> {
+        let input = input_name(input)?;
+        let target = match target {
+            Some(target) => MidiTarget::parse(target)?,
+            None => {
+                self.inputs.remove(&input);
+                return Ok(());
+            }
+        };
+        let analog = is_analog(&input);
+        let digital = BUTTONS.iter().any(|b| button_name(*b) == input);
+        match target {
+            MidiTarget::Pad(_) if !digital => {
+                return Err(format!("[Social Noise] {} cannot trigger pads", input))
+            }
+            MidiTarget::MasterVolume | MidiTarget::PadVolume(_) if !analog => {
+                return Err(format!("[Social Noise] {} is not an analog input", input))
+            }
+            _ => {}
+        }
+        self.inputs.insert(input, target.name());
+        Ok(())
+    }
+
+    fn target(&self, input: &str) -> Option<MidiTarget> {
+        MidiTarget::parse(self.inputs.get(input)?).ok()
+    }
+}
+
+/// Entry of `gamepad_list`, also the payload of `gamepad-connected`/`-disconnected`
+#[derive(Debug, Clone, Serialize)]
+pub struct GamepadInfo {
+    pub id: usize,
+    pub name: String,
+}
+
+/// A mapped gamepad input, or a hotplug event
+pub enum GamepadEvent {
+    Pad {
+        pad: &'static str,
+        pressed: bool,
+    },
+    /// A named control moved to `value` (0.0-1.0)
+    Control {
+        target: MidiTarget,
+        value: f32,
+    },
+    Connected(GamepadInfo),
+    Disconnected(GamepadInfo),
+}
+
+/// State shared between the commands and the gilrs thread
+pub struct GamepadState {
+    enabled: Arc<AtomicBool>,
+    mapping: Arc<Mutex<GamepadMapping>>,
+    connected: Arc<Mutex<Vec<GamepadInfo>>>,
+}
+
+impl GamepadState {
+    pub fn new(mapping: GamepadMapping) -> Self {
+        Self {
+            enabled: Arc::new(AtomicBool::new(false)),
+            mapping: Arc::new(Mutex::new(mapping)),
+            connected: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Pads and controls only react while enabled; hotplug is always tracked
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn list(&self) -> Vec<GamepadInfo> {
+        self.connected.lock().map(|c| c.clone()).unwrap_or_default()
+    }
+
+    pub fn mapping(&self) -> GamepadMapping {
+        self.mapping.lock().map(|m| m.clone()).unwrap_or_default()
+    }
+
+    /// Changes one input binding and persists the whole mapping
+    pub fn set_mapping(&self, input: &str, target: Option<&str>) -> Result<GamepadMapping, String> {
+        let mut mapping = self.mapping.lock().map_err(|e| e.to_string())?;
+        mapping.set(input, target)?;
+        save_mapping(&mapping)?;
+        Ok(mapping.clone())
+    }
+
+    /// Spawns the gilrs thread. `on_event` runs on that thread.
+    pub fn start(&self, mut on_event: impl FnMut(GamepadEvent) + Send + 'static) {
+        let enabled = Arc::clone(&self.enabled);
+        let mapping = Arc::clone(&self.mapping);
+        let connected = Arc::clone(&self.connected);
+
+        thread::spawn(move || {
+            // Gilrs is not Send on every platform, so it lives on this thread only
+            let mut gilrs = match Gilrs::new() {
+                Ok(gilrs) => gilrs,
+                Err(e) => {
+                    warn!("Gamepad input unavailable: {}", e);
+                    return;
+                }
+            };
+            if let Ok(mut connected) = connected.lock() {
+                *connected = gilrs
+                    .gamepads()
+                    .map(|(id, pad)| GamepadInfo {
+                        id: id.into(),
+                        name: pad.name().to_string(),
+                    })
+                    .collect();
+            }
+
+            let mut axes = AxisLimiter::default();
+            loop {
+                if let Some(Event { id, event, .. }) =
+                    gilrs.next_event_blocking(Some(AXIS_INTERVAL))
+                {
+                    let info = || GamepadInfo {
+                        id: id.into(),
+                        name: gilrs.gamepad(id).name().to_string(),
+                    };
+                    match event {
+                        EventType::Connected => {
+                            let info = info();
+                            info!("Gamepad connected: {}", info.name);
+                            if let Ok(mut connected) = connected.lock() {
+                                connected.retain(|c| c.id != info.id);
+                                connected.push(info.clone());
+                            }
+                            on_event(GamepadEvent::Connected(info));
+                        }
+                        EventType::Disconnected => {
+                            let info = info();
+                            info!("Gamepad disconnected: {}", info.name);
+                            if let Ok(mut connected) = connected.lock() {
+                                connected.retain(|c| c.id != info.id);
+                            }
+                            axes.pending.clear();
+                            on_event(GamepadEvent::Disconnected(info));
+                        }
+                        _ if !enabled.load(Ordering::Relaxed) => {}
+                        EventType::ButtonPressed(button, _)
+                        | EventType::ButtonReleased(button, _) => {
+                            let pressed = matches!(event, EventType::ButtonPressed(..));
+                            if let Some(MidiTarget::Pad(pad)) = mapping
+                                .lock()
+                                .ok()
+                                .and_then(|m| m.target(&button_name(button)))
+                            {
+                                on_event(GamepadEvent::Pad { pad, pressed });
+                            }
+                        }
+                        EventType::ButtonChanged(button, value, _) => {
+                            axes.queue(&mapping, button_name(button), trigger_value(value));
+                        }
+                        EventType::AxisChanged(axis, value, _) => {
+                            axes.queue(&mapping, format!("{:?}", axis), stick_value(value));
+                        }
+                        _ => {}
+                    }
+                }
+                for (target, value) in axes.due() {
+                    on_event(GamepadEvent::Control { target, value });
+                }
+            }
+        });
+    }
+}
+
+/// Holds back analog values so one input updates at most every `AXIS_INTERVAL`;
+/// the latest value is always delivered once the interval has passed.
+#[derive(Default)]
+struct AxisLimiter {
+    pending: HashMap<String, (MidiTarget, f32)>,
+    sent: HashMap<String, (Instant, f32)>,
+}
+
+impl AxisLimiter {
+    fn queue(&mut self, mapping: &Mutex<GamepadMapping>, input: String, value: f32) {
+        if let Some(target @ (MidiTarget::MasterVolume | MidiTarget::PadVolume(_))) =
+            mapping.lock().ok().and_then(|m| m.target(&input))
+        {
+            self.pending.insert(input, (target, value));
+        }
+    }
+
+    fn due(&mut self) -> Vec<(MidiTarget, f32)> {
+        let mut due = Vec::new();
+        let sent = &mut self.sent;
+        self.pending.retain(|input, (target, value)| {
+            let last = sent.get(input).copied();
+            if last.is_some_and(|(at, _)| at.elapsed() < AXIS_INTERVAL) {
+                return true; // Too soon; the latest value waits for the next round
+            }
+            // Tiny moves are dropped, except landing exactly on an end stop
+            let end_stop = *value == 0.0 || *value == 1.0;
+            let moved = match last {
+                Some((_, old)) => {
+                    (old - *value).abs() >= AXIS_EPSILON || (end_stop && old != *value)
+                }
+                None => true,
+            };
+            if moved {
+                sent.insert(input.clone(), (Instant::now(), *value));
+                due.push((*target, *value));
+            }
+            false
+        });
+        due
+    }
+}
+
+/// 0.0-1.0 trigger travel with the resting slack removed
+fn trigger_value(value: f32) -> f32 {
+    ((value - DEAD_ZONE) / (1.0 - DEAD_ZONE)).clamp(0.0, 1.0)
+}
+
+/// -1.0..1.0 stick travel mapped to 0.0..1.0, centre (0.5) held through the dead zone
+fn stick_value(value: f32) -> f32 {
+    let magnitude = ((value.abs() - DEAD_ZONE) / (1.0 - DEAD_ZONE)).clamp(0.0, 1.0);
+    0.5 + magnitude.copysign(value) * 0.5
+}
+
+fn button_name(button: Button) -> String {
+    format!("{:?}", button)
+}
+
+fn is_analog(input: &str) -> bool {
+    TRIGGERS.iter().any(|b| button_name(*b) == input)
+        || AXES.iter().any(|a| format!("{:?}", a) == input)
+}
+
+/// Canonical input name for a case-insensitive button or axis name
+fn input_name(name: &str) -> Result<String, String> {
+    BUTTONS
+        .iter()
+        .map(|b| button_name(*b))
+        .chain(AXES.iter().map(|a| format!("{:?}", a)))
+        .find(|input| input.eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("[Social Noise] Unknown gamepad input: {:?}", name))
+}
+
+fn mapping_path() -> Result<PathBuf, String> {
+    let dir = dirs::config_dir()
+        .ok_or("Failed to get config dir".to_string())?
+        .join("lsamp-100");
+    if !dir.exists() {
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("[Inner Cosmos] Config folder creation failed: {}", e))?;
+    }
+    Ok(dir.join("gamepad.json"))
+}
+
+/// Saved mapping, or the default face-buttons-first layout
+/// Saved mapping, the default when none was saved. A file that cannot be
+/// read or parsed is an error, so the caller can report it.
+pub fn load_mapping() -> Result<GamepadMapping, String> {
+    let path = mapping_path()?;
+    match fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str(&contents)
+            .map_err(|e| format!("Gamepad mapping {:?} is corrupt: {}", path, e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(GamepadMapping::default()),
+        Err(e) => Err(format!("Gamepad mapping {:?} is unreadable: {}", path, e)),
+    }
+}
+
+fn save_mapping(mapping: &GamepadMapping) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(mapping).map_err(|e| e.to_string())?;
+    fs::write(mapping_path()?, json)
+        .map_err(|e| format!("[Social Noise] Gamepad mapping save failed: {}", e))
+}
@@ -0,0 +1,1059 @@
+/**
+ * harbor.rs
+ * L-SAMP 100 | Harbor Management
+ *
+ * The harbor is the docking bay (~/.config/lsamp-100/audio on Linux) where
+ * samples are stored before being deployed to the performance grid.
+ * Everything that touches it on disk lives here; the IPC handlers in
+ * main.rs stay thin wrappers around these functions.
+ */
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+use crate::audio_engine::SampleSettings;
+
+/// File extensions the harbor accepts (lowercase, without the dot)
+pub const AUDIO_EXTENSIONS: &[&str] = &["mp3", "wav", "ogg", "flac", "aiff", "aif", "m4a", "aac"];
+
+/// Default ceiling for `import_file` (512 MB)
+pub const DEFAULT_IMPORT_LIMIT_BYTES: u64 = 512 * 1024 * 1024;
+
+/// OS clutter that is never a sample
+const JUNK_FILES: &[&str] = &[".DS_Store", "Thumbs.db", "desktop.ini"];
+
+/// Runtime-tunable harbor behavior, set through `apply_config`
+pub struct HarborSettings {
+    pub import_limit_bytes: u64,
+    pub watch_enabled: bool, // Emit `harbor-changed` events for external edits
+    pub scan: ScanOptions,
+}
+
+impl Default for HarborSettings {
+    fn default() -> Self {
+        Self {
+            import_limit_bytes: DEFAULT_IMPORT_LIMIT_BYTES,
+            watch_enabled: true,
+            scan: ScanOptions::default(),
+        }
+    }
+}
+
+/// Filters applied by harbor scans and the watcher
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanOptions {
+    pub max_depth: Option<usize>, // Folder levels below the root; None = unlimited
+    pub include_hidden: bool,     // Also list dot-files and OS junk
+    pub extra_extensions: Vec<String>,
+    pub exclude: Vec<String>, // Glob patterns matched against harbor-relative paths
+}
+
+impl ScanOptions {
+    /// Rejects exclude patterns that are not valid globs
+    pub fn validate(&self) -> Result<(), String> {
+        for pattern in &self.exclude {
+            glob::Pattern::new(pattern)
+                .map_err(|e| format!("Invalid exclude pattern {:?}: {}", pattern, e))?;
+        }
+        Ok(())
+    }
+
+    fn accepts_extension(&self, path: &Path) -> bool {
+        path.extension()
+            .map(|ext| {
+                let ext = ext.to_string_lossy().to_lowercase();
+                AUDIO_EXTENSIONS.contains(&ext.as_str())
+                    || self
+                        .extra_extensions
+                        .iter()
+                        .any(|extra| extra.trim_start_matches('.').eq_ignore_ascii_case(&ext))
+            })
+            .unwrap_or(false)
+    }
+
+    fn accepts_name(&self, name: &str) -> bool {
+        self.include_hidden || !(name.starts_with('.') || JUNK_FILES.contains(&name))
+    }
+
+    fn is_excluded(&self, patterns: &[glob::Pattern], relative: &Path) -> bool {
+        patterns.iter().any(|p| p.matches_path(relative))
+    }
+
+    fn patterns(&self) -> Vec<glob::Pattern> {
+        self.exclude
+            .iter()
+            .filter_map(|p| glob::Pattern::new(p).ok())
+            .collect()
+    }
+
+    /// Whether a scan would descend into this harbor-relative folder
+    pub fn accepts_dir(&self, relative: &Path) -> bool {
+        let names: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+        !matches!(self.max_depth, Some(max) if names.len() > max)
+            && names.iter().all(|name| self.accepts_name(name))
+            && !self.is_excluded(&self.patterns(), relative)
+    }
+
+    /// Whether a scan would list this harbor-relative file
+    pub fn accepts_file(&self, relative: &Path) -> bool {
+        let folder = relative.parent().unwrap_or(Path::new(""));
+        let name = relative
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        self.accepts_dir(folder)
+            && self.accepts_name(&name)
+            && !self.is_excluded(&self.patterns(), relative)
+            && self.accepts_extension(relative)
+    }
+}
+
+/// Harbor-relative audio files plus how many entries the filters dropped
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanResult {
+    pub files: Vec<String>,
+    pub skipped: usize,
+}
+
+/// Result of copying a file into the harbor
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportedFile {
+    pub path: String, // Harbor-relative path of the new file
+    pub size: u64,
+    pub extension: String,
+}
+
+/// Result of sending a harbor file to the trash
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletedFile {
+    pub path: String,
+    pub referenced: bool, // A loaded pad still plays this file
+    pub referencing_pads: Vec<String>,
+}
+
+/// Files with identical contents, smallest path first
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateGroup {
+    pub hash: String,
+    pub size: u64,
+    pub paths: Vec<String>,
+}
+
+/// Content hashes keyed by path, valid while (size, mtime) are unchanged
+#[derive(Debug, Clone, Default)]
+pub struct HashCache {
+    entries: HashMap<PathBuf, (u64, u64, String)>,
+}
+
+impl HashCache {
+    /// Hash of a file, recomputed only when its size or mtime changed
+    pub fn hash(&mut self, path: &Path) -> Result<String, String> {
+        let metadata = fs::metadata(path)
+            .map_err(|e| format!("[Social Noise] Cannot read {:?}: {}", path, e))?;
+        self.hash_with(path, metadata.len(), modified_millis(&metadata))
+    }
+
+    fn hash_with(&mut self, path: &Path, size: u64, mtime: u64) -> Result<String, String> {
+        if let Some((s, m, hash)) = self.entries.get(path) {
+            if *s == size && *m == mtime {
+                return Ok(hash.clone());
+            }
+        }
+        let hash = hash_file(path)?;
+        self.entries
+            .insert(path.to_path_buf(), (size, mtime, hash.clone()));
+        Ok(hash)
+    }
+}
+
+/// Suffix of the per-sample settings file written next to each sample
+pub const SIDECAR_SUFFIX: &str = ".lsamp.json";
+
+/// Harbor directory chosen through `apply_config`, in place of the default
+static HARBOR_OVERRIDE: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Moves the harbor to `path`, an existing directory the app can read and
+/// write. Anything else is rejected and the harbor stays where it was.
+pub fn set_audio_harbor(path: &Path) -> Result<(), String> {
+    let metadata = fs::metadata(path)
+        .map_err(|e| format!("[Social Noise] Harbor {:?} not found: {}", path, e))?;
+    if !metadata.is_dir() {
+        return Err(format!(
+            "[Social Noise] Harbor {:?} is not a directory",
+            path
+        ));
+    }
+    fs::read_dir(path)
+        .map_err(|e| format!("[Social Noise] Harbor {:?} is not readable: {}", path, e))?;
+    // Imports write into it, so a read-only directory is no harbor either
+    let probe = path.join(".lsamp-probe");
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .map_err(|e| format!("[Social Noise] Harbor {:?} is not writable: {}", path, e))?;
+    let _ = fs::remove_file(&probe);
+    *HARBOR_OVERRIDE.write().map_err(|e| e.to_string())? = Some(path.to_path_buf());
+    info!("Harbor moved to: {:?}", path);
+    Ok(())
+}
+
+/// Get the audio harbor directory path
+pub fn get_audio_harbor() -> Result<PathBuf, String> {
+    if let Some(path) = HARBOR_OVERRIDE.read().map_err(|e| e.to_string())?.clone() {
+        return Ok(path);
+    }
+    // Use standard config directory: ~/.config/lsamp-100/audio (on Linux)
+    let config_dir = dirs::config_dir()
+        .ok_or("Failed to get config dir".to_string())?
+        .join("lsamp-100");
+
+    let harbor_path = config_dir.join("audio");
+
+    // Ensure the directory exists
+    if !harbor_path.exists() {
+        fs::create_dir_all(&harbor_path)
+            .map_err(|e| format!("[Inner Cosmos] Harbor creation failed: {}", e))?;
+        info!("Harbor created at: {:?}", harbor_path);
+    }
+
+    Ok(harbor_path)
+}
+
+/// Recursively scan directory for audio files
+pub fn scan_harbor(dir_path: &PathBuf, options: &ScanOptions) -> Result<ScanResult, String> {
+    let mut result = ScanResult {
+        files: Vec::new(),
+        skipped: 0,
+    };
+    let patterns = options.patterns();
+    let mut visited = HashSet::new();
+
+    fn scan_recursive(
+        dir: &PathBuf,
+        base_dir: &PathBuf,
+        depth: usize,
+        options: &ScanOptions,
+        patterns: &[glob::Pattern],
+        visited: &mut HashSet<PathBuf>,
+        result: &mut ScanResult,
+    ) -> Result<(), String> {
+        // Symlinked folders may point back up the tree; never enter one twice
+        let canonical = fs::canonicalize(dir).unwrap_or_else(|_| dir.clone());
+        if !visited.insert(canonical) {
+            return Ok(());
+        }
+
+        let entries =
+            fs::read_dir(dir).map_err(|e| format!("[Social Noise] Harbor scan failed: {}", e))?;
+
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("[Social Noise] Entry error: {}", e))?;
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            let rel_path = match path.strip_prefix(base_dir) {
+                Ok(rel_path) => rel_path.to_path_buf(),
+                Err(_) => continue,
+            };
+
+            if !options.accepts_name(&name) || options.is_excluded(patterns, &rel_path) {
+                result.skipped += 1;
+                continue;
+            }
+
+            if path.is_dir() {
+                if !matches!(options.max_depth, Some(max) if depth >= max) {
+                    scan_recursive(
+                        &path,
+                        base_dir,
+                        depth + 1,
+                        options,
+                        patterns,
+                        visited,
+                        result,
+                    )?;
+                } else {
+                    result.skipped += 1;
+                }
+            } else if options.accepts_extension(&path) {
+                result.files.push(to_api_path(&rel_path));
+            } else {
+                result.skipped += 1;
+            }
+        }
+        Ok(())
+    }
+
+    scan_recursive(
+        dir_path,
+        dir_path,
+        0,
+        options,
+        &patterns,
+        &mut visited,
+        &mut result,
+    )?;
+    Ok(result)
+}
+
+/// Sort order for the detailed harbor listing
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HarborSort {
+    #[default]
+    Name,
+    Modified, // Newest first
+    Size,     // Largest first
+}
+
+/// One harbor file with filesystem metadata. `duration` and `bpm` are only
+/// filled when the file was decoded before (nothing is decoded for the listing).
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarborFileInfo {
+    pub path: String,
+    pub size: u64,
+    pub modified: u64, // Milliseconds since the Unix epoch
+    pub extension: String,
+    pub duration: Option<f32>,
+    pub bpm: Option<f32>,
+}
+
+/// Scans the harbor and stats every audio file, sorted by `sort`
+pub fn scan_harbor_detailed(
+    dir_path: &PathBuf,
+    options: &ScanOptions,
+    sort: HarborSort,
+) -> Result<Vec<HarborFileInfo>, String> {
+    let mut files: Vec<HarborFileInfo> = scan_harbor(dir_path, options)?
+        .files
+        .into_iter()
+        .filter_map(|rel_path| {
+            let metadata = fs::metadata(dir_path.join(&rel_path)).ok()?;
+            let modified = modified_millis(&metadata);
+            let extension = Path::new(&rel_path)
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            Some(HarborFileInfo {
+                path: rel_path,
+                size: metadata.len(),
+                modified,
+                extension,
+                duration: None,
+                bpm: None,
+            })
+        })
+        .collect();
+
+    match sort {
+        HarborSort::Name => files.sort_by_key(|f| f.path.to_lowercase()),
+        HarborSort::Modified => files.sort_by_key(|f| std::cmp::Reverse(f.modified)),
+        HarborSort::Size => files.sort_by_key(|f| std::cmp::Reverse(f.size)),
+    }
+    Ok(files)
+}
+
+/// Files between two `harbor-scan-progress` events of a full scan
+const SCAN_PROGRESS_EVERY: usize = 250;
+
+/// One page of `scan_harbor_incremental`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarborPage {
+    pub files: Vec<String>,
+    pub cursor: Option<String>, // Pass back for the next page; None once everything was listed
+    pub total: Option<usize>,   // Known once the walk has reached the end
+}
+
+/// A folder being walked, entries stored in reverse so `pop` yields the next one
+struct WalkFrame {
+    depth: usize,
+    entries: Vec<(PathBuf, bool)>, // (path, is_dir)
+}
+
+/// Lazily walked harbor listing kept between paging calls. The walk only
+/// advances as far as the requested page needs, and pages already walked are
+/// served from `files`.
+///
+/// Ordering: folders are walked depth-first and each folder's entries are
+/// taken in case-insensitive name order, so the pages concatenated are sorted
+/// by path component by component (`a/b.wav` comes before `a-b.wav`, unlike a
+/// plain string sort). A page is a contiguous slice of that order; pages never
+/// overlap and never skip entries while the cursor stays valid.
+///
+/// The cache is dropped when the root, the scan options or the mtime of any
+/// folder read so far changes. Cursors from a dropped cache are rejected.
+#[derive(Default)]
+pub struct HarborScan {
+    id: u64,
+    root: PathBuf,
+    options: ScanOptions,
+    patterns: Vec<glob::Pattern>,
+    files: Vec<String>,
+    frames: Vec<WalkFrame>,
+    visited: HashSet<PathBuf>,
+    dir_mtimes: HashMap<PathBuf, u64>,
+}
+
+impl HarborScan {
+    /// Up to `limit` files starting at `cursor` (the first page when None).
+    /// Without a limit the rest of the harbor is listed and `progress(scanned, done)`
+    /// is called as the walk goes.
+    pub fn page(
+        &mut self,
+        root: &Path,
+        options: &ScanOptions,
+        cursor: Option<&str>,
+        limit: Option<usize>,
+        mut progress: impl FnMut(usize, bool),
+    ) -> Result<HarborPage, String> {
+        let offset = match cursor {
+            Some(cursor) => {
+                let (id, offset) = parse_cursor(cursor)?;
+                if id != self.id || self.is_stale(root, options) {
+                    return Err(
+                        "[Social Noise] Harbor changed since this scan started; restart from the first page"
+                            .to_string(),
+                    );
+                }
+                offset
+            }
+            None => {
+                if self.is_stale(root, options) {
+                    self.reset(root, options)?;
+                }
+                0
+            }
+        };
+
+        let wanted = limit.map(|limit| offset + limit.max(1));
+        let mut reported = self.files.len();
+        // One file past the page, so the last page already comes without a cursor
+        while !self.frames.is_empty() && !matches!(wanted, Some(w) if self.files.len() > w) {
+            if let Err(e) = self.step() {
+                *self = HarborScan::default();
+                return Err(e);
+            }
+            if limit.is_none() && self.files.len() >= reported + SCAN_PROGRESS_EVERY {
+                reported = self.files.len();
+                progress(reported, false);
+            }
+        }
+
+        let finished = self.frames.is_empty();
+        if limit.is_none() {
+            progress(self.files.len(), true);
+        }
+        let end = wanted.map_or(self.files.len(), |w| w.min(self.files.len()));
+        Ok(HarborPage {
+            files: self.files[offset.min(end)..end].to_vec(),
+            cursor: if finished && end == self.files.len() {
+                None
+            } else {
+                Some(format!("{:x}-{}", self.id, end))
+            },
+            total: finished.then_some(self.files.len()),
+        })
+    }
+
+    fn is_stale(&self, root: &Path, options: &ScanOptions) -> bool {
+        self.root != root
+            || self.options != *options
+            || self.dir_mtimes.iter().any(|(dir, mtime)| {
+                fs::metadata(dir)
+                    .map(|m| modified_millis(&m) != *mtime)
+                    .unwrap_or(true)
+            })
+    }
+
+    fn reset(&mut self, root: &Path, options: &ScanOptions) -> Result<(), String> {
+        *self = HarborScan {
+            id: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or(0),
+            root: root.to_path_buf(),
+            options: options.clone(),
+            patterns: options.patterns(),
+            ..Default::default()
+        };
+        self.enter(root, 0)
+    }
+
+    /// Reads a folder and queues its entries, same filters as `scan_harbor`
+    fn enter(&mut self, dir: &Path, depth: usize) -> Result<(), String> {
+        // Symlinked folders may point back up the tree; never enter one twice
+        let canonical = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+        if !self.visited.insert(canonical) {
+            return Ok(());
+        }
+
+        let metadata =
+            fs::metadata(dir).map_err(|e| format!("[Social Noise] Harbor scan failed: {}", e))?;
+        let mut entries = Vec::new();
+        for entry in
+            fs::read_dir(dir).map_err(|e| format!("[Social Noise] Harbor scan failed: {}", e))?
+        {
+            let path = entry
+                .map_err(|e| format!("[Social Noise] Entry error: {}", e))?
+                .path();
+            let is_dir = path.is_dir();
+            entries.push((path, is_dir));
+        }
+        entries.sort_by_key(|(path, _)| {
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            (name.to_lowercase(), name)
+        });
+        entries.reverse();
+
+        self.dir_mtimes
+            .insert(dir.to_path_buf(), modified_millis(&metadata));
+        self.frames.push(WalkFrame { depth, entries });
+        Ok(())
+    }
+
+    /// Handles the next queued entry
+    fn step(&mut self) -> Result<(), String> {
+        let frame = match self.frames.last_mut() {
+            Some(frame) => frame,
+            None => return Ok(()),
+        };
+        let depth = frame.depth;
+        let (path, is_dir) = match frame.entries.pop() {
+            Some(entry) => entry,
+            None => {
+                self.frames.pop();
+                return Ok(());
+            }
+        };
+
+        let rel_path = match path.strip_prefix(&self.root) {
+            Ok(rel_path) => rel_path.to_path_buf(),
+            Err(_) => return Ok(()),
+        };
+        let name = rel_path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if !self.options.accepts_name(&name) || self.options.is_excluded(&self.patterns, &rel_path)
+        {
+            return Ok(());
+        }
+
+        if is_dir {
+            if !matches!(self.options.max_depth, Some(max) if depth >= max) {
+                self.enter(&path, depth + 1)?;
+            }
+        } else if self.options.accepts_extension(&path) {
+            self.files.push(to_api_path(&rel_path));
+        }
+        Ok(())
+    }
+}
+
+fn parse_cursor(cursor: &str) -> Result<(u64, usize), String> {
+    cursor
+        .split_once('-')
+        .and_then(|(id, offset)| Some((u64::from_str_radix(id, 16).ok()?, offset.parse().ok()?)))
+        .ok_or_else(|| format!("[Social Noise] Invalid scan cursor: {:?}", cursor))
+}
+
+/// Whether the path carries one of the supported audio extensions
+pub fn is_audio_file(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| AUDIO_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// `file_name` in `dir`, then the same suffixed " (1)", " (2)", ... before the extension
+fn destination_candidates<'a>(
+    dir: &'a Path,
+    file_name: &'a Path,
+) -> impl Iterator<Item = PathBuf> + 'a {
+    let stem = file_name
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let ext = file_name
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+
+    std::iter::once(dir.join(file_name))
+        .chain((1..).map(move |n| dir.join(format!("{} ({}){}", stem, n, ext))))
+}
+
+/// Picks a non-existing destination by suffixing " (1)", " (2)", ... before the extension
+pub fn unique_destination(dir: &Path, file_name: &Path) -> PathBuf {
+    destination_candidates(dir, file_name)
+        .find(|p| !p.exists())
+        .expect("unbounded suffix search")
+}
+
+/// Like `unique_destination`, but creates the file (empty) so two imports of
+/// the same name at once can never pick the same path
+pub fn reserve_destination(dir: &Path, file_name: &Path) -> Result<PathBuf, String> {
+    for candidate in destination_candidates(dir, file_name) {
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&candidate)
+        {
+            Ok(_) => return Ok(candidate),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(format!("[Social Noise] Import failed: {}", e)),
+        }
+    }
+    unreachable!("unbounded suffix search")
+}
+
+/// Copies (or moves) an external file into the harbor root, validating
+/// extension and size first. Name collisions are resolved by suffixing.
+pub fn import_file(
+    harbor: &Path,
+    source: &Path,
+    move_file: bool,
+    size_limit: u64,
+) -> Result<ImportedFile, String> {
+    if !is_audio_file(source) {
+        return Err(format!(
+            "[Social Noise] Unsupported file type: {:?} (expected one of {})",
+            source.file_name().unwrap_or_default(),
+            AUDIO_EXTENSIONS.join(", ")
+        ));
+    }
+
+    let metadata = fs::metadata(source)
+        .map_err(|e| format!("[Social Noise] Cannot read {:?}: {}", source, e))?;
+    if !metadata.is_file() {
+        return Err(format!("[Social Noise] Not a file: {:?}", source));
+    }
+    if metadata.len() > size_limit {
+        return Err(format!(
+            "[Social Noise] File is {} MB, above the {} MB import limit",
+            metadata.len() / (1024 * 1024),
+            size_limit / (1024 * 1024)
+        ));
+    }
+
+    let file_name = source.file_name().ok_or("Source has no file name")?;
+    let destination = reserve_destination(harbor, Path::new(file_name))?;
+    // A failed copy leaves no empty placeholder behind
+    let copy = |destination: &Path| {
+        fs::copy(source, destination).map_err(|e| {
+            let _ = fs::remove_file(destination);
+            format!("[Social Noise] Import failed: {}", e)
+        })
+    };
+
+    if move_file {
+        // rename fails across filesystems; fall back to copy + remove
+        if fs::rename(source, &destination).is_err() {
+            copy(&destination)?;
+            fs::remove_file(source)
+                .map_err(|e| format!("[Social Noise] Could not remove original: {}", e))?;
+        }
+    } else {
+        copy(&destination)?;
+    }
+
+    info!("Imported {:?} -> {:?}", source, destination);
+
+    let relative = api_path_in(harbor, &destination)?;
+
+    Ok(ImportedFile {
+        path: relative,
+        size: metadata.len(),
+        extension: destination
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default(),
+    })
+}
+
+/// Harbor-relative path as exposed over IPC: always `/`-separated, whatever
+/// the platform, so sessions and sidecars move between machines.
+pub fn to_api_path(relative: &Path) -> String {
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// `path` relative to the harbor, in API form
+pub fn api_path_in(harbor: &Path, path: &Path) -> Result<String, String> {
+    path.strip_prefix(harbor)
+        .map(to_api_path)
+        .map_err(|_| format!("{:?} is outside the harbor", path))
+}
+
+/// Joins a harbor-relative path onto the harbor root. Both `/` and `\` are
+/// accepted as separators; absolute paths, drive prefixes and `..` segments are
+/// rejected so the result can never escape the harbor.
+pub fn resolve_in_harbor(harbor: &Path, relative: &str) -> Result<PathBuf, String> {
+    let traversal = || Err("Path traversal detected".to_string());
+    if relative.starts_with(['/', '\\']) || Path::new(relative).is_absolute() {
+        return traversal();
+    }
+
+    let mut path = harbor.to_path_buf();
+    let mut segments = 0;
+    for segment in relative.split(['/', '\\']) {
+        match segment {
+            "" | "." => continue,
+            ".." => return traversal(),
+            _ => {
+                let mut components = Path::new(segment).components();
+                if !matches!(
+                    (components.next(), components.next()),
+                    (Some(Component::Normal(_)), None)
+                ) {
+                    return traversal();
+                }
+                path.push(segment);
+                segments += 1;
+            }
+        }
+    }
+    if segments == 0 {
+        return traversal();
+    }
+    Ok(path)
+}
+
+/// Moves a harbor file to the OS trash rather than unlinking it
+pub fn trash_file(path: &Path) -> Result<(), String> {
+    if !path.is_file() {
+        return Err(format!("File not found: {:?}", path));
+    }
+    trash::delete(path).map_err(|e| format!("[Social Noise] Move to trash failed: {}", e))?;
+    let sidecar = sidecar_path(path);
+    if sidecar.exists() {
+        let _ = trash::delete(&sidecar);
+    }
+    info!("Moved to trash: {:?}", path);
+    Ok(())
+}
+
+/// Renames or moves a file inside the harbor, creating destination folders.
+/// Returns the canonical harbor-relative path of the file's new location.
+pub fn rename_file(
+    harbor: &Path,
+    old_relative: &str,
+    new_relative: &str,
+    overwrite: bool,
+) -> Result<String, String> {
+    let source = resolve_in_harbor(harbor, old_relative)?;
+    let destination = resolve_in_harbor(harbor, new_relative)?;
+
+    if !source.is_file() {
+        return Err(format!("File not found: {:?}", source));
+    }
+    if !is_audio_file(&destination) {
+        return Err(format!(
+            "[Social Noise] Destination must keep an audio extension ({})",
+            AUDIO_EXTENSIONS.join(", ")
+        ));
+    }
+    if destination.exists() && !overwrite {
+        return Err(format!("[Social Noise] {:?} already exists", new_relative));
+    }
+
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("[Social Noise] Rename failed: {}", e))?;
+    }
+    fs::rename(&source, &destination)
+        .map_err(|e| format!("[Social Noise] Rename failed: {}", e))?;
+    // Settings travel with the sample
+    let sidecar = sidecar_path(&source);
+    if sidecar.exists() {
+        let _ = fs::rename(&sidecar, sidecar_path(&destination));
+    }
+    info!("Renamed {:?} -> {:?}", source, destination);
+
+    api_path_in(harbor, &destination)
+}
+
+fn modified_millis(metadata: &fs::Metadata) -> u64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+pub fn hash_file(path: &Path) -> Result<String, String> {
+    let mut file = fs::File::open(path)
+        .map_err(|e| format!("[Social Noise] Cannot read {:?}: {}", path, e))?;
+    let mut hasher = blake3::Hasher::new();
+    std::io::copy(&mut file, &mut hasher)
+        .map_err(|e| format!("[Social Noise] Cannot read {:?}: {}", path, e))?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+/// Groups byte-identical harbor files. Only files sharing a size get hashed;
+/// `progress(done, total)` is called after each hash.
+pub fn find_duplicates(
+    dir_path: &PathBuf,
+    options: &ScanOptions,
+    cache: &mut HashCache,
+    mut progress: impl FnMut(usize, usize),
+) -> Result<Vec<DuplicateGroup>, String> {
+    // 1. Size pre-filter
+    let mut by_size: HashMap<u64, Vec<(String, u64)>> = HashMap::new();
+    for rel_path in scan_harbor(dir_path, options)?.files {
+        if let Ok(metadata) = fs::metadata(dir_path.join(&rel_path)) {
+            by_size
+                .entry(metadata.len())
+                .or_default()
+                .push((rel_path, modified_millis(&metadata)));
+        }
+    }
+    by_size.retain(|_, files| files.len() > 1);
+
+    // 2. Hash the candidates, reusing cached hashes for untouched files
+    let total: usize = by_size.values().map(|files| files.len()).sum();
+    let mut done = 0;
+    let mut by_hash: HashMap<(u64, String), Vec<String>> = HashMap::new();
+    for (size, files) in by_size {
+        for (rel_path, mtime) in files {
+            let path = dir_path.join(&rel_path);
+            let hash = match cache.hash_with(&path, size, mtime) {
+                Ok(hash) => hash,
+                Err(e) => {
+                    warn!("{}", e);
+                    continue;
+                }
+            };
+            by_hash.entry((size, hash)).or_default().push(rel_path);
+            done += 1;
+            progress(done, total);
+        }
+    }
+
+    let mut groups: Vec<DuplicateGroup> = by_hash
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .map(|((size, hash), mut paths)| {
+            paths.sort_by_key(|p| (p.len(), p.to_lowercase()));
+            DuplicateGroup { hash, size, paths }
+        })
+        .collect();
+    groups.sort_by_key(|group| std::cmp::Reverse(group.size));
+    Ok(groups)
+}
+
+/// `kick.wav` -> `kick.wav.lsamp.json`
+pub fn sidecar_path(sample: &Path) -> PathBuf {
+    let mut name = sample.as_os_str().to_os_string();
+    name.push(SIDECAR_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// Reads a sample's sidecar. Missing or corrupt sidecars yield `None`.
+pub fn load_sidecar(sample: &Path) -> Option<SampleSettings> {
+    let path = sidecar_path(sample);
+    let contents = fs::read_to_string(&path).ok()?;
+    match serde_json::from_str(&contents) {
+        Ok(settings) => Some(settings),
+        Err(e) => {
+            warn!("Ignoring corrupt sidecar {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+/// Writes a sample's sidecar through a temp file so a crash never leaves half a file
+pub fn save_sidecar(sample: &Path, settings: &SampleSettings) -> Result<(), String> {
+    if !sample.is_file() {
+        return Err(format!("File not found: {:?}", sample));
+    }
+    let path = sidecar_path(sample);
+    let tmp = path.with_extension("json.tmp");
+    let json = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+    fs::write(&tmp, json).map_err(|e| format!("[Social Noise] Sidecar write failed: {}", e))?;
+    fs::rename(&tmp, &path).map_err(|e| format!("[Social Noise] Sidecar write failed: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty folder of its own in the temp directory
+    fn harbor(name: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("lsamp-harbor-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    #[test]
+    fn api_paths_use_forward_slashes() {
+        let native: PathBuf = ["Drums", "Kicks", "kick.wav"].iter().collect();
+        assert_eq!(to_api_path(&native), "Drums/Kicks/kick.wav");
+        let root = harbor("api");
+        assert_eq!(
+            api_path_in(&root, &root.join(&native)).unwrap(),
+            "Drums/Kicks/kick.wav"
+        );
+        assert!(api_path_in(&root, Path::new("/elsewhere/kick.wav")).is_err());
+    }
+
+    #[test]
+    fn either_separator_resolves_to_the_same_file() {
+        let root = harbor("separators");
+        let kick = root.join("Drums").join("Kicks").join("kick.wav");
+        fs::create_dir_all(kick.parent().unwrap()).unwrap();
+        fs::write(&kick, b"").unwrap();
+        for relative in [
+            "Drums/Kicks/kick.wav",
+            "Drums\\Kicks\\kick.wav",
+            "Drums\\Kicks/kick.wav",
+            "./Drums//Kicks/kick.wav",
+        ] {
+            let path = resolve_in_harbor(&root, relative).unwrap();
+            assert_eq!(path, kick, "{:?}", relative);
+            assert!(path.is_file());
+            assert_eq!(api_path_in(&root, &path).unwrap(), "Drums/Kicks/kick.wav");
+        }
+    }
+
+    #[test]
+    fn paths_leaving_the_harbor_are_rejected() {
+        let root = harbor("traversal");
+        for relative in [
+            "..",
+            "../kick.wav",
+            "Drums/../../kick.wav",
+            "Drums\\..\\kick.wav",
+            "..\\..\\etc\\passwd",
+            "/etc/passwd",
+            "\\Windows\\kick.wav",
+            "",
+            "./",
+        ] {
+            assert!(
+                resolve_in_harbor(&root, relative).is_err(),
+                "{:?}",
+                relative
+            );
+        }
+    }
+
+    /// A harbor holding `files` (empty files, folders made as needed)
+    fn harbor_with(name: &str, files: &[&str]) -> PathBuf {
+        let root = harbor(name);
+        for file in files {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, b"").unwrap();
+        }
+        root
+    }
+
+    /// Every page of `limit` files, in order, with the total the last one reports
+    fn all_pages(scan: &mut HarborScan, root: &Path, limit: usize) -> (Vec<Vec<String>>, usize) {
+        let options = ScanOptions::default();
+        let mut pages = Vec::new();
+        let mut cursor = None;
+        loop {
+            let page = scan
+                .page(root, &options, cursor.as_deref(), Some(limit), |_, _| {})
+                .unwrap();
+            pages.push(page.files);
+            match page.cursor {
+                Some(next) => cursor = Some(next),
+                None => return (pages, page.total.unwrap()),
+            }
+        }
+    }
+
+    #[test]
+    fn pages_follow_the_documented_order() {
+        let root = harbor_with(
+            "pages",
+            &[
+                "b.wav",
+                "A.wav",
+                "a-b.wav",
+                "a/b.wav",
+                "a/C.flac",
+                "a/a.mp3",
+                "c/d/e.ogg",
+                "notes.txt",
+                ".hidden.wav",
+            ],
+        );
+        let sorted = [
+            "a/a.mp3",
+            "a/b.wav",
+            "a/C.flac",
+            "a-b.wav",
+            "A.wav",
+            "b.wav",
+            "c/d/e.ogg",
+        ];
+        for limit in [1, 2, 3, 7, 100] {
+            let (pages, total) = all_pages(&mut HarborScan::default(), &root, limit);
+            assert_eq!(pages.concat(), sorted, "pages of {}", limit);
+            assert_eq!(total, sorted.len());
+            assert!(pages.iter().all(|page| page.len() <= limit));
+            assert_eq!(pages.len(), sorted.len().div_ceil(limit));
+        }
+        // Paging again is served from the cache, in the same order
+        let mut scan = HarborScan::default();
+        let first = all_pages(&mut scan, &root, 3);
+        assert_eq!(all_pages(&mut scan, &root, 2).0.concat(), first.0.concat());
+    }
+
+    #[test]
+    fn a_changed_folder_invalidates_its_cursors() {
+        let root = harbor_with("stale", &["a/1.wav", "a/2.wav", "b.wav"]);
+        let options = ScanOptions::default();
+        let mut scan = HarborScan::default();
+        let page = scan
+            .page(&root, &options, None, Some(1), |_, _| {})
+            .unwrap();
+        assert_eq!(page.files, ["a/1.wav"]);
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        fs::write(root.join("a").join("0.wav"), b"").unwrap();
+        let cursor = page.cursor.unwrap();
+        assert!(scan
+            .page(&root, &options, Some(&cursor), Some(1), |_, _| {})
+            .is_err());
+        let (pages, total) = all_pages(&mut scan, &root, 10);
+        assert_eq!(pages.concat(), ["a/0.wav", "a/1.wav", "a/2.wav", "b.wav"]);
+        assert_eq!(total, 4);
+        // Other options are another listing too
+        let page = scan
+            .page(&root, &options, None, Some(1), |_, _| {})
+            .unwrap();
+        let hidden = ScanOptions {
+            include_hidden: true,
+            ..Default::default()
+        };
+        let cursor = page.cursor.unwrap();
+        assert!(scan
+            .page(&root, &hidden, Some(&cursor), Some(1), |_, _| {})
+            .is_err());
+    }
+}
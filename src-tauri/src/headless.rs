@@ -0,0 +1,209 @@
+/**
+ * headless.rs
+ * L-SAMP 100 | Headless Mode
+ *
+ * `lsamp-100 --headless ~/kits/gig.json` runs the engine with no window or
+ * webview, e.g. on a Raspberry Pi next to the mixer: it opens the audio
+ * device, restores the session, listens to the keyboard (or with
+ * `--midi PORT`, to that MIDI input instead) and runs until Ctrl+C,
+ * logging meter summaries and errors to the console and the log file.
+ *
+ * Pads toggle like the pad grid does: a press starts the pad with its
+ * session params, a second press stops it with its release. The rest of
+ * the app is not there, so headless runs have:
+ * - default key bindings, capture settings and listener toggle combo (the
+ *   frontend config never arrives); the saved MIDI mapping does apply
+ * - no harbor watcher, tray, autosave, deep links, single-instance check,
+ *   remote control or gamepad input
+ * - no frontend events: the ones worth knowing about are logged instead
+ * - on Linux, keyboard input only inside an X session (use `--midi` on a
+ *   bare console)
+ */
+use serde::Serialize;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::audio_engine::{AudioEngine, PartialPlayParams};
+use crate::cli::HeadlessOptions;
+use crate::keymap::KeyBindings;
+use crate::midi::{self, MidiState};
+use crate::notifications::Notifications;
+use crate::{
+    config_or_default, midi_input, report_config_problems, restore_session, session,
+    start_background_listener, start_callback_monitor, start_stream_faults, start_voice_events,
+    BankSelector, Host, HotkeyBackend, HotkeyRegistry,
+};
+
+/// How often the meter summary is printed while anything plays
+const METER_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Events that still mean something without a frontend: (name, is an error)
+const LOGGED_EVENTS: &[(&str, bool)] = &[
+    ("listener-error", true),
+    ("input-permission-missing", true),
+    ("listener-toggled", false),
+    ("bank-changed", false),
+    ("analysis-complete", false),
+    ("sample-rate-changed", false),
+];
+
+/// The app state a headless run owns, in place of Tauri's managed state
+#[derive(Clone)]
+struct Headless(Arc<Parts>);
+
+struct Parts {
+    audio: AudioEngine,
+    banks: BankSelector,
+    registry: HotkeyRegistry,
+    key_bindings: Mutex<KeyBindings>,
+    notifications: Notifications,
+}
+
+impl Host for Headless {
+    fn audio(&self) -> &AudioEngine {
+        &self.0.audio
+    }
+
+    fn banks(&self) -> &BankSelector {
+        &self.0.banks
+    }
+
+    fn registry(&self) -> &HotkeyRegistry {
+        &self.0.registry
+    }
+
+    fn key_bindings(&self) -> &Mutex<KeyBindings> {
+        &self.0.key_bindings
+    }
+
+    fn notifications(&self) -> &Notifications {
+        &self.0.notifications
+    }
+
+    fn emit_event<S: Serialize + Clone>(&self, event: &str, payload: S) {
+        if let Some(&(_, error)) = LOGGED_EVENTS.iter().find(|(name, _)| *name == event) {
+            let payload = serde_json::to_string(&payload).unwrap_or_default();
+            if error {
+                error!("{}: {}", event, payload);
+            } else {
+                info!("{}: {}", event, payload);
+            }
+        }
+    }
+
+    fn trigger_pad(&self, pad: String, pressed: bool, velocity: f32) {
+        if !pressed {
+            return;
+        }
+        let audio = self.audio();
+        let bank = self.banks().active.load(Ordering::Relaxed);
+        let playing = audio.get_levels(bank).all_active_keys.contains(&pad);
+        let result = if playing {
+            audio.stop_sound(pad.clone(), None).map(drop)
+        } else {
+            let partial = PartialPlayParams {
+                velocity: Some(velocity),
+                ..Default::default()
+            };
+            // The session's last params are what the pad grid would play
+            match audio.pad_params(&pad) {
+                Some(params) => Ok(partial.over(params)),
+                None => audio.resolve_play_params(&pad, partial),
+            }
+            .and_then(|params| audio.play_sound(pad.clone(), params))
+        };
+        if let Err(e) = result {
+            warn!("Pad {}: {}", pad, e);
+        }
+    }
+}
+
+/// Runs until Ctrl+C. Errors are the ones that prevent the start.
+pub fn run(options: HeadlessOptions) -> Result<(), String> {
+    info!("Headless: {}", options.session.display());
+    let saved = session::load_file(&options.session)?;
+    let host = Headless(Arc::new(Parts {
+        audio: AudioEngine::new()?,
+        banks: BankSelector::default(),
+        registry: HotkeyRegistry::new(),
+        key_bindings: Mutex::new(KeyBindings::default()),
+        notifications: Notifications::default(),
+    }));
+    // `notify` has already logged these; the event itself goes nowhere
+    let mut config_problems = Vec::new();
+    let pad_defaults = config_or_default(
+        session::load_pad_defaults(),
+        "pad_defaults.json",
+        &mut config_problems,
+    );
+    let midi_mapping = config_or_default(midi::load_mapping(), "midi.json", &mut config_problems);
+    report_config_problems(&host, config_problems);
+    host.audio().replace_pad_defaults(pad_defaults);
+    start_voice_events(host.clone());
+    start_stream_faults(host.clone());
+    start_callback_monitor(host.clone());
+
+    tauri::async_runtime::block_on(async {
+        let report = restore_session(saved, &host).await?;
+        info!(
+            "Session {:?}: {} pads loaded, {} failed",
+            report.name,
+            report.loaded,
+            report.failed.len()
+        );
+
+        // Kept alive for as long as the run: dropping it closes the port
+        let mut midi_state = MidiState::new(midi_mapping);
+        match options.midi_port.as_deref() {
+            Some(port) => {
+                midi_state.open(port, midi_input(host.clone()))?;
+                info!("Pads follow MIDI input {:?}", port);
+            }
+            None if host.registry().backend == HotkeyBackend::Global => {
+                start_background_listener(host.clone());
+            }
+            None => {
+                return Err(
+                    "[Social Noise] No global keyboard on Wayland; use --midi PORT".to_string(),
+                )
+            }
+        }
+
+        let mut meter = tokio::time::interval(METER_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => break,
+                _ = meter.tick() => print_meter(&host),
+            }
+        }
+        info!("Headless run stopped");
+        host.audio().stop_all();
+        Ok(())
+    })
+}
+
+/// One line per interval with the playing pads and their peaks; silent when idle
+fn print_meter(host: &Headless) {
+    let bank = host.banks().active.load(Ordering::Relaxed);
+    let levels = host.audio().get_levels(bank);
+    if levels.all_active_keys.is_empty() {
+        return;
+    }
+    let mut pads: Vec<String> = levels
+        .all_active_keys
+        .iter()
+        .map(|key| {
+            let peak = levels.all_data.get(key).map_or(0.0, |data| data.peak);
+            format!("{} {:.2}", key, peak)
+        })
+        .collect();
+    pads.sort();
+    info!(
+        "Bank {} | {} playing | {}",
+        bank + 1,
+        pads.len(),
+        pads.join("  ")
+    );
+}
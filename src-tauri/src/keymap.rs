@@ -0,0 +1,459 @@
+/**
+ * keymap.rs
+ * L-SAMP 100 | Hotkey Bindings
+ *
+ * Maps physical keys (rdev `Key` names such as "KeyQ", "Num1", "F5") to the
+ * pad slots of the grid, the SPACE stop-all action and the bank switches
+ * BANK1..BANK4. The global listener
+ * resolves every key press through the managed `KeyBindings`; the frontend
+ * persists the mapping in its config and pushes it back via `apply_config`.
+ * A `Combo` ("Ctrl+Alt+KeyL") describes the listener's own on/off switch.
+ * The numpad can be switched on as a second, fixed grid of aliases.
+ *
+ * Key names always mean US-QWERTY physical positions, like the browser's
+ * `KeyboardEvent.code`. rdev reports hardware keycodes on macOS and Linux,
+ * but layout-dependent virtual keys on Windows; there `KeyboardLayout`
+ * translates them back to positions before the bindings are consulted.
+ */
+use rdev::Key;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Pad slots of one bank, in grid order
+pub const PAD_KEYS: [&str; 12] = ["Q", "W", "E", "R", "A", "S", "D", "F", "Z", "X", "C", "V"];
+
+/// Binding target of the global stop
+pub const STOP_ALL: &str = "SPACE";
+
+/// Binding targets that switch the active bank (BANK1 selects bank 0)
+pub const BANK_KEYS: [&str; 4] = ["BANK1", "BANK2", "BANK3", "BANK4"];
+
+/// One entry of `get_key_bindings`
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyBinding {
+    pub pad: String, // Pad slot ("Q".."V"), "SPACE" or "BANK1".."BANK4"
+    pub key: String, // rdev key name
+}
+
+/// Numpad aliases, laid out like the letter grid (7 8 9 - / 4 5 6 + / 1 2 3 Enter / 0)
+const NUMPAD: [(Key, &str); 13] = [
+    (Key::Kp7, "Q"),
+    (Key::Kp8, "W"),
+    (Key::Kp9, "E"),
+    (Key::KpMinus, "R"),
+    (Key::Kp4, "A"),
+    (Key::Kp5, "S"),
+    (Key::Kp6, "D"),
+    (Key::KpPlus, "F"),
+    (Key::Kp1, "Z"),
+    (Key::Kp2, "X"),
+    (Key::Kp3, "C"),
+    (Key::Kp0, "V"),
+    (Key::KpReturn, STOP_ALL),
+];
+
+/// What Windows reports for the digit keys with NumLock off (macOS has no
+/// NumLock and Linux reports keycodes, so both always send the Kp keys).
+/// rdev cannot tell these from the dedicated navigation keys, which then
+/// fire pads as well while the numpad grid is on.
+const NUMPAD_NUMLOCK_OFF: [(Key, Key); 10] = [
+    (Key::End, Key::Kp1),
+    (Key::DownArrow, Key::Kp2),
+    (Key::PageDown, Key::Kp3),
+    (Key::LeftArrow, Key::Kp4),
+    (Key::Unknown(0x0C), Key::Kp5), // VK_CLEAR
+    (Key::RightArrow, Key::Kp6),
+    (Key::Home, Key::Kp7),
+    (Key::UpArrow, Key::Kp8),
+    (Key::PageUp, Key::Kp9),
+    (Key::Insert, Key::Kp0),
+];
+
+#[derive(Debug, Clone)]
+pub struct KeyBindings {
+    bindings: Vec<(&'static str, Key)>, // Grid order, then STOP_ALL, then the bank keys
+    numpad: bool,                       // Also resolve the `NUMPAD` aliases
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            bindings: targets().map(|pad| (pad, default_key(pad))).collect(),
+            numpad: false,
+        }
+    }
+}
+
+impl KeyBindings {
+    /// Builds the mapping from the persisted `pad -> key name` table.
+    /// Pads missing from the table keep their default key.
+    pub fn from_config(table: &HashMap<String, String>) -> Result<Self, String> {
+        let mut bindings = Vec::new();
+        for pad in targets() {
+            let key = match table.iter().find(|(p, _)| p.eq_ignore_ascii_case(pad)) {
+                Some((_, name)) => parse_key(name)?,
+                None => default_key(pad),
+            };
+            bindings.push((pad, key));
+        }
+        for name in table.keys() {
+            target(name)?;
+        }
+
+        let mut seen = HashSet::new();
+        for (pad, key) in &bindings {
+            if !seen.insert(*key) {
+                return Err(format!(
+                    "[Social Noise] Key {} is bound twice (again on {})",
+                    key_name(*key),
+                    pad
+                ));
+            }
+        }
+        Ok(Self {
+            bindings,
+            numpad: false,
+        })
+    }
+
+    pub fn numpad(&self) -> bool {
+        self.numpad
+    }
+
+    /// Switches the numpad grid on or off, independently of the main bindings
+    pub fn set_numpad(&mut self, enabled: bool) {
+        self.numpad = enabled;
+    }
+
+    /// Every main binding as (target, key), in grid order
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, Key)> + '_ {
+        self.bindings.iter().copied()
+    }
+
+    /// Target (pad slot, STOP_ALL or bank key) a physical key triggers.
+    /// The main bindings win over numpad aliases.
+    pub fn pad_for(&self, key: Key) -> Option<&'static str> {
+        self.bindings
+            .iter()
+            .find(|(_, bound)| *bound == key)
+            .map(|(pad, _)| *pad)
+            .or_else(|| numpad_target(key).filter(|_| self.numpad))
+    }
+
+    /// Rebinds one pad. A key already used by another pad is rejected.
+    pub fn set(&mut self, pad: &str, key_name: &str) -> Result<(), String> {
+        let pad = target(pad)?;
+        let key = parse_key(key_name)?;
+        if let Some(other) = self.pad_for(key).filter(|other| *other != pad) {
+            return Err(format!(
+                "[Social Noise] Key {} is already bound to {}",
+                key_name, other
+            ));
+        }
+        for binding in self.bindings.iter_mut().filter(|(p, _)| *p == pad) {
+            binding.1 = key;
+        }
+        Ok(())
+    }
+
+    pub fn list(&self) -> Vec<KeyBinding> {
+        self.bindings
+            .iter()
+            .map(|(pad, key)| KeyBinding {
+                pad: pad.to_string(),
+                key: key_name(*key),
+            })
+            .collect()
+    }
+}
+
+/// Key plus exact modifier state, e.g. "Ctrl+Alt+KeyL"
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Combo {
+    pub key: Key,
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+}
+
+impl Default for Combo {
+    /// Ctrl+Alt+L, the listener toggle
+    fn default() -> Self {
+        Self {
+            key: Key::KeyL,
+            shift: false,
+            ctrl: true,
+            alt: true,
+        }
+    }
+}
+
+impl Combo {
+    /// Parses "+"-separated modifiers (Shift, Ctrl, Alt) followed by one rdev key name
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+        let key = parse_key(parts.pop().unwrap_or_default())?;
+        let mut combo = Self {
+            key,
+            shift: false,
+            ctrl: false,
+            alt: false,
+        };
+        for part in parts {
+            let flag = match part.to_ascii_lowercase().as_str() {
+                "shift" => &mut combo.shift,
+                "ctrl" | "control" => &mut combo.ctrl,
+                "alt" => &mut combo.alt,
+                _ => return Err(format!("[Social Noise] Unknown modifier: {:?}", part)),
+            };
+            *flag = true;
+        }
+        Ok(combo)
+    }
+
+    pub fn matches(&self, key: Key, shift: bool, ctrl: bool, alt: bool) -> bool {
+        self.key == key && self.shift == shift && self.ctrl == ctrl && self.alt == alt
+    }
+}
+
+/// Keyboard layout the OS reports keys in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyboardLayout {
+    #[default]
+    Physical, // Take rdev keys as positions
+    Qwerty,
+    Azerty,
+    Qwertz,
+}
+
+/// rdev hands out hardware keycodes here, so no layout needs undoing
+const REPORTS_PHYSICAL_KEYS: bool = cfg!(not(target_os = "windows"));
+
+/// Reported key -> US-QWERTY position, French AZERTY
+const AZERTY: [(Key, Key); 6] = [
+    (Key::KeyA, Key::KeyQ),
+    (Key::KeyZ, Key::KeyW),
+    (Key::KeyQ, Key::KeyA),
+    (Key::KeyM, Key::SemiColon),
+    (Key::KeyW, Key::KeyZ),
+    (Key::Comma, Key::KeyM),
+];
+
+/// Reported key -> US-QWERTY position, German QWERTZ
+const QWERTZ: [(Key, Key); 2] = [(Key::KeyZ, Key::KeyY), (Key::KeyY, Key::KeyZ)];
+
+impl KeyboardLayout {
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Self::Qwerty,
+            2 => Self::Azerty,
+            3 => Self::Qwertz,
+            _ => Self::Physical,
+        }
+    }
+
+    /// Physical position of a reported key, the space bindings are written in
+    pub fn to_physical(self, key: Key) -> Key {
+        if REPORTS_PHYSICAL_KEYS {
+            return key;
+        }
+        self.position(key)
+    }
+
+    /// US-QWERTY position of a virtual key in this layout
+    fn position(self, key: Key) -> Key {
+        let table: &[(Key, Key)] = match self {
+            Self::Physical | Self::Qwerty => &[],
+            Self::Azerty => &AZERTY,
+            Self::Qwertz => &QWERTZ,
+        };
+        table
+            .iter()
+            .find(|(reported, _)| *reported == key)
+            .map_or(key, |(_, physical)| *physical)
+    }
+}
+
+fn numpad_target(key: Key) -> Option<&'static str> {
+    let key = if cfg!(target_os = "windows") {
+        NUMPAD_NUMLOCK_OFF
+            .iter()
+            .find(|(reported, _)| *reported == key)
+            .map_or(key, |(_, digit)| *digit)
+    } else {
+        key
+    };
+    NUMPAD
+        .iter()
+        .find(|(alias, _)| *alias == key)
+        .map(|(_, pad)| *pad)
+}
+
+/// Every binding target: pad slots in grid order, STOP_ALL, then the bank keys
+pub fn targets() -> impl Iterator<Item = &'static str> {
+    PAD_KEYS
+        .iter()
+        .copied()
+        .chain(std::iter::once(STOP_ALL))
+        .chain(BANK_KEYS.iter().copied())
+}
+
+/// Bank index selected by a bank key target
+pub fn bank_index(target: &str) -> Option<u32> {
+    BANK_KEYS
+        .iter()
+        .position(|bank| *bank == target)
+        .map(|i| i as u32)
+}
+
+/// Canonical binding target for a case-insensitive name
+pub fn target(name: &str) -> Result<&'static str, String> {
+    targets()
+        .find(|pad| pad.eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("[Social Noise] Unknown pad: {:?}", name))
+}
+
+fn default_key(pad: &str) -> Key {
+    match pad {
+        "Q" => Key::KeyQ,
+        "W" => Key::KeyW,
+        "E" => Key::KeyE,
+        "R" => Key::KeyR,
+        "A" => Key::KeyA,
+        "S" => Key::KeyS,
+        "D" => Key::KeyD,
+        "F" => Key::KeyF,
+        "Z" => Key::KeyZ,
+        "X" => Key::KeyX,
+        "C" => Key::KeyC,
+        "V" => Key::KeyV,
+        "BANK1" => Key::Num1,
+        "BANK2" => Key::Num2,
+        "BANK3" => Key::Num3,
+        "BANK4" => Key::Num4,
+        _ => Key::Space,
+    }
+}
+
+/// rdev key from its variant name, e.g. "KeyQ" or "Space"
+pub fn parse_key(name: &str) -> Result<Key, String> {
+    serde_json::from_value(serde_json::Value::String(name.to_string()))
+        .map_err(|_| format!("[Social Noise] Unknown key name: {:?}", name))
+}
+
+/// The W3C `KeyboardEvent.code` name of a key, which global shortcuts take,
+/// e.g. "Digit1" for rdev's "Num1"
+pub fn code_name(key: Key) -> String {
+    let name = key_name(key);
+    let renamed = match name.as_str() {
+        "Return" => "Enter",
+        "KpReturn" => "NumpadEnter",
+        "KpMinus" => "NumpadSubtract",
+        "KpPlus" => "NumpadAdd",
+        "KpMultiply" => "NumpadMultiply",
+        "KpDivide" => "NumpadDivide",
+        "KpDelete" => "NumpadDecimal",
+        "SemiColon" => "Semicolon",
+        "BackQuote" => "Backquote",
+        "LeftBracket" => "BracketLeft",
+        "RightBracket" => "BracketRight",
+        "BackSlash" => "Backslash",
+        "Dot" => "Period",
+        "UpArrow" => "ArrowUp",
+        "DownArrow" => "ArrowDown",
+        "LeftArrow" => "ArrowLeft",
+        "RightArrow" => "ArrowRight",
+        _ => {
+            if let Some(digit) = name.strip_prefix("Num").filter(|d| d.len() == 1) {
+                return format!("Digit{}", digit);
+            }
+            if let Some(digit) = name.strip_prefix("Kp").filter(|d| d.len() == 1) {
+                return format!("Numpad{}", digit);
+            }
+            return name;
+        }
+    };
+    renamed.to_string()
+}
+
+pub fn key_name(key: Key) -> String {
+    match serde_json::to_value(key) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => format!("{:?}", key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What each layout's virtual keys read on the pad grid's twelve keys, row by row
+    const GRID_AS_TYPED: [(KeyboardLayout, [Key; 12]); 4] = {
+        use Key::*;
+        [
+            (
+                KeyboardLayout::Physical,
+                [
+                    KeyQ, KeyW, KeyE, KeyR, KeyA, KeyS, KeyD, KeyF, KeyZ, KeyX, KeyC, KeyV,
+                ],
+            ),
+            (
+                KeyboardLayout::Qwerty,
+                [
+                    KeyQ, KeyW, KeyE, KeyR, KeyA, KeyS, KeyD, KeyF, KeyZ, KeyX, KeyC, KeyV,
+                ],
+            ),
+            (
+                KeyboardLayout::Azerty,
+                [
+                    KeyA, KeyZ, KeyE, KeyR, KeyQ, KeyS, KeyD, KeyF, KeyW, KeyX, KeyC, KeyV,
+                ],
+            ),
+            (
+                KeyboardLayout::Qwertz,
+                [
+                    KeyQ, KeyW, KeyE, KeyR, KeyA, KeyS, KeyD, KeyF, KeyY, KeyX, KeyC, KeyV,
+                ],
+            ),
+        ]
+    };
+
+    #[test]
+    fn every_layout_plays_the_grid_by_position() {
+        let bindings = KeyBindings::default();
+        for (layout, typed) in GRID_AS_TYPED {
+            let pads: Vec<_> = typed
+                .iter()
+                .map(|&key| bindings.pad_for(layout.position(key)))
+                .collect();
+            let expected: Vec<_> = PAD_KEYS.iter().map(|&pad| Some(pad)).collect();
+            assert_eq!(pads, expected, "{:?}", layout);
+        }
+    }
+
+    #[test]
+    fn keys_off_the_grid_keep_their_position() {
+        assert_eq!(KeyboardLayout::Azerty.position(Key::KeyM), Key::SemiColon);
+        assert_eq!(KeyboardLayout::Azerty.position(Key::Comma), Key::KeyM);
+        assert_eq!(KeyboardLayout::Qwertz.position(Key::KeyZ), Key::KeyY);
+        for layout in [KeyboardLayout::Azerty, KeyboardLayout::Qwertz] {
+            assert_eq!(layout.position(Key::Space), Key::Space);
+            assert_eq!(layout.position(Key::Num1), Key::Num1);
+        }
+    }
+
+    #[test]
+    fn hardware_keycodes_pass_untranslated() {
+        for (layout, typed) in GRID_AS_TYPED {
+            for key in typed {
+                let expected = if REPORTS_PHYSICAL_KEYS {
+                    key
+                } else {
+                    layout.position(key)
+                };
+                assert_eq!(layout.to_physical(key), expected);
+            }
+        }
+    }
+}
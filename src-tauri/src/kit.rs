@@ -0,0 +1,445 @@
+/**
+ * kit.rs
+ * L-SAMP 100 | Kit Archives
+ *
+ * A kit is a zip holding a session plus every audio file it references,
+ * so a performance setup can move between machines. Layout:
+ *   manifest.json   format version, file list with hashes
+ *   session.json    the Session, pad paths rewritten to archive paths
+ *   audio/...       files that came from the harbor (harbor-relative)
+ *   external/...    files that lived anywhere else
+ */
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use tracing::info;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::harbor::{api_path_in, hash_file, to_api_path, unique_destination, HashCache};
+use crate::session::{Session, SessionLoadReport};
+
+/// Bumped whenever the archive layout changes incompatibly
+pub const KIT_FORMAT_VERSION: u32 = 1;
+pub const KIT_EXTENSION: &str = "lsampkit";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KitFile {
+    pub path: String, // Path inside the archive
+    pub hash: String, // blake3 of the contents
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KitManifest {
+    pub format_version: u32,
+    pub name: String,
+    pub files: Vec<KitFile>,
+}
+
+/// Progress payload for `kit-export-progress`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KitProgress {
+    pub done_bytes: u64,
+    pub total_bytes: u64,
+    pub current: String,
+}
+
+/// One archive entry that was not extracted as-is
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KitEntryNote {
+    pub path: String,
+    pub reason: String,
+}
+
+/// Outcome of `kit_import`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KitImportSummary {
+    pub kit_name: String,
+    pub folder: String,        // Harbor-relative folder the kit was extracted into
+    pub imported: Vec<String>, // Harbor-relative paths of extracted files
+    pub skipped: Vec<KitEntryNote>, // Already in the harbor (same hash)
+    pub failed: Vec<KitEntryNote>,
+    pub warnings: Vec<String>,
+    pub session: Option<SessionLoadReport>,
+}
+
+/// Archive path for a pad's file: harbor files keep their relative layout,
+/// anything else is flattened into `external/` with a numeric suffix on clashes.
+fn archive_path(file: &Path, harbor: &Path, taken: &mut HashMap<String, PathBuf>) -> String {
+    let base = match file.strip_prefix(harbor) {
+        Ok(rel) => format!("audio/{}", to_api_path(rel)),
+        Err(_) => format!(
+            "external/{}",
+            file.file_name().unwrap_or_default().to_string_lossy()
+        ),
+    };
+
+    let mut candidate = base.clone();
+    let mut n = 1;
+    while let Some(owner) = taken.get(&candidate) {
+        if owner == file {
+            return candidate;
+        }
+        let (stem, ext) = match base.rsplit_once('.') {
+            Some((stem, ext)) => (stem.to_string(), format!(".{}", ext)),
+            None => (base.clone(), String::new()),
+        };
+        candidate = format!("{} ({}){}", stem, n, ext);
+        n += 1;
+    }
+    taken.insert(candidate.clone(), file.to_path_buf());
+    candidate
+}
+
+/// Writes `session` and its audio into a kit archive at `destination`
+pub fn export_kit(
+    destination: &Path,
+    harbor: &Path,
+    mut session: Session,
+    mut progress: impl FnMut(KitProgress),
+) -> Result<KitManifest, String> {
+    // 1. Plan: map every distinct source file to an archive path
+    let mut taken = HashMap::new();
+    let mut sources: Vec<(PathBuf, String)> = Vec::new();
+    for pad in session.pads.iter_mut() {
+        let source = PathBuf::from(&pad.path);
+        if !source.is_file() {
+            return Err(format!(
+                "[Social Noise] Pad {} file is missing: {}",
+                pad.key, pad.path
+            ));
+        }
+        let in_archive = archive_path(&source, harbor, &mut taken);
+        if !sources.iter().any(|(_, p)| *p == in_archive) {
+            sources.push((source, in_archive.clone()));
+        }
+        pad.path = in_archive;
+    }
+
+    let total_bytes = sources
+        .iter()
+        .filter_map(|(source, _)| fs::metadata(source).ok())
+        .map(|m| m.len())
+        .sum();
+
+    // 2. Write audio first, then the session and manifest describing it
+    let file = fs::File::create(destination)
+        .map_err(|e| format!("[Social Noise] Cannot create {:?}: {}", destination, e))?;
+    let mut zip = ZipWriter::new(file);
+    let zip_err = |e: zip::result::ZipError| format!("[Social Noise] Kit write failed: {}", e);
+    let io_err = |e: io::Error| format!("[Social Noise] Kit write failed: {}", e);
+    // Compressed audio barely shrinks; storing keeps large kits fast
+    let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let mut manifest = KitManifest {
+        format_version: KIT_FORMAT_VERSION,
+        name: session.name.clone(),
+        files: Vec::new(),
+    };
+    let mut done_bytes = 0;
+    for (source, in_archive) in &sources {
+        let hash = hash_file(source)?;
+        let mut reader = fs::File::open(source).map_err(io_err)?;
+        zip.start_file(in_archive.as_str(), stored)
+            .map_err(zip_err)?;
+        let size = io::copy(&mut reader, &mut zip).map_err(io_err)?;
+
+        done_bytes += size;
+        progress(KitProgress {
+            done_bytes,
+            total_bytes,
+            current: in_archive.clone(),
+        });
+        manifest.files.push(KitFile {
+            path: in_archive.clone(),
+            hash,
+            size,
+        });
+    }
+
+    let session_json = serde_json::to_vec_pretty(&session).map_err(|e| e.to_string())?;
+    zip.start_file("session.json", deflated).map_err(zip_err)?;
+    zip.write_all(&session_json).map_err(io_err)?;
+
+    let manifest_json = serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?;
+    zip.start_file("manifest.json", deflated).map_err(zip_err)?;
+    zip.write_all(&manifest_json).map_err(io_err)?;
+
+    zip.finish().map_err(zip_err)?;
+    info!(
+        "Kit exported to {:?} ({} files)",
+        destination,
+        manifest.files.len()
+    );
+    Ok(manifest)
+}
+
+/// Kit names become folder names; keep only characters safe everywhere
+fn folder_name(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let cleaned = cleaned.trim().trim_start_matches('.').to_string();
+    if cleaned.is_empty() {
+        "kit".to_string()
+    } else {
+        cleaned
+    }
+}
+
+/// Checks the manifest has the fields import relies on. An unknown format
+/// version only warns so newer kits still open where possible.
+fn read_manifest(raw: &str, warnings: &mut Vec<String>) -> Result<KitManifest, String> {
+    let value: serde_json::Value = serde_json::from_str(raw)
+        .map_err(|e| format!("[Social Noise] Kit manifest is not valid JSON: {}", e))?;
+    for field in ["formatVersion", "name", "files"] {
+        if value.get(field).is_none() {
+            return Err(format!(
+                "[Social Noise] Kit manifest is missing {:?}",
+                field
+            ));
+        }
+    }
+    let manifest: KitManifest = serde_json::from_value(value)
+        .map_err(|e| format!("[Social Noise] Kit manifest is malformed: {}", e))?;
+    if manifest.format_version != KIT_FORMAT_VERSION {
+        warnings.push(format!(
+            "Kit format version {} differs from supported version {}",
+            manifest.format_version, KIT_FORMAT_VERSION
+        ));
+    }
+    Ok(manifest)
+}
+
+/// Archive entry name -> location under the kit folder, rejecting anything that
+/// could escape it (absolute paths, `..`, drive prefixes). The `audio/` or
+/// `external/` folder is kept, so a harbor file and an outside file of the
+/// same name do not land on each other.
+fn safe_entry_path(name: &str) -> Option<PathBuf> {
+    let path = Path::new(name);
+    if !path.components().all(|c| matches!(c, Component::Normal(_))) {
+        return None;
+    }
+    let inner = path
+        .strip_prefix("audio")
+        .or_else(|_| path.strip_prefix("external"))
+        .ok()?;
+    if inner.as_os_str().is_empty() {
+        return None;
+    }
+    Some(path.to_path_buf())
+}
+
+fn read_entry<R: Read + io::Seek>(zip: &mut ZipArchive<R>, name: &str) -> Result<String, String> {
+    let mut entry = zip
+        .by_name(name)
+        .map_err(|_| format!("[Social Noise] Kit has no {}", name))?;
+    let mut contents = String::new();
+    entry
+        .read_to_string(&mut contents)
+        .map_err(|e| format!("[Social Noise] Kit {} unreadable: {}", name, e))?;
+    Ok(contents)
+}
+
+/// Extracts a kit into a fresh harbor subfolder. Files whose hash already
+/// exists in the harbor are not extracted again; the session is rewritten to
+/// point at wherever each file ended up.
+pub fn import_kit(
+    archive: &Path,
+    harbor: &Path,
+    harbor_files: &[String],
+    cache: &mut HashCache,
+) -> Result<(KitImportSummary, Session), String> {
+    let file =
+        fs::File::open(archive).map_err(|e| format!("[Social Noise] Cannot open kit: {}", e))?;
+    let mut zip = ZipArchive::new(file)
+        .map_err(|e| format!("[Social Noise] Not a valid kit archive: {}", e))?;
+
+    // 1. Zip-slip guard: refuse the whole archive if any entry could escape
+    for i in 0..zip.len() {
+        let entry = zip.by_index(i).map_err(|e| e.to_string())?;
+        if entry.enclosed_name().is_none() {
+            return Err(format!(
+                "[Social Noise] Kit entry escapes the archive: {:?}",
+                entry.name()
+            ));
+        }
+    }
+
+    let mut warnings = Vec::new();
+    let manifest = read_manifest(&read_entry(&mut zip, "manifest.json")?, &mut warnings)?;
+    let mut session: Session = serde_json::from_str(&read_entry(&mut zip, "session.json")?)
+        .map_err(|e| format!("[Social Noise] Kit session is malformed: {}", e))?;
+
+    // 2. Hashes of harbor files that could be duplicates (same size as a kit file)
+    let kit_sizes: Vec<u64> = manifest.files.iter().map(|f| f.size).collect();
+    let mut existing: HashMap<String, PathBuf> = HashMap::new();
+    for rel_path in harbor_files {
+        let path = harbor.join(rel_path);
+        let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(u64::MAX);
+        if kit_sizes.contains(&size) {
+            if let Ok(hash) = cache.hash(&path) {
+                existing.entry(hash).or_insert(path);
+            }
+        }
+    }
+
+    let folder = unique_destination(harbor, Path::new(&folder_name(&manifest.name)));
+    let folder_rel = api_path_in(harbor, &folder)?;
+    let mut summary = KitImportSummary {
+        kit_name: manifest.name.clone(),
+        folder: folder_rel,
+        imported: Vec::new(),
+        skipped: Vec::new(),
+        failed: Vec::new(),
+        warnings,
+        session: None,
+    };
+
+    // 3. Extract, verifying each file against its manifest hash
+    let mut resolved: HashMap<String, PathBuf> = HashMap::new();
+    for kit_file in &manifest.files {
+        let note = |reason: String| KitEntryNote {
+            path: kit_file.path.clone(),
+            reason,
+        };
+        if let Some(path) = existing.get(&kit_file.hash) {
+            let reason = format!(
+                "Already in harbor as {}",
+                api_path_in(harbor, path).unwrap_or_else(|_| path.to_string_lossy().to_string())
+            );
+            summary.skipped.push(note(reason));
+            resolved.insert(kit_file.path.clone(), path.clone());
+            continue;
+        }
+        let inner = match safe_entry_path(&kit_file.path) {
+            Some(inner) => inner,
+            None => {
+                summary
+                    .failed
+                    .push(note("Unsafe path in manifest".to_string()));
+                continue;
+            }
+        };
+
+        let destination = folder.join(&inner);
+        let outcome = (|| -> Result<(), String> {
+            let mut entry = zip
+                .by_name(&kit_file.path)
+                .map_err(|_| "Listed in manifest but missing from archive".to_string())?;
+            if let Some(parent) = destination.parent() {
+                fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+            }
+            let mut out = fs::File::create(&destination).map_err(|e| e.to_string())?;
+            io::copy(&mut entry, &mut out).map_err(|e| e.to_string())?;
+            if hash_file(&destination)? != kit_file.hash {
+                return Err("Contents do not match the manifest hash".to_string());
+            }
+            Ok(())
+        })();
+
+        match outcome {
+            Ok(()) => {
+                let rel = api_path_in(harbor, &destination)?;
+                existing.insert(kit_file.hash.clone(), destination.clone());
+                resolved.insert(kit_file.path.clone(), destination);
+                summary.imported.push(rel);
+            }
+            Err(reason) => {
+                let _ = fs::remove_file(&destination);
+                summary.failed.push(note(reason));
+            }
+        }
+    }
+
+    // 4. Point the session at the extracted (or already present) files
+    for pad in session.pads.iter_mut() {
+        if let Some(path) = resolved.get(&pad.path) {
+            pad.path = path.to_string_lossy().to_string();
+        }
+    }
+
+    info!(
+        "Kit {:?} imported: {} new, {} already present, {} failed",
+        manifest.name,
+        summary.imported.len(),
+        summary.skipped.len(),
+        summary.failed.len()
+    );
+    Ok((summary, session))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::SessionPad;
+
+    fn pad(key: &str, path: &Path) -> SessionPad {
+        SessionPad {
+            key: key.to_string(),
+            path: path.to_string_lossy().to_string(),
+            params: None,
+            defaults: None,
+            bpm: Some(120.0),
+        }
+    }
+
+    #[test]
+    fn a_harbor_file_and_an_outside_file_of_one_name_both_come_back() {
+        let dir = std::env::temp_dir().join(format!("lsamp-kit-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let harbor = dir.join("harbor");
+        let inside = harbor.join("kick.wav");
+        let outside = dir.join("elsewhere").join("kick.wav");
+        for (path, contents) in [(&inside, b"harbor kick"), (&outside, b"outer kick!")] {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        let session = Session {
+            name: "Twins".to_string(),
+            saved_at: 0,
+            master_bpm: 120.0,
+            master_volume: 1.0,
+            pads: vec![pad("1-a", &inside), pad("1-s", &outside)],
+            performance: None,
+            scenes: Vec::new(),
+        };
+        let archive = dir.join("twins.lsampkit");
+        let manifest = export_kit(&archive, &harbor, session, |_| {}).unwrap();
+        let paths: Vec<&str> = manifest.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, ["audio/kick.wav", "external/kick.wav"]);
+
+        let other = dir.join("other-harbor");
+        fs::create_dir_all(&other).unwrap();
+        let (summary, session) =
+            import_kit(&archive, &other, &[], &mut HashCache::default()).unwrap();
+        assert_eq!(
+            summary.imported,
+            ["Twins/audio/kick.wav", "Twins/external/kick.wav"]
+        );
+        assert!(summary.skipped.is_empty() && summary.failed.is_empty());
+        let contents: Vec<Vec<u8>> = session
+            .pads
+            .iter()
+            .map(|pad| fs::read(&pad.path).unwrap())
+            .collect();
+        assert_eq!(contents, [b"harbor kick".to_vec(), b"outer kick!".to_vec()]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1 +1 @@
-pub mod audio_engine;
+pub use lsamp_engine as audio_engine;
@@ -0,0 +1,186 @@
+/**
+ * logging.rs
+ * L-SAMP 100 | Logging
+ *
+ * Everything the backend reports goes through `tracing`. Logs land in daily
+ * files under the config dir (`lsamp-100/logs`, the last week kept) so a
+ * release build on Windows still leaves a trail; debug builds and headless
+ * runs also print a compact copy to the console. `RUST_LOG` picks the
+ * starting filter, `set_log_level` changes it at runtime and `export_logs`
+ * zips the files for a bug report.
+ *
+ * The audio callback never logs: it counts its problems and a monitor
+ * thread logs the counts (see `start_callback_monitor` in main.rs).
+ */
+use serde::Deserialize;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+use tracing_appender::rolling::{self, Rotation};
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{fmt, reload, EnvFilter, Registry};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// Filter when `RUST_LOG` is unset; symphonia is chatty about every probe
+const DEFAULT_FILTER: &str = "info,symphonia=warn";
+
+const FILE_PREFIX: &str = "lsamp-100";
+const FILE_SUFFIX: &str = "log";
+
+/// Daily files kept before the oldest is deleted
+const MAX_LOG_FILES: usize = 7;
+
+/// Levels `set_log_level` accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    fn directive(self) -> String {
+        let level = match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        };
+        format!("{},symphonia=warn", level)
+    }
+}
+
+/// Handle on the installed subscriber
+pub struct Logging {
+    filter: reload::Handle<EnvFilter, Registry>,
+    dir: Option<PathBuf>, // None when the log folder could not be created
+}
+
+fn logs_dir() -> Result<PathBuf, String> {
+    let dir = dirs::config_dir()
+        .ok_or("Failed to get config dir".to_string())?
+        .join("lsamp-100")
+        .join("logs");
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("[Inner Cosmos] Logs folder creation failed: {}", e))?;
+    Ok(dir)
+}
+
+/// Installs the global subscriber. Call once, before anything logs.
+pub fn init(console: bool) -> Logging {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let (filter, handle) = reload::Layer::new(filter);
+
+    // No file is not fatal: the console (if any) still gets everything
+    let mut problem = None;
+    let appender = logs_dir().and_then(|dir| {
+        rolling::Builder::new()
+            .rotation(Rotation::DAILY)
+            .filename_prefix(FILE_PREFIX)
+            .filename_suffix(FILE_SUFFIX)
+            .max_log_files(MAX_LOG_FILES)
+            .build(&dir)
+            .map(|appender| (dir, appender))
+            .map_err(|e| format!("[Inner Cosmos] Log file unavailable: {}", e))
+    });
+    let (dir, file) = match appender {
+        Ok((dir, appender)) => {
+            let layer = fmt::layer()
+                .with_writer(appender)
+                .with_ansi(false)
+                .with_span_events(FmtSpan::CLOSE); // Timings of the instrumented calls
+            (Some(dir), Some(layer))
+        }
+        Err(e) => {
+            problem = Some(e);
+            (None, None)
+        }
+    };
+    let console = console.then(|| fmt::layer().compact());
+
+    if let Err(e) = tracing_subscriber::registry()
+        .with(filter)
+        .with(file)
+        .with(console)
+        .try_init()
+    {
+        eprintln!("[Inner Cosmos] Logging already initialized: {}", e);
+    }
+    if let Some(problem) = problem {
+        warn!("{}", problem);
+    }
+    Logging {
+        filter: handle,
+        dir,
+    }
+}
+
+impl Logging {
+    /// Replaces the filter (including one from `RUST_LOG`) with a single level
+    pub fn set_level(&self, level: LogLevel) -> Result<(), String> {
+        let filter = EnvFilter::try_new(level.directive()).map_err(|e| e.to_string())?;
+        self.filter
+            .reload(filter)
+            .map_err(|e| format!("[Inner Cosmos] Log level not changed: {}", e))?;
+        info!("Log level set to {:?}", level);
+        Ok(())
+    }
+
+    /// Zips the current log files next to the log folder and returns the archive
+    pub fn export(&self) -> Result<PathBuf, String> {
+        let dir = self
+            .dir
+            .as_ref()
+            .ok_or("[Inner Cosmos] No log files are being written")?;
+        let mut files: Vec<PathBuf> = fs::read_dir(dir)
+            .map_err(|e| format!("[Inner Cosmos] Cannot read {:?}: {}", dir, e))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.is_file()
+                    && path
+                        .file_name()
+                        .and_then(|name| name.to_str())
+                        .is_some_and(|name| name.starts_with(FILE_PREFIX))
+            })
+            .collect();
+        if files.is_empty() {
+            return Err("[Inner Cosmos] No log files to export".to_string());
+        }
+        files.sort();
+
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis());
+        let parent = dir.parent().unwrap_or(dir);
+        let destination = parent.join(format!("lsamp-100-logs-{}.zip", stamp));
+        let archive = fs::File::create(&destination)
+            .map_err(|e| format!("[Social Noise] Cannot create {:?}: {}", destination, e))?;
+        let mut zip = ZipWriter::new(archive);
+        let zip_err = |e: zip::result::ZipError| format!("[Social Noise] Log export failed: {}", e);
+        let io_err = |e: io::Error| format!("[Social Noise] Log export failed: {}", e);
+        let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        for path in &files {
+            // The current file is still being appended to; what is there now is enough
+            let contents = fs::read(path).map_err(io_err)?;
+            let name = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or("log");
+            zip.start_file(name, deflated).map_err(zip_err)?;
+            zip.write_all(&contents).map_err(io_err)?;
+        }
+        zip.finish().map_err(zip_err)?;
+        info!("Exported {} log files to {:?}", files.len(), destination);
+        Ok(destination)
+    }
+}
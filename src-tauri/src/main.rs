@@ -5,16 +5,67 @@
 
 use rdev::{listen as rdev_listen, EventType, Key};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex};
+use std::io::{Read, Seek, SeekFrom};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
-use tauri::{AppHandle, Emitter, Manager, State};
-
-mod audio_engine;
-
-use crate::audio_engine::{AudioEngine, LevelsResponse, LoadResult};
+use std::time::{Duration, Instant};
+use tauri::{
+    AppHandle, DragDropEvent, Emitter, Manager, PhysicalPosition, RunEvent, State, WindowEvent,
+};
+use tauri_plugin_deep_link::DeepLinkExt;
+use tauri_plugin_global_shortcut::{
+    Code as ShortcutCode, GlobalShortcutExt, Shortcut, ShortcutEvent, ShortcutState,
+};
+use tracing::{debug, error, info, warn};
+
+// The engine crate, under the module name the rest of the app grew up with
+use lsamp_engine as audio_engine;
+
+mod cli;
+mod deeplink;
+mod gamepad;
+mod harbor;
+mod headless;
+mod keymap;
+mod kit;
+mod logging;
+mod midi;
+mod notifications;
+mod remote;
+mod session;
+mod tray;
+mod watcher;
+
+use crate::audio_engine::{
+    split_pad_id, AnalysisStatus, AudioEngine, BpmSource, CallbackErrors, CompressorSettings,
+    CrossfadeCurve, CrossfadeGroup, DelaySettings, FileTags, LevelsResponse, LoadError,
+    LoadErrorKind, LoadOptions, LoadProgress, LoadResult, LoopPoints, PartialPlayParams,
+    Performance, PlayParams, RenderSource, SampleSettings, Scene, SceneRecall, SearchDirection,
+    SendBus, StepDivision, StepEvent, Transport, VoiceMatch, WavFormat, WaveformChannel,
+    BANK_COUNT, MAX_MASTER_VOLUME, WAVEFORM_POINTS,
+};
+use crate::cli::LaunchRequest;
+use crate::deeplink::{DeepLink, DeepLinkHandled};
+use crate::gamepad::{GamepadEvent, GamepadInfo, GamepadMapping, GamepadState};
+use crate::harbor::{
+    get_audio_harbor, scan_harbor, set_audio_harbor, DeletedFile, DuplicateGroup, HarborFileInfo,
+    HarborPage, HarborScan, HarborSettings, HarborSort, HashCache, ImportedFile, ScanOptions,
+    ScanResult,
+};
+use crate::keymap::{Combo, KeyBinding, KeyBindings, KeyboardLayout, STOP_ALL};
+use crate::kit::KitImportSummary;
+use crate::logging::{LogLevel, Logging};
+use crate::midi::{MidiEvent, MidiMapping, MidiState, MidiTarget};
+use crate::notifications::{notify, Code, Level, Notification, Notifications};
+use crate::remote::{RemoteCommand, RemoteHost, RemoteServer, RemoteSettings};
+use crate::session::{Session, SessionInfo, SessionLoadReport, SessionPadError, SessionPadLoaded};
+use crate::tray::{Tray, TrayAction};
+use crate::watcher::{HarborChange, HarborChangeKind};
 /**
  * main.rs
  * L-SAMP 100 | Tauri Backend
@@ -31,8 +82,291 @@ use crate::audio_engine::{AudioEngine, LevelsResponse, LoadResult};
 pub struct HotkeyRegistry {
     /// Fast, lock-free enabled flag checked by callbacks
     pub enabled: Arc<AtomicBool>,
-    /// Registered hotkey identifiers (managed under a Mutex)
-    pub registrations: Mutex<Vec<String>>,
+    /// Binding targets switched off individually; `enabled` stays the master switch
+    pub disabled_keys: Arc<RwLock<HashSet<&'static str>>>,
+    /// Keys the listener saw go down and not come up yet (modifiers included).
+    /// Cleared when the window regains focus, in case a release was missed.
+    pub held: Arc<Mutex<HashSet<Key>>>,
+    /// `CaptureMode` set by the frontend while a text input has focus
+    pub capture_mode: Arc<AtomicU8>,
+    /// `KeyboardLayout` the OS reports keys in (only translated on Windows)
+    pub layout: Arc<AtomicU8>,
+    /// Treat the main window having focus like `CaptureMode::SuppressPads`
+    pub suppress_when_focused: Arc<AtomicBool>,
+    pub window_focused: Arc<AtomicBool>,
+    /// Let OS auto-repeat retrigger held keys (`KeyRepeat::Allow`)
+    pub allow_repeat: Arc<AtomicBool>,
+    /// Combo that flips `enabled`; the only binding honoured while disabled
+    pub toggle_combo: Arc<RwLock<Combo>>,
+    /// Play a confirmation blip when the toggle combo fires
+    pub toggle_blip: Arc<AtomicBool>,
+    /// Health of the supervised rdev thread, see `get_listener_status`
+    pub listener: Arc<Mutex<ListenerHealth>>,
+    /// Set by `restart_listener` to cut the supervisor's backoff short
+    pub restart_requested: Arc<AtomicBool>,
+    /// Fixed at startup from the session type
+    pub backend: HotkeyBackend,
+    /// Global shortcuts registered for the bindings (`HotkeyBackend::Shortcuts`
+    /// only), with the target each one triggers. Empty while disabled.
+    pub registrations: Mutex<Vec<(Shortcut, &'static str)>>,
+}
+
+impl HotkeyRegistry {
+    fn new() -> Self {
+        Self {
+            enabled: Arc::new(AtomicBool::new(true)),
+            disabled_keys: Arc::new(RwLock::new(HashSet::new())),
+            held: Arc::new(Mutex::new(HashSet::new())),
+            capture_mode: Arc::new(AtomicU8::new(CaptureMode::Normal as u8)),
+            layout: Arc::new(AtomicU8::new(KeyboardLayout::Physical as u8)),
+            suppress_when_focused: Arc::new(AtomicBool::new(false)),
+            window_focused: Arc::new(AtomicBool::new(false)),
+            allow_repeat: Arc::new(AtomicBool::new(false)),
+            toggle_combo: Arc::new(RwLock::new(Combo::default())),
+            toggle_blip: Arc::new(AtomicBool::new(true)),
+            listener: Arc::new(Mutex::new(ListenerHealth {
+                state: ListenerState::Running,
+                restarts: 0,
+                last_error: None,
+            })),
+            restart_requested: Arc::new(AtomicBool::new(false)),
+            backend: HotkeyBackend::detect(),
+            registrations: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+/// Where hotkeys come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HotkeyBackend {
+    Global,    // rdev hooks the keyboard system-wide
+    Shortcuts, // Wayland: one global-shortcut plugin registration per bound key
+}
+
+impl HotkeyBackend {
+    /// Wayland offers no global grab, so rdev only hears XWayland windows; the
+    /// global-shortcut plugin gets as far as the compositor lets it
+    fn detect() -> Self {
+        let wayland = cfg!(target_os = "linux")
+            && (std::env::var("XDG_SESSION_TYPE").is_ok_and(|t| t == "wayland")
+                || std::env::var_os("WAYLAND_DISPLAY").is_some());
+        if wayland {
+            Self::Shortcuts
+        } else {
+            Self::Global
+        }
+    }
+
+    fn limitations(self) -> Vec<&'static str> {
+        match self {
+            Self::Global => Vec::new(),
+            Self::Shortcuts => vec![
+                "Pad keys may only work while the L-SAMP window or another XWayland window has focus",
+                "The listener toggle hotkey, modifier combos and the numpad grid are unavailable",
+            ],
+        }
+    }
+}
+
+/// Payload of `hotkey-backend` and result of `get_hotkey_backend`
+#[derive(Clone, Serialize)]
+struct HotkeyBackendInfo {
+    backend: HotkeyBackend,
+    limitations: Vec<&'static str>,
+}
+
+/// Minimum and maximum wait before the supervisor listens again
+const LISTENER_BACKOFF_MIN: Duration = Duration::from_secs(1);
+const LISTENER_BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListenerState {
+    Running,
+    BackingOff,           // Died, waiting to retry
+    Unavailable,          // Not started: no global hook on this session (Wayland)
+    WaitingForPermission, // macOS Accessibility access not granted yet
+}
+
+/// Result of `get_input_permission_status`, payload of `input-permission-missing`
+#[derive(Clone, Serialize)]
+struct InputPermission {
+    granted: bool,
+    required: bool, // Only macOS gates global key events behind a permission
+}
+
+/// How often the listener re-checks a missing input permission
+const PERMISSION_POLL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ListenerHealth {
+    state: ListenerState,
+    restarts: u32,              // Retries since launch
+    last_error: Option<String>, // Reason of the latest failure, if any
+}
+
+/// Payload of `listener-error`
+#[derive(Clone, Serialize)]
+struct ListenerError {
+    reason: String,
+    retry_in_ms: u64,
+}
+
+/// Result of `get_listener_status`
+#[derive(Clone, Serialize)]
+struct ListenerStatus {
+    #[serde(flatten)]
+    health: ListenerHealth,
+    enabled: bool,
+}
+
+/// What OS auto-repeat of a held key does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyRepeat {
+    Suppress, // Only the first press of a hold fires
+    Allow,    // Every repeat fires again, for roll effects
+}
+
+/// How much of the global listener stays live while the user types in the app
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureMode {
+    Normal,
+    SuppressPads, // Everything but the SPACE stop
+    SuppressAll,
+}
+
+impl CaptureMode {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Self::SuppressPads,
+            2 => Self::SuppressAll,
+            _ => Self::Normal,
+        }
+    }
+
+    /// Whether a resolved binding target may fire under this mode
+    fn allows(self, target: &str) -> bool {
+        match self {
+            Self::Normal => true,
+            Self::SuppressPads => target == STOP_ALL,
+            Self::SuppressAll => false,
+        }
+    }
+}
+
+/// Modifier state at the time of a key event
+#[derive(Clone, Copy, Default, Serialize)]
+struct Modifiers {
+    shift: bool,
+    ctrl: bool,
+    alt: bool,
+}
+
+impl Modifiers {
+    fn from_held(held: &HashSet<Key>) -> Self {
+        let any = |keys: &[Key]| keys.iter().any(|k| held.contains(k));
+        Self {
+            shift: any(&[Key::ShiftLeft, Key::ShiftRight]),
+            ctrl: any(&[Key::ControlLeft, Key::ControlRight]),
+            alt: any(&[Key::Alt, Key::AltGr]),
+        }
+    }
+}
+
+/// Payload of `global-key-press`/`-release`, e.g. `{ key: "Q", shift: true, ctrl: false, alt: false }`
+#[derive(Clone, Serialize)]
+struct KeyPress {
+    key: String,
+    #[serde(flatten)]
+    modifiers: Modifiers,
+    velocity: f32, // 1.0 for keyboard presses, note-on velocity for MIDI
+}
+
+/// Payload of `listener-toggled`, sent whenever the listener is armed or disarmed
+#[derive(Clone, Serialize)]
+struct ListenerToggled {
+    enabled: bool,
+}
+
+/// Payload of `global-pad-press`/`-release`: a hotkey resolved against the active bank
+#[derive(Clone, Serialize)]
+struct PadPress {
+    bank: u32,
+    key: String,
+    pad: String, // Internal "{bank}:{key}" id
+    #[serde(flatten)]
+    modifiers: Modifiers,
+    velocity: f32,
+}
+
+/// Active pad bank. An atomic so the background listener can read it
+/// without touching the audio engine lock.
+#[derive(Default)]
+pub struct BankSelector {
+    pub active: Arc<AtomicU32>,
+}
+
+impl BankSelector {
+    /// Internal pad id for a frontend key; `bank` defaults to the active bank
+    fn pad_id(&self, key: &str, bank: Option<u32>) -> String {
+        audio_engine::pad_id(
+            bank.unwrap_or_else(|| self.active.load(Ordering::Relaxed)),
+            key,
+        )
+    }
+}
+
+/// The app pieces the input, load and voice-event paths need. `AppHandle`
+/// finds them in managed state and emits to the webview; headless mode
+/// owns them and logs to stdout instead (see headless.rs).
+trait Host: Clone + Send + Sync + 'static {
+    fn audio(&self) -> &AudioEngine;
+    fn banks(&self) -> &BankSelector;
+    fn registry(&self) -> &HotkeyRegistry;
+    fn key_bindings(&self) -> &Mutex<KeyBindings>;
+    fn notifications(&self) -> &Notifications;
+    fn emit_event<S: Serialize + Clone>(&self, event: &str, payload: S);
+
+    /// The frontend owns trigger modes and plays pads itself on the
+    /// pad events; without one, pads are played here
+    fn trigger_pad(&self, _pad: String, _pressed: bool, _velocity: f32) {}
+
+    fn listener_changed(&self, enabled: bool) {
+        self.emit_event("listener-toggled", ListenerToggled { enabled });
+    }
+}
+
+impl Host for AppHandle {
+    fn audio(&self) -> &AudioEngine {
+        self.state::<AudioEngine>().inner()
+    }
+
+    fn banks(&self) -> &BankSelector {
+        self.state::<BankSelector>().inner()
+    }
+
+    fn registry(&self) -> &HotkeyRegistry {
+        self.state::<HotkeyRegistry>().inner()
+    }
+
+    fn key_bindings(&self) -> &Mutex<KeyBindings> {
+        self.state::<Mutex<KeyBindings>>().inner()
+    }
+
+    fn notifications(&self) -> &Notifications {
+        self.state::<Notifications>().inner()
+    }
+
+    fn emit_event<S: Serialize + Clone>(&self, event: &str, payload: S) {
+        let _ = self.emit(event, payload);
+    }
+
+    fn listener_changed(&self, enabled: bool) {
+        listener_changed(self, enabled);
+    }
 }
 
 /// Configuration structure
@@ -40,6 +374,68 @@ pub struct HotkeyRegistry {
 pub struct AppConfig {
     accent_color: String,
     master_volume: f32,
+    /// Preferred BPM window used to fold half/double-time detections
+    #[serde(default)]
+    bpm_range_min: Option<f32>,
+    #[serde(default)]
+    bpm_range_max: Option<f32>,
+    /// Largest file `import_file` accepts, in megabytes
+    #[serde(default)]
+    import_limit_mb: Option<u64>,
+    /// Most decoded audio one pad may hold, in megabytes; longer files are cut short
+    #[serde(default)]
+    decode_limit_mb: Option<usize>,
+    /// Files decoded at once while loading (2 by default)
+    #[serde(default)]
+    decode_concurrency: Option<usize>,
+    /// Seconds SPACE / stop all fades every voice over; unset keeps each pad's release
+    #[serde(default)]
+    stop_all_release: Option<f32>,
+    /// Go back to each pad's own release on stop all
+    #[serde(default)]
+    clear_stop_all_release: Option<bool>,
+    /// Watch the harbor for files added or removed outside the app
+    #[serde(default)]
+    harbor_watch: Option<bool>,
+    /// Harbor scan filters: depth, hidden files, extra extensions, exclude globs
+    #[serde(default)]
+    harbor_scan: Option<ScanOptions>,
+    /// Directory samples are stored in, in place of ~/.config/lsamp-100/audio
+    #[serde(default)]
+    harbor_path: Option<String>,
+    /// Name of the output device to play through; unset keeps the current one
+    #[serde(default)]
+    output_device: Option<String>,
+    /// Target ("Q".."V", "SPACE", "BANK1".."BANK4") -> rdev key name; missing ones keep their default
+    #[serde(default)]
+    key_bindings: Option<HashMap<String, String>>,
+    /// Ignore pad keys in the global listener while the main window has focus
+    #[serde(default)]
+    suppress_pads_when_focused: Option<bool>,
+    /// "suppress" (default) or "allow" OS auto-repeat retriggering of held keys
+    #[serde(default)]
+    key_repeat: Option<KeyRepeat>,
+    /// Combo toggling the global listener, e.g. "Ctrl+Alt+KeyL" (the default)
+    #[serde(default)]
+    listener_toggle: Option<String>,
+    /// Play a short blip when that combo fires (default on)
+    #[serde(default)]
+    listener_toggle_blip: Option<bool>,
+    /// "physical" (default), "qwerty", "azerty" or "qwertz"
+    #[serde(default)]
+    keyboard_layout: Option<KeyboardLayout>,
+    /// Also trigger pads from the numpad (7 8 9 - / 4 5 6 + / 1 2 3 Enter / 0)
+    #[serde(default)]
+    numpad_pads: Option<bool>,
+    /// Let gamepad buttons and axes drive pads and controls (off by default)
+    #[serde(default)]
+    gamepad_enabled: Option<bool>,
+    /// Seconds between session autosaves (default 60, 0 turns the timer off)
+    #[serde(default)]
+    autosave_interval_secs: Option<u64>,
+    /// WebSocket remote control: enabled, lan, port, token (off by default)
+    #[serde(default)]
+    remote: Option<RemoteSettings>,
 }
 
 pub const IS_COMMUNITY_BUILD: bool = true; // I am just sitting here
@@ -53,35 +449,285 @@ fn main() {
     #[cfg(target_os = "linux")]
     std::env::set_var("WEBKIT_DISABLE_COMPOSITING_MODE", "1");
 
+    let args: Vec<String> = std::env::args().collect();
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let headless = cli::headless(&args, &cwd);
+    // Release builds have no console on Windows; the log file is what remains
+    let logging = logging::init(cfg!(debug_assertions) || headless.is_some());
+    if let Some(options) = headless {
+        let code = match options.and_then(headless::run) {
+            Ok(()) => 0,
+            Err(e) => {
+                error!("{}", e);
+                1
+            }
+        };
+        std::process::exit(code);
+    }
+
+    // Read before the app exists; reported as notifications once it does
+    let mut config_problems = Vec::new();
+    let midi_mapping = config_or_default(midi::load_mapping(), "midi.json", &mut config_problems);
+    let gamepad_mapping = config_or_default(
+        gamepad::load_mapping(),
+        "gamepad.json",
+        &mut config_problems,
+    );
+
     tauri::Builder::default()
+        // Registered first: a second launch forwards its arguments here and
+        // exits before it could open a stream or a keyboard hook of its own
+        .plugin(tauri_plugin_single_instance::init(
+            |app_handle, argv, cwd| {
+                info!("Second launch forwarded to this instance");
+                focus_main_window(app_handle);
+                handle_launch_args(app_handle, argv, PathBuf::from(cwd));
+            },
+        ))
+        .plugin(tauri_plugin_deep_link::init())
+        .plugin(
+            tauri_plugin_global_shortcut::Builder::new()
+                .with_handler(shortcut_fired)
+                .build(),
+        )
         // Manage a shared hotkey registry: an `AtomicBool` for quick checks
         // and a `Mutex` for safe registration/unregistration operations.
-        .manage(HotkeyRegistry {
-            enabled: Arc::new(AtomicBool::new(true)),
-            registrations: Mutex::new(Vec::new()),
+        .manage(HotkeyRegistry::new())
+        .manage(BankSelector::default())
+        .manage(Mutex::new(HarborSettings::default()))
+        .manage(Mutex::new(HashCache::default()))
+        .manage(Mutex::new(HarborScan::default()))
+        .manage(Mutex::new(KeyBindings::default()))
+        .manage(Mutex::new(MidiState::new(midi_mapping)))
+        .manage(GamepadState::new(gamepad_mapping))
+        .manage(Notifications::default())
+        .manage(logging)
+        .manage(PendingRestore {
+            offer: Mutex::new(None),
+        })
+        .manage(Autosave {
+            interval_secs: AtomicU64::new(DEFAULT_AUTOSAVE_SECS),
+            last: Mutex::new(None),
+        })
+        .manage(LaunchQueue {
+            ready: AtomicBool::new(false),
+            pending: Mutex::new(Vec::new()),
+        })
+        .manage(RemoteServer::default())
+        .manage(LevelStream {
+            subscription: AtomicU64::new(0),
         })
-        .manage(AudioEngine::new().expect("Failed to initialize audio engine"))
         .invoke_handler(tauri::generate_handler![
             get_is_community_build,
             get_harbor_files,
+            get_harbor_scan,
+            get_harbor_files_detailed,
+            find_harbor_duplicates,
+            scan_harbor_incremental,
+            import_file,
+            delete_harbor_file,
+            rename_harbor_file,
             open_audio_folder,
             get_audio_file,
+            get_audio_file_size,
+            get_file_tags,
             toggle_listener,
+            get_listener_status,
+            restart_listener,
+            get_hotkey_backend,
+            get_input_permission_status,
+            open_input_permission_settings,
+            set_input_capture_mode,
+            get_key_states,
+            set_key_enabled,
             apply_config,
+            get_key_bindings,
+            set_key_binding,
+            midi_list_ports,
+            midi_open_port,
+            midi_close_port,
+            midi_get_mapping,
+            midi_set_note,
+            midi_learn_start,
+            midi_learn_cancel,
+            midi_clear_binding,
+            gamepad_list,
+            gamepad_get_mapping,
+            gamepad_set_mapping,
             select_file,
             toggle_devtools,
             audio_load,
+            audio_analysis_status,
+            save_sample_settings,
+            load_sample_settings,
             audio_play,
+            set_pad_defaults,
+            get_pad_defaults,
             audio_stop,
             get_harbor_path,
             audio_get_levels,
+            audio_levels_subscribe,
+            audio_levels_unsubscribe,
+            audio_set_active_bank,
+            audio_load_cancel,
+            audio_reopen_output,
             audio_get_waveform,
+            audio_export_region,
+            audio_render_offline,
+            audio_find_zero_crossing,
+            audio_snap_loop_points,
             audio_set_master_bpm,
+            audio_set_swing,
+            audio_set_launch_quantize,
+            audio_beat_repeat_start,
+            audio_beat_repeat_stop,
+            audio_get_transport,
+            audio_seq_set_pattern,
+            audio_seq_set_length,
+            audio_seq_start,
+            audio_seq_stop,
+            audio_pattern_record_start,
+            audio_pattern_record_stop,
+            audio_pattern_play,
+            audio_pattern_stop,
+            audio_looper_record,
+            audio_record_start,
+            audio_record_stop,
+            audio_record_cancel,
+            audio_looper_overdub,
+            audio_looper_undo,
+            audio_looper_clear,
+            audio_set_crossfader,
+            audio_set_crossfade_curve,
+            audio_set_pad_group,
+            audio_set_compressor,
+            audio_set_delay,
+            audio_set_send,
+            audio_mute_sends,
+            audio_scene_save,
+            audio_scene_recall,
+            audio_scene_list,
+            audio_bpm_multiply,
             audio_update_params,
+            session_save,
+            session_load,
+            session_list,
+            autosave_now,
+            session_restore_available,
+            session_restore_autosave,
+            session_discard_autosave,
+            launch_ready,
+            get_recent_notifications,
+            set_log_level,
+            export_logs,
+            kit_export,
+            kit_import,
         ])
-        .setup(|app| {
+        .setup(move |app| {
             let app_handle = app.handle().clone();
-            start_background_listener(app_handle);
+            // Opened here rather than on the builder, so a second instance
+            // never grabs the output device before the plugin sends it away
+            app.manage(AudioEngine::new().expect("Failed to initialize audio engine"));
+            let pad_defaults = config_or_default(
+                session::load_pad_defaults(),
+                "pad_defaults.json",
+                &mut config_problems,
+            );
+            app_handle
+                .state::<AudioEngine>()
+                .replace_pad_defaults(pad_defaults);
+            report_config_problems(&app_handle, config_problems);
+            let backend = app_handle.state::<HotkeyRegistry>().backend;
+            if backend == HotkeyBackend::Global {
+                start_background_listener(app_handle.clone());
+            } else {
+                info!("Wayland session: hotkeys through global shortcuts");
+                if let Ok(mut health) = app_handle.state::<HotkeyRegistry>().listener.lock() {
+                    health.state = ListenerState::Unavailable;
+                }
+                if let Err(e) = register_shortcuts(&app_handle) {
+                    warn!("Global shortcuts not registered: {}", e);
+                }
+            }
+            let _ = app_handle.emit("hotkey-backend", hotkey_backend_info(backend));
+            watcher::start_harbor_watcher(app_handle.clone());
+            start_gamepad_input(app_handle.clone());
+            start_voice_events(app_handle.clone());
+            start_seq_steps(app_handle.clone());
+            start_count_in_ticks(app_handle.clone());
+            start_stream_faults(app_handle.clone());
+            start_callback_monitor(app_handle.clone());
+            let listener_enabled = app_handle
+                .state::<HotkeyRegistry>()
+                .enabled
+                .load(Ordering::SeqCst);
+            match tray::install(app, listener_enabled, handle_tray_action) {
+                Ok(tray) => {
+                    app.manage(tray);
+                }
+                // The window stays the only way to reach these controls
+                Err(e) => warn!("{}", e),
+            }
+            // Offer the autosave of a run that crashed (before this run's
+            // first autosave can replace it)
+            match session::take_crash_autosave() {
+                Ok(Some(info)) => {
+                    info!("Autosave from an unclean shutdown found");
+                    if let Ok(mut offer) = app_handle.state::<PendingRestore>().offer.lock() {
+                        *offer = Some(info.clone());
+                    }
+                    let _ = app_handle.emit("session-restore-available", info);
+                }
+                Ok(None) => {}
+                Err(e) => warn!("{}", e),
+            }
+            start_autosave(app_handle.clone());
+            // Windows and Linux start (or forward to) the app with the link
+            // as an argument; macOS delivers it as an event instead
+            #[cfg(target_os = "macos")]
+            {
+                let link_handle = app_handle.clone();
+                app.deep_link().on_open_url(move |event| {
+                    let links = event
+                        .urls()
+                        .into_iter()
+                        .map(|url| LaunchRequest::DeepLink(url.to_string()))
+                        .collect();
+                    queue_launch_requests(&link_handle, links);
+                });
+            }
+            // Installers register the scheme; this also covers dev and portable runs
+            #[cfg(any(target_os = "windows", target_os = "linux"))]
+            if let Err(e) = app.deep_link().register_all() {
+                warn!("lsamp:// links not registered: {}", e);
+            }
+            // The same handler runs for arguments a later launch forwards
+            handle_launch_args(
+                &app_handle,
+                std::env::args().collect(),
+                std::env::current_dir().unwrap_or_default(),
+            );
+
+            // Files dragged onto the window are imported into the harbor
+            if let Some(window) = app.get_webview_window("main") {
+                window.on_window_event(move |event| match event {
+                    WindowEvent::DragDrop(DragDropEvent::Drop { paths, position }) => {
+                        import_dropped_files(app_handle.clone(), paths.clone(), *position);
+                    }
+                    WindowEvent::Focused(focused) => {
+                        let registry = app_handle.state::<HotkeyRegistry>();
+                        registry.window_focused.store(*focused, Ordering::Relaxed);
+                        // Releases that happened elsewhere (lock screen, secure input) may
+                        // never reach the listener; start over with nothing held
+                        if *focused {
+                            if let Ok(mut held) = registry.held.lock() {
+                                held.clear();
+                            }
+                        }
+                    }
+                    _ => {}
+                });
+            }
 
             #[cfg(target_os = "macos")]
             {
@@ -97,128 +743,679 @@ fn main() {
 
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app_handle, event| {
+            if let RunEvent::Exit = event {
+                // A final autosave first: the timer then has nothing newer to write
+                let _ = autosave(app_handle);
+                if let Err(e) = session::mark_clean_shutdown() {
+                    warn!("{}", e);
+                }
+            }
+        });
 }
 
 // ============================================================================
 // GLOBAL BACKGROUND LISTENER (using rdev)
 // ============================================================================
 
-/// Start the background keyboard listener in a separate thread
-fn start_background_listener(app_handle: tauri::AppHandle) {
-    let enabled = Arc::clone(&app_handle.state::<HotkeyRegistry>().enabled);
-
+/// Start the background keyboard listener in a separate, supervised thread.
+/// `rdev::listen` only returns on failure (permissions revoked on macOS, X
+/// server hiccups); the supervisor then reports `listener-error`, backs off
+/// and listens again. On macOS it first waits for Accessibility access,
+/// without which rdev hears nothing. Each attempt builds its callback from the shared
+/// registry, so the enabled flag, capture settings and key bindings in
+/// effect at that moment carry over to the new listener.
+fn start_background_listener<H: Host>(host: H) {
     thread::spawn(move || {
-        rdev_listen(move |event| {
-            if !enabled.load(Ordering::Relaxed) {
-                return;
-            }
-
-            if let EventType::KeyPress(key) = event.event_type {
-                // Map rdev Key to a String for Angular
-                let key_str = match key {
-                    // Row 1
-                    Key::KeyQ => Some("Q"),
-                    Key::KeyW => Some("W"),
-                    Key::KeyE => Some("E"),
-                    Key::KeyR => Some("R"),
-
-                    // Row 2
-                    Key::KeyA => Some("A"),
-                    Key::KeyS => Some("S"),
-                    Key::KeyD => Some("D"),
-                    Key::KeyF => Some("F"),
-
-                    // Row 3
-                    Key::KeyZ => Some("Z"),
-                    Key::KeyX => Some("X"),
-                    Key::KeyC => Some("C"),
-                    Key::KeyV => Some("V"),
-
-                    // Global Stop
-                    Key::Space => Some("SPACE"),
-
-                    _ => None,
+        let registry = host.registry();
+        let mut backoff = LISTENER_BACKOFF_MIN;
+        loop {
+            // Without permission rdev hears nothing; wait until the user grants it
+            if !input_permission_granted() {
+                if let Ok(mut health) = registry.listener.lock() {
+                    health.state = ListenerState::WaitingForPermission;
+                }
+                warn!("Keyboard listener is waiting for Accessibility access");
+                host.emit_event("input-permission-missing", input_permission());
+                while !input_permission_granted() {
+                    thread::sleep(PERMISSION_POLL);
+                }
+                info!("Accessibility access granted");
+            }
+            // Presses and releases seen by a dead listener are meaningless now
+            if let Ok(mut held) = registry.held.lock() {
+                held.clear();
+            }
+            if let Ok(mut health) = registry.listener.lock() {
+                health.state = ListenerState::Running;
+            }
+            info!("Keyboard listener started");
+
+            let started = Instant::now();
+            let result = panic::catch_unwind(AssertUnwindSafe(|| run_listener(&host)));
+            let reason = match result {
+                Ok(Ok(())) => "listener stopped unexpectedly".to_string(),
+                Ok(Err(e)) => format!("{:?}", e),
+                Err(_) => "listener thread panicked".to_string(),
+            };
+            // A listener that ran for a while failed afresh; start the backoff over
+            if started.elapsed() > LISTENER_BACKOFF_MAX {
+                backoff = LISTENER_BACKOFF_MIN;
+            }
+            notify(
+                &host,
+                Level::Error,
+                Code::ListenerFailed,
+                format!(
+                    "Keyboard listener died: {} (retrying in {:?})",
+                    reason, backoff
+                ),
+                serde_json::json!({ "retryInMs": backoff.as_millis() as u64 }),
+            );
+            if let Ok(mut health) = registry.listener.lock() {
+                health.state = ListenerState::BackingOff;
+                health.last_error = Some(reason.clone());
+            }
+            host.emit_event(
+                "listener-error",
+                ListenerError {
+                    reason,
+                    retry_in_ms: backoff.as_millis() as u64,
+                },
+            );
+
+            // Sleep out the backoff unless `restart_listener` asks for a retry now
+            let deadline = Instant::now() + backoff;
+            while Instant::now() < deadline
+                && !registry.restart_requested.swap(false, Ordering::SeqCst)
+            {
+                thread::sleep(Duration::from_millis(100));
+            }
+            backoff = (backoff * 2).min(LISTENER_BACKOFF_MAX);
+            if let Ok(mut health) = registry.listener.lock() {
+                health.restarts += 1;
+            }
+        }
+    });
+}
+
+/// One `rdev::listen` session; blocks until the OS hook fails
+fn run_listener<H: Host>(host: &H) -> Result<(), rdev::ListenError> {
+    let host = host.clone();
+    let enabled = Arc::clone(&host.registry().enabled);
+    let held = Arc::clone(&host.registry().held);
+    let capture_mode = Arc::clone(&host.registry().capture_mode);
+    let layout = Arc::clone(&host.registry().layout);
+    let suppress_when_focused = Arc::clone(&host.registry().suppress_when_focused);
+    let window_focused = Arc::clone(&host.registry().window_focused);
+    let allow_repeat = Arc::clone(&host.registry().allow_repeat);
+    let disabled_keys = Arc::clone(&host.registry().disabled_keys);
+    let toggle_combo = Arc::clone(&host.registry().toggle_combo);
+    let toggle_blip = Arc::clone(&host.registry().toggle_blip);
+
+    rdev_listen(move |event| {
+        let (key, pressed) = match event.event_type {
+            EventType::KeyPress(key) => (key, true),
+            EventType::KeyRelease(key) => (key, false),
+            _ => return,
+        };
+        // Everything below works on physical positions
+        let key = KeyboardLayout::from_u8(layout.load(Ordering::Relaxed)).to_physical(key);
+        // OS auto-repeat re-sends KeyPress for a held key without a release.
+        // The held table is shared with release handling and modifier state.
+        let (repeat, modifiers) = match held.lock() {
+            Ok(mut held) => {
+                let repeat = if pressed {
+                    !held.insert(key)
+                } else {
+                    !held.remove(&key)
                 };
+                (repeat, Modifiers::from_held(&held))
+            }
+            Err(_) => return,
+        };
+        // A release without a tracked press is always dropped
+        let repeat = repeat && !(pressed && allow_repeat.load(Ordering::Relaxed));
+        if repeat {
+            return;
+        }
 
-                if let Some(k) = key_str {
-                    if k == "SPACE" {
-                        let audio = app_handle.state::<AudioEngine>();
-                        audio.stop_all();
-                    }
-                    let _ = app_handle.emit("global-key-press", k);
-                }
+        // The toggle combo is checked before `enabled` so it can re-arm the listener
+        let toggle = toggle_combo
+            .read()
+            .is_ok_and(|combo| combo.matches(key, modifiers.shift, modifiers.ctrl, modifiers.alt));
+        if pressed && toggle {
+            let now = !enabled.fetch_xor(true, Ordering::SeqCst);
+            info!(
+                "Keyboard sensing: {} (hotkey)",
+                if now { "ACTIVE" } else { "RELEASED" }
+            );
+            if toggle_blip.load(Ordering::Relaxed) {
+                let freq = if now { 1320.0 } else { 660.0 };
+                let _ = host.audio().play_blip(freq);
             }
-        })
-        .expect("[Consonance] Could not spy on keyboard");
+            host.listener_changed(now);
+            return;
+        }
+        if !enabled.load(Ordering::Relaxed) {
+            return;
+        }
+
+        // Resolve the physical key through the (remappable) bindings
+        let key_str = host
+            .key_bindings()
+            .lock()
+            .ok()
+            .and_then(|bindings| bindings.pad_for(key));
+
+        // The frontend handles in-app keys itself while it has focus
+        let mut mode = CaptureMode::from_u8(capture_mode.load(Ordering::Relaxed));
+        if mode == CaptureMode::Normal
+            && suppress_when_focused.load(Ordering::Relaxed)
+            && window_focused.load(Ordering::Relaxed)
+        {
+            mode = CaptureMode::SuppressPads;
+        }
+
+        let key_on = |k: &str| disabled_keys.read().map_or(true, |d| !d.contains(k));
+        if let Some(k) = key_str.filter(|k| mode.allows(k) && key_on(k)) {
+            emit_pad_event(&host, k, pressed, modifiers, 1.0);
+        }
+    })
+}
+
+/// Shared trigger path of the keyboard listener and MIDI input: emits the
+/// key and pad events for a resolved pad slot (or STOP_ALL), or switches
+/// banks for a bank key.
+fn emit_pad_event<H: Host>(
+    host: &H,
+    k: &'static str,
+    pressed: bool,
+    modifiers: Modifiers,
+    velocity: f32,
+) {
+    // Bank keys never reach the pad-triggering events
+    if let Some(bank) = keymap::bank_index(k) {
+        if pressed {
+            set_active_bank(host, bank);
+        }
+        return;
+    }
+    // Modified SPACE variants are passed on as-is; until a configurable
+    // stop-all exists they stop immediately as well
+    if pressed && k == STOP_ALL {
+        host.audio().stop_all();
+    }
+    let (key_event, pad_event) = if pressed {
+        ("global-key-press", "global-pad-press")
+    } else {
+        ("global-key-release", "global-pad-release")
+    };
+    host.emit_event(
+        key_event,
+        KeyPress {
+            key: k.to_string(),
+            modifiers,
+            velocity,
+        },
+    );
+    if k != STOP_ALL {
+        let bank = host.banks().active.load(Ordering::Relaxed);
+        let pad = audio_engine::pad_id(bank, k);
+        host.emit_event(
+            pad_event,
+            PadPress {
+                bank,
+                key: k.to_string(),
+                pad: pad.clone(),
+                modifiers,
+                velocity,
+            },
+        );
+        host.trigger_pad(pad, pressed, velocity);
+    }
+}
+
+// ============================================================================
+// MIDI INPUT
+// ============================================================================
+
+/// IPC Command: Names of the available MIDI input ports
+#[tauri::command]
+fn midi_list_ports() -> Result<Vec<String>, String> {
+    midi::list_ports()
+}
+
+/// IPC Command: Open a MIDI input port by name. Mapped notes trigger pads
+/// through the same events as the keyboard, with note-on velocity attached.
+#[tauri::command]
+fn midi_open_port(
+    name: String,
+    midi_state: State<'_, Mutex<MidiState>>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    let mut midi_state = midi_state.lock().map_err(|e| e.to_string())?;
+    midi_state.open(&name, midi_input(app_handle))
+}
+
+/// MIDI port callback: pads, controls and learn captures
+fn midi_input<H: Host>(host: H) -> impl FnMut(MidiEvent) + Send + 'static {
+    move |event| match event {
+        MidiEvent::Pad {
+            pad,
+            pressed,
+            velocity,
+        } => emit_pad_event(&host, pad, pressed, Modifiers::default(), velocity),
+        MidiEvent::Control { target, value } => apply_midi_control(&host, target, value),
+        MidiEvent::Learned(learned) => {
+            info!("MIDI learned: {:?}", learned);
+            host.emit_event("midi-learned", learned);
+        }
+    }
+}
+
+/// Payload of `midi-control`, so the UI can follow hardware faders
+#[derive(Clone, Serialize)]
+struct MidiControl {
+    target: String,
+    value: f32,
+}
+
+/// Drives a live value from a CC, a note's velocity or a gamepad axis. Pads use the active bank.
+fn apply_midi_control<H: Host>(host: &H, target: MidiTarget, value: f32) {
+    let audio = host.audio();
+    match target {
+        MidiTarget::MasterVolume => audio.set_master_volume(value),
+        MidiTarget::PadVolume(slot) => {
+            let key = host.banks().pad_id(slot, None);
+            // A pad that never played has no params yet; the UI still gets the value
+            if let Some(mut params) = audio.pad_params(&key) {
+                params.volume = value;
+                let _ = audio.update_voice(key, params);
+            }
+        }
+        MidiTarget::Pad(_) => return,
+    }
+    host.emit_event(
+        "midi-control",
+        MidiControl {
+            target: target.name(),
+            value,
+        },
+    );
+}
+
+/// IPC Command: Close the open MIDI port, if any
+#[tauri::command]
+fn midi_close_port(midi_state: State<'_, Mutex<MidiState>>) -> Result<(), String> {
+    midi_state.lock().map_err(|e| e.to_string())?.close();
+    Ok(())
+}
+
+/// IPC Command: Open port name (None when closed) and the note/CC bindings
+#[tauri::command]
+fn midi_get_mapping(
+    midi_state: State<'_, Mutex<MidiState>>,
+) -> Result<(Option<String>, MidiMapping), String> {
+    let midi_state = midi_state.lock().map_err(|e| e.to_string())?;
+    Ok((
+        midi_state.port_name().map(str::to_string),
+        midi_state.mapping(),
+    ))
+}
+
+/// IPC Command: Bind the next note-on or CC to `target`: a pad slot, "master_volume"
+/// or "pad:Q:volume". The capture is reported through `midi-learned` and saved.
+#[tauri::command]
+fn midi_learn_start(target: String, midi_state: State<'_, Mutex<MidiState>>) -> Result<(), String> {
+    midi_state
+        .lock()
+        .map_err(|e| e.to_string())?
+        .start_learn(&target)
+}
+
+/// IPC Command: Disarm a pending MIDI learn
+#[tauri::command]
+fn midi_learn_cancel(midi_state: State<'_, Mutex<MidiState>>) -> Result<(), String> {
+    midi_state.lock().map_err(|e| e.to_string())?.cancel_learn()
+}
+
+/// IPC Command: Remove every note/CC bound to `target`
+#[tauri::command]
+fn midi_clear_binding(
+    target: String,
+    midi_state: State<'_, Mutex<MidiState>>,
+) -> Result<MidiMapping, String> {
+    midi_state
+        .lock()
+        .map_err(|e| e.to_string())?
+        .clear_binding(&target)
+}
+
+/// IPC Command: Bind a MIDI note to a pad slot ("Q".."V", "SPACE"), or unbind
+/// it when `pad` is null. The mapping is saved right away.
+#[tauri::command]
+fn midi_set_note(
+    note: u8,
+    pad: Option<String>,
+    midi_state: State<'_, Mutex<MidiState>>,
+) -> Result<MidiMapping, String> {
+    midi_state
+        .lock()
+        .map_err(|e| e.to_string())?
+        .set_note(note, pad.as_deref())
+}
+
+// ============================================================================
+// GAMEPAD INPUT
+// ============================================================================
+
+/// Start the gilrs thread. Buttons go through the same events as the keyboard,
+/// analog triggers and sticks drive controls like MIDI CCs.
+fn start_gamepad_input(app_handle: AppHandle) {
+    let state = app_handle.state::<GamepadState>();
+    let app_handle = app_handle.clone();
+    state.start(move |event| match event {
+        GamepadEvent::Pad { pad, pressed } => {
+            emit_pad_event(&app_handle, pad, pressed, Modifiers::default(), 1.0)
+        }
+        GamepadEvent::Control { target, value } => apply_midi_control(&app_handle, target, value),
+        GamepadEvent::Connected(info) => {
+            let _ = app_handle.emit("gamepad-connected", info);
+        }
+        GamepadEvent::Disconnected(info) => {
+            let _ = app_handle.emit("gamepad-disconnected", info);
+        }
     });
 }
 
+/// IPC Command: Gamepads currently connected
+#[tauri::command]
+fn gamepad_list(gamepad: State<'_, GamepadState>) -> Vec<GamepadInfo> {
+    gamepad.list()
+}
+
+/// IPC Command: Current input -> target mapping
+#[tauri::command]
+fn gamepad_get_mapping(gamepad: State<'_, GamepadState>) -> GamepadMapping {
+    gamepad.mapping()
+}
+
+/// IPC Command: Bind a button ("South", "DPadUp") to a pad slot, or an analog
+/// input ("RightTrigger2", "LeftStickY") to "master_volume" / "pad:Q:volume".
+/// `pad: null` unbinds the input. Returns the saved mapping.
+#[tauri::command]
+fn gamepad_set_mapping(
+    button: String,
+    pad: Option<String>,
+    gamepad: State<'_, GamepadState>,
+) -> Result<GamepadMapping, String> {
+    gamepad.set_mapping(&button, pad.as_deref())
+}
+
 // ============================================================================
 // FILE OPERATIONS (Harbor Management)
 // ============================================================================
 
-/// Get the audio harbor directory path
-fn get_audio_harbor(_app_handle: &AppHandle) -> Result<PathBuf, String> {
-    // Use standard config directory: ~/.config/lsamp-100/audio (on Linux)
-    let config_dir = dirs::config_dir()
-        .ok_or("Failed to get config dir".to_string())?
-        .join("lsamp-100");
+/// Current scan filters (cloned so scans never hold the settings lock)
+fn scan_options(settings: &Mutex<HarborSettings>) -> Result<ScanOptions, String> {
+    Ok(settings.lock().map_err(|e| e.to_string())?.scan.clone())
+}
+
+/// IPC Command: Get all audio files from harbor
+#[tauri::command]
+async fn get_harbor_files(
+    settings: State<'_, Mutex<HarborSettings>>,
+) -> Result<Vec<String>, String> {
+    let harbor_path = get_audio_harbor()?;
+    Ok(scan_harbor(&harbor_path, &scan_options(&settings)?)?.files)
+}
 
-    let harbor_path = config_dir.join("audio");
+/// IPC Command: Harbor scan including how many entries the filters skipped
+#[tauri::command]
+async fn get_harbor_scan(settings: State<'_, Mutex<HarborSettings>>) -> Result<ScanResult, String> {
+    let harbor_path = get_audio_harbor()?;
+    scan_harbor(&harbor_path, &scan_options(&settings)?)
+}
 
-    // Ensure the directory exists
-    if !harbor_path.exists() {
-        fs::create_dir_all(&harbor_path)
-            .map_err(|e| format!("[Inner Cosmos] Harbor creation failed: {}", e))?;
-        println!("[Inner Cosmos] Harbor created at: {:?}", harbor_path);
+/// IPC Command: Harbor listing with size, mtime and any cached duration/BPM
+#[tauri::command]
+async fn get_harbor_files_detailed(
+    sort: Option<HarborSort>,
+    settings: State<'_, Mutex<HarborSettings>>,
+    audio: State<'_, AudioEngine>,
+) -> Result<Vec<HarborFileInfo>, String> {
+    let harbor_path = get_audio_harbor()?;
+    let options = scan_options(&settings)?;
+    let mut files = harbor::scan_harbor_detailed(&harbor_path, &options, sort.unwrap_or_default())?;
+
+    let cache = audio.inner().file_analysis_snapshot();
+    for file in files.iter_mut() {
+        if let Some(known) = cache.get(&harbor_path.join(&file.path)) {
+            file.duration = Some(known.duration);
+            file.bpm = known.bpm;
+        }
     }
+    Ok(files)
+}
 
-    Ok(harbor_path)
+/// Progress payload for `duplicate-scan-progress`
+#[derive(Clone, Serialize)]
+struct ScanProgress {
+    done: usize,
+    total: usize,
 }
 
-/// Recursively scan directory for audio files
-fn scan_harbor(dir_path: &PathBuf) -> Result<Vec<String>, String> {
-    let mut audio_files = Vec::new();
+/// Progress payload for `harbor-scan-progress`
+#[derive(Clone, Serialize)]
+struct HarborScanProgress {
+    scanned: usize,
+    done: bool,
+}
+
+/// IPC Command: One page of the harbor listing, see `harbor::HarborScan` for
+/// ordering. Without `limit` the rest of the harbor is listed in one call and
+/// `harbor-scan-progress` events report the walk.
+#[tauri::command]
+async fn scan_harbor_incremental(
+    cursor: Option<String>,
+    limit: Option<usize>,
+    scan_cache: State<'_, Mutex<HarborScan>>,
+    settings: State<'_, Mutex<HarborSettings>>,
+    app_handle: AppHandle,
+) -> Result<HarborPage, String> {
+    let harbor_path = get_audio_harbor()?;
+    let options = scan_options(&settings)?;
+    // Taken out for the walk so a slow page never holds the lock
+    let mut scan = std::mem::take(&mut *scan_cache.lock().map_err(|e| e.to_string())?);
+
+    let (page, scan) = tokio::task::spawn_blocking(move || {
+        let page = scan.page(
+            &harbor_path,
+            &options,
+            cursor.as_deref(),
+            limit,
+            |scanned, done| {
+                let _ =
+                    app_handle.emit("harbor-scan-progress", HarborScanProgress { scanned, done });
+            },
+        );
+        (page, scan)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    *scan_cache.lock().map_err(|e| e.to_string())? = scan;
+    page
+}
 
-    fn scan_recursive(
-        dir: &PathBuf,
-        base_dir: &PathBuf,
-        files: &mut Vec<String>,
-    ) -> Result<(), String> {
-        let entries =
-            fs::read_dir(dir).map_err(|e| format!("[Social Noise] Harbor scan failed: {}", e))?;
+/// IPC Command: Group byte-identical harbor files. Hashing runs on a blocking
+/// worker and reports `duplicate-scan-progress` events as it goes.
+#[tauri::command]
+async fn find_harbor_duplicates(
+    hash_cache: State<'_, Mutex<HashCache>>,
+    settings: State<'_, Mutex<HarborSettings>>,
+    app_handle: AppHandle,
+) -> Result<Vec<DuplicateGroup>, String> {
+    let harbor_path = get_audio_harbor()?;
+    let options = scan_options(&settings)?;
+    let mut cache = hash_cache.lock().map_err(|e| e.to_string())?.clone();
+
+    let (groups, cache) = tokio::task::spawn_blocking(move || {
+        let groups = harbor::find_duplicates(&harbor_path, &options, &mut cache, |done, total| {
+            let _ = app_handle.emit("duplicate-scan-progress", ScanProgress { done, total });
+        });
+        (groups, cache)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    *hash_cache.lock().map_err(|e| e.to_string())? = cache;
+    groups
+}
 
-        for entry in entries {
-            let entry = entry.map_err(|e| format!("[Social Noise] Entry error: {}", e))?;
-            let path = entry.path();
+/// Outcome for one file of a window drop
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DroppedFile {
+    source: String,
+    path: Option<String>, // Harbor-relative path when the import succeeded
+    error: Option<String>,
+}
 
-            if path.is_dir() {
-                scan_recursive(&path, base_dir, files)?;
-            } else {
-                if let Some(ext) = path.extension() {
-                    let ext_str = ext.to_string_lossy().to_lowercase();
-                    if matches!(ext_str.as_str(), "mp3" | "wav" | "ogg" | "flac") {
-                        if let Ok(rel_path) = path.strip_prefix(base_dir) {
-                            files.push(rel_path.to_string_lossy().to_string());
-                        }
-                    }
-                }
-            }
+/// Payload of `files-dropped`
+#[derive(Clone, Serialize)]
+struct FilesDropped {
+    x: f64, // Drop position in physical window pixels
+    y: f64,
+    files: Vec<DroppedFile>,
+}
+
+/// Import every dropped file in parallel, then report them together so the
+/// frontend can pick the pad under the drop position.
+fn import_dropped_files(
+    app_handle: AppHandle,
+    paths: Vec<PathBuf>,
+    position: PhysicalPosition<f64>,
+) {
+    tauri::async_runtime::spawn(async move {
+        let limit = app_handle
+            .state::<Mutex<HarborSettings>>()
+            .lock()
+            .map(|s| s.import_limit_bytes)
+            .unwrap_or(harbor::DEFAULT_IMPORT_LIMIT_BYTES);
+
+        let jobs: Vec<_> = paths
+            .into_iter()
+            .map(|source| {
+                let label = source.to_string_lossy().to_string();
+                let job = tauri::async_runtime::spawn_blocking(move || {
+                    get_audio_harbor().and_then(|harbor_path| {
+                        harbor::import_file(&harbor_path, &source, false, limit)
+                    })
+                });
+                (label, job)
+            })
+            .collect();
+
+        let mut files = Vec::new();
+        for (source, job) in jobs {
+            let outcome = job.await.map_err(|e| e.to_string()).and_then(|r| r);
+            files.push(DroppedFile {
+                source,
+                path: outcome.as_ref().ok().map(|imported| imported.path.clone()),
+                error: outcome.err(),
+            });
         }
-        Ok(())
+
+        let _ = app_handle.emit(
+            "files-dropped",
+            FilesDropped {
+                x: position.x,
+                y: position.y,
+                files,
+            },
+        );
+    });
+}
+
+/// IPC Command: Copy (or move) an external file into the harbor
+#[tauri::command]
+async fn import_file(
+    source_path: String,
+    move_file: Option<bool>,
+    settings: State<'_, Mutex<HarborSettings>>,
+) -> Result<ImportedFile, String> {
+    let harbor_path = get_audio_harbor()?;
+    let limit = settings
+        .lock()
+        .map_err(|e| e.to_string())?
+        .import_limit_bytes;
+    harbor::import_file(
+        &harbor_path,
+        &PathBuf::from(source_path),
+        move_file.unwrap_or(false),
+        limit,
+    )
+}
+
+/// IPC Command: Move a harbor file to the OS trash. Pads that already loaded
+/// it keep playing from memory; the result tells the frontend to warn.
+#[tauri::command]
+async fn delete_harbor_file(
+    relative_path: String,
+    audio: State<'_, AudioEngine>,
+) -> Result<DeletedFile, String> {
+    let harbor_path = get_audio_harbor()?;
+    let file_path = harbor::resolve_in_harbor(&harbor_path, &relative_path)?;
+    if !file_path.is_file() {
+        return Err(format!("File not found: {:?}", file_path));
     }
 
-    scan_recursive(dir_path, dir_path, &mut audio_files)?;
-    Ok(audio_files)
+    // Resolve references while the file still exists on disk
+    let referencing_pads = audio.inner().forget_file(&file_path);
+    harbor::trash_file(&file_path)?;
+
+    Ok(DeletedFile {
+        path: harbor::api_path_in(&harbor_path, &file_path)?,
+        referenced: !referencing_pads.is_empty(),
+        referencing_pads,
+    })
 }
 
-/// IPC Command: Get all audio files from harbor
+/// IPC Command: Rename or move a file within the harbor
 #[tauri::command]
-async fn get_harbor_files(app_handle: AppHandle) -> Result<Vec<String>, String> {
-    let harbor_path = get_audio_harbor(&app_handle)?;
-    scan_harbor(&harbor_path)
+async fn rename_harbor_file(
+    old_rel_path: String,
+    new_rel_path: String,
+    overwrite: Option<bool>,
+    audio: State<'_, AudioEngine>,
+    app_handle: AppHandle,
+) -> Result<String, String> {
+    let harbor_path = get_audio_harbor()?;
+    let new_path = harbor::rename_file(
+        &harbor_path,
+        &old_rel_path,
+        &new_rel_path,
+        overwrite.unwrap_or(false),
+    )?;
+
+    let old_path = harbor::resolve_in_harbor(&harbor_path, &old_rel_path)?;
+    audio.inner().rename_file(
+        &old_path,
+        &harbor::resolve_in_harbor(&harbor_path, &new_path)?,
+    );
+    watcher::emit_change(
+        &app_handle,
+        HarborChange {
+            kind: HarborChangeKind::Renamed,
+            path: new_path.clone(),
+            from: harbor::api_path_in(&harbor_path, &old_path).ok(),
+        },
+    );
+    Ok(new_path)
 }
 
 #[tauri::command]
@@ -227,15 +1424,15 @@ fn get_is_community_build() -> bool {
 }
 
 #[tauri::command]
-async fn get_harbor_path(app_handle: AppHandle) -> Result<String, String> {
-    let path = get_audio_harbor(&app_handle)?;
+async fn get_harbor_path() -> Result<String, String> {
+    let path = get_audio_harbor()?;
     Ok(path.to_string_lossy().to_string())
 }
 
 /// IPC Command: Open the audio folder in file explorer
 #[tauri::command]
-async fn open_audio_folder(app_handle: AppHandle) -> Result<(), String> {
-    let harbor_path = get_audio_harbor(&app_handle)?;
+async fn open_audio_folder() -> Result<(), String> {
+    let harbor_path = get_audio_harbor()?;
 
     #[cfg(target_os = "windows")]
     {
@@ -268,212 +1465,2214 @@ async fn open_audio_folder(app_handle: AppHandle) -> Result<(), String> {
 // KEYBOARD CONTROL
 // ============================================================================
 
-/// IPC Command: Toggle global keyboard listener state
-#[tauri::command]
-fn toggle_listener(
-    state: bool,
-    registry: State<'_, HotkeyRegistry>,
-    _app_handle: AppHandle,
-) -> Result<(), String> {
-    // Fast, lock-free publish of the enabled/disabled state so any callbacks
-    // that are racing with unregister can short-circuit quickly.
-    registry.enabled.store(state, Ordering::SeqCst);
-
-    // Now manage registration lifecycle under a mutex to avoid races
-    // when adding/removing OS-level hooks. The actual register/unregister
-    // calls are plugin-specific and should be placed where indicated below.
-    let mut regs = registry.registrations.lock().map_err(|e| e.to_string())?;
-
-    if state {
-        // If enabling, (re-)register all required hotkeys. This is a good
-        // place to call into a global-shortcut plugin to register keys and
-        // store their tokens/ids in `regs` for later unregistration.
-
-        // Example (pseudocode - plugin-specific):
-        // let token = global_shortcut::register(&app_handle, "Q", || { /* emit event */ });
-        // regs.push(token);
-
-        // For now we store the logical key names so the registration intent is tracked.
-        if regs.is_empty() {
-            regs.push("Q".to_string());
-            regs.push("W".to_string());
-            regs.push("E".to_string());
-            regs.push("R".to_string());
-            regs.push("A".to_string());
-            regs.push("S".to_string());
-            regs.push("D".to_string());
-            regs.push("F".to_string());
-            regs.push("Z".to_string());
-            regs.push("X".to_string());
-            regs.push("C".to_string());
-            regs.push("V".to_string());
-            regs.push("SPACE".to_string());
+/// Whether the OS lets the global listener see key events (macOS Accessibility)
+fn input_permission_granted() -> bool {
+    #[cfg(target_os = "macos")]
+    {
+        #[link(name = "ApplicationServices", kind = "framework")]
+        extern "C" {
+            fn AXIsProcessTrusted() -> bool;
         }
-    } else {
-        // If disabling, unregister all OS hooks. Use plugin API to unregister
-        // any tokens stored in `regs`. After successful unregistration clear the list.
-
-        // Example (pseudocode - plugin-specific):
-        // for token in regs.iter() { global_shortcut::unregister(token); }
-
-        regs.clear();
+        // SAFETY: takes no arguments and only reads the process' trust state
+        unsafe { AXIsProcessTrusted() }
     }
+    #[cfg(not(target_os = "macos"))]
+    {
+        true
+    }
+}
+
+fn input_permission() -> InputPermission {
+    InputPermission {
+        granted: input_permission_granted(),
+        required: cfg!(target_os = "macos"),
+    }
+}
+
+/// IPC Command: Whether hotkeys can work yet. The listener starts on its own
+/// once access is granted; no restart needed.
+#[tauri::command]
+fn get_input_permission_status() -> InputPermission {
+    input_permission()
+}
+
+/// IPC Command: Open the System Settings pane where Accessibility access is granted
+#[tauri::command]
+fn open_input_permission_settings() -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    {
+        std::process::Command::new("open")
+            .arg("x-apple.systempreferences:com.apple.preference.security?Privacy_Accessibility")
+            .spawn()
+            .map_err(|e| format!("Failed to open System Settings: {}", e))?;
+    }
+    Ok(())
+}
+
+/// IPC Command: Called on focus/blur of text inputs so typing does not fire pads
+/// ("normal", "suppress_pads" or "suppress_all")
+#[tauri::command]
+fn set_input_capture_mode(mode: CaptureMode, registry: State<'_, HotkeyRegistry>) {
+    registry.capture_mode.store(mode as u8, Ordering::Relaxed);
+}
 
-    println!(
-        "[Consonance] Keyboard sensing: {}",
+/// IPC Command: Toggle global keyboard listener state
+#[tauri::command]
+fn toggle_listener(
+    state: bool,
+    registry: State<'_, HotkeyRegistry>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    // Fast, lock-free publish of the enabled/disabled state so any callbacks
+    // that are racing with a toggle can short-circuit quickly.
+    registry.enabled.store(state, Ordering::SeqCst);
+    if registry.backend == HotkeyBackend::Shortcuts {
+        if state {
+            register_shortcuts(&app_handle)?;
+        } else {
+            unregister_shortcuts(&app_handle)?;
+        }
+    }
+
+    info!(
+        "Keyboard sensing: {}",
         if state { "ACTIVE" } else { "RELEASED" }
     );
+    listener_changed(&app_handle, state);
+    Ok(())
+}
+
+/// Registers a global shortcut for every main binding, unless they are
+/// registered already. Keys the plugin cannot take are skipped.
+fn register_shortcuts(app_handle: &AppHandle) -> Result<(), String> {
+    let registry = app_handle.state::<HotkeyRegistry>();
+    let mut registrations = registry.registrations.lock().map_err(|e| e.to_string())?;
+    if !registrations.is_empty() {
+        return Ok(());
+    }
+    let bindings: Vec<_> = app_handle
+        .state::<Mutex<KeyBindings>>()
+        .lock()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .collect();
+    for (target, key) in bindings {
+        let name = keymap::code_name(key);
+        let code = match name.parse::<ShortcutCode>() {
+            Ok(code) => code,
+            Err(_) => {
+                warn!("No global shortcut for key {} ({})", name, target);
+                continue;
+            }
+        };
+        let shortcut = Shortcut::new(None, code);
+        match app_handle.global_shortcut().register(shortcut) {
+            Ok(()) => registrations.push((shortcut, target)),
+            Err(e) => warn!(
+                "Global shortcut {} ({}) not registered: {}",
+                name, target, e
+            ),
+        }
+    }
+    info!("{} global shortcuts registered", registrations.len());
+    Ok(())
+}
+
+/// Drops every registered global shortcut
+fn unregister_shortcuts(app_handle: &AppHandle) -> Result<(), String> {
+    let registry = app_handle.state::<HotkeyRegistry>();
+    let mut registrations = registry.registrations.lock().map_err(|e| e.to_string())?;
+    for (shortcut, target) in registrations.drain(..) {
+        if let Err(e) = app_handle.global_shortcut().unregister(shortcut) {
+            warn!("Global shortcut for {} not unregistered: {}", target, e);
+        }
+    }
+    Ok(())
+}
+
+/// Global-shortcut plugin handler: the `Shortcuts` backend's key path
+fn shortcut_fired(app_handle: &AppHandle, shortcut: &Shortcut, event: ShortcutEvent) {
+    let registry = app_handle.state::<HotkeyRegistry>();
+    if !registry.enabled.load(Ordering::Relaxed) {
+        return;
+    }
+    let target = registry
+        .registrations
+        .lock()
+        .ok()
+        .and_then(|registrations| {
+            registrations
+                .iter()
+                .find(|(registered, _)| registered == shortcut)
+                .map(|(_, target)| *target)
+        });
+    let mut mode = CaptureMode::from_u8(registry.capture_mode.load(Ordering::Relaxed));
+    if mode == CaptureMode::Normal
+        && registry.suppress_when_focused.load(Ordering::Relaxed)
+        && registry.window_focused.load(Ordering::Relaxed)
+    {
+        mode = CaptureMode::SuppressPads;
+    }
+    let key_on = |k: &str| {
+        registry
+            .disabled_keys
+            .read()
+            .map_or(true, |d| !d.contains(k))
+    };
+    if let Some(k) = target.filter(|k| mode.allows(k) && key_on(k)) {
+        let pressed = event.state() == ShortcutState::Pressed;
+        emit_pad_event(app_handle, k, pressed, Modifiers::default(), 1.0);
+    }
+}
+
+/// Tells the frontend and the tray that the listener was armed or disarmed
+fn listener_changed(app_handle: &AppHandle, enabled: bool) {
+    if let Some(tray) = app_handle.try_state::<Tray>() {
+        tray.set_listener_checked(enabled);
+    }
+    let _ = app_handle.emit("listener-toggled", ListenerToggled { enabled });
+}
+
+/// Brings the main window to the front, e.g. for a forwarded second launch
+fn focus_main_window(app_handle: &AppHandle) {
+    if let Some(window) = app_handle.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+/// Command line of this or a forwarded launch. `argv[0]` is the executable;
+/// relative paths resolve against `cwd` of the launch that passed them.
+/// Requests wait in `LaunchQueue` until the frontend calls `launch_ready`.
+fn handle_launch_args(app_handle: &AppHandle, argv: Vec<String>, cwd: PathBuf) {
+    let requests = cli::parse(&argv, &cwd);
+    if !requests.is_empty() {
+        info!("Launch arguments {:?}", &argv[1..]);
+        queue_launch_requests(app_handle, requests);
+    }
+}
+
+/// Runs launch requests now, or once the frontend is ready for their events
+fn queue_launch_requests(app_handle: &AppHandle, requests: Vec<LaunchRequest>) {
+    let queue = app_handle.state::<LaunchQueue>();
+    // `ready` is only read and set under the lock, so nothing is queued after the drain
+    if let Ok(mut pending) = queue.pending.lock() {
+        if !queue.ready.load(Ordering::SeqCst) {
+            pending.extend(requests);
+            return;
+        }
+    }
+    run_launch_requests(app_handle.clone(), requests);
+}
+
+/// Command line requests held back until the frontend listens for their events
+pub struct LaunchQueue {
+    ready: AtomicBool,
+    pending: Mutex<Vec<LaunchRequest>>,
+}
+
+/// Payload of `startup-warning`
+#[derive(Clone, Serialize)]
+struct StartupWarning {
+    message: String,
+}
+
+/// IPC Command: The frontend's listeners are in place. Runs the command
+/// line of the first launch; later (forwarded) launches run right away.
+#[tauri::command]
+fn launch_ready(queue: State<'_, LaunchQueue>, app_handle: AppHandle) -> Result<(), String> {
+    let requests = {
+        let mut pending = queue.pending.lock().map_err(|e| e.to_string())?;
+        queue.ready.store(true, Ordering::SeqCst);
+        std::mem::take(&mut *pending)
+    };
+    run_launch_requests(app_handle, requests);
     Ok(())
 }
 
-// ============================================================================
-// AUDIO FILE SERVING
-// ============================================================================
+/// Loads sessions and pads from the command line in order. Results arrive
+/// as the usual `session-pad-loaded` events (plus `session-loaded` for a
+/// whole session); problems as `startup-warning`.
+fn run_launch_requests(app_handle: AppHandle, requests: Vec<LaunchRequest>) {
+    if requests.is_empty() {
+        return;
+    }
+    tauri::async_runtime::spawn(async move {
+        for request in requests {
+            if let Err(message) = run_launch_request(&app_handle, request).await {
+                warn!("{}", message);
+                let _ = app_handle.emit("startup-warning", StartupWarning { message });
+            }
+        }
+    });
+}
+
+async fn run_launch_request(app_handle: &AppHandle, request: LaunchRequest) -> Result<(), String> {
+    match request {
+        LaunchRequest::Warning(message) => Err(message),
+        LaunchRequest::Session(path) => {
+            let saved = session::load_file(&path)?;
+            let report = restore_session(saved, app_handle).await?;
+            let _ = app_handle.emit("session-loaded", report);
+            Ok(())
+        }
+        LaunchRequest::Pad { pad, path } => load_launch_pad(app_handle, pad, None, &path)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("[Social Noise] --pad {} {}: {}", pad, path.display(), e)),
+        LaunchRequest::DeepLink(url) => {
+            let handled = run_deep_link(app_handle, &url).await;
+            if let Some(error) = &handled.error {
+                warn!("{}", error);
+            }
+            let _ = app_handle.emit("deeplink-handled", handled);
+            Ok(())
+        }
+    }
+}
+
+/// `audio_load` on behalf of the command line or a link. The outcome is
+/// reported as `session-pad-loaded`, since no IPC caller receives the result.
+async fn load_launch_pad(
+    app_handle: &AppHandle,
+    pad: &'static str,
+    bank: Option<u32>,
+    path: &std::path::Path,
+) -> Result<String, String> {
+    let key = app_handle.state::<BankSelector>().pad_id(pad, bank);
+    let path = path.to_string_lossy().to_string();
+    let outcome = audio_load(
+        pad.to_string(),
+        bank,
+        path.clone(),
+        None,
+        app_handle.state::<BankSelector>(),
+        app_handle.state::<AudioEngine>(),
+        app_handle.clone(),
+    )
+    .await
+    .map_err(|e| e.to_string());
+    let (result, error) = match outcome {
+        Ok(result) => (Some(result), None),
+        Err(e) => (None, Some(e)),
+    };
+    let _ = app_handle.emit(
+        "session-pad-loaded",
+        SessionPadLoaded {
+            key: key.clone(),
+            path,
+            params: None,
+            result,
+            error: error.clone(),
+        },
+    );
+    match error {
+        Some(error) => Err(error),
+        None => Ok(key),
+    }
+}
+
+/// Validates an lsamp:// link against the harbor and runs it
+async fn run_deep_link(app_handle: &AppHandle, url: &str) -> DeepLinkHandled {
+    let link = match get_audio_harbor().and_then(|harbor| deeplink::parse(url, &harbor)) {
+        Ok(link) => link,
+        Err(e) => return DeepLinkHandled::rejected(url, e),
+    };
+    match link {
+        DeepLink::Load {
+            pad,
+            bank,
+            file,
+            path,
+        } => {
+            info!("Link loads {} onto {}", file, pad);
+            let outcome = load_launch_pad(app_handle, pad, bank, &path).await;
+            DeepLinkHandled {
+                url: url.to_string(),
+                action: Some("load".to_string()),
+                pad: outcome.as_ref().ok().cloned(),
+                file: Some(file),
+                error: outcome.err(),
+            }
+        }
+    }
+}
+
+/// Remote-control commands, run through the same command functions as IPC
+impl RemoteHost for AppHandle {
+    fn run(&self, command: RemoteCommand) -> remote::Reply<'_> {
+        Box::pin(async move {
+            let banks = || self.state::<BankSelector>();
+            let audio = || self.state::<AudioEngine>();
+            match command {
+                RemoteCommand::Load { key, bank, file } => {
+                    let path = harbor::resolve_in_harbor(&get_audio_harbor()?, &file)?;
+                    if !harbor::is_audio_file(&path) || !path.is_file() {
+                        return Err(format!("{:?} is not an audio file in the harbor", file));
+                    }
+                    let path = path.to_string_lossy().to_string();
+                    let result = audio_load(key, bank, path, None, banks(), audio(), self.clone())
+                        .await
+                        .map_err(|e| e.to_string())?;
+                    serde_json::to_value(result).map_err(|e| e.to_string())
+                }
+                RemoteCommand::Play { key, bank, params } => {
+                    audio_play(key, bank, params, banks(), audio()).await?;
+                    Ok(serde_json::Value::Null)
+                }
+                RemoteCommand::Stop {
+                    key,
+                    bank,
+                    effective_release,
+                } => {
+                    audio_stop(key, bank, effective_release, banks(), audio()).await?;
+                    Ok(serde_json::Value::Null)
+                }
+                RemoteCommand::StopAll => {
+                    audio().stop_all();
+                    Ok(serde_json::Value::Null)
+                }
+                RemoteCommand::Update { key, bank, params } => {
+                    audio_update_params(key, bank, params, banks(), audio()).await?;
+                    Ok(serde_json::Value::Null)
+                }
+                RemoteCommand::SetBpm { bpm } => {
+                    audio_set_master_bpm(bpm, audio()).await?;
+                    Ok(serde_json::Value::Null)
+                }
+                // Handled per connection by the server
+                RemoteCommand::Auth { .. }
+                | RemoteCommand::SubscribeLevels { .. }
+                | RemoteCommand::UnsubscribeLevels => Ok(serde_json::Value::Null),
+            }
+        })
+    }
+
+    fn levels(&self) -> Arc<LevelsResponse> {
+        let bank = self.state::<BankSelector>().active.load(Ordering::Relaxed);
+        self.state::<AudioEngine>().get_levels(bank)
+    }
+}
+
+/// Tray menu clicks, routed like the matching commands
+fn handle_tray_action(app_handle: &AppHandle, action: TrayAction) {
+    match action {
+        TrayAction::ToggleListener => {
+            let registry = app_handle.state::<HotkeyRegistry>();
+            let now = !registry.enabled.fetch_xor(true, Ordering::SeqCst);
+            info!(
+                "Keyboard sensing: {} (tray)",
+                if now { "ACTIVE" } else { "RELEASED" }
+            );
+            listener_changed(app_handle, now);
+        }
+        TrayAction::StopAll => app_handle.state::<AudioEngine>().stop_all(),
+        TrayAction::ToggleWindow => {
+            if let Some(window) = app_handle.get_webview_window("main") {
+                if window.is_visible().unwrap_or(true) {
+                    let _ = window.hide();
+                    return;
+                }
+            }
+            focus_main_window(app_handle);
+        }
+        TrayAction::Quit => app_handle.exit(0),
+    }
+}
+
+fn listener_status(registry: &HotkeyRegistry) -> Result<ListenerStatus, String> {
+    Ok(ListenerStatus {
+        health: registry.listener.lock().map_err(|e| e.to_string())?.clone(),
+        enabled: registry.enabled.load(Ordering::SeqCst),
+    })
+}
+
+/// IPC Command: Whether the keyboard listener is alive, how often it was
+/// restarted and why it last died
+#[tauri::command]
+fn get_listener_status(registry: State<'_, HotkeyRegistry>) -> Result<ListenerStatus, String> {
+    listener_status(&registry)
+}
+
+fn hotkey_backend_info(backend: HotkeyBackend) -> HotkeyBackendInfo {
+    HotkeyBackendInfo {
+        backend,
+        limitations: backend.limitations(),
+    }
+}
+
+/// IPC Command: Which hotkey path is active and what it cannot do
+#[tauri::command]
+fn get_hotkey_backend(registry: State<'_, HotkeyRegistry>) -> HotkeyBackendInfo {
+    hotkey_backend_info(registry.backend)
+}
+
+/// IPC Command: Retry a dead keyboard listener now instead of after the backoff.
+/// A running listener is left alone (rdev offers no way to stop it).
+#[tauri::command]
+fn restart_listener(registry: State<'_, HotkeyRegistry>) -> Result<ListenerStatus, String> {
+    let status = listener_status(&registry)?;
+    if status.health.state == ListenerState::BackingOff {
+        info!("Keyboard listener restart requested");
+        registry.restart_requested.store(true, Ordering::SeqCst);
+    }
+    Ok(status)
+}
+
+/// Entry of `get_key_states`
+#[derive(Clone, Serialize)]
+struct KeyState {
+    key: &'static str,
+    enabled: bool,
+}
+
+fn key_states(registry: &HotkeyRegistry) -> Result<Vec<KeyState>, String> {
+    let disabled = registry.disabled_keys.read().map_err(|e| e.to_string())?;
+    Ok(keymap::targets()
+        .map(|key| KeyState {
+            key,
+            enabled: !disabled.contains(key),
+        })
+        .collect())
+}
+
+/// IPC Command: Per-key enabled flags (pads, "SPACE", bank keys), in grid order
+#[tauri::command]
+fn get_key_states(registry: State<'_, HotkeyRegistry>) -> Result<Vec<KeyState>, String> {
+    key_states(&registry)
+}
+
+/// IPC Command: Switch one global hotkey on or off without touching the others
+#[tauri::command]
+fn set_key_enabled(
+    key: String,
+    enabled: bool,
+    registry: State<'_, HotkeyRegistry>,
+) -> Result<Vec<KeyState>, String> {
+    let key = keymap::target(&key)?;
+    {
+        let mut disabled = registry.disabled_keys.write().map_err(|e| e.to_string())?;
+        if enabled {
+            disabled.remove(key);
+        } else {
+            disabled.insert(key);
+        }
+    }
+    key_states(&registry)
+}
+
+// ============================================================================
+// AUDIO FILE SERVING
+// ============================================================================
+
+/// Resolves a harbor-relative (or absolute) file name to an existing file
+fn resolve_audio_file(file_name: &str) -> Result<PathBuf, String> {
+    let harbor_path = get_audio_harbor()?;
+    let p = PathBuf::from(file_name);
+
+    let file_path = if p.is_absolute() {
+        p
+    } else {
+        // Security: Prevent path traversal for relative paths
+        harbor::resolve_in_harbor(&harbor_path, file_name)?
+    };
+
+    if !file_path.exists() {
+        return Err(format!("File not found: {:?}", file_path));
+    }
+    Ok(file_path)
+}
+
+/// IPC Command: Read an audio file, optionally as a `length`-byte chunk starting
+/// at `offset`. Chunks running past EOF come back short rather than failing.
+#[tauri::command]
+async fn get_audio_file(
+    file_name: String,
+    offset: Option<u64>,
+    length: Option<u64>,
+) -> Result<Vec<u8>, String> {
+    let file_path = resolve_audio_file(&file_name)?;
+
+    if offset.is_none() && length.is_none() {
+        return fs::read(&file_path).map_err(|e| format!("[Social Noise] File read failed: {}", e));
+    }
+
+    let mut file = fs::File::open(&file_path)
+        .map_err(|e| format!("[Social Noise] File read failed: {}", e))?;
+    file.seek(SeekFrom::Start(offset.unwrap_or(0)))
+        .map_err(|e| format!("[Social Noise] File seek failed: {}", e))?;
+
+    let mut chunk = Vec::new();
+    match length {
+        Some(len) => file.take(len).read_to_end(&mut chunk),
+        None => file.read_to_end(&mut chunk),
+    }
+    .map_err(|e| format!("[Social Noise] File read failed: {}", e))?;
+    Ok(chunk)
+}
+
+/// IPC Command: Title/artist/album/genre/comment of a file, without decoding audio
+#[tauri::command]
+async fn get_file_tags(path: String) -> Result<FileTags, String> {
+    let file_path = resolve_audio_file(&path)?;
+    tokio::task::spawn_blocking(move || audio_engine::read_tags(&file_path.to_string_lossy()))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// IPC Command: Size in bytes of an audio file, for chunked reads
+#[tauri::command]
+async fn get_audio_file_size(file_name: String) -> Result<u64, String> {
+    let file_path = resolve_audio_file(&file_name)?;
+    fs::metadata(&file_path)
+        .map(|m| m.len())
+        .map_err(|e| format!("[Social Noise] File read failed: {}", e))
+}
+
+// ============================================================================
+// SESSIONS
+// ============================================================================
+
+/// IPC Command: Save every loaded pad plus master settings as a named session
+#[tauri::command]
+async fn session_save(name: String, audio: State<'_, AudioEngine>) -> Result<(), String> {
+    let (master_volume, master_bpm) = audio.inner().master_settings();
+    let snapshot = Session::from_snapshot(
+        &name,
+        audio.inner().pad_snapshot(),
+        master_volume,
+        master_bpm,
+        audio.inner().performance(),
+        audio.inner().scene_list(),
+        &get_audio_harbor()?,
+    );
+    session::save(&snapshot)
+}
+
+/// IPC Command: Restore a saved session. Pads load one by one and each reports a
+/// `session-pad-loaded` event; a missing file fails only its own pad.
+#[tauri::command]
+async fn session_load(name: String, app_handle: AppHandle) -> Result<SessionLoadReport, String> {
+    let saved = session::load(&name)?;
+    restore_session(saved, &app_handle).await
+}
+
+/// Apply a session's master settings and load its pads one by one. A tempo
+/// the engine refuses fails the whole restore, before anything changes.
+async fn restore_session<H: Host>(
+    mut saved: Session,
+    host: &H,
+) -> Result<SessionLoadReport, String> {
+    let audio = host.audio();
+    audio
+        .set_master_bpm(saved.master_bpm)
+        .map_err(|e| format!("Session {:?}: {}", saved.name, e))?;
+    if let Ok(harbor_path) = get_audio_harbor() {
+        saved.resolve_paths(&harbor_path);
+    }
+    audio.set_master_volume(saved.master_volume);
+    if let Err(e) = audio.set_performance(saved.performance.take()) {
+        warn!("Session {:?}: take not restored: {}", saved.name, e);
+    }
+    if let Err(e) = audio.set_scenes(std::mem::take(&mut saved.scenes)) {
+        warn!("Session {:?}: scenes not restored: {}", saved.name, e);
+    }
+
+    let known = audio.file_analysis_snapshot();
+    let mut report = SessionLoadReport {
+        name: saved.name.clone(),
+        loaded: 0,
+        failed: Vec::new(),
+    };
+
+    for pad in saved.pads {
+        let (_, plain_key) = split_pad_id(&pad.key);
+        let outcome = if IS_COMMUNITY_BUILD && !["Q", "W", "E", "R"].contains(&plain_key) {
+            Err("This pad is restricted in the Community Build.".to_string())
+        } else if !PathBuf::from(&pad.path).is_file() {
+            Err(format!("File not found: {}", pad.path))
+        } else {
+            // Reuse analysis from the session file or from this run's cache
+            let cached_bpm = pad
+                .bpm
+                .or_else(|| known.get(&PathBuf::from(&pad.path)).and_then(|f| f.bpm));
+            audio
+                .load_sound(
+                    pad.key.clone(),
+                    &pad.path,
+                    cached_bpm,
+                    LoadOptions::default(),
+                    load_progress(host),
+                )
+                .await
+                .map_err(|e| report_load_error(host, &pad.key, &pad.path, e).to_string())
+        };
+
+        let event = match outcome {
+            Ok(result) => {
+                if let Some(params) = pad.params.clone() {
+                    audio.set_pad_params(&pad.key, params);
+                }
+                audio.set_pad_defaults(&pad.key, pad.defaults.clone());
+                if result.bpm.is_none() {
+                    spawn_analysis(host.clone(), pad.key.clone());
+                }
+                report.loaded += 1;
+                SessionPadLoaded {
+                    key: pad.key,
+                    path: pad.path,
+                    params: pad.params,
+                    result: Some(result),
+                    error: None,
+                }
+            }
+            Err(error) => {
+                warn!("Session pad {} failed: {}", pad.key, error);
+                report.failed.push(SessionPadError {
+                    key: pad.key.clone(),
+                    path: pad.path.clone(),
+                    error: error.clone(),
+                });
+                SessionPadLoaded {
+                    key: pad.key,
+                    path: pad.path,
+                    params: pad.params,
+                    result: None,
+                    error: Some(error),
+                }
+            }
+        };
+        host.emit_event("session-pad-loaded", event);
+    }
+    if let Err(e) = session::save_pad_defaults(&audio.pad_defaults()) {
+        warn!("{}", e);
+    }
+
+    Ok(report)
+}
+
+/// IPC Command: Bundle the current pads and their audio into a kit archive.
+/// Without `path` a save dialog asks for the destination. Returns the archive path.
+#[tauri::command]
+async fn kit_export(
+    path: Option<String>,
+    audio: State<'_, AudioEngine>,
+    app_handle: AppHandle,
+) -> Result<String, String> {
+    let destination = match path {
+        Some(path) => PathBuf::from(path),
+        None => rfd::AsyncFileDialog::new()
+            .add_filter("L-SAMP Kit", &[kit::KIT_EXTENSION])
+            .set_file_name(format!("kit.{}", kit::KIT_EXTENSION))
+            .save_file()
+            .await
+            .map(|handle| handle.path().to_path_buf())
+            .ok_or("User cancelled")?,
+    };
+
+    let name = destination
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "kit".to_string());
+    let harbor_path = get_audio_harbor()?;
+    let (master_volume, master_bpm) = audio.inner().master_settings();
+    let mut snapshot = Session::from_snapshot(
+        &name,
+        audio.inner().pad_snapshot(),
+        master_volume,
+        master_bpm,
+        audio.inner().performance(),
+        audio.inner().scene_list(),
+        &harbor_path,
+    );
+    // The kit lays files out itself from their real locations
+    snapshot.resolve_paths(&harbor_path);
+
+    let target = destination.clone();
+    tokio::task::spawn_blocking(move || {
+        kit::export_kit(&target, &harbor_path, snapshot, |progress| {
+            let _ = app_handle.emit("kit-export-progress", progress);
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    Ok(destination.to_string_lossy().to_string())
+}
+
+/// IPC Command: Extract a kit archive into a new harbor folder and load its session
+#[tauri::command]
+async fn kit_import(
+    archive_path: String,
+    hash_cache: State<'_, Mutex<HashCache>>,
+    settings: State<'_, Mutex<HarborSettings>>,
+    app_handle: AppHandle,
+) -> Result<KitImportSummary, String> {
+    let harbor_path = get_audio_harbor()?;
+    let harbor_files = scan_harbor(&harbor_path, &scan_options(&settings)?)?.files;
+    let mut cache = hash_cache.lock().map_err(|e| e.to_string())?.clone();
+
+    let (outcome, cache) = tokio::task::spawn_blocking(move || {
+        let outcome = kit::import_kit(
+            &PathBuf::from(archive_path),
+            &harbor_path,
+            &harbor_files,
+            &mut cache,
+        );
+        (outcome, cache)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    *hash_cache.lock().map_err(|e| e.to_string())? = cache;
+
+    let (mut summary, kit_session) = outcome?;
+    // The files are in the harbor either way
+    match restore_session(kit_session, &app_handle).await {
+        Ok(report) => summary.session = Some(report),
+        Err(e) => summary.warnings.push(e),
+    }
+    Ok(summary)
+}
+
+/// IPC Command: Saved session names with their timestamps, newest first
+#[tauri::command]
+async fn session_list() -> Result<Vec<SessionInfo>, String> {
+    session::list()
+}
+
+/// Periodic crash-recovery snapshot of the session (autosave.json)
+pub struct Autosave {
+    pub interval_secs: AtomicU64, // 0 stops the timer; `autosave_now` still works
+    last: Mutex<Option<String>>,  // Content of the latest write, to skip unchanged ones
+}
+
+const DEFAULT_AUTOSAVE_SECS: u64 = 60;
+
+/// Snapshots the engine (a quick clone under its lock) and writes the autosave
+/// when anything changed. Returns whether a file was written.
+fn autosave(app_handle: &AppHandle) -> Result<bool, String> {
+    let audio = app_handle.state::<AudioEngine>();
+    let (master_volume, master_bpm) = audio.master_settings();
+    let snapshot = Session::from_snapshot(
+        "autosave",
+        audio.pad_snapshot(),
+        master_volume,
+        master_bpm,
+        audio.performance(),
+        audio.scene_list(),
+        &get_audio_harbor()?,
+    );
+    let autosave = app_handle.state::<Autosave>();
+    let mut last = autosave.last.lock().map_err(|e| e.to_string())?;
+    match session::write_autosave(&snapshot, last.as_deref())? {
+        Some(content) => {
+            *last = Some(content);
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+/// Forward voices retired by the audio callback as `voice-ended` events.
+/// The callback can't emit itself, so it queues them for this thread.
+fn start_voice_events<H: Host>(host: H) {
+    if let Some(events) = host.audio().take_voice_events() {
+        thread::spawn(move || {
+            for ended in events {
+                host.emit_event("voice-ended", ended);
+            }
+        });
+    }
+}
+
+/// Least time between two `seq-step` events; the playhead shows the latest step
+const SEQ_STEP_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Emits the sequencer's steps as `seq-step`, coalesced to the newest one
+/// per `SEQ_STEP_INTERVAL` so a fast pattern cannot flood the webview
+fn start_seq_steps<H: Host>(host: H) {
+    if let Some(steps) = host.audio().take_seq_steps() {
+        thread::spawn(move || {
+            while let Ok(mut step) = steps.recv() {
+                while let Ok(newer) = steps.try_recv() {
+                    step = newer;
+                }
+                host.emit_event("seq-step", step);
+                thread::sleep(SEQ_STEP_INTERVAL);
+            }
+        });
+    }
+}
+
+/// Emits every count-in click as `count-in-tick`
+fn start_count_in_ticks<H: Host>(host: H) {
+    if let Some(ticks) = host.audio().take_count_in_ticks() {
+        thread::spawn(move || {
+            for tick in ticks {
+                host.emit_event("count-in-tick", tick);
+            }
+        });
+    }
+}
+
+/// Payload of `sample-rate-changed`
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SampleRateChanged {
+    previous: u32,
+    sample_rate: u32,
+}
+
+/// Moves the engine onto the named output, or the current default one, and
+/// tells the UI when that came with a different sample rate
+fn reopen_output<H: Host>(host: &H, device: Option<&str>) -> Result<(), String> {
+    let audio = host.audio();
+    let reopened = match device {
+        Some(name) => audio.reopen_output_named(name)?,
+        None => audio.reopen_default_output()?,
+    };
+    if let Some(previous) = reopened {
+        let sample_rate = audio.sample_rate();
+        host.emit_event(
+            "sample-rate-changed",
+            SampleRateChanged {
+                previous,
+                sample_rate,
+            },
+        );
+    }
+    Ok(())
+}
+
+/// Drains the engine's stream errors into notifications. A lost device is
+/// replaced by the default output where there is one.
+fn start_stream_faults<H: Host>(host: H) {
+    if let Some(faults) = host.audio().take_stream_faults() {
+        thread::spawn(move || {
+            for fault in &faults {
+                if fault.device_lost {
+                    match reopen_output(&host, None) {
+                        Ok(()) => {
+                            // Whatever else the old stream reported is moot now
+                            while faults.try_recv().is_ok() {}
+                            notify(
+                                &host,
+                                Level::Warning,
+                                Code::DeviceLost,
+                                format!(
+                                    "Audio device lost, default output reopened: {}",
+                                    fault.message
+                                ),
+                                serde_json::Value::Null,
+                            );
+                            continue;
+                        }
+                        Err(e) => warn!("Default output not reopened: {}", e),
+                    }
+                }
+                let (code, message) = if fault.device_lost {
+                    (
+                        Code::DeviceLost,
+                        format!("Audio device lost: {}", fault.message),
+                    )
+                } else {
+                    (
+                        Code::StreamError,
+                        format!("Audio stream error: {}", fault.message),
+                    )
+                };
+                notify(&host, Level::Error, code, message, serde_json::Value::Null);
+            }
+        });
+    }
+}
+
+/// How often the audio callback's problem counters are checked
+const CALLBACK_MONITOR_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Logs what the audio callback counted instead of logging, at most once per
+/// interval and only when a counter moved. Caught panics are also notified.
+fn start_callback_monitor<H: Host>(host: H) {
+    thread::spawn(move || {
+        let mut reported = CallbackErrors::default();
+        loop {
+            thread::sleep(CALLBACK_MONITOR_INTERVAL);
+            let errors = host.audio().callback_errors();
+            if errors.dropped_voice_events > reported.dropped_voice_events {
+                warn!(
+                    total = errors.dropped_voice_events,
+                    "{} voice-ended events dropped: queue full",
+                    errors.dropped_voice_events - reported.dropped_voice_events
+                );
+            }
+            if errors.non_finite_voices > reported.non_finite_voices {
+                warn!(
+                    total = errors.non_finite_voices,
+                    "{} voices killed for rendering NaN or infinity",
+                    errors.non_finite_voices - reported.non_finite_voices
+                );
+            }
+            if errors.late_sequencer_loops > reported.late_sequencer_loops {
+                warn!(
+                    total = errors.late_sequencer_loops,
+                    "{} sequencer loops played silent: not built in time",
+                    errors.late_sequencer_loops - reported.late_sequencer_loops
+                );
+            }
+            if errors.panics > reported.panics || errors.poisoned_locks > reported.poisoned_locks {
+                notify(
+                    &host,
+                    Level::Error,
+                    Code::EngineFault,
+                    format!(
+                        "Audio engine recovered from a panic ({} in the callback, {} poisoned locks so far)",
+                        errors.panics, errors.poisoned_locks
+                    ),
+                    serde_json::json!({
+                        "panics": errors.panics,
+                        "poisonedLocks": errors.poisoned_locks,
+                    }),
+                );
+            }
+            reported = errors;
+        }
+    });
+}
+
+/// Value of a config file load, or its default with the problem kept for
+/// `report_config_problems`
+fn config_or_default<T: Default>(
+    result: Result<T, String>,
+    file: &'static str,
+    problems: &mut Vec<(&'static str, String)>,
+) -> T {
+    result.unwrap_or_else(|message| {
+        problems.push((file, message));
+        T::default()
+    })
+}
+
+/// Startup config problems. The frontend is not listening yet; the ring
+/// buffer keeps them for `get_recent_notifications`.
+fn report_config_problems<H: Host>(host: &H, problems: Vec<(&'static str, String)>) {
+    for (file, message) in problems {
+        notify(
+            host,
+            Level::Warning,
+            Code::ConfigLoadFailed,
+            format!("{}; using defaults", message),
+            serde_json::json!({ "file": file }),
+        );
+    }
+}
+
+/// IPC Command: Latest backend notifications, oldest first (diagnostics panel)
+#[tauri::command]
+fn get_recent_notifications(notifications: State<'_, Notifications>) -> Vec<Notification> {
+    notifications.recent()
+}
+
+/// IPC Command: Change the log verbosity until the next launch
+#[tauri::command]
+fn set_log_level(level: LogLevel, logging: State<'_, Logging>) -> Result<(), String> {
+    logging.set_level(level)
+}
+
+/// IPC Command: Zip the log files for a bug report; returns the archive path
+#[tauri::command]
+async fn export_logs(logging: State<'_, Logging>) -> Result<String, String> {
+    logging
+        .export()
+        .map(|path| path.to_string_lossy().to_string())
+}
+
+/// Start the autosave timer thread. Serialization and the write happen here,
+/// never on an IPC handler or the audio callback.
+fn start_autosave(app_handle: AppHandle) {
+    thread::spawn(move || {
+        let mut waited = 0;
+        loop {
+            thread::sleep(Duration::from_secs(1));
+            waited += 1;
+            // Re-read every second so interval changes apply without a restart
+            let interval = app_handle
+                .state::<Autosave>()
+                .interval_secs
+                .load(Ordering::Relaxed);
+            if interval == 0 || waited < interval {
+                continue;
+            }
+            waited = 0;
+            if let Err(e) = autosave(&app_handle) {
+                warn!("{}", e);
+            }
+        }
+    });
+}
+
+/// IPC Command: Autosave immediately, e.g. before a risky operation.
+/// Returns false when nothing changed since the last autosave.
+#[tauri::command]
+async fn autosave_now(app_handle: AppHandle) -> Result<bool, String> {
+    tokio::task::spawn_blocking(move || autosave(&app_handle))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Autosave of a crashed run, found at startup and not restored or discarded yet
+pub struct PendingRestore {
+    offer: Mutex<Option<SessionInfo>>,
+}
+
+/// IPC Command: The restore offered by `session-restore-available`, if still open
+/// (the event may fire before the frontend listens)
+#[tauri::command]
+fn session_restore_available(
+    pending: State<'_, PendingRestore>,
+) -> Result<Option<SessionInfo>, String> {
+    Ok(pending.offer.lock().map_err(|e| e.to_string())?.clone())
+}
+
+/// IPC Command: Load the crashed run's autosave through the normal session path.
+/// Pads whose files went missing are reported individually.
+#[tauri::command]
+async fn session_restore_autosave(
+    pending: State<'_, PendingRestore>,
+    app_handle: AppHandle,
+) -> Result<SessionLoadReport, String> {
+    let saved = session::load_restore()?;
+    let report = restore_session(saved, &app_handle).await?;
+    session::discard_restore()?;
+    *pending.offer.lock().map_err(|e| e.to_string())? = None;
+    Ok(report)
+}
+
+/// IPC Command: Decline the restore; the crashed run's autosave is removed
+#[tauri::command]
+fn session_discard_autosave(pending: State<'_, PendingRestore>) -> Result<(), String> {
+    session::discard_restore()?;
+    *pending.offer.lock().map_err(|e| e.to_string())? = None;
+    Ok(())
+}
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+// ============================================================================
+// FILE PICKER
+// ============================================================================
+
+/// IPC Command: Open native file dialog to pick an audio file
+#[tauri::command]
+async fn select_file() -> Result<String, String> {
+    let file = rfd::AsyncFileDialog::new()
+        .add_filter("Audio", harbor::AUDIO_EXTENSIONS)
+        .pick_file()
+        .await;
+
+    match file {
+        Some(handle) => Ok(handle.path().to_string_lossy().to_string()),
+        None => Err("User cancelled".to_string()),
+    }
+}
+
+/// IPC Command: Toggle Developer Tools
+#[tauri::command]
+fn toggle_devtools(app_handle: AppHandle) -> Result<(), String> {
+    if let Some(window) = app_handle.get_webview_window("main") {
+        if window.is_devtools_open() {
+            window.close_devtools();
+        } else {
+            window.open_devtools();
+        }
+        Ok(())
+    } else {
+        Err("Main window not found".to_string())
+    }
+}
+
+/// One rejected `apply_config` field; the rest of the config still applies
+#[derive(Debug, Clone, Serialize)]
+struct ConfigFieldError {
+    field: &'static str, // AppConfig field name, e.g. "master_volume"
+    message: String,
+}
+
+/// "#rgb", "#rrggbb" or "#rrggbbaa"
+fn is_hex_color(color: &str) -> bool {
+    color.strip_prefix('#').is_some_and(|hex| {
+        matches!(hex.len(), 3 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit())
+    })
+}
+
+/// IPC Command: Apply configuration changes. Every field is validated on its
+/// own: invalid ones keep their previous setting and are listed in the error.
+#[tauri::command]
+fn apply_config(config: AppConfig, app_handle: AppHandle) -> Result<(), Vec<ConfigFieldError>> {
+    // The config reaches most of the managed state
+    let audio = app_handle.state::<AudioEngine>();
+    let harbor_settings = app_handle.state::<Mutex<HarborSettings>>();
+    let key_bindings = app_handle.state::<Mutex<KeyBindings>>();
+    let registry = app_handle.state::<HotkeyRegistry>();
+    // In Tauri 2, event emission to windows is handled differently
+    // The config is accepted and logged; frontend state management handles it
+    debug!("Applied: {:?}", config);
+    let mut errors = Vec::new();
+    let mut check = |field: &'static str, result: Result<(), String>| {
+        if let Err(message) = result {
+            warn!("Config {} rejected: {}", field, message);
+            errors.push(ConfigFieldError { field, message });
+        }
+    };
+
+    // An empty accent means "unchanged" (volume-only updates send one)
+    if !config.accent_color.is_empty() && !is_hex_color(&config.accent_color) {
+        check(
+            "accent_color",
+            Err(format!("Not a hex color: {:?}", config.accent_color)),
+        );
+    }
+    check(
+        "master_volume",
+        if (0.0..=MAX_MASTER_VOLUME).contains(&config.master_volume) {
+            audio.inner().set_master_volume(config.master_volume);
+            Ok(())
+        } else {
+            Err(format!(
+                "{} is outside 0..{}",
+                config.master_volume, MAX_MASTER_VOLUME
+            ))
+        },
+    );
+    if let (Some(min), Some(max)) = (config.bpm_range_min, config.bpm_range_max) {
+        check("bpm_range_min", audio.inner().set_bpm_range(min, max));
+    }
+    if let Some(limit_mb) = config.decode_limit_mb {
+        check(
+            "decode_limit_mb",
+            audio.inner().set_decode_limit_mb(limit_mb),
+        );
+    }
+    if let Some(permits) = config.decode_concurrency {
+        check(
+            "decode_concurrency",
+            audio.inner().set_decode_concurrency(permits),
+        );
+    }
+    if let Some(release) = config.stop_all_release {
+        check(
+            "stop_all_release",
+            audio.inner().set_stop_all_release(Some(release)),
+        );
+    } else if config.clear_stop_all_release == Some(true) {
+        check(
+            "clear_stop_all_release",
+            audio.inner().set_stop_all_release(None),
+        );
+    }
+    match harbor_settings.lock() {
+        Ok(mut settings) => {
+            if let Some(limit_mb) = config.import_limit_mb {
+                check(
+                    "import_limit_mb",
+                    match limit_mb.checked_mul(1024 * 1024) {
+                        Some(bytes) if bytes > 0 => {
+                            settings.import_limit_bytes = bytes;
+                            Ok(())
+                        }
+                        _ => Err(format!("{} MB is not a usable limit", limit_mb)),
+                    },
+                );
+            }
+            if let Some(watch) = config.harbor_watch {
+                settings.watch_enabled = watch;
+            }
+            if let Some(scan) = config.harbor_scan.clone() {
+                check("harbor_scan", scan.validate().map(|_| settings.scan = scan));
+            }
+        }
+        Err(e) => check("harbor_scan", Err(e.to_string())),
+    }
+    if let Some(path) = config
+        .harbor_path
+        .as_deref()
+        .filter(|path| !path.is_empty())
+    {
+        check("harbor_path", set_audio_harbor(Path::new(path)));
+    }
+    if let Some(name) = config
+        .output_device
+        .as_deref()
+        .filter(|name| !name.is_empty())
+    {
+        check("output_device", reopen_output(&app_handle, Some(name)));
+    }
+    if let Some(suppress) = config.suppress_pads_when_focused {
+        registry
+            .suppress_when_focused
+            .store(suppress, Ordering::Relaxed);
+    }
+    if let Some(repeat) = config.key_repeat {
+        registry
+            .allow_repeat
+            .store(repeat == KeyRepeat::Allow, Ordering::Relaxed);
+    }
+    if let Some(combo) = config.listener_toggle.as_deref() {
+        check(
+            "listener_toggle",
+            Combo::parse(combo).and_then(|combo| {
+                *registry.toggle_combo.write().map_err(|e| e.to_string())? = combo;
+                Ok(())
+            }),
+        );
+    }
+    if let Some(enabled) = config.gamepad_enabled {
+        app_handle.state::<GamepadState>().set_enabled(enabled);
+    }
+    if let Some(secs) = config.autosave_interval_secs {
+        app_handle
+            .state::<Autosave>()
+            .interval_secs
+            .store(secs, Ordering::Relaxed);
+    }
+    if let Some(layout) = config.keyboard_layout {
+        registry.layout.store(layout as u8, Ordering::Relaxed);
+    }
+    if let Some(blip) = config.listener_toggle_blip {
+        registry.toggle_blip.store(blip, Ordering::Relaxed);
+    }
+    match key_bindings.lock() {
+        Ok(mut bindings) => {
+            let numpad = config.numpad_pads.unwrap_or(bindings.numpad());
+            if let Some(table) = config.key_bindings.as_ref() {
+                check(
+                    "key_bindings",
+                    KeyBindings::from_config(table).map(|table| *bindings = table),
+                );
+            }
+            bindings.set_numpad(numpad);
+        }
+        Err(e) => check("key_bindings", Err(e.to_string())),
+    }
+    // Registered shortcuts follow the new bindings
+    if config.key_bindings.is_some()
+        && registry.backend == HotkeyBackend::Shortcuts
+        && registry.enabled.load(Ordering::SeqCst)
+    {
+        check(
+            "key_bindings",
+            unregister_shortcuts(&app_handle).and_then(|_| register_shortcuts(&app_handle)),
+        );
+    }
+    if let Some(settings) = config.remote.clone() {
+        let remote = app_handle.state::<RemoteServer>();
+        check(
+            "remote",
+            remote.configure(settings, Arc::new(app_handle.clone())),
+        );
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// IPC Command: Current hotkey -> pad mapping, in grid order
+#[tauri::command]
+fn get_key_bindings(
+    key_bindings: State<'_, Mutex<KeyBindings>>,
+) -> Result<Vec<KeyBinding>, String> {
+    Ok(key_bindings.lock().map_err(|e| e.to_string())?.list())
+}
+
+/// IPC Command: Bind a pad slot (or "SPACE") to an rdev key name such as "KeyQ".
+/// Returns the updated mapping for the frontend to persist in its config.
+#[tauri::command]
+fn set_key_binding(
+    pad: String,
+    key_name: String,
+    key_bindings: State<'_, Mutex<KeyBindings>>,
+) -> Result<Vec<KeyBinding>, String> {
+    let mut bindings = key_bindings.lock().map_err(|e| e.to_string())?;
+    bindings.set(&pad, &key_name)?;
+    info!("{} bound to {}", pad, key_name);
+    Ok(bindings.list())
+}
+
+// ============================================================================
+// AUDIO CONTROL COMMANDS
+// ============================================================================
+
+/// The optional part of an `audio_load` request; everything is off when omitted
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PadLoadOptions {
+    cached_bpm: Option<f32>, // Known tempo of the file, skips the analysis
+    #[serde(default)]
+    auto_trim: bool, // Start the suggested region past leading/trailing silence
+    loudness_target: Option<f32>, // Normalize playback to this LUFS
+    #[serde(default)]
+    reset_defaults: bool, // Drop the pad's stored PlayParams defaults
+    #[serde(default)]
+    jump_queue: bool, // Decode ahead of loads queued for other pads
+}
+
+#[tauri::command]
+async fn audio_load(
+    key: String,
+    bank: Option<u32>, // Defaults to the active bank
+    path: String,
+    options: Option<PadLoadOptions>,
+    banks: State<'_, BankSelector>,
+    audio: State<'_, AudioEngine>,
+    app_handle: AppHandle,
+) -> Result<LoadResult, LoadError> {
+    let PadLoadOptions {
+        cached_bpm,
+        auto_trim,
+        loudness_target,
+        reset_defaults,
+        jump_queue,
+    } = options.unwrap_or_default();
+    if IS_COMMUNITY_BUILD && !["Q", "W", "E", "R"].contains(&key.as_str()) {
+        info!("BLOCKED Community Build Request: {}", key);
+        return Err("This pad is restricted in the Community Build."
+            .to_string()
+            .into());
+    }
+    let key = banks.pad_id(&key, bank);
+    // DIAGNOSTIC: This MUST show Some(val) for the optimization to work
+    debug!("Request: {} | Cached BPM: {:?}", key, cached_bpm);
+    // audio.inner().load_sound(key, &path).await
+    // Saved per-sample settings win over frontend defaults
+    let settings = harbor::load_sidecar(&PathBuf::from(&path));
+    let saved = settings.clone().unwrap_or_default();
+    let options = LoadOptions {
+        auto_trim,
+        loudness_target,
+        gain_trim_db: saved.gain_trim_db.unwrap_or(0.0),
+        jump_queue,
+    };
+    let mut result = audio
+        .inner()
+        .load_sound(
+            key.clone(),
+            &path,
+            saved.bpm_override.or(cached_bpm),
+            options,
+            load_progress(&app_handle),
+        )
+        .await
+        .map_err(|e| report_load_error(&app_handle, &key, &path, e))?; // Replaced the above line with this
+
+    // Defaults tuned for the previous sample rarely fit a new one
+    if reset_defaults && audio.inner().pad_defaults().contains_key(&key) {
+        audio.inner().set_pad_defaults(&key, None);
+        session::save_pad_defaults(&audio.inner().pad_defaults())?;
+    }
+
+    if settings.is_some() {
+        result.trim_start = saved.trim_start.unwrap_or(result.trim_start);
+        result.trim_end = saved.trim_end.unwrap_or(result.trim_end);
+        result.start_time = saved.start_time.unwrap_or(result.start_time);
+        result.end_time = saved.end_time.unwrap_or(result.end_time);
+        result.settings = settings;
+    }
+
+    // Without a cached BPM, analysis runs in the background and reports via event
+    if result.bpm.is_none() {
+        spawn_analysis(app_handle, key);
+    }
+
+    Ok(result)
+}
+
+/// IPC Command: Stop a pad's unfinished load; its `audio_load` fails with
+/// kind `Cancelled`. False when nothing was loading.
+#[tauri::command]
+fn audio_load_cancel(
+    key: String,
+    bank: Option<u32>, // Defaults to the active bank
+    banks: State<'_, BankSelector>,
+    audio: State<'_, AudioEngine>,
+) -> Result<bool, String> {
+    audio.inner().cancel_load(&banks.pad_id(&key, bank))
+}
+
+/// IPC Command: Reopen the output on the current default device, e.g. after
+/// a headset connected. Playing pads carry over.
+#[tauri::command]
+fn audio_reopen_output(app_handle: AppHandle) -> Result<(), String> {
+    reopen_output(&app_handle, None)
+}
+
+/// Notifies about files that failed to decode and passes the error on.
+/// Missing files, cancelled loads and engine-side failures are ordinary
+/// command errors.
+fn report_load_error<H: Host>(host: &H, key: &str, path: &str, error: LoadError) -> LoadError {
+    if !matches!(
+        error.kind,
+        LoadErrorKind::FileMissing
+            | LoadErrorKind::Superseded
+            | LoadErrorKind::Cancelled
+            | LoadErrorKind::Other
+    ) {
+        notify(
+            host,
+            Level::Error,
+            Code::DecodeFailed,
+            format!("{} could not be decoded: {}", path, error.message),
+            serde_json::json!({ "pad": key, "path": path, "kind": error.kind }),
+        );
+    }
+    error
+}
+
+/// Progress callback for `load_sound` that emits `load-progress`
+fn load_progress<H: Host>(host: &H) -> impl Fn(LoadProgress) + Send + 'static {
+    let host = host.clone();
+    move |progress| {
+        host.emit_event("load-progress", progress);
+    }
+}
+
+/// Run a pad's pending BPM analysis off the IPC path and emit `analysis-complete`
+fn spawn_analysis<H: Host>(host: H, key: String) {
+    tauri::async_runtime::spawn(async move {
+        if let Some(done) = host.audio().run_analysis(&key).await {
+            if matches!(done.bpm_source, BpmSource::Fallback) {
+                let reason = done.bpm_error.as_deref().unwrap_or("unknown error");
+                notify(
+                    &host,
+                    Level::Warning,
+                    Code::AnalysisFallback,
+                    format!(
+                        "BPM detection failed for {} ({}); sync stays off unless forced",
+                        key, reason
+                    ),
+                    serde_json::json!({ "pad": key, "reason": reason }),
+                );
+            }
+            host.emit_event("analysis-complete", done);
+        }
+    });
+}
+
+/// IPC Command: Persist pad settings for a harbor sample in its sidecar file
+#[tauri::command]
+async fn save_sample_settings(
+    relative_path: String,
+    settings: SampleSettings,
+) -> Result<(), String> {
+    let harbor_path = get_audio_harbor()?;
+    let sample = harbor::resolve_in_harbor(&harbor_path, &relative_path)?;
+    harbor::save_sidecar(&sample, &settings)
+}
 
+/// IPC Command: Read a harbor sample's saved pad settings (None if absent or corrupt)
 #[tauri::command]
-async fn get_audio_file(file_name: String, app_handle: AppHandle) -> Result<Vec<u8>, String> {
-    let harbor_path = get_audio_harbor(&app_handle)?;
-    let p = PathBuf::from(&file_name);
+async fn load_sample_settings(relative_path: String) -> Result<Option<SampleSettings>, String> {
+    let harbor_path = get_audio_harbor()?;
+    let sample = harbor::resolve_in_harbor(&harbor_path, &relative_path)?;
+    Ok(harbor::load_sidecar(&sample))
+}
 
-    let file_path = if p.is_absolute() {
-        p
-    } else {
-        let path = harbor_path.join(&file_name);
-        // Security: Prevent path traversal for relative paths
-        if !path.starts_with(&harbor_path) {
-            return Err("Path traversal detected".to_string());
-        }
-        path
-    };
+/// IPC Command: Poll the background BPM analysis of a pad (fallback for missed events)
+#[tauri::command]
+async fn audio_analysis_status(
+    key: String,
+    bank: Option<u32>, // Defaults to the active bank
+    banks: State<'_, BankSelector>,
+    audio: State<'_, AudioEngine>,
+) -> Result<AnalysisStatus, String> {
+    let key = banks.pad_id(&key, bank);
+    audio.inner().get_analysis_status(&key)
+}
 
-    if !file_path.exists() {
-        return Err(format!("File not found: {:?}", file_path));
+/// IPC Command: Play a pad. Returns the params that had to be corrected
+/// (NaN, negative times, volume out of range, ...), usually none.
+#[tauri::command]
+async fn audio_play(
+    key: String,
+    bank: Option<u32>,         // Defaults to the active bank
+    params: PartialPlayParams, // Omitted fields come from the pad's defaults
+    banks: State<'_, BankSelector>,
+    audio: State<'_, AudioEngine>,
+) -> Result<Vec<&'static str>, String> {
+    if IS_COMMUNITY_BUILD && !["Q", "W", "E", "R"].contains(&key.as_str()) {
+        debug!("BLOCKED Community Build Play: {}", key);
+        return Err("This pad is restricted in the Community Build.".to_string());
     }
+    let key = banks.pad_id(&key, bank);
+    let mut params = audio.inner().resolve_play_params(&key, params)?;
+    let corrected = params.sanitize();
+    debug!("Key: {}, Params: {:?}", key, params);
+    audio.inner().play_sound(key, params)?;
+    Ok(corrected)
+}
 
-    fs::read(&file_path).map_err(|e| format!("[Social Noise] File read failed: {}", e))
+/// IPC Command: Store a pad's default PlayParams, or drop them with `params: null`.
+/// They fill whatever `audio_play` leaves out and survive frontend crashes.
+#[tauri::command]
+async fn set_pad_defaults(
+    key: String,
+    bank: Option<u32>, // Defaults to the active bank
+    params: Option<PlayParams>,
+    banks: State<'_, BankSelector>,
+    audio: State<'_, AudioEngine>,
+) -> Result<(), String> {
+    let key = banks.pad_id(&key, bank);
+    audio
+        .inner()
+        .set_pad_defaults(&key, params.map(PartialPlayParams::from));
+    session::save_pad_defaults(&audio.inner().pad_defaults())
 }
 
-// ============================================================================
-// CONFIGURATION
-// ============================================================================
+/// IPC Command: Every pad's stored defaults, keyed by "{bank}:{key}"
+#[tauri::command]
+async fn get_pad_defaults(
+    audio: State<'_, AudioEngine>,
+) -> Result<HashMap<String, PartialPlayParams>, String> {
+    Ok(audio.inner().pad_defaults())
+}
 
-// ============================================================================
-// FILE PICKER
-// ============================================================================
+/// IPC Command: Fade out a pad. Reports how many voices that caught; a pad
+/// with nothing loaded is an error.
+#[tauri::command]
+async fn audio_stop(
+    key: String,
+    bank: Option<u32>, // Defaults to the active bank
+    effective_release: Option<f32>,
+    banks: State<'_, BankSelector>,
+    audio: State<'_, AudioEngine>,
+) -> Result<VoiceMatch, String> {
+    let key = banks.pad_id(&key, bank);
+    audio.inner().stop_sound(key, effective_release)
+}
 
-/// IPC Command: Open native file dialog to pick an audio file
+/// IPC Command: Change a pad's params and those of its playing voices.
+/// Reports the voices it reached and the params it corrected or clamped.
 #[tauri::command]
-async fn select_file() -> Result<String, String> {
-    let file = rfd::AsyncFileDialog::new()
-        .add_filter("Audio", &["mp3", "wav", "ogg", "flac"])
-        .pick_file()
-        .await;
+async fn audio_update_params(
+    key: String,
+    bank: Option<u32>, // Defaults to the active bank
+    params: crate::audio_engine::PlayParams,
+    banks: State<'_, BankSelector>,
+    audio: State<'_, AudioEngine>,
+) -> Result<VoiceMatch, String> {
+    let key = banks.pad_id(&key, bank);
+    debug!("Key: {}, Params: {:?}", key, params);
+    audio.inner().update_voice(key, params)
+}
 
-    match file {
-        Some(handle) => Ok(handle.path().to_string_lossy().to_string()),
-        None => Err("User cancelled".to_string()),
+#[tauri::command]
+async fn audio_get_levels(
+    banks: State<'_, BankSelector>,
+    audio: State<'_, AudioEngine>,
+) -> Result<Arc<LevelsResponse>, String> {
+    Ok(audio
+        .inner()
+        .get_levels(banks.active.load(Ordering::Relaxed)))
+}
+
+/// Pushes `levels` events from a metering thread, replacing JS polling
+pub struct LevelStream {
+    subscription: AtomicU64, // Bumped per (un)subscribe; a thread runs while it holds the latest
+}
+
+/// Accepted `levels` event intervals (~120 fps down to 1 fps)
+const LEVELS_INTERVAL_MS: std::ops::RangeInclusive<u64> = 8..=1000;
+
+/// IPC Command: Start (or retime) the `levels` event stream. Replaces any
+/// earlier subscription. Payload is the `audio_get_levels` result; both read
+/// the same published snapshot.
+#[tauri::command]
+async fn audio_levels_subscribe(
+    interval_ms: u64,
+    streams: State<'_, LevelStream>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    if !LEVELS_INTERVAL_MS.contains(&interval_ms) {
+        return Err(format!(
+            "[Social Noise] Levels interval {} ms out of range ({}-{})",
+            interval_ms,
+            LEVELS_INTERVAL_MS.start(),
+            LEVELS_INTERVAL_MS.end()
+        ));
     }
+    let subscription = streams.subscription.fetch_add(1, Ordering::SeqCst) + 1;
+    start_level_stream(app_handle, subscription, Duration::from_millis(interval_ms));
+    Ok(())
 }
 
-/// IPC Command: Toggle Developer Tools
+/// IPC Command: Stop the `levels` event stream. Polling keeps working.
 #[tauri::command]
-fn toggle_devtools(app_handle: AppHandle) -> Result<(), String> {
-    if let Some(window) = app_handle.get_webview_window("main") {
-        if window.is_devtools_open() {
-            window.close_devtools();
-        } else {
-            window.open_devtools();
+async fn audio_levels_unsubscribe(streams: State<'_, LevelStream>) -> Result<(), String> {
+    streams.subscription.fetch_add(1, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Metering thread of one subscription. Ticks that were missed, e.g. while
+/// a slow webview held up an emit, are skipped instead of sent in a burst,
+/// so at most one snapshot is ever in flight. A `levels` event may still
+/// show a voice whose `voice-ended` was emitted a tick earlier.
+fn start_level_stream(app_handle: AppHandle, subscription: u64, interval: Duration) {
+    thread::spawn(move || {
+        let mut next = Instant::now();
+        while app_handle
+            .state::<LevelStream>()
+            .subscription
+            .load(Ordering::SeqCst)
+            == subscription
+        {
+            let audio = app_handle.state::<AudioEngine>();
+            audio.publish_levels(
+                app_handle
+                    .state::<BankSelector>()
+                    .active
+                    .load(Ordering::Relaxed),
+            );
+            let _ = audio.with_levels(|levels| app_handle.emit("levels", levels));
+
+            next += interval;
+            let now = Instant::now();
+            if next <= now {
+                next = now + interval;
+            }
+            thread::sleep(next - now);
         }
-        Ok(())
-    } else {
-        Err("Main window not found".to_string())
+    });
+}
+
+/// Payload of `bank-changed`
+#[derive(Clone, Serialize)]
+struct BankChanged {
+    bank: u32,
+}
+
+fn set_active_bank<H: Host>(host: &H, bank: u32) {
+    host.banks().active.store(bank, Ordering::Relaxed);
+    info!("Active bank: {}", bank);
+    host.emit_event("bank-changed", BankChanged { bank });
+}
+
+/// IPC Command: Choose which bank the pads and global hotkeys address.
+/// Sounds loaded in other banks stay resident.
+#[tauri::command]
+async fn audio_set_active_bank(bank: u32, app_handle: AppHandle) -> Result<(), String> {
+    if bank >= BANK_COUNT {
+        return Err(format!("Bank {} out of range (0-{})", bank, BANK_COUNT - 1));
     }
+    set_active_bank(&app_handle, bank);
+    Ok(())
+}
+
+#[tauri::command]
+async fn audio_set_master_bpm(bpm: f32, audio: State<'_, AudioEngine>) -> Result<(), String> {
+    audio.inner().set_master_bpm(bpm)
+}
+
+/// IPC Command: Swing every second step of the sequencer, quantized
+/// launches, beat repeat and quantized recording (0 to 0.75 of a step)
+#[tauri::command]
+async fn audio_set_swing(swing: f32, audio: State<'_, AudioEngine>) -> Result<(), String> {
+    audio.inner().set_swing(swing)
 }
 
-/// IPC Command: Apply configuration changes
+/// IPC Command: Make pad triggers wait for the next step of `division`, or
+/// play at once with none
 #[tauri::command]
-fn apply_config(
-    config: AppConfig,
+async fn audio_set_launch_quantize(
+    division: Option<StepDivision>,
     audio: State<'_, AudioEngine>,
-    _app_handle: AppHandle,
 ) -> Result<(), String> {
-    // In Tauri 2, event emission to windows is handled differently
-    // The config is accepted and logged; frontend state management handles it
-    println!("[Config] Applied: {:?}", config);
-    audio.inner().set_master_volume(config.master_volume);
-    Ok(())
+    audio.inner().set_launch_quantize(division)
 }
 
-// ============================================================================
-// AUDIO CONTROL COMMANDS
-// ============================================================================
+/// IPC Command: Retrigger a pad on every step of `division` until
+/// `audio_beat_repeat_stop`
+#[tauri::command]
+async fn audio_beat_repeat_start(
+    key: String,
+    bank: Option<u32>, // Defaults to the active bank
+    division: StepDivision,
+    banks: State<'_, BankSelector>,
+    audio: State<'_, AudioEngine>,
+) -> Result<(), String> {
+    let key = banks.pad_id(&key, bank);
+    audio.inner().beat_repeat_start(key, division)
+}
 
 #[tauri::command]
-async fn audio_load(
+async fn audio_beat_repeat_stop(audio: State<'_, AudioEngine>) -> Result<(), String> {
+    audio.inner().beat_repeat_stop()
+}
+
+/// IPC Command: Where the master clock is (frame, seconds, beat, bar, bpm)
+#[tauri::command]
+async fn audio_get_transport(audio: State<'_, AudioEngine>) -> Result<Transport, String> {
+    Ok(audio.inner().transport())
+}
+
+/// IPC Command: Set a pad's sequencer steps (none clears them), heard from the next loop
+#[tauri::command]
+async fn audio_seq_set_pattern(
     key: String,
-    path: String,
-    // This tells Serde to look for 'cachedBpm' from the frontend
-    cached_bpm: Option<f32>, // Add this parameter to add bpm caching
+    bank: Option<u32>, // Defaults to the active bank
+    steps: Vec<StepEvent>,
+    banks: State<'_, BankSelector>,
     audio: State<'_, AudioEngine>,
-) -> Result<LoadResult, String> {
-    if IS_COMMUNITY_BUILD && !["Q", "W", "E", "R"].contains(&key.as_str()) {
-        println!("[Bridge] BLOCKED Community Build Request: {}", key);
-        return Err("This pad is restricted in the Community Build.".to_string());
-    }
-    // DIAGNOSTIC: This MUST show Some(val) for the optimization to work
-    println!("[Bridge] Request: {} | Cached BPM: {:?}", key, cached_bpm);
-    // audio.inner().load_sound(key, &path).await
-    audio.inner().load_sound(key, &path, cached_bpm).await // Replaced the above line with this
+) -> Result<(), String> {
+    let key = banks.pad_id(&key, bank);
+    audio.inner().seq_set_pattern(key, steps)
 }
 
+/// IPC Command: Steps per sequencer loop and the note value of one step
 #[tauri::command]
-async fn audio_play(
+async fn audio_seq_set_length(
+    steps: usize,
+    division: Option<StepDivision>, // Sixteenths when omitted
+    audio: State<'_, AudioEngine>,
+) -> Result<(), String> {
+    audio
+        .inner()
+        .seq_set_length(steps, division.unwrap_or_default())
+}
+
+/// IPC Command: Start the sequencer on the next step of the master clock
+#[tauri::command]
+async fn audio_seq_start(audio: State<'_, AudioEngine>) -> Result<(), String> {
+    audio.inner().seq_start()
+}
+
+#[tauri::command]
+async fn audio_seq_stop(audio: State<'_, AudioEngine>) -> Result<(), String> {
+    audio.inner().seq_stop()
+}
+
+/// IPC Command: Record pad triggers and stops from the start of the current
+/// bar, optionally quantized; with `overdub`, into the playing take
+#[tauri::command]
+async fn audio_pattern_record_start(
+    quantize: Option<StepDivision>,
+    overdub: Option<bool>,
+    audio: State<'_, AudioEngine>,
+) -> Result<(), String> {
+    audio
+        .inner()
+        .pattern_record_start(quantize, overdub.unwrap_or(false))
+}
+
+/// IPC Command: End the recording and return the take
+#[tauri::command]
+async fn audio_pattern_record_stop(audio: State<'_, AudioEngine>) -> Result<Performance, String> {
+    audio.inner().pattern_record_stop()
+}
+
+/// IPC Command: Play a take (or the last one) from the next bar
+#[tauri::command]
+async fn audio_pattern_play(
+    performance: Option<Performance>,
+    looping: bool,
+    audio: State<'_, AudioEngine>,
+) -> Result<(), String> {
+    audio.inner().pattern_play(performance, looping)
+}
+
+#[tauri::command]
+async fn audio_pattern_stop(audio: State<'_, AudioEngine>) -> Result<(), String> {
+    audio.inner().pattern_stop()
+}
+
+/// IPC Command: Loop `bars` bars of the pads (all, or `pads` of one bank)
+/// from the next bar, or after `count_in_bars` bars of clicks from it
+#[tauri::command]
+async fn audio_looper_record(
+    bars: u32,
+    pads: Option<Vec<String>>,
+    bank: Option<u32>, // Bank of `pads`, defaults to the active bank
+    count_in_bars: Option<u32>,
+    banks: State<'_, BankSelector>,
+    audio: State<'_, AudioEngine>,
+) -> Result<(), String> {
+    let pads = pads.map(|pads| pads.iter().map(|key| banks.pad_id(key, bank)).collect());
+    audio.inner().looper_record(bars, pads, count_in_bars)
+}
+
+/// IPC Command: Record one more looper layer from the loop's next start
+#[tauri::command]
+async fn audio_looper_overdub(
+    pads: Option<Vec<String>>,
+    bank: Option<u32>,
+    banks: State<'_, BankSelector>,
+    audio: State<'_, AudioEngine>,
+) -> Result<(), String> {
+    let pads = pads.map(|pads| pads.iter().map(|key| banks.pad_id(key, bank)).collect());
+    audio.inner().looper_overdub(pads)
+}
+
+#[tauri::command]
+async fn audio_looper_undo(audio: State<'_, AudioEngine>) -> Result<(), String> {
+    audio.inner().looper_undo()
+}
+
+#[tauri::command]
+async fn audio_looper_clear(audio: State<'_, AudioEngine>) -> Result<(), String> {
+    audio.inner().looper_clear()
+}
+
+/// IPC Command: Set the master compressor; a ratio of 1 turns it off
+#[tauri::command]
+async fn audio_set_compressor(
+    settings: CompressorSettings,
+    audio: State<'_, AudioEngine>,
+) -> Result<(), String> {
+    audio.inner().set_compressor(settings)
+}
+
+/// IPC Command: Set the delay bus the pads send into
+#[tauri::command]
+async fn audio_set_delay(
+    settings: DelaySettings,
+    audio: State<'_, AudioEngine>,
+) -> Result<(), String> {
+    audio.inner().set_delay(settings)
+}
+
+/// IPC Command: Set a pad's send to `bus` (0-1) for its playing and later voices.
+/// It is kept with the pad's defaults.
+#[tauri::command]
+async fn audio_set_send(
     key: String,
-    params: crate::audio_engine::PlayParams,
+    bank: Option<u32>, // Defaults to the active bank
+    bus: SendBus,
+    amount: f32,
+    banks: State<'_, BankSelector>,
     audio: State<'_, AudioEngine>,
 ) -> Result<(), String> {
-    if IS_COMMUNITY_BUILD && !["Q", "W", "E", "R"].contains(&key.as_str()) {
-        println!("[AudioPlay] BLOCKED Community Build Play: {}", key);
-        return Err("This pad is restricted in the Community Build.".to_string());
-    }
-    println!("[AudioPlay] Key: {}, Params: {:?}", key, params);
-    audio.inner().play_sound(key, params)
+    let key = banks.pad_id(&key, bank);
+    audio.inner().set_send(&key, bus, amount)?;
+    session::save_pad_defaults(&audio.inner().pad_defaults())
 }
 
+/// IPC Command: Mute (or unmute) every send of a pad; its dry signal plays on
 #[tauri::command]
-async fn audio_stop(
+async fn audio_mute_sends(
     key: String,
-    effective_release: Option<f32>,
+    bank: Option<u32>, // Defaults to the active bank
+    muted: bool,
+    banks: State<'_, BankSelector>,
     audio: State<'_, AudioEngine>,
 ) -> Result<(), String> {
-    audio.inner().stop_sound(key, effective_release)
+    let key = banks.pad_id(&key, bank);
+    audio.inner().mute_sends(&key, muted)?;
+    session::save_pad_defaults(&audio.inner().pad_defaults())
 }
 
+/// IPC Command: Move the crossfader, 0 (side A only) to 1 (side B only)
 #[tauri::command]
-async fn audio_update_params(
+async fn audio_set_crossfader(position: f32, audio: State<'_, AudioEngine>) -> Result<(), String> {
+    audio.inner().set_crossfader(position)
+}
+
+#[tauri::command]
+async fn audio_set_crossfade_curve(
+    curve: CrossfadeCurve, // "constantPower" or "sharpCut"
+    audio: State<'_, AudioEngine>,
+) -> Result<(), String> {
+    audio.inner().set_crossfade_curve(curve)
+}
+
+/// IPC Command: Put a pad on crossfader side "a" or "b", or on neither (null)
+#[tauri::command]
+async fn audio_set_pad_group(
     key: String,
-    params: crate::audio_engine::PlayParams,
+    bank: Option<u32>, // Defaults to the active bank
+    group: Option<CrossfadeGroup>,
+    banks: State<'_, BankSelector>,
     audio: State<'_, AudioEngine>,
 ) -> Result<(), String> {
-    println!("[AudioUpdate] Key: {}, Params: {:?}", key, params);
-    audio.inner().update_voice(key, params)
+    let key = banks.pad_id(&key, bank);
+    audio.inner().set_pad_group(&key, group)
+}
+
+/// IPC Command: Keep the mix as it is in scene `slot` (0-7)
+#[tauri::command]
+async fn audio_scene_save(slot: usize, audio: State<'_, AudioEngine>) -> Result<Scene, String> {
+    audio.inner().scene_save(slot)
 }
 
+/// Time between two steps of a scene morph; the mixer's ramps fill in between
+const SCENE_TICK: Duration = Duration::from_millis(16);
+
+/// IPC Command: Morph to scene `slot` over `fade_seconds` (at once by default)
 #[tauri::command]
-async fn audio_get_levels(audio: State<'_, AudioEngine>) -> Result<LevelsResponse, String> {
-    Ok(audio.inner().get_levels())
+async fn audio_scene_recall(
+    slot: usize,
+    fade_seconds: Option<f32>,
+    app_handle: AppHandle,
+) -> Result<SceneRecall, String> {
+    let recall = app_handle
+        .audio()
+        .scene_recall(slot, fade_seconds.unwrap_or(0.0))?;
+    if recall.fading {
+        // A recall during a fade starts another ticker, which only ticks more often
+        thread::spawn(move || {
+            while app_handle.audio().scene_tick() {
+                thread::sleep(SCENE_TICK);
+            }
+        });
+    }
+    Ok(recall)
 }
 
 #[tauri::command]
-async fn audio_set_master_bpm(bpm: f32, audio: State<'_, AudioEngine>) -> Result<(), String> {
-    audio.inner().set_master_bpm(bpm);
-    Ok(())
+async fn audio_scene_list(audio: State<'_, AudioEngine>) -> Result<Vec<Scene>, String> {
+    Ok(audio.inner().scene_list())
 }
 
+/// IPC Command: Correct a half/double-time BPM detection for a pad (factor 0.5 or 2.0)
 #[tauri::command]
-async fn audio_get_waveform(
+async fn audio_bpm_multiply(
+    key: String,
+    bank: Option<u32>, // Defaults to the active bank
+    factor: f32,
+    banks: State<'_, BankSelector>,
+    audio: State<'_, AudioEngine>,
+) -> Result<f32, String> {
+    let key = banks.pad_id(&key, bank);
+    audio.inner().bpm_multiply(&key, factor)
+}
+
+/// IPC Command: Snap a loop marker to the nearest zero crossing (±50 ms)
+#[tauri::command]
+async fn audio_find_zero_crossing(
+    key: String,
+    bank: Option<u32>, // Defaults to the active bank
+    time: f32,
+    direction: Option<SearchDirection>, // "nearest" (default), "forward" or "backward"
+    banks: State<'_, BankSelector>,
+    audio: State<'_, AudioEngine>,
+) -> Result<f32, String> {
+    let key = banks.pad_id(&key, bank);
+    audio
+        .inner()
+        .find_zero_crossing(&key, time, direction.unwrap_or_default())
+}
+
+/// IPC Command: Snap both loop markers to zero crossings in one round trip
+#[tauri::command]
+async fn audio_snap_loop_points(
+    key: String,
+    bank: Option<u32>, // Defaults to the active bank
+    start_time: f32,
+    end_time: f32,
+    banks: State<'_, BankSelector>,
+    audio: State<'_, AudioEngine>,
+) -> Result<LoopPoints, String> {
+    let key = banks.pad_id(&key, bank);
+    audio.inner().snap_loop_points(&key, start_time, end_time)
+}
+
+/// Result of `audio_export_region`
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedRegion {
+    path: String, // Harbor-relative path of the new WAV
+    duration: f32,
+}
+
+/// What `audio_export_region` bounces, and where to
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RegionExport {
+    start_time: f32,
+    end_time: f32,
+    filename: String,          // Harbor-relative, ".wav" is added
+    format: Option<WavFormat>, // "float32" (default) or "pcm16"
+    fade_ms: Option<f32>,      // Edge fades against clicks, 0 by default
+    overwrite: Option<bool>,
+}
+
+/// IPC Command: Bounce a pad region (with its gain) to a new WAV in the harbor
+#[tauri::command]
+async fn audio_export_region(
     key: String,
+    bank: Option<u32>,
+    export: RegionExport,
+    banks: State<'_, BankSelector>,
+    app_handle: AppHandle,
+) -> Result<ExportedRegion, String> {
+    let key = banks.pad_id(&key, bank);
+    let harbor_path = get_audio_harbor()?;
+    let RegionExport {
+        start_time,
+        end_time,
+        filename,
+        format,
+        fade_ms,
+        overwrite,
+    } = export;
+
+    let mut relative = PathBuf::from(&filename);
+    relative.set_extension("wav");
+    let relative = relative.to_string_lossy().to_string();
+    let destination = harbor::resolve_in_harbor(&harbor_path, &relative)?;
+    if destination.exists() && !overwrite.unwrap_or(false) {
+        return Err(format!("[Social Noise] {:?} already exists", relative));
+    }
+
+    let duration = tokio::task::spawn_blocking(move || {
+        let audio = app_handle.state::<AudioEngine>();
+        let region = audio.render_region(&key, start_time, end_time, fade_ms.unwrap_or(0.0))?;
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        audio_engine::write_wav(&destination, &region, format.unwrap_or_default())?;
+        Ok::<f32, String>(region.duration())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    info!("Exported region to {:?}", relative);
+    Ok(ExportedRegion {
+        path: relative,
+        duration,
+    })
+}
+
+/// Result of `audio_render_offline` and `audio_record_stop`
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RenderedMix {
+    path: String, // Harbor-relative path of the new WAV
+    duration: f32,
+    peak: f32, // Highest sample magnitude, 1.0 is full scale
+}
+
+/// IPC Command: Bounce `bars` bars of the pattern, the take or both to a new
+/// WAV in the harbor, faster than real time. `render-progress` events report
+/// every bar.
+#[tauri::command]
+async fn audio_render_offline(
+    source: RenderSource, // "pattern", "take" or "session" (both)
+    bars: u32,
+    filename: String,
+    format: Option<WavFormat>, // "float32" (default) or "pcm16"
+    overwrite: Option<bool>,
+    app_handle: AppHandle,
+) -> Result<RenderedMix, String> {
+    let harbor_path = get_audio_harbor()?;
+
+    let mut relative = PathBuf::from(&filename);
+    relative.set_extension("wav");
+    let relative = relative.to_string_lossy().to_string();
+    let destination = harbor::resolve_in_harbor(&harbor_path, &relative)?;
+    if destination.exists() && !overwrite.unwrap_or(false) {
+        return Err(format!("[Social Noise] {:?} already exists", relative));
+    }
+
+    let (duration, peak) = tokio::task::spawn_blocking(move || {
+        let audio = app_handle.state::<AudioEngine>();
+        let render = audio.render_offline(source, bars, |progress| {
+            let _ = app_handle.emit("render-progress", progress);
+        })?;
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        audio_engine::write_wav(&destination, &render.region, format.unwrap_or_default())?;
+        Ok::<(f32, f32), String>((render.region.duration(), render.peak))
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    info!(peak, "Rendered {} bars to {:?}", bars, relative);
+    Ok(RenderedMix {
+        path: relative,
+        duration,
+        peak,
+    })
+}
+
+/// IPC Command: Record the master output to a new WAV in the harbor. With
+/// `count_in_bars` (1-2) the recording starts on the downbeat after that
+/// many bars of clicks (as `count-in-tick`) from the next bar.
+#[tauri::command]
+async fn audio_record_start(
+    filename: String,
+    format: Option<WavFormat>, // "float32" (default) or "pcm16"
+    overwrite: Option<bool>,
+    count_in_bars: Option<u32>,
     audio: State<'_, AudioEngine>,
-) -> Result<Vec<f32>, String> {
-    Ok(audio.inner().get_buffer_waveform(&key))
+) -> Result<(), String> {
+    let harbor_path = get_audio_harbor()?;
+
+    let mut relative = PathBuf::from(&filename);
+    relative.set_extension("wav");
+    let relative = relative.to_string_lossy().to_string();
+    let destination = harbor::resolve_in_harbor(&harbor_path, &relative)?;
+    if destination.exists() && !overwrite.unwrap_or(false) {
+        return Err(format!("[Social Noise] {:?} already exists", relative));
+    }
+    audio
+        .inner()
+        .record_start(destination, format.unwrap_or_default(), count_in_bars)
+}
+
+/// IPC Command: Finish the master recording. None when it was stopped
+/// during its count-in, which leaves no file.
+#[tauri::command]
+async fn audio_record_stop(app_handle: AppHandle) -> Result<Option<RenderedMix>, String> {
+    let recording =
+        tokio::task::spawn_blocking(move || app_handle.state::<AudioEngine>().record_stop())
+            .await
+            .map_err(|e| e.to_string())??;
+    let recording = match recording {
+        Some(recording) => recording,
+        None => return Ok(None),
+    };
+    let path = harbor::api_path_in(&get_audio_harbor()?, &recording.path)?;
+    info!(
+        peak = recording.peak,
+        "Recorded the master output to {:?}", path
+    );
+    Ok(Some(RenderedMix {
+        path,
+        duration: recording.duration,
+        peak: recording.peak,
+    }))
+}
+
+/// IPC Command: Abandon the master recording and remove what it wrote
+#[tauri::command]
+async fn audio_record_cancel(app_handle: AppHandle) -> Result<(), String> {
+    tokio::task::spawn_blocking(move || app_handle.state::<AudioEngine>().record_cancel())
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn audio_get_waveform(
+    key: String,
+    bank: Option<u32>,                // Defaults to the active bank
+    channel: Option<WaveformChannel>, // "mixed" (default), "left" or "right"
+    points: Option<usize>,            // Resolution, 400 by default
+    app_handle: AppHandle,
+    banks: State<'_, BankSelector>,
+) -> Result<Arc<Vec<f32>>, String> {
+    let key = banks.pad_id(&key, bank);
+    // A cache miss walks the whole buffer
+    tokio::task::spawn_blocking(move || {
+        app_handle.state::<AudioEngine>().get_buffer_waveform(
+            &key,
+            channel.unwrap_or_default(),
+            points.unwrap_or(WAVEFORM_POINTS),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())
 }
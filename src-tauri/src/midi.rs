@@ -0,0 +1,378 @@
+/**
+ * midi.rs
+ * L-SAMP 100 | MIDI Input
+ *
+ * Opens one MIDI input port and turns note-on/note-off into pad triggers,
+ * the same way the keyboard listener does, and CC messages into live control
+ * values. Bindings can be typed in or captured with MIDI learn, and are
+ * persisted as midi.json under the lsamp-100 config directory.
+ */
+use midir::{Ignore, MidiInput, MidiInputConnection};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tracing::{info, warn};
+
+use crate::keymap::{self, PAD_KEYS};
+
+/// Note of the first pad in the default mapping (C1, where most pad controllers start)
+pub const DEFAULT_BASE_NOTE: u8 = 36;
+
+const CLIENT_NAME: &str = "L-SAMP 100";
+
+/// Named controls a binding can drive besides pad triggers
+pub const MASTER_VOLUME: &str = "master_volume";
+
+/// What a MIDI binding acts on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MidiTarget {
+    Pad(&'static str),       // Pad slot, SPACE or bank key, triggered like a key
+    MasterVolume,            // "master_volume"
+    PadVolume(&'static str), // "pad:Q:volume"
+}
+
+impl MidiTarget {
+    pub fn parse(name: &str) -> Result<Self, String> {
+        if name.eq_ignore_ascii_case(MASTER_VOLUME) {
+            return Ok(Self::MasterVolume);
+        }
+        let parts: Vec<&str> = name.split(':').collect();
+        match parts.as_slice() {
+            [slot] => Ok(Self::Pad(keymap::target(slot)?)),
+            ["pad", slot, "volume"] => Ok(Self::PadVolume(keymap::target(slot)?)),
+            _ => Err(format!("[Social Noise] Unknown MIDI target: {:?}", name)),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Self::Pad(slot) => slot.to_string(),
+            Self::MasterVolume => MASTER_VOLUME.to_string(),
+            Self::PadVolume(slot) => format!("pad:{}:volume", slot),
+        }
+    }
+}
+
+/// Note number -> target and CC number -> target. Targets are a pad slot
+/// ("Q".."V", "SPACE") or a named control ("master_volume", "pad:Q:volume").
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MidiMapping {
+    pub notes: BTreeMap<u8, String>,
+    #[serde(default)]
+    pub controls: BTreeMap<u8, String>,
+}
+
+impl Default for MidiMapping {
+    fn default() -> Self {
+        Self {
+            notes: PAD_KEYS
+                .iter()
+                .enumerate()
+                .map(|(i, pad)| (DEFAULT_BASE_NOTE + i as u8, pad.to_string()))
+                .collect(),
+            controls: BTreeMap::new(),
+        }
+    }
+}
+
+impl MidiMapping {
+    /// Binds `note` to a pad slot, or unbinds it when `pad` is None
+    pub fn set(&mut self, note: u8, pad: Option<&str>) -> Result<(), String> {
+        if note > 127 {
+            return Err(format!("[Social Noise] Invalid MIDI note: {}", note));
+        }
+        match pad {
+            Some(pad) => {
+                self.notes.insert(note, keymap::target(pad)?.to_string());
+            }
+            None => {
+                self.notes.remove(&note);
+            }
+        }
+        Ok(())
+    }
+
+    /// Drops every note and CC bound to `target`
+    pub fn clear_target(&mut self, target: MidiTarget) {
+        let name = target.name();
+        self.notes.retain(|_, bound| *bound != name);
+        self.controls.retain(|_, bound| *bound != name);
+    }
+
+    /// Binds a captured message to `target`, replacing its previous bindings
+    fn learn(&mut self, source: MidiSource, number: u8, target: MidiTarget) {
+        self.clear_target(target);
+        let table = match source {
+            MidiSource::Note => &mut self.notes,
+            MidiSource::Cc => &mut self.controls,
+        };
+        table.insert(number, target.name());
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MidiSource {
+    Note,
+    Cc,
+}
+
+/// Payload of `midi-learned`
+#[derive(Debug, Clone, Serialize)]
+pub struct MidiLearned {
+    pub target: String,
+    pub source: MidiSource,
+    pub number: u8, // Note or CC number
+}
+
+/// A decoded, mapped MIDI message
+pub enum MidiEvent {
+    /// Note-on (velocity 0.0-1.0) or note-off, or a CC switch, on a pad slot
+    Pad {
+        pad: &'static str,
+        pressed: bool,
+        velocity: f32,
+    },
+    /// A named control moved to `value` (0.0-1.0)
+    Control { target: MidiTarget, value: f32 },
+    /// MIDI learn captured a message; it is not acted on otherwise
+    Learned(MidiLearned),
+}
+
+/// The open port, if any, plus the state shared with its callback thread
+pub struct MidiState {
+    connection: Option<MidiInputConnection<()>>,
+    port_name: Option<String>,
+    mapping: Arc<Mutex<MidiMapping>>,
+    learning: Arc<Mutex<Option<MidiTarget>>>, // Armed by `start_learn`
+}
+
+impl MidiState {
+    pub fn new(mapping: MidiMapping) -> Self {
+        Self {
+            connection: None,
+            port_name: None,
+            mapping: Arc::new(Mutex::new(mapping)),
+            learning: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn port_name(&self) -> Option<&str> {
+        self.port_name.as_deref()
+    }
+
+    pub fn mapping(&self) -> MidiMapping {
+        self.mapping.lock().map(|m| m.clone()).unwrap_or_default()
+    }
+
+    /// Changes one note binding and persists the whole mapping
+    pub fn set_note(&self, note: u8, pad: Option<&str>) -> Result<MidiMapping, String> {
+        let mut mapping = self.mapping.lock().map_err(|e| e.to_string())?;
+        mapping.set(note, pad)?;
+        save_mapping(&mapping)?;
+        Ok(mapping.clone())
+    }
+
+    /// Binds the next note-on or CC message to `target`
+    pub fn start_learn(&self, target: &str) -> Result<(), String> {
+        let target = MidiTarget::parse(target)?;
+        *self.learning.lock().map_err(|e| e.to_string())? = Some(target);
+        Ok(())
+    }
+
+    pub fn cancel_learn(&self) -> Result<(), String> {
+        *self.learning.lock().map_err(|e| e.to_string())? = None;
+        Ok(())
+    }
+
+    /// Removes all bindings of `target` and persists the mapping
+    pub fn clear_binding(&self, target: &str) -> Result<MidiMapping, String> {
+        let target = MidiTarget::parse(target)?;
+        let mut mapping = self.mapping.lock().map_err(|e| e.to_string())?;
+        mapping.clear_target(target);
+        save_mapping(&mapping)?;
+        Ok(mapping.clone())
+    }
+
+    /// Opens `name`, replacing any open port. `on_event` runs on midir's thread.
+    pub fn open(
+        &mut self,
+        name: &str,
+        mut on_event: impl FnMut(MidiEvent) + Send + 'static,
+    ) -> Result<(), String> {
+        self.close();
+
+        let mut input = new_input()?;
+        input.ignore(Ignore::All);
+        let port = input
+            .ports()
+            .into_iter()
+            .find(|p| input.port_name(p).map(|n| n == name).unwrap_or(false))
+            .ok_or_else(|| format!("[Social Noise] MIDI port not found: {:?}", name))?;
+
+        let mapping = Arc::clone(&self.mapping);
+        let learning = Arc::clone(&self.learning);
+        let mut switches_down = HashSet::new(); // CCs bound to pads currently "pressed"
+        let connection = input
+            .connect(
+                &port,
+                "lsamp-100-input",
+                move |_stamp, message, _| {
+                    // Never panic here: this runs on midir's thread, which also sees
+                    // garbage and short reads while a controller is being unplugged
+                    let event = match decode(message) {
+                        Some(message) => message,
+                        None => return,
+                    };
+                    if let Some(learned) = try_learn(event, &learning, &mapping) {
+                        on_event(MidiEvent::Learned(learned));
+                    } else if let Some(event) = resolve(event, &mapping, &mut switches_down) {
+                        on_event(event);
+                    }
+                },
+                (),
+            )
+            .map_err(|e| format!("[Social Noise] MIDI port {:?} failed to open: {}", name, e))?;
+
+        info!("MIDI input opened: {}", name);
+        self.connection = Some(connection);
+        self.port_name = Some(name.to_string());
+        Ok(())
+    }
+
+    pub fn close(&mut self) {
+        if let Some(connection) = self.connection.take() {
+            connection.close();
+            info!("MIDI input closed");
+        }
+        self.port_name = None;
+    }
+}
+
+fn new_input() -> Result<MidiInput, String> {
+    MidiInput::new(CLIENT_NAME).map_err(|e| format!("[Social Noise] MIDI unavailable: {}", e))
+}
+
+/// Names of the MIDI input ports currently present
+pub fn list_ports() -> Result<Vec<String>, String> {
+    let input = new_input()?;
+    Ok(input
+        .ports()
+        .iter()
+        .filter_map(|p| input.port_name(p).ok())
+        .collect())
+}
+
+/// Raw channel message: (source, note or CC number, velocity or CC value)
+type RawMessage = (MidiSource, u8, Option<u8>);
+
+fn decode(message: &[u8]) -> Option<RawMessage> {
+    let (status, number, value) = match message {
+        [status, number, value, ..] => (status & 0xF0, *number, *value),
+        _ => return None,
+    };
+    match status {
+        0x90 if value > 0 => Some((MidiSource::Note, number, Some(value))),
+        // Note-on with velocity 0 is the running-status form of note-off
+        0x80 | 0x90 => Some((MidiSource::Note, number, None)),
+        0xB0 => Some((MidiSource::Cc, number, Some(value))),
+        _ => None,
+    }
+}
+
+/// Consumes the message as a MIDI learn capture when learning is armed.
+/// Note-offs are ignored so the release of the learned key is not captured.
+fn try_learn(
+    (source, number, value): RawMessage,
+    learning: &Mutex<Option<MidiTarget>>,
+    mapping: &Mutex<MidiMapping>,
+) -> Option<MidiLearned> {
+    value?;
+    let target = learning.lock().ok()?.take()?;
+    let mut mapping = mapping.lock().ok()?;
+    mapping.learn(source, number, target);
+    if let Err(e) = save_mapping(&mapping) {
+        warn!("{}", e);
+    }
+    Some(MidiLearned {
+        target: target.name(),
+        source,
+        number,
+    })
+}
+
+fn resolve(
+    (source, number, value): RawMessage,
+    mapping: &Mutex<MidiMapping>,
+    switches_down: &mut HashSet<u8>,
+) -> Option<MidiEvent> {
+    let target = {
+        let mapping = mapping.lock().ok()?;
+        let table = match source {
+            MidiSource::Note => &mapping.notes,
+            MidiSource::Cc => &mapping.controls,
+        };
+        MidiTarget::parse(table.get(&number)?).ok()?
+    };
+    let scaled = value.map(|v| v as f32 / 127.0);
+
+    match (target, source) {
+        (MidiTarget::Pad(pad), MidiSource::Note) => Some(MidiEvent::Pad {
+            pad,
+            pressed: value.is_some(),
+            velocity: scaled.unwrap_or(0.0),
+        }),
+        // A CC on a pad acts as a switch: pressed from 64 up, edges only
+        (MidiTarget::Pad(pad), MidiSource::Cc) => {
+            let pressed = value? >= 64;
+            let changed = if pressed {
+                switches_down.insert(number)
+            } else {
+                switches_down.remove(&number)
+            };
+            changed.then_some(MidiEvent::Pad {
+                pad,
+                pressed,
+                velocity: 1.0,
+            })
+        }
+        // Notes on a control set it from their velocity; note-offs leave it alone
+        (target, _) => Some(MidiEvent::Control {
+            target,
+            value: scaled?,
+        }),
+    }
+}
+
+fn mapping_path() -> Result<PathBuf, String> {
+    let dir = dirs::config_dir()
+        .ok_or("Failed to get config dir".to_string())?
+        .join("lsamp-100");
+    if !dir.exists() {
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("[Inner Cosmos] Config folder creation failed: {}", e))?;
+    }
+    Ok(dir.join("midi.json"))
+}
+
+/// Saved mapping, or the default C1-based layout when none was saved (or it is corrupt)
+/// Saved mapping, the default when none was saved. A file that cannot be
+/// read or parsed is an error, so the caller can report it.
+pub fn load_mapping() -> Result<MidiMapping, String> {
+    let path = mapping_path()?;
+    match fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str(&contents)
+            .map_err(|e| format!("MIDI mapping {:?} is corrupt: {}", path, e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(MidiMapping::default()),
+        Err(e) => Err(format!("MIDI mapping {:?} is unreadable: {}", path, e)),
+    }
+}
+
+fn save_mapping(mapping: &MidiMapping) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(mapping).map_err(|e| e.to_string())?;
+    fs::write(mapping_path()?, json)
+        .map_err(|e| format!("[Social Noise] MIDI mapping save failed: {}", e))
+}
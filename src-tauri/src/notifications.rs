@@ -0,0 +1,105 @@
+/**
+ * notifications.rs
+ * L-SAMP 100 | Backend Notifications
+ *
+ * Problems the user should hear about even where stdout goes nowhere
+ * (release builds on Windows). `notify` logs a notification and emits it
+ * as `backend-notification`; the latest ones stay in a ring buffer for
+ * the diagnostics panel (`get_recent_notifications`). `code` is the stable
+ * part the frontend maps to localized text; `message` is English detail
+ * and `context` carries the ids involved (pad, path, ...).
+ */
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{error, info, warn};
+
+use crate::Host;
+
+/// Notifications kept for `get_recent_notifications`
+const RECENT_CAPACITY: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    #[allow(dead_code)] // No informational notices yet; part of the protocol
+    Info,
+    Warning,
+    Error,
+}
+
+/// Stable notification codes. Renaming one breaks the frontend's translations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Code {
+    DecodeFailed,     // A file could not be decoded onto a pad
+    DeviceLost,       // The output device went away; a warning if the default output took over
+    StreamError,      // Any other failure reported by the audio stream
+    WatcherFailed,    // The harbor watcher could not attach or lost events
+    ListenerFailed,   // The keyboard listener died and is backing off
+    AnalysisFallback, // BPM detection failed; the pad shows a 120 BPM placeholder and won't sync
+    ConfigLoadFailed, // A saved config file is unreadable; defaults are in use
+    EngineFault,      // The engine caught a panic; playing voices were stopped
+}
+
+/// Payload of `backend-notification`, entry of `get_recent_notifications`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Notification {
+    pub level: Level,
+    pub code: Code,
+    pub message: String,
+    pub context: serde_json::Value, // Object of ids, or null
+    pub at: u64,                    // Milliseconds since the Unix epoch
+}
+
+/// Ring buffer of the latest notifications, oldest first
+#[derive(Default)]
+pub struct Notifications {
+    recent: Mutex<VecDeque<Notification>>,
+}
+
+impl Notifications {
+    fn push(&self, notification: Notification) {
+        if let Ok(mut recent) = self.recent.lock() {
+            if recent.len() == RECENT_CAPACITY {
+                recent.pop_front();
+            }
+            recent.push_back(notification);
+        }
+    }
+
+    pub fn recent(&self) -> Vec<Notification> {
+        self.recent
+            .lock()
+            .map(|recent| recent.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Logs, records and emits one notification
+pub fn notify<H: Host>(
+    host: &H,
+    level: Level,
+    code: Code,
+    message: impl Into<String>,
+    context: serde_json::Value,
+) {
+    let notification = Notification {
+        level,
+        code,
+        message: message.into(),
+        context,
+        at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64),
+    };
+    match level {
+        Level::Info => info!(code = ?code, "{}", notification.message),
+        Level::Warning => warn!(code = ?code, "{}", notification.message),
+        Level::Error => error!(code = ?code, "{}", notification.message),
+    }
+    host.notifications().push(notification.clone());
+    host.emit_event("backend-notification", notification);
+}
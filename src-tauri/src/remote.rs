@@ -0,0 +1,537 @@
+/**
+ * remote.rs
+ * L-SAMP 100 | Remote Control
+ *
+ * Optional WebSocket server for browser remotes (a phone on the same
+ * network, a tablet next to the DAW). Off by default; bound to 127.0.0.1
+ * unless LAN access is opted into, which requires a shared token.
+ *
+ * Browsers let any web page open a WebSocket to 127.0.0.1, so with no token
+ * the handshake is refused when it carries an `Origin` other than a
+ * loopback page or one listed in `origins`. Clients outside a browser send
+ * no `Origin` and are let in.
+ *
+ * The protocol is JSON text frames mirroring the Tauri commands and their
+ * serde structs (`PlayParams`, `PartialPlayParams`, `LoadResult`,
+ * `LevelsResponse`). Every request may carry an `id`, echoed in its reply:
+ *
+ *   -> {"id": 1, "cmd": "auth", "token": "..."}
+ *   -> {"id": 2, "cmd": "play", "key": "Q", "params": {"volume": 0.8}}
+ *   <- {"type": "result", "id": 2, "data": null}
+ *   <- {"type": "error", "id": 3, "code": "command_failed", "message": "..."}
+ *   <- {"type": "levels", "data": { ...LevelsResponse }}
+ *
+ * `load` takes harbor-relative files only. The commands themselves run in
+ * main.rs through `RemoteHost`, so they share one implementation with IPC.
+ */
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::future::Future;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tokio::time::{Interval, MissedTickBehavior};
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::StatusCode;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+use tracing::{info, warn};
+
+use crate::audio_engine::{LevelsResponse, PartialPlayParams, PlayParams};
+
+/// Protocol revision, sent in `hello`
+const PROTOCOL_VERSION: u32 = 1;
+
+pub const DEFAULT_PORT: u16 = 7170;
+
+/// Accepted `subscribeLevels` intervals, as for `audio_levels_subscribe`
+const LEVELS_INTERVAL_MS: std::ops::RangeInclusive<u64> = 8..=1000;
+
+/// `remote` section of the app config
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RemoteSettings {
+    pub enabled: bool,
+    pub lan: bool, // Bind 0.0.0.0 instead of 127.0.0.1
+    pub port: u16,
+    pub token: String,        // Empty: no auth (loopback only)
+    pub origins: Vec<String>, // Pages let in without a token, e.g. "http://192.168.1.5:8080"
+}
+
+impl Default for RemoteSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            lan: false,
+            port: DEFAULT_PORT,
+            token: String::new(),
+            origins: Vec::new(),
+        }
+    }
+}
+
+impl RemoteSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.port == 0 {
+            return Err("Port 0 is not usable".to_string());
+        }
+        if self.lan && self.token.trim().is_empty() {
+            return Err("LAN access needs a token".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Commands a remote can send. `auth` and the levels subscription are
+/// handled per connection; everything else goes to `RemoteHost::run`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "cmd", rename_all = "camelCase")]
+pub enum RemoteCommand {
+    Auth {
+        token: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Load {
+        key: String,
+        bank: Option<u32>,
+        file: String, // Harbor-relative
+    },
+    #[serde(rename_all = "camelCase")]
+    Play {
+        key: String,
+        bank: Option<u32>,
+        #[serde(default)]
+        params: PartialPlayParams,
+    },
+    #[serde(rename_all = "camelCase")]
+    Stop {
+        key: String,
+        bank: Option<u32>,
+        effective_release: Option<f32>,
+    },
+    StopAll,
+    #[serde(rename_all = "camelCase")]
+    Update {
+        key: String,
+        bank: Option<u32>,
+        params: PlayParams,
+    },
+    SetBpm {
+        bpm: f32,
+    },
+    #[serde(rename_all = "camelCase")]
+    SubscribeLevels {
+        interval_ms: u64,
+    },
+    UnsubscribeLevels,
+}
+
+#[derive(Deserialize)]
+struct Incoming {
+    #[serde(default)]
+    id: Option<serde_json::Value>,
+    #[serde(flatten)]
+    command: RemoteCommand,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum Outgoing<'a> {
+    #[serde(rename_all = "camelCase")]
+    Hello {
+        version: u32,
+        auth_required: bool,
+    },
+    Result {
+        id: Option<serde_json::Value>,
+        data: serde_json::Value,
+    },
+    Error {
+        id: Option<serde_json::Value>,
+        code: ErrorCode,
+        message: String,
+    },
+    Levels {
+        data: &'a LevelsResponse,
+    },
+    Closing {
+        reason: &'a str,
+    },
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+enum ErrorCode {
+    AuthFailed,      // Wrong token; the connection is closed
+    Unauthenticated, // A command before a successful `auth`
+    BadRequest,      // Not JSON, unknown `cmd` or missing fields
+    CommandFailed,   // The command ran and returned an error
+}
+
+pub type Reply<'a> = Pin<Box<dyn Future<Output = Result<serde_json::Value, String>> + Send + 'a>>;
+
+/// What the server needs from the app: running commands and reading levels
+pub trait RemoteHost: Send + Sync + 'static {
+    fn run(&self, command: RemoteCommand) -> Reply<'_>;
+    fn levels(&self) -> Arc<LevelsResponse>;
+}
+
+struct Running {
+    settings: RemoteSettings,
+    shutdown: watch::Sender<bool>,
+    server: tauri::async_runtime::JoinHandle<()>,
+}
+
+/// Managed handle of the (at most one) running server
+#[derive(Default)]
+pub struct RemoteServer {
+    running: Mutex<Option<Running>>,
+}
+
+impl RemoteServer {
+    /// Applies new settings: unchanged settings keep the server (and its
+    /// connections) as they are, anything else stops it and, if still
+    /// enabled, starts it again on the new address. A port that cannot be
+    /// bound is an error and leaves the server off, so the same settings are
+    /// tried again next time.
+    pub fn configure(
+        &self,
+        settings: RemoteSettings,
+        host: Arc<dyn RemoteHost>,
+    ) -> Result<(), String> {
+        settings.validate()?;
+        let mut running = self.running.lock().map_err(|e| e.to_string())?;
+        if running.as_ref().map(|r| &r.settings) == Some(&settings) {
+            return Ok(());
+        }
+        if let Some(old) = running.take() {
+            let _ = old.shutdown.send(true);
+            // The old listener has to release the port before a new one can bind it
+            let _ = tauri::async_runtime::block_on(old.server);
+        }
+        if !settings.enabled {
+            return Ok(());
+        }
+        let ip = if settings.lan {
+            Ipv4Addr::UNSPECIFIED
+        } else {
+            Ipv4Addr::LOCALHOST
+        };
+        let listener = std::net::TcpListener::bind((ip, settings.port))
+            .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+            .map_err(|e| format!("Cannot listen on {}:{}: {}", ip, settings.port, e))?;
+        info!("Remote control listening on ws://{}:{}", ip, settings.port);
+        let (shutdown, stopped) = watch::channel(false);
+        let server = tauri::async_runtime::spawn(serve(settings.clone(), listener, host, stopped));
+        *running = Some(Running {
+            settings,
+            shutdown,
+            server,
+        });
+        Ok(())
+    }
+}
+
+async fn serve(
+    settings: RemoteSettings,
+    listener: std::net::TcpListener,
+    host: Arc<dyn RemoteHost>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let listener = match TcpListener::from_std(listener) {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!("Remote control cannot accept connections: {}", e);
+            return;
+        }
+    };
+    let token = Some(settings.token.clone()).filter(|token| !token.is_empty());
+    let origins = Arc::new(settings.origins.clone());
+
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => break,
+            accepted = listener.accept() => match accepted {
+                Ok((stream, peer)) => {
+                    tokio::spawn(serve_connection(
+                        stream,
+                        peer,
+                        token.clone(),
+                        Arc::clone(&origins),
+                        Arc::clone(&host),
+                        shutdown.clone(),
+                    ));
+                }
+                Err(e) => warn!("Remote control accept failed: {}", e),
+            },
+        }
+    }
+    info!("Remote control stopped");
+}
+
+async fn serve_connection(
+    stream: TcpStream,
+    peer: SocketAddr,
+    token: Option<String>,
+    origins: Arc<Vec<String>>,
+    host: Arc<dyn RemoteHost>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let guarded = token.is_some();
+    #[allow(clippy::result_large_err)] // The error type is tungstenite's
+    let check_origin = move |request: &Request, response: Response| {
+        let origin = request
+            .headers()
+            .get("origin")
+            .map(|origin| origin.to_str().unwrap_or_default());
+        match origin {
+            Some(origin) if !guarded && !origin_allowed(origin, &origins) => {
+                warn!("Remote handshake from {} refused: origin {}", peer, origin);
+                let mut refusal = ErrorResponse::new(Some("Origin not allowed".to_string()));
+                *refusal.status_mut() = StatusCode::FORBIDDEN;
+                Err(refusal)
+            }
+            _ => Ok(response),
+        }
+    };
+    let mut ws = match tokio_tungstenite::accept_hdr_async(stream, check_origin).await {
+        Ok(ws) => ws,
+        Err(e) => {
+            warn!("Remote handshake with {} failed: {}", peer, e);
+            return;
+        }
+    };
+    info!("Remote connected: {}", peer);
+    let mut authenticated = token.is_none();
+    let mut levels: Option<Interval> = None;
+    let hello = Outgoing::Hello {
+        version: PROTOCOL_VERSION,
+        auth_required: !authenticated,
+    };
+    if send(&mut ws, &hello).await.is_err() {
+        return;
+    }
+
+    loop {
+        tokio::select! {
+            _ = shutdown.changed() => {
+                let _ = send(&mut ws, &Outgoing::Closing { reason: "server_disabled" }).await;
+                close(&mut ws, CloseCode::Away, "server disabled").await;
+                break;
+            }
+            _ = next_tick(&mut levels) => {
+                let snapshot = host.levels();
+                if send(&mut ws, &Outgoing::Levels { data: &snapshot }).await.is_err() {
+                    break;
+                }
+            }
+            message = ws.next() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Binary(_))) => {
+                        let reply = error(None, ErrorCode::BadRequest, "Binary frames are not supported");
+                        if send(&mut ws, &reply).await.is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                    // Pings are answered by tungstenite itself
+                    Some(Ok(Message::Ping(_))) | Some(Ok(Message::Pong(_))) | Some(Ok(Message::Frame(_))) => continue,
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                };
+                let (reply, stay) = handle_text(&text, &token, &mut authenticated, &mut levels, &*host).await;
+                if send(&mut ws, &reply).await.is_err() || !stay {
+                    if !stay {
+                        close(&mut ws, CloseCode::Policy, "authentication failed").await;
+                    }
+                    break;
+                }
+            }
+        }
+    }
+    info!("Remote disconnected: {}", peer);
+}
+
+/// Runs one text frame. Returns the reply and whether to keep the connection.
+async fn handle_text<'a>(
+    text: &str,
+    token: &Option<String>,
+    authenticated: &mut bool,
+    levels: &mut Option<Interval>,
+    host: &dyn RemoteHost,
+) -> (Outgoing<'a>, bool) {
+    let Incoming { id, command } = match serde_json::from_str(text) {
+        Ok(incoming) => incoming,
+        Err(e) => return (error(None, ErrorCode::BadRequest, e.to_string()), true),
+    };
+
+    match command {
+        RemoteCommand::Auth { token: offered } => match token {
+            Some(expected) if !same_token(expected, &offered) => {
+                (error(id, ErrorCode::AuthFailed, "Wrong token"), false)
+            }
+            _ => {
+                *authenticated = true;
+                (ok(id, serde_json::Value::Null), true)
+            }
+        },
+        _ if !*authenticated => (
+            error(id, ErrorCode::Unauthenticated, "Send auth first"),
+            true,
+        ),
+        RemoteCommand::SubscribeLevels { interval_ms } => {
+            if !LEVELS_INTERVAL_MS.contains(&interval_ms) {
+                let message = format!(
+                    "Levels interval {} ms out of range ({}-{})",
+                    interval_ms,
+                    LEVELS_INTERVAL_MS.start(),
+                    LEVELS_INTERVAL_MS.end()
+                );
+                return (error(id, ErrorCode::BadRequest, message), true);
+            }
+            // A slow client gets fewer frames, never a backlog
+            let mut interval = tokio::time::interval(Duration::from_millis(interval_ms));
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            *levels = Some(interval);
+            (ok(id, serde_json::Value::Null), true)
+        }
+        RemoteCommand::UnsubscribeLevels => {
+            *levels = None;
+            (ok(id, serde_json::Value::Null), true)
+        }
+        command => match host.run(command).await {
+            Ok(data) => (ok(id, data), true),
+            Err(message) => (error(id, ErrorCode::CommandFailed, message), true),
+        },
+    }
+}
+
+fn ok<'a>(id: Option<serde_json::Value>, data: serde_json::Value) -> Outgoing<'a> {
+    Outgoing::Result { id, data }
+}
+
+fn error<'a>(
+    id: Option<serde_json::Value>,
+    code: ErrorCode,
+    message: impl Into<String>,
+) -> Outgoing<'a> {
+    Outgoing::Error {
+        id,
+        code,
+        message: message.into(),
+    }
+}
+
+/// Whether a page at `origin` may connect without a token: a loopback page,
+/// or one of `allowed`
+fn origin_allowed(origin: &str, allowed: &[String]) -> bool {
+    if allowed.iter().any(|page| page.eq_ignore_ascii_case(origin)) {
+        return true;
+    }
+    let host = match origin.split_once("://") {
+        Some(("http" | "https", rest)) => rest,
+        _ => return false,
+    };
+    let host = match host.strip_prefix('[') {
+        Some(v6) => v6.split(']').next().unwrap_or_default(),
+        None => host.split(':').next().unwrap_or_default(),
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "::1")
+}
+
+/// Compares every byte so the reply time does not leak the matching prefix
+fn same_token(expected: &str, offered: &str) -> bool {
+    expected.len() == offered.len()
+        && expected
+            .bytes()
+            .zip(offered.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+async fn next_tick(levels: &mut Option<Interval>) {
+    match levels {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+async fn send(
+    ws: &mut WebSocketStream<TcpStream>,
+    message: &Outgoing<'_>,
+) -> Result<(), tokio_tungstenite::tungstenite::Error> {
+    let text = serde_json::to_string(message).unwrap_or_default();
+    ws.send(Message::Text(text)).await
+}
+
+async fn close(ws: &mut WebSocketStream<TcpStream>, code: CloseCode, reason: &'static str) {
+    let frame = CloseFrame {
+        code,
+        reason: Cow::Borrowed(reason),
+    };
+    let _ = ws.send(Message::Close(Some(frame))).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct NoHost;
+
+    impl RemoteHost for NoHost {
+        fn run(&self, _: RemoteCommand) -> Reply<'_> {
+            Box::pin(async { Ok(serde_json::Value::Null) })
+        }
+
+        fn levels(&self) -> Arc<LevelsResponse> {
+            Arc::default()
+        }
+    }
+
+    #[test]
+    fn a_port_in_use_is_reported_and_tried_again() {
+        let taken = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let settings = RemoteSettings {
+            enabled: true,
+            port: taken.local_addr().unwrap().port(),
+            ..RemoteSettings::default()
+        };
+        let server = RemoteServer::default();
+        for _ in 0..2 {
+            let result = server.configure(settings.clone(), Arc::new(NoHost));
+            assert!(result.unwrap_err().starts_with("Cannot listen on"));
+            assert!(server.running.lock().unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn only_loopback_and_listed_pages_get_in_without_a_token() {
+        let listed = vec!["http://192.168.1.5:8080".to_string()];
+        for origin in [
+            "http://localhost:1420",
+            "http://127.0.0.1",
+            "https://[::1]:8443",
+            "http://192.168.1.5:8080",
+        ] {
+            assert!(origin_allowed(origin, &listed), "{}", origin);
+        }
+        for origin in [
+            "https://example.com",
+            "http://localhost.example.com",
+            "http://127.0.0.1.evil.net",
+            "http://192.168.1.5:8081",
+            "null",
+            "file://",
+        ] {
+            assert!(!origin_allowed(origin, &listed), "{}", origin);
+        }
+    }
+}
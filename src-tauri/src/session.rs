@@ -0,0 +1,374 @@
+/**
+ * session.rs
+ * L-SAMP 100 | Session Store
+ *
+ * A session is the whole performance setup: which file sits on each pad,
+ * the pad's last and default PlayParams, the master settings, the last
+ * recorded take and the saved scenes. Sessions live as JSON files under the
+ * lsamp-100 config directory, next to the pad defaults of the running setup
+ * (pad_defaults.json) and the periodic crash-recovery snapshot
+ * (autosave.json). A run that ends cleanly leaves a marker; an autosave
+ * newer than it is offered for restore on the next start.
+ */
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+use crate::audio_engine::{
+    LoadResult, PadSnapshot, PartialPlayParams, Performance, PlayParams, Scene,
+};
+use crate::harbor::{api_path_in, resolve_in_harbor};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionPad {
+    pub key: String,
+    pub path: String, // Harbor-relative ("/"-separated) for harbor files, absolute otherwise
+    #[serde(default)]
+    pub params: Option<PlayParams>,
+    #[serde(default)]
+    pub defaults: Option<PartialPlayParams>, // See `set_pad_defaults`
+    #[serde(default)]
+    pub bpm: Option<f32>, // Cached analysis, so loading skips BPM detection
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    pub name: String,
+    pub saved_at: u64, // Milliseconds since the Unix epoch
+    pub master_bpm: f32,
+    pub master_volume: f32,
+    pub pads: Vec<SessionPad>,
+    #[serde(default)]
+    pub performance: Option<Performance>, // See `pattern_record_stop`
+    #[serde(default)]
+    pub scenes: Vec<Scene>, // See `scene_save`
+}
+
+/// Entry of `session_list`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionInfo {
+    pub name: String,
+    pub saved_at: u64,
+}
+
+/// Payload of the per-pad `session-pad-loaded` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionPadLoaded {
+    pub key: String,
+    pub path: String,
+    pub params: Option<PlayParams>,
+    pub result: Option<LoadResult>,
+    pub error: Option<String>, // Set when this pad could not be restored
+}
+
+/// Pad that failed to restore, as reported by `session_load`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionPadError {
+    pub key: String,
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionLoadReport {
+    pub name: String,
+    pub loaded: usize,
+    pub failed: Vec<SessionPadError>,
+}
+
+impl Session {
+    pub fn from_snapshot(
+        name: &str,
+        pads: Vec<PadSnapshot>,
+        master_volume: f32,
+        master_bpm: f32,
+        performance: Option<Performance>,
+        scenes: Vec<Scene>,
+        harbor: &Path,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            saved_at: now_millis(),
+            master_bpm,
+            master_volume,
+            pads: pads
+                .into_iter()
+                .map(|pad| SessionPad {
+                    key: pad.key,
+                    path: api_path_in(harbor, &pad.path)
+                        .unwrap_or_else(|_| pad.path.to_string_lossy().to_string()),
+                    params: pad.params,
+                    defaults: pad.defaults,
+                    bpm: pad.bpm,
+                })
+                .collect(),
+            performance,
+            scenes,
+        }
+    }
+
+    /// Turns harbor-relative pad paths back into absolute native paths.
+    /// Paths that fail validation are left as-is and will report as missing.
+    pub fn resolve_paths(&mut self, harbor: &Path) {
+        for pad in self.pads.iter_mut() {
+            if Path::new(&pad.path).is_absolute() {
+                continue;
+            }
+            if let Ok(path) = resolve_in_harbor(harbor, &pad.path) {
+                pad.path = path.to_string_lossy().to_string();
+            }
+        }
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Get the sessions directory, creating it on first use
+pub fn sessions_dir() -> Result<PathBuf, String> {
+    let dir = dirs::config_dir()
+        .ok_or("Failed to get config dir".to_string())?
+        .join("lsamp-100")
+        .join("sessions");
+
+    if !dir.exists() {
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("[Inner Cosmos] Sessions folder creation failed: {}", e))?;
+    }
+    Ok(dir)
+}
+
+/// Session names become file names, so keep them to a single plain component
+fn session_path(name: &str) -> Result<PathBuf, String> {
+    let trimmed = name.trim();
+    if trimmed.is_empty()
+        || trimmed.starts_with('.')
+        || trimmed.contains(|c: char| matches!(c, '/' | '\\' | ':') || c.is_control())
+    {
+        return Err(format!("[Social Noise] Invalid session name: {:?}", name));
+    }
+    Ok(sessions_dir()?.join(format!("{}.json", trimmed)))
+}
+
+pub fn save(session: &Session) -> Result<(), String> {
+    let path = session_path(&session.name)?;
+    let json = serde_json::to_string_pretty(session).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| format!("[Social Noise] Session save failed: {}", e))?;
+    info!("Session saved: {:?}", path);
+    Ok(())
+}
+
+pub fn load(name: &str) -> Result<Session, String> {
+    let path = session_path(name)?;
+    let contents = fs::read_to_string(&path)
+        .map_err(|e| format!("[Social Noise] Session {:?} not found: {}", name, e))?;
+    serde_json::from_str(&contents)
+        .map_err(|e| format!("[Social Noise] Session {:?} is corrupt: {}", name, e))
+}
+
+/// Session file outside the sessions folder, e.g. passed on the command line
+pub fn load_file(path: &Path) -> Result<Session, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("[Social Noise] Session {:?} unreadable: {}", path, e))?;
+    serde_json::from_str(&contents)
+        .map_err(|e| format!("[Social Noise] Session {:?} is corrupt: {}", path, e))
+}
+
+/// Saved sessions, newest first. Unreadable files are skipped.
+pub fn list() -> Result<Vec<SessionInfo>, String> {
+    let entries = fs::read_dir(sessions_dir()?)
+        .map_err(|e| format!("[Social Noise] Session scan failed: {}", e))?;
+
+    let mut sessions: Vec<SessionInfo> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "json" {
+                return None;
+            }
+            let session: Session = serde_json::from_str(&fs::read_to_string(&path).ok()?).ok()?;
+            Some(SessionInfo {
+                name: session.name,
+                saved_at: session.saved_at,
+            })
+        })
+        .collect();
+    sessions.sort_by_key(|session| std::cmp::Reverse(session.saved_at));
+    Ok(sessions)
+}
+
+fn pad_defaults_path() -> Result<PathBuf, String> {
+    config_file("pad_defaults.json")
+}
+
+/// Pad id -> default params saved by `save_pad_defaults`; empty when none
+/// were saved. A corrupt or unreadable file is an error.
+pub fn load_pad_defaults() -> Result<HashMap<String, PartialPlayParams>, String> {
+    let path = pad_defaults_path()?;
+    match fs::read_to_string(&path) {
+        Ok(contents) => serde_json::from_str(&contents)
+            .map_err(|e| format!("Pad defaults {:?} are corrupt: {}", path, e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(HashMap::new()),
+        Err(e) => Err(format!("Pad defaults {:?} are unreadable: {}", path, e)),
+    }
+}
+
+pub fn save_pad_defaults(defaults: &HashMap<String, PartialPlayParams>) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(defaults).map_err(|e| e.to_string())?;
+    fs::write(pad_defaults_path()?, json)
+        .map_err(|e| format!("[Social Noise] Pad defaults save failed: {}", e))
+}
+
+fn autosave_path() -> Result<PathBuf, String> {
+    config_file("autosave.json")
+}
+
+/// Writes `session` as the autosave unless it equals `last` (the content of
+/// the previous autosave, timestamps aside). Returns the new content when written.
+pub fn write_autosave(session: &Session, last: Option<&str>) -> Result<Option<String>, String> {
+    let unstamped = Session {
+        saved_at: 0,
+        ..session.clone()
+    };
+    let content = serde_json::to_string(&unstamped).map_err(|e| e.to_string())?;
+    if last == Some(content.as_str()) {
+        return Ok(None);
+    }
+    let json = serde_json::to_string_pretty(session).map_err(|e| e.to_string())?;
+    // Write-then-rename, so a crash mid-write never leaves a torn autosave
+    let path = autosave_path()?;
+    let partial = path.with_extension("json.tmp");
+    fs::write(&partial, json)
+        .and_then(|_| fs::rename(&partial, &path))
+        .map_err(|e| format!("[Social Noise] Autosave failed: {}", e))?;
+    Ok(Some(content))
+}
+
+fn config_file(name: &str) -> Result<PathBuf, String> {
+    Ok(sessions_dir()?
+        .parent()
+        .ok_or("Failed to get config dir".to_string())?
+        .join(name))
+}
+
+/// Records that this run ended normally, so its autosave is not offered again
+pub fn mark_clean_shutdown() -> Result<(), String> {
+    fs::write(config_file("clean_shutdown")?, now_millis().to_string())
+        .map_err(|e| format!("[Social Noise] Shutdown marker write failed: {}", e))
+}
+
+/// Autosave left behind by a run that crashed, if any. It is moved to
+/// autosave.restore.json so this run's own autosaves cannot overwrite it.
+pub fn take_crash_autosave() -> Result<Option<SessionInfo>, String> {
+    let path = autosave_path()?;
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    let autosaved = match modified(&path) {
+        Some(time) => time,
+        None => return Ok(None),
+    };
+    if modified(&config_file("clean_shutdown")?).is_some_and(|clean| clean >= autosaved) {
+        return Ok(None);
+    }
+    let restore = config_file("autosave.restore.json")?;
+    fs::rename(&path, &restore)
+        .map_err(|e| format!("[Social Noise] Autosave could not be set aside: {}", e))?;
+    let session = read_restore(&restore)?;
+    Ok(Some(SessionInfo {
+        name: session.name,
+        saved_at: session.saved_at,
+    }))
+}
+
+/// The crashed run's autosave, as set aside by `take_crash_autosave`
+pub fn load_restore() -> Result<Session, String> {
+    read_restore(&config_file("autosave.restore.json")?)
+}
+
+/// Removes the restore candidate once it was loaded (or declined)
+pub fn discard_restore() -> Result<(), String> {
+    let path = config_file("autosave.restore.json")?;
+    if path.exists() {
+        fs::remove_file(&path)
+            .map_err(|e| format!("[Social Noise] Restore cleanup failed: {}", e))?;
+    }
+    Ok(())
+}
+
+/// A corrupt file is renamed to autosave.corrupt-<millis>.json, never deleted,
+/// so it can still be recovered by hand
+fn read_restore(path: &Path) -> Result<Session, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("[Social Noise] Autosave not found: {}", e))?;
+    serde_json::from_str(&contents).map_err(|e| {
+        let aside = path.with_file_name(format!("autosave.corrupt-{}.json", now_millis()));
+        let _ = fs::rename(path, &aside);
+        format!(
+            "[Social Noise] Autosave is corrupt ({}), kept as {:?}",
+            e, aside
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pad(key: &str, path: PathBuf) -> PadSnapshot {
+        PadSnapshot {
+            key: key.to_string(),
+            path,
+            params: None,
+            defaults: None,
+            bpm: Some(120.0),
+        }
+    }
+
+    #[test]
+    fn pad_paths_survive_save_and_load_from_either_platform() {
+        let dir = std::env::temp_dir().join(format!("lsamp-session-{}", std::process::id()));
+        let harbor = dir.join("harbor");
+        let kick = harbor.join("Drums").join("kick.wav");
+        fs::create_dir_all(kick.parent().unwrap()).unwrap();
+        fs::write(&kick, b"").unwrap();
+        let outside = PathBuf::from("/elsewhere/snare.wav");
+        let pads = vec![pad("1-a", kick.clone()), pad("1-s", outside.clone())];
+        let session = Session::from_snapshot("Test", pads, 1.0, 120.0, None, Vec::new(), &harbor);
+        assert_eq!(session.pads[0].path, "Drums/kick.wav");
+        assert_eq!(PathBuf::from(&session.pads[1].path), outside);
+
+        // As saved here, as a Windows machine would have saved it, and tampered with
+        let saved = serde_json::to_string(&session).unwrap();
+        let windows = saved.replace("Drums/kick.wav", "Drums\\\\kick.wav");
+        let escaping = saved.replace("Drums/kick.wav", "../Drums/kick.wav");
+        let file = dir.join("session.json");
+        for (json, resolved) in [(&saved, true), (&windows, true), (&escaping, false)] {
+            fs::write(&file, json).unwrap();
+            let mut loaded = load_file(&file).unwrap();
+            loaded.resolve_paths(&harbor);
+            let path = PathBuf::from(&loaded.pads[0].path);
+            if resolved {
+                assert_eq!(path, kick);
+                assert!(resolve_in_harbor(&harbor, &session.pads[0].path)
+                    .unwrap()
+                    .is_file());
+            } else {
+                // Left relative, so it reports as missing
+                assert_eq!(loaded.pads[0].path, "../Drums/kick.wav");
+            }
+            assert_eq!(PathBuf::from(&loaded.pads[1].path), outside);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
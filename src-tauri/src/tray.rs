@@ -0,0 +1,115 @@
+/**
+ * tray.rs
+ * L-SAMP 100 | Tray Icon
+ *
+ * Keeps the panic controls reachable while the window sits behind a DAW:
+ * arm/disarm the keyboard listener, stop all audio, show or hide the
+ * window and quit. The menu only reports clicks as `TrayAction`s; main.rs
+ * routes them through the same paths as the matching IPC commands and
+ * calls `Tray::set_listener_checked` whenever the listener state changes.
+ */
+use std::panic::{self, AssertUnwindSafe};
+use tauri::menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::{TrayIcon, TrayIconBuilder};
+use tauri::{App, AppHandle, Wry};
+use tracing::warn;
+
+/// What a tray menu click asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrayAction {
+    ToggleListener,
+    StopAll,
+    ToggleWindow,
+    Quit,
+}
+
+impl TrayAction {
+    fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "toggle_listener" => Some(Self::ToggleListener),
+            "stop_all" => Some(Self::StopAll),
+            "toggle_window" => Some(Self::ToggleWindow),
+            "quit" => Some(Self::Quit),
+            _ => None,
+        }
+    }
+}
+
+/// Managed once the icon is up; absent when the platform has no tray
+pub struct Tray {
+    listener_item: CheckMenuItem<Wry>,
+    _icon: TrayIcon<Wry>, // Dropping it removes the icon
+}
+
+impl Tray {
+    /// Mirrors the listener state in the menu's checkmark
+    pub fn set_listener_checked(&self, enabled: bool) {
+        if let Err(e) = self.listener_item.set_checked(enabled) {
+            warn!("Tray checkmark not updated: {}", e);
+        }
+    }
+}
+
+/// Builds the tray icon and its menu. Linux desktops without an
+/// appindicator library make the tray backend panic instead of failing,
+/// so that is caught and reported like any other error.
+pub fn install(
+    app: &App,
+    listener_enabled: bool,
+    on_action: impl Fn(&AppHandle, TrayAction) + Send + Sync + 'static,
+) -> Result<Tray, String> {
+    panic::catch_unwind(AssertUnwindSafe(|| build(app, listener_enabled, on_action)))
+        .unwrap_or_else(|_| Err("[Social Noise] No system tray available".to_string()))
+}
+
+fn build(
+    app: &App,
+    listener_enabled: bool,
+    on_action: impl Fn(&AppHandle, TrayAction) + Send + Sync + 'static,
+) -> Result<Tray, String> {
+    let listener_item = CheckMenuItem::with_id(
+        app,
+        "toggle_listener",
+        "Keyboard listener",
+        true,
+        listener_enabled,
+        None::<&str>,
+    )
+    .map_err(|e| e.to_string())?;
+    let stop_all = MenuItem::with_id(app, "stop_all", "Stop all audio", true, None::<&str>)
+        .map_err(|e| e.to_string())?;
+    let toggle_window = MenuItem::with_id(
+        app,
+        "toggle_window",
+        "Show / hide window",
+        true,
+        None::<&str>,
+    )
+    .map_err(|e| e.to_string())?;
+    let quit =
+        MenuItem::with_id(app, "quit", "Quit", true, None::<&str>).map_err(|e| e.to_string())?;
+    let separator = PredefinedMenuItem::separator(app).map_err(|e| e.to_string())?;
+    let menu = Menu::with_items(
+        app,
+        &[&listener_item, &stop_all, &separator, &toggle_window, &quit],
+    )
+    .map_err(|e| e.to_string())?;
+
+    let mut builder = TrayIconBuilder::with_id("main")
+        .tooltip("L-SAMP 100")
+        .menu(&menu)
+        .on_menu_event(move |app_handle, event| {
+            if let Some(action) = TrayAction::from_id(event.id().as_ref()) {
+                on_action(app_handle, action);
+            }
+        });
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    let icon = builder.build(app).map_err(|e| e.to_string())?;
+
+    Ok(Tray {
+        listener_item,
+        _icon: icon,
+    })
+}
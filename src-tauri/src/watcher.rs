@@ -0,0 +1,213 @@
+/**
+ * watcher.rs
+ * L-SAMP 100 | Harbor Watcher
+ *
+ * Watches the harbor recursively so files dropped in with a file manager
+ * show up without a manual refresh. Raw filesystem events are collapsed
+ * over a short quiet window and emitted as `harbor-changed` events.
+ */
+use notify::event::ModifyKind;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::mpsc::channel;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::info;
+
+use crate::harbor::{api_path_in, get_audio_harbor, HarborSettings, ScanOptions};
+use crate::notifications::{notify, Code, Level};
+
+/// Quiet period before buffered changes are flushed (collapses bulk copies)
+const DEBOUNCE: Duration = Duration::from_millis(300);
+/// How often the supervisor re-checks the harbor root and the enabled flag
+const SUPERVISE_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HarborChangeKind {
+    Created,
+    Removed,
+    Renamed,
+}
+
+/// Payload of the `harbor-changed` event
+#[derive(Debug, Clone, Serialize)]
+pub struct HarborChange {
+    pub kind: HarborChangeKind,
+    pub path: String,         // Harbor-relative path ("" means rescan everything)
+    pub from: Option<String>, // Previous relative path for renames, when known
+}
+
+/// Start the harbor watcher thread. It owns the notify watcher and rebuilds it
+/// whenever the harbor directory disappears and comes back, or when watching
+/// is toggled through `apply_config`.
+pub fn start_harbor_watcher(app_handle: AppHandle) {
+    thread::spawn(move || {
+        let (tx, rx) = channel::<notify::Result<Event>>();
+        let mut watcher: Option<RecommendedWatcher> = None;
+        let mut harbor = get_audio_harbor().ok();
+        let mut pending: HashMap<String, HarborChange> = HashMap::new();
+        let mut last_event = Instant::now();
+        let mut last_check = Instant::now() - SUPERVISE_INTERVAL;
+
+        loop {
+            // 1. Supervise: (re)attach or detach the OS watcher as needed
+            if last_check.elapsed() >= SUPERVISE_INTERVAL {
+                last_check = Instant::now();
+                let enabled = watch_enabled(&app_handle);
+
+                // A harbor moved through `apply_config` counts as gone
+                let root_alive = harbor.as_ref().is_some_and(|h| h.is_dir())
+                    && harbor == get_audio_harbor().ok();
+                if watcher.is_some() && (!enabled || !root_alive) {
+                    info!("Harbor watcher detached");
+                    watcher = None;
+                }
+
+                if watcher.is_none() && enabled {
+                    if !root_alive {
+                        harbor = get_audio_harbor().ok();
+                    }
+                    if let Some(root) = harbor.as_ref().filter(|h| h.is_dir()) {
+                        let tx = tx.clone();
+                        match notify::recommended_watcher(move |res| {
+                            let _ = tx.send(res);
+                        })
+                        .and_then(|mut w| w.watch(root, RecursiveMode::Recursive).map(|_| w))
+                        {
+                            Ok(w) => {
+                                info!("Harbor watcher attached to {:?}", root);
+                                watcher = Some(w);
+                                // The harbor may have changed while unwatched
+                                let change = HarborChange {
+                                    kind: HarborChangeKind::Created,
+                                    path: String::new(),
+                                    from: None,
+                                };
+                                emit_change(&app_handle, change);
+                            }
+                            Err(e) => notify(
+                                &app_handle,
+                                Level::Error,
+                                Code::WatcherFailed,
+                                format!("Harbor watcher failed: {}", e),
+                                serde_json::json!({ "path": root }),
+                            ),
+                        }
+                    }
+                }
+            }
+
+            // 2. Collect raw events
+            match rx.recv_timeout(Duration::from_millis(100)) {
+                Ok(Ok(event)) => {
+                    if let Some(root) = harbor.as_ref() {
+                        let options = scan_options(&app_handle);
+                        for change in translate_event(&event, root, &options) {
+                            pending.insert(change.path.clone(), change);
+                        }
+                        last_event = Instant::now();
+                    }
+                }
+                Ok(Err(e)) => notify(
+                    &app_handle,
+                    Level::Warning,
+                    Code::WatcherFailed,
+                    format!("Harbor watcher error: {}", e),
+                    serde_json::Value::Null,
+                ),
+                // A timeout: the channel never disconnects, as this thread
+                // keeps `tx` for the next watcher
+                Err(_) => {}
+            }
+
+            // 3. Flush once the filesystem has been quiet for the debounce window
+            if !pending.is_empty() && last_event.elapsed() >= DEBOUNCE {
+                for (_, change) in pending.drain() {
+                    emit_change(&app_handle, change);
+                }
+            }
+        }
+    });
+}
+
+/// Emit a change through the same channel the watcher uses, for edits made
+/// by the app itself.
+pub fn emit_change(app_handle: &AppHandle, change: HarborChange) {
+    let _ = app_handle.emit("harbor-changed", change);
+}
+
+fn watch_enabled(app_handle: &AppHandle) -> bool {
+    app_handle
+        .state::<Mutex<HarborSettings>>()
+        .lock()
+        .map(|s| s.watch_enabled)
+        .unwrap_or(true)
+}
+
+fn scan_options(app_handle: &AppHandle) -> ScanOptions {
+    app_handle
+        .state::<Mutex<HarborSettings>>()
+        .lock()
+        .map(|s| s.scan.clone())
+        .unwrap_or_default()
+}
+
+/// Applies the harbor scan filters. Extension-less paths are treated as
+/// folders, since removed paths can no longer be inspected.
+fn is_relevant(relative: &Path, options: &ScanOptions) -> bool {
+    match relative.extension() {
+        Some(_) => options.accepts_file(relative),
+        None => options.accepts_dir(relative),
+    }
+}
+
+fn relative(path: &Path, root: &Path) -> Option<String> {
+    api_path_in(root, path).ok()
+}
+
+fn translate_event(event: &Event, root: &Path, options: &ScanOptions) -> Vec<HarborChange> {
+    let change = |kind, path: &Path, from: Option<String>| {
+        let rel = relative(path, root)?;
+        if !is_relevant(Path::new(&rel), options) {
+            return None;
+        }
+        Some(HarborChange {
+            kind,
+            path: rel,
+            from,
+        })
+    };
+
+    match event.kind {
+        EventKind::Create(_) => event
+            .paths
+            .iter()
+            .filter_map(|p| change(HarborChangeKind::Created, p, None))
+            .collect(),
+        EventKind::Remove(_) => event
+            .paths
+            .iter()
+            .filter_map(|p| change(HarborChangeKind::Removed, p, None))
+            .collect(),
+        EventKind::Modify(ModifyKind::Name(_)) => {
+            // Renames arrive either as one event with [from, to] or as separate halves
+            if let [from, to] = event.paths.as_slice() {
+                change(HarborChangeKind::Renamed, to, relative(from, root))
+                    .into_iter()
+                    .collect()
+            } else {
+                event
+                    .paths
+                    .iter()
+                    .filter_map(|p| change(HarborChangeKind::Renamed, p, None))
+                    .collect()
+            }
+        }
+        _ => Vec::new(),
+    }
+}
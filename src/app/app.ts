@@ -155,6 +155,14 @@ export class App implements OnInit, OnDestroy {
       return () => subscription.unsubscribe();
     });
 
+    effect(() => {
+      const subscription = this.audio.bpmDetected$.subscribe(({ key, bpm }) => {
+        this.updatePadBpm(key, bpm);
+      });
+      this.subscriptions.push(subscription);
+      return () => subscription.unsubscribe();
+    });
+
     // Handle deferred waveform drawing when a pad finishes loading
     effect(() => {
       const selected = this.selectedPad();
@@ -314,6 +322,10 @@ export class App implements OnInit, OnDestroy {
     });
 
     // Modal opening
+    this.tauriBridge.onListenerToggled.subscribe((enabled: boolean) => {
+      this.isKeyboardCaptured.set(enabled);
+    });
+
     this.tauriBridge.onOpenModal.subscribe((mode: 'instructions' | 'settings' | 'info' | 'factory-reset' | 'clear-selected' | 'clear-all') => {
       this.modalMode.set(mode);
       this.showModal.set(true);
@@ -15,7 +15,7 @@ export class Audio {
   private tauriBridge: TauriBridgeService;
 
   // Storage Maps (Metadata only, Audio is in Rust)
-  private soundInfo = new Map<string, { duration: number, waveform: number[], bpm: number }>();
+  private soundInfo = new Map<string, { duration: number, waveform: number[], bpm: number | null }>();
   private syncSettings = new Map<string, boolean>();
   private activePads = new Set<string>();
   private latestLevels: Record<string, VisualData> = {};
@@ -38,6 +38,7 @@ export class Audio {
   public padFinished$ = new Subject<string>();
   public fadeOutComplete$ = new Subject<string>();
   public loadingProgress$ = new Subject<{ key: string, progress: number }>();
+  public bpmDetected$ = new Subject<{ key: string, bpm: number }>();
 
   public onGlobalStop = new Subject<void>();
   public levelData$ = new Subject<Record<string, VisualData>>();
@@ -52,6 +53,17 @@ export class Audio {
       this.onGlobalStop.next();
     });
 
+    // Pads loaded without a cached BPM get theirs once the analysis finishes
+    this.tauriBridge.onAnalysisComplete.subscribe(({ key: padId, bpm }) => {
+      const key = padId.split(':').pop() ?? padId;
+      const info = this.soundInfo.get(key);
+      if (info) {
+        info.bpm = bpm;
+        this.syncParamsToRust(key);
+        this.bpmDetected$.next({ key, bpm });
+      }
+    });
+
     this.startLevelPolling();
   }
 
@@ -224,7 +236,7 @@ export class Audio {
         this.trimOutSettings.set(key, duration);
       }
 
-      console.log(`[AudioService] ${key} buffered: ${duration}s, BPM: ${bpm} ${cachedBpm ? '(from cache)' : '(analyzed)'}`);
+      console.log(`[AudioService] ${key} buffered: ${duration}s, BPM: ${bpm} ${cachedBpm ? '(from cache)' : '(analyzing)'}`);
 
       setTimeout(() => this.loadingProgress$.next({ key, progress: -1 }), 500);
       return true;
@@ -280,6 +292,7 @@ export class Audio {
       endTime: endOffset,
       sync,
       sample_bpm,
+      velocity: this.tauriBridge.takeVelocity(key),
     });
 
     return true;
@@ -21,11 +21,277 @@ export interface AppConfig {
 export interface VisualData {
   peak: number;
   samples: number[];
+  // Envelope of the pad's newest voice (absent from older backends)
+  envelope_stage?: 'attack' | 'sustain' | 'release';
+  envelope_gain?: number; // 0-1
+  remaining_secs?: number; // Until the voice ends; absent while it loops
 }
 
 export interface LevelsResponse {
   data: Record<string, VisualData>;
   active_keys: string[];
+  active_bank?: number;
+  all_data?: Record<string, VisualData>; // Keyed by "{bank}:{key}", looper layers by "__looper_<n>"
+  all_active_keys?: string[];
+  compressor_reduction_db?: number; // Master compressor, most over the last window
+  limiter_reduction_db?: number; // Master limiter, likewise
+}
+
+/** Payload of `global-key-press` / `global-key-release` */
+export interface GlobalKeyPress {
+  key: string;
+  shift: boolean;
+  ctrl: boolean;
+  alt: boolean;
+  velocity?: number; // 0-1, note-on velocity for MIDI triggers
+}
+
+/** Result of `get_listener_status` / `restart_listener` */
+export interface ListenerStatus {
+  state: 'running' | 'backing_off' | 'waiting_for_permission' | 'unavailable';
+  restarts: number;
+  last_error: string | null;
+  enabled: boolean;
+}
+
+/** Payload of `hotkey-backend`, result of `get_hotkey_backend` */
+export interface HotkeyBackendInfo {
+  backend: 'global' | 'shortcuts'; // shortcuts on Wayland sessions
+  limitations: string[];
+}
+
+/** Result of `get_input_permission_status` */
+export interface InputPermission {
+  granted: boolean;
+  required: boolean; // Only macOS gates global key events behind Accessibility access
+}
+
+/** One entry of the `apply_config` rejection; other fields were still applied */
+export interface ConfigFieldError {
+  field: string; // e.g. 'master_volume'
+  message: string;
+}
+
+/** Payload of `voice-ended`. The voice is already gone from `get_levels` when this arrives. */
+export interface VoiceEnded {
+  key: string; // Pad id ("{bank}:{key}")
+  voiceId: number;
+  reason: 'natural' | 'stopped' | 'choked' | 'stolen';
+}
+
+/** Result of `audio_stop`/`audio_update_params`. Both reject when the pad has no sound loaded. */
+export interface VoiceMatch {
+  matched: number; // Voices of the pad playing as of the last meter window; 0 if it had already ended
+  corrected: string[]; // Params the engine corrected or clamped, e.g. 'endTime'
+}
+
+/** Result of `audio_get_transport`. Beats and bars count from 0; their fractions are the phase. */
+export interface Transport {
+  frame: number; // Output frames rendered since the engine started
+  seconds: number;
+  beat: number;
+  bar: number; // 4 beats each
+  bpm: number;
+}
+
+/**
+ * A voice's low-pass, open by default; omitted fields fall back like the
+ * other play params. The envelope moves the cutoff by `filterEnvAmount`
+ * octaves (negative sweeps down) and starts over on every trigger.
+ */
+export interface FilterParams {
+  filterCutoff?: number; // Hz, 20-20000 (open)
+  filterEnvAmount?: number; // Octaves, -10 to 10
+  filterEnvAttack?: number; // Seconds
+  filterEnvDecay?: number; // Seconds
+}
+
+/** A voice's bitcrusher, off at 16 bits and a factor of 1 (the defaults) */
+export interface CrushParams {
+  crushBits?: number; // 1-16
+  crushDownsample?: number; // Frames per held sample, 1-32; changes glide
+}
+
+/** Everything a voice runs its samples through before its volume */
+export interface VoiceFxParams extends FilterParams, CrushParams {
+  drive?: number; // 0-1 saturation at about the same loudness, 0 for none
+  delaySend?: number; // 0-1 at trigger time; updates keep it, `audioSetSend` changes it
+}
+
+/** One trigger of a sequencer pattern; omitted params come from the pad's last play */
+export interface StepEvent {
+  step: number; // 0-based, below 64
+  velocity: number;
+  params?: {
+    volume?: number;
+    attack?: number;
+    release?: number;
+    looping?: boolean;
+    startTime?: number;
+    endTime?: number;
+    sync?: boolean;
+    sampleBpm?: number;
+    forceSync?: boolean;
+  } & VoiceFxParams;
+}
+
+export type StepDivision = 'quarter' | 'eighth' | 'sixteenth' | 'thirtySecond';
+
+/** Payload of `seq-step`, the newest step fired (at most ~60 per second) */
+export interface SeqStep {
+  step: number;
+  length: number;
+}
+
+/** Payload of `count-in-tick`, sent as each count-in click starts */
+export interface CountInTick {
+  bar: number; // Of the count-in, from 1
+  beat: number; // Of the bar, from 1
+  beatsLeft: number; // Clicks still to come before recording starts
+}
+
+/** One pad trigger or stop of a recorded take */
+export type PerformanceEvent = { beat: number; key: string } & (
+  | { type: 'play'; params: Record<string, unknown> } // The PlayParams it was played with
+  | { type: 'stop'; release: number | null }
+);
+
+/** A recorded take, as `audioPatternRecordStop` returns it and sessions store it */
+export interface Performance {
+  length: number; // Beats, whole bars when recorded
+  events: PerformanceEvent[];
+}
+
+/** Crossfader side of a pad */
+export type CrossfadeGroup = 'a' | 'b';
+
+/** `constantPower` keeps the loudness even; `sharpCut` only fades at the very ends */
+export type CrossfadeCurve = 'constantPower' | 'sharpCut';
+
+/** What a scene keeps of one pad */
+export interface PadScene {
+  volume: number;
+  group: CrossfadeGroup | null;
+  // Null in scenes saved before these were kept; a recall leaves them be
+  delaySend: number | null;
+  sendsMuted: boolean | null;
+  filterCutoff: number | null; // Hz
+}
+
+/** A saved mix, see `audioSceneSave` */
+export interface Scene {
+  slot: number; // 0-7
+  masterVolume: number;
+  masterBpm: number;
+  crossfader: number;
+  pads: Record<string, PadScene>; // Per pad id
+}
+
+/** Result of `audioSceneRecall` */
+export interface SceneRecall {
+  skipped: string[]; // Pads of the scene that are not loaded any more
+  fading: boolean; // False when the scene was applied at once
+}
+
+/** Master compressor; a ratio of 1 turns it off */
+export interface CompressorSettings {
+  thresholdDb: number; // -60 to 0
+  ratio: number; // 1 to 20
+  attack: number; // Seconds, 0.0001 to 1
+  release: number; // Seconds, 0.001 to 5
+  makeupDb: number; // 0 to 24
+}
+
+/** Buses a pad can send to */
+export type SendBus = 'delay';
+
+/** The tempo-synced delay bus */
+export interface DelaySettings {
+  division: StepDivision; // Note value of one repeat
+  feedback: number; // 0 to 0.95
+}
+
+/** What `audioRenderOffline` bounces; `session` is the pattern and the take together */
+export type RenderSource = 'pattern' | 'take' | 'session';
+
+/** Payload of `render-progress`, once per bar rendered */
+export interface RenderProgress {
+  bar: number;
+  bars: number;
+}
+
+/** Result of `audioRenderOffline` and `audioRecordStop` */
+export interface RenderedMix {
+  path: string; // Harbor-relative path of the new WAV
+  duration: number;
+  peak: number; // Highest sample magnitude, 1 is full scale
+}
+
+/** Payload of `sample-rate-changed`, sent when a reopened output runs at a new rate */
+export interface SampleRateChanged {
+  previous: number;
+  sampleRate: number;
+}
+
+/** Payload of `load-progress`, at most ~5 per second while `audio_load` is pending */
+export interface LoadProgress {
+  key: string; // Pad id ("{bank}:{key}")
+  stage: 'queued' | 'decoding' | 'analyzing';
+  percent: number; // 0-100 within the stage
+  generation: number; // Newer loads of the same pad carry a higher number
+  queuePosition: number | null; // 1 = next to decode; only while queued
+}
+
+/** Payload of `analysis-complete`: the background BPM detection of a pad loaded without a cached BPM */
+export interface AnalysisComplete {
+  key: string; // Pad id ("{bank}:{key}")
+  bpm: number;
+  rawBpm: number; // Before folding into the preferred BPM window
+  bpmConfidence: number; // 0.0-1.0
+  bpmSource: 'detected' | 'cached' | 'fallback';
+  bpmError: string | null; // Why detection failed when the source is 'fallback'
+  keyDetected: string | null;
+}
+
+/** Payload of `session-pad-loaded`: one pad of a restored session or a `--pad` launch argument */
+export interface SessionPadLoaded {
+  key: string; // Pad id ("{bank}:{key}")
+  path: string;
+  params: any | null;
+  result: any | null; // LoadResult, when the pad loaded
+  error: string | null;
+}
+
+/** Payload of `deeplink-handled`; toast `error` when set */
+export interface DeepLinkHandled {
+  url: string;
+  action: string | null; // 'load'
+  pad: string | null; // Pad id the file landed on
+  file: string | null; // Harbor-relative
+  error: string | null;
+}
+
+/** Payload of `backend-notification`; map `code` to localized text */
+export interface BackendNotification {
+  level: 'info' | 'warning' | 'error';
+  code:
+    | 'decode_failed'
+    | 'device_lost'
+    | 'stream_error'
+    | 'watcher_failed'
+    | 'listener_failed'
+    | 'analysis_fallback'
+    | 'config_load_failed'
+    | 'engine_fault';
+  message: string; // English detail
+  context: Record<string, unknown> | null; // pad, path, file, ...
+  at: number; // Milliseconds since the Unix epoch
+}
+
+/** Rejection value of `audio_load` */
+export interface LoadError {
+  kind: 'FileMissing' | 'UnsupportedCodec' | 'TruncatedStream' | 'ZeroChannels' | 'EmptyAudio' | 'Superseded' | 'Cancelled' | 'Other';
+  message: string;
 }
 
 @Injectable({
@@ -34,7 +300,33 @@ export interface LevelsResponse {
 export class TauriBridgeService implements OnDestroy {
   // Event subjects for frontend subscriptions
   onKeyTriggered = new Subject<string>();
+  onKeyCombo = new Subject<GlobalKeyPress>();
+  // Velocity of the latest backend trigger per pad (MIDI note-on, 1 for keys)
+  private pressVelocity = new Map<string, number>();
   onGlobalStop = new Subject<void>();
+  // New listener state after the toggle hotkey (Ctrl+Alt+L by default)
+  onListenerToggled = new Subject<boolean>();
+  // Reason the keyboard listener died; the backend retries on its own
+  onListenerError = new Subject<string>();
+  // macOS has not granted Accessibility access; hotkeys stay silent until it does
+  onInputPermissionMissing = new Subject<void>();
+  onHotkeyBackend = new Subject<HotkeyBackendInfo>();
+  // A voice finished or was stopped, including one-shots too short for level polling
+  onVoiceEnded = new Subject<VoiceEnded>();
+  onSampleRateChanged = new Subject<SampleRateChanged>();
+  onSeqStep = new Subject<SeqStep>();
+  onCountInTick = new Subject<CountInTick>();
+  onRenderProgress = new Subject<RenderProgress>();
+  onLoadProgress = new Subject<LoadProgress>();
+  onAnalysisComplete = new Subject<AnalysisComplete>();
+  // Pushed while subscribed via audioLevelsSubscribe
+  onLevels = new Subject<LevelsResponse>();
+  onSessionPadLoaded = new Subject<SessionPadLoaded>();
+  // Unusable command line arguments or files they named
+  onStartupWarning = new Subject<string>();
+  // An lsamp:// link was run or rejected
+  onDeepLinkHandled = new Subject<DeepLinkHandled>();
+  onBackendNotification = new Subject<BackendNotification>();
   onApplyConfig = new Subject<AppConfig>();
   onOpenModal = new Subject<any>();
 
@@ -151,11 +443,15 @@ export class TauriBridgeService implements OnDestroy {
 
       // Listen for the new global-key-press from rdev (Background)
       const globalKeyPressUnlisten = await this.listen('global-key-press', (event: any) => {
-        const key = event.payload;
-        if (key === 'SPACE') {
+        const press = event.payload as GlobalKeyPress;
+        if (press.key === 'SPACE') {
           this.onGlobalStop.next();
+        } else if (press.shift || press.ctrl || press.alt) {
+          // Secondary pad actions (e.g. Shift+Q) never toggle the pad itself
+          this.onKeyCombo.next(press);
         } else {
-          this.onKeyTriggered.next(key);
+          this.pressVelocity.set(press.key, press.velocity ?? 1);
+          this.onKeyTriggered.next(press.key);
         }
       });
 
@@ -164,6 +460,77 @@ export class TauriBridgeService implements OnDestroy {
         this.onGlobalStop.next();
       });
 
+      // Listener flipped by its own hotkey, possibly while the app was in the background
+      const listenerToggledUnlisten = await this.listen('listener-toggled', (event: any) => {
+        this.listenerActive = !!event.payload.enabled;
+        this.onListenerToggled.next(this.listenerActive);
+      });
+
+      const listenerErrorUnlisten = await this.listen('listener-error', (event: any) => {
+        console.warn('[TauriBridge] Keyboard listener died:', event.payload.reason);
+        this.onListenerError.next(event.payload.reason);
+      });
+
+      const permissionUnlisten = await this.listen('input-permission-missing', () => {
+        this.onInputPermissionMissing.next();
+      });
+
+      const hotkeyBackendUnlisten = await this.listen('hotkey-backend', (event: any) => {
+        this.onHotkeyBackend.next(event.payload);
+      });
+
+      const voiceEndedUnlisten = await this.listen('voice-ended', (event: any) => {
+        this.onVoiceEnded.next(event.payload);
+      });
+
+      const sampleRateUnlisten = await this.listen('sample-rate-changed', (event: any) => {
+        this.onSampleRateChanged.next(event.payload);
+      });
+
+      const seqStepUnlisten = await this.listen('seq-step', (event: any) => {
+        this.onSeqStep.next(event.payload);
+      });
+
+      const countInUnlisten = await this.listen('count-in-tick', (event: any) => {
+        this.onCountInTick.next(event.payload);
+      });
+
+      const renderProgressUnlisten = await this.listen('render-progress', (event: any) => {
+        this.onRenderProgress.next(event.payload);
+      });
+
+      const loadProgressUnlisten = await this.listen('load-progress', (event: any) => {
+        this.onLoadProgress.next(event.payload);
+      });
+
+      const analysisUnlisten = await this.listen('analysis-complete', (event: any) => {
+        this.onAnalysisComplete.next(event.payload);
+      });
+
+      const levelsUnlisten = await this.listen('levels', (event: any) => {
+        this.onLevels.next(event.payload);
+      });
+
+      const sessionPadUnlisten = await this.listen('session-pad-loaded', (event: any) => {
+        this.onSessionPadLoaded.next(event.payload);
+      });
+
+      const startupWarningUnlisten = await this.listen('startup-warning', (event: any) => {
+        console.warn('[TauriBridge] Startup:', event.payload.message);
+        this.onStartupWarning.next(event.payload.message);
+      });
+
+      const deepLinkUnlisten = await this.listen('deeplink-handled', (event: any) => {
+        if (event.payload.error) {
+          console.warn('[TauriBridge] Link rejected:', event.payload.error);
+        }
+        this.onDeepLinkHandled.next(event.payload);
+      });
+
+      const notificationUnlisten = await this.listen('backend-notification', (event: any) => {
+        this.onBackendNotification.next(event.payload);
+      });
+
       // Listen for config updates from Rust backend
       const configUnlisten = await this.listen('apply-config', (event: any) => {
         this.onApplyConfig.next(event.payload);
@@ -174,8 +541,27 @@ export class TauriBridgeService implements OnDestroy {
         keyTriggerUnlisten,
         globalKeyPressUnlisten,
         globalStopUnlisten,
+        listenerToggledUnlisten,
+        listenerErrorUnlisten,
+        permissionUnlisten,
+        hotkeyBackendUnlisten,
+        voiceEndedUnlisten,
+        sampleRateUnlisten,
+        seqStepUnlisten,
+        countInUnlisten,
+        renderProgressUnlisten,
+        loadProgressUnlisten,
+        analysisUnlisten,
+        levelsUnlisten,
+        sessionPadUnlisten,
+        startupWarningUnlisten,
+        deepLinkUnlisten,
+        notificationUnlisten,
         configUnlisten
       ];
+
+      // Command line loads wait for these listeners
+      await this.invoke('launch_ready');
     } catch (error) {
       console.error('[TauriBridge] Failed to initialize listeners:', error);
     }
@@ -355,14 +741,14 @@ export class TauriBridgeService implements OnDestroy {
   }
   */
 
-  async audioLoad(key: string, path: string, cachedBpm?: number): Promise<{ duration: number, bpm: number, waveform: number[] }> {
+  /** `bpm` is null until the pad's `analysis-complete` arrives, unless a cached BPM was given */
+  async audioLoad(key: string, path: string, cachedBpm?: number): Promise<{ duration: number, bpm: number | null, waveform: number[] }> {
     try {
       await this.waitForReady();
-      // We pass cached_bpm (snake_case) to match the Rust Option<f32>
       return await this.invoke('audio_load', {
         key,
         path,
-        cachedBpm: cachedBpm ?? null
+        options: { cachedBpm: cachedBpm ?? null }
       });
     } catch (error) {
       console.error(`[TauriBridge] Failed to load audio ${key}:`, error);
@@ -370,9 +756,28 @@ export class TauriBridgeService implements OnDestroy {
     }
   }
 
+  /** Stop a pad's unfinished load; its `audioLoad` rejects with kind 'Cancelled' */
+  async audioLoadCancel(key: string): Promise<boolean> {
+    await this.waitForReady();
+    return await this.invoke('audio_load_cancel', { key });
+  }
+
+  /** Move the output to the current default device; playing pads carry over */
+  async audioReopenOutput(): Promise<void> {
+    await this.waitForReady();
+    await this.invoke('audio_reopen_output');
+  }
+
   /**
    * Play a sound from the Rust engine
    */
+  /** Velocity of the trigger that just fired `key`, consumed on read */
+  takeVelocity(key: string): number {
+    const velocity = this.pressVelocity.get(key) ?? 1;
+    this.pressVelocity.delete(key);
+    return velocity;
+  }
+
   async audioPlay(
     key: string,
     params: {
@@ -384,7 +789,9 @@ export class TauriBridgeService implements OnDestroy {
       endTime: number;
       sync: boolean;
       sample_bpm: number;
-    }
+      velocity?: number;
+      forceSync?: boolean; // Sync even when BPM detection failed (source 'fallback')
+    } & VoiceFxParams
   ): Promise<void> {
     try {
       await this.waitForReady();
@@ -399,12 +806,61 @@ export class TauriBridgeService implements OnDestroy {
           endTime: params.endTime,
           sync: params.sync,
           sampleBpm: params.sample_bpm,
+          velocity: params.velocity ?? 1,
+          forceSync: params.forceSync ?? false,
+          filterCutoff: params.filterCutoff,
+          filterEnvAmount: params.filterEnvAmount,
+          filterEnvAttack: params.filterEnvAttack,
+          filterEnvDecay: params.filterEnvDecay,
+          crushBits: params.crushBits,
+          crushDownsample: params.crushDownsample,
+          drive: params.drive,
+          delaySend: params.delaySend,
         }
       });
     } catch (error) {
       console.error(`[TauriBridge] Failed to play audio ${key}:`, error);
     }
   }
+  /**
+   * Store a pad's default play params in the backend (null clears them).
+   * They fill any fields a later audio_play leaves out.
+   */
+  async setPadDefaults(
+    key: string,
+    params: {
+      volume: number;
+      attack: number;
+      release: number;
+      looping: boolean;
+      startTime: number;
+      endTime: number;
+      sync: boolean;
+      sampleBpm: number;
+    } | null
+  ): Promise<void> {
+    try {
+      await this.waitForReady();
+      await this.invoke('set_pad_defaults', { key, params });
+    } catch (error) {
+      console.error(`[TauriBridge] Failed to set pad defaults ${key}:`, error);
+    }
+  }
+
+  /**
+   * Stored pad defaults keyed by "{bank}:{key}". Fields a pad has no default
+   * for are null, e.g. everything but the sends of a pad only sent from.
+   */
+  async getPadDefaults(): Promise<Record<string, any>> {
+    try {
+      await this.waitForReady();
+      return await this.invoke('get_pad_defaults');
+    } catch (error) {
+      console.error('[TauriBridge] Failed to get pad defaults:', error);
+      return {};
+    }
+  }
+
 
   /**
    * Update parameters for an active sound in the Rust engine
@@ -420,11 +876,11 @@ export class TauriBridgeService implements OnDestroy {
       endTime: number;
       sync: boolean;
       sample_bpm: number;
-    }
-  ): Promise<void> {
+    } & VoiceFxParams
+  ): Promise<VoiceMatch | null> {
     try {
       await this.waitForReady();
-      await this.invoke('audio_update_params', {
+      return await this.invoke('audio_update_params', {
         key,
         params: {
           volume: params.volume,
@@ -435,25 +891,231 @@ export class TauriBridgeService implements OnDestroy {
           endTime: params.endTime,
           sync: params.sync,
           sampleBpm: params.sample_bpm,
+          filterCutoff: params.filterCutoff,
+          filterEnvAmount: params.filterEnvAmount,
+          filterEnvAttack: params.filterEnvAttack,
+          filterEnvDecay: params.filterEnvDecay,
+          crushBits: params.crushBits,
+          crushDownsample: params.crushDownsample,
+          drive: params.drive,
         }
       });
     } catch (error) {
       console.error(`[TauriBridge] Failed to update audio params ${key}:`, error);
+      return null;
     }
   }
 
   /**
    * Stop a sound in the Rust engine (with fade-out)
    */
-  async audioStop(key: string, effective_release?: number): Promise<void> {
+  async audioStop(key: string, effective_release?: number): Promise<VoiceMatch | null> {
     try {
       await this.waitForReady();
-      await this.invoke('audio_stop', { key, effective_release: effective_release ?? null });
+      return await this.invoke('audio_stop', { key, effective_release: effective_release ?? null });
     } catch (error) {
       console.error(`[TauriBridge] Failed to stop audio ${key}:`, error);
+      return null;
     }
   }
 
+  /** Set a pad's sequencer steps (an empty list clears them); heard from the next loop */
+  async audioSeqSetPattern(key: string, steps: StepEvent[]): Promise<void> {
+    await this.waitForReady();
+    await this.invoke('audio_seq_set_pattern', { key, steps });
+  }
+
+  /** Steps per sequencer loop (1-64) and the note value of one step */
+  async audioSeqSetLength(steps: number, division: StepDivision = 'sixteenth'): Promise<void> {
+    await this.waitForReady();
+    await this.invoke('audio_seq_set_length', { steps, division });
+  }
+
+  /** Start the sequencer on the next step of the master clock; restarts it when running */
+  async audioSeqStart(): Promise<void> {
+    await this.waitForReady();
+    await this.invoke('audio_seq_start');
+  }
+
+  async audioSeqStop(): Promise<void> {
+    await this.waitForReady();
+    await this.invoke('audio_seq_stop');
+  }
+
+  /** Record pad triggers and stops from the current bar; with overdub, into the playing take */
+  async audioPatternRecordStart(quantize?: StepDivision, overdub = false): Promise<void> {
+    await this.waitForReady();
+    await this.invoke('audio_pattern_record_start', { quantize: quantize ?? null, overdub });
+  }
+
+  async audioPatternRecordStop(): Promise<Performance> {
+    await this.waitForReady();
+    return await this.invoke('audio_pattern_record_stop');
+  }
+
+  /** Play a take (or the last one recorded or played) from the next bar */
+  async audioPatternPlay(looping: boolean, performance?: Performance): Promise<void> {
+    await this.waitForReady();
+    await this.invoke('audio_pattern_play', { performance: performance ?? null, looping });
+  }
+
+  async audioPatternStop(): Promise<void> {
+    await this.waitForReady();
+    await this.invoke('audio_pattern_stop');
+  }
+
+  /**
+   * Loop `bars` bars (1-16) of every pad, or of `pads` in `bank`, from the next
+   * bar, or after `countInBars` (1-2) bars of clicks from it; each click comes
+   * as `onCountInTick`. Layers play under `__looper_<n>` ids in the levels' `all_data`.
+   */
+  async audioLooperRecord(
+    bars: number,
+    pads?: string[],
+    bank?: number,
+    countInBars?: number
+  ): Promise<void> {
+    await this.waitForReady();
+    await this.invoke('audio_looper_record', {
+      bars,
+      pads: pads ?? null,
+      bank: bank ?? null,
+      countInBars: countInBars ?? null
+    });
+  }
+
+  /**
+   * Record the master output to a new WAV in the harbor, or after `countInBars`
+   * (1-2) bars of clicks from the next bar; each click comes as `onCountInTick`
+   * and nothing is written before the downbeat after them.
+   */
+  async audioRecordStart(
+    filename: string,
+    format?: 'float32' | 'pcm16',
+    overwrite = false,
+    countInBars?: number
+  ): Promise<void> {
+    await this.waitForReady();
+    await this.invoke('audio_record_start', {
+      filename,
+      format: format ?? null,
+      overwrite,
+      countInBars: countInBars ?? null
+    });
+  }
+
+  /** Finish the master recording; null when stopped during its count-in (no file) */
+  async audioRecordStop(): Promise<RenderedMix | null> {
+    await this.waitForReady();
+    return await this.invoke('audio_record_stop');
+  }
+
+  /** Abandon the master recording, removing anything it wrote */
+  async audioRecordCancel(): Promise<void> {
+    await this.waitForReady();
+    await this.invoke('audio_record_cancel');
+  }
+
+  /** Record one more layer over the loop, from its next start */
+  async audioLooperOverdub(pads?: string[], bank?: number): Promise<void> {
+    await this.waitForReady();
+    await this.invoke('audio_looper_overdub', { pads: pads ?? null, bank: bank ?? null });
+  }
+
+  /** Drop the newest looper layer */
+  async audioLooperUndo(): Promise<void> {
+    await this.waitForReady();
+    await this.invoke('audio_looper_undo');
+  }
+
+  async audioLooperClear(): Promise<void> {
+    await this.waitForReady();
+    await this.invoke('audio_looper_clear');
+  }
+
+  async audioSetCompressor(settings: CompressorSettings): Promise<void> {
+    await this.waitForReady();
+    await this.invoke('audio_set_compressor', { settings });
+  }
+
+  async audioSetDelay(settings: DelaySettings): Promise<void> {
+    await this.waitForReady();
+    await this.invoke('audio_set_delay', { settings });
+  }
+
+  /** Set a pad's send (0-1) for its playing and later voices; kept with its defaults */
+  async audioSetSend(key: string, bus: SendBus, amount: number, bank?: number): Promise<void> {
+    await this.waitForReady();
+    await this.invoke('audio_set_send', { key, bank: bank ?? null, bus, amount });
+  }
+
+  /** Mute a pad's sends, e.g. for a delay throw; its dry signal plays on */
+  async audioMuteSends(key: string, muted: boolean, bank?: number): Promise<void> {
+    await this.waitForReady();
+    await this.invoke('audio_mute_sends', { key, bank: bank ?? null, muted });
+  }
+
+  /** Move the crossfader, 0 (side A only) to 1 (side B only) */
+  async audioSetCrossfader(position: number): Promise<void> {
+    await this.waitForReady();
+    await this.invoke('audio_set_crossfader', { position });
+  }
+
+  async audioSetCrossfadeCurve(curve: CrossfadeCurve): Promise<void> {
+    await this.waitForReady();
+    await this.invoke('audio_set_crossfade_curve', { curve });
+  }
+
+  /** Put a pad on a crossfader side, or on neither with null */
+  async audioSetPadGroup(key: string, group: CrossfadeGroup | null, bank?: number): Promise<void> {
+    await this.waitForReady();
+    await this.invoke('audio_set_pad_group', { key, bank: bank ?? null, group });
+  }
+
+  /** Keep the mix as it is in scene `slot` (0-7) */
+  async audioSceneSave(slot: number): Promise<Scene> {
+    await this.waitForReady();
+    return await this.invoke('audio_scene_save', { slot });
+  }
+
+  /** Morph to scene `slot` over `fadeSeconds`, at once by default */
+  async audioSceneRecall(slot: number, fadeSeconds?: number): Promise<SceneRecall> {
+    await this.waitForReady();
+    return await this.invoke('audio_scene_recall', {
+      slot,
+      fadeSeconds: fadeSeconds ?? null,
+    });
+  }
+
+  async audioSceneList(): Promise<Scene[]> {
+    await this.waitForReady();
+    return await this.invoke('audio_scene_list');
+  }
+
+  /** Bounce `bars` bars to a new WAV in the harbor, faster than real time */
+  async audioRenderOffline(
+    source: RenderSource,
+    bars: number,
+    filename: string,
+    format?: 'float32' | 'pcm16',
+    overwrite = false
+  ): Promise<RenderedMix> {
+    await this.waitForReady();
+    return await this.invoke('audio_render_offline', {
+      source,
+      bars,
+      filename,
+      format: format ?? null,
+      overwrite
+    });
+  }
+
+  /** Read the engine's master clock, what tempo-synced features should follow */
+  async audioGetTransport(): Promise<Transport> {
+    await this.waitForReady();
+    return await this.invoke('audio_get_transport');
+  }
+
   /**
    * Set the global master BPM in the Rust engine
    */
@@ -466,6 +1128,29 @@ export class TauriBridgeService implements OnDestroy {
     }
   }
 
+  /** Delay every second step (sequencer, launches, beat repeat, quantized recording) by `swing` (0-0.75) of a step */
+  async audioSetSwing(swing: number): Promise<void> {
+    await this.waitForReady();
+    await this.invoke('audio_set_swing', { swing });
+  }
+
+  /** Make pad triggers wait for the next step of `division` (from the bar line); null plays them at once */
+  async audioSetLaunchQuantize(division: StepDivision | null): Promise<void> {
+    await this.waitForReady();
+    await this.invoke('audio_set_launch_quantize', { division });
+  }
+
+  /** Retrigger a pad on every step of `division`, from the next pair of steps, until `audioBeatRepeatStop` */
+  async audioBeatRepeatStart(key: string, division: StepDivision): Promise<void> {
+    await this.waitForReady();
+    await this.invoke('audio_beat_repeat_start', { key, division });
+  }
+
+  async audioBeatRepeatStop(): Promise<void> {
+    await this.waitForReady();
+    await this.invoke('audio_beat_repeat_stop');
+  }
+
   /**
    * Get real-time audio levels and snapshots for all active sounds
    */
@@ -479,6 +1164,50 @@ export class TauriBridgeService implements OnDestroy {
     }
   }
 
+  /**
+   * Have the backend push levels as `levels` events instead of polling (8-1000 ms)
+   */
+  async audioLevelsSubscribe(intervalMs: number): Promise<void> {
+    try {
+      await this.waitForReady();
+      await this.invoke('audio_levels_subscribe', { intervalMs });
+    } catch (error) {
+      console.error('[TauriBridge] Failed to subscribe to levels:', error);
+    }
+  }
+
+  async audioLevelsUnsubscribe(): Promise<void> {
+    try {
+      await this.waitForReady();
+      await this.invoke('audio_levels_unsubscribe');
+    } catch (error) {
+      console.error('[TauriBridge] Failed to unsubscribe from levels:', error);
+    }
+  }
+
+  /** Latest backend notifications, oldest first, for the diagnostics panel */
+  async getRecentNotifications(): Promise<BackendNotification[]> {
+    try {
+      await this.waitForReady();
+      return await this.invoke('get_recent_notifications');
+    } catch (error) {
+      console.error('[TauriBridge] Failed to get notifications:', error);
+      return [];
+    }
+  }
+
+  /** Change the backend log verbosity until the next launch */
+  async setLogLevel(level: 'error' | 'warn' | 'info' | 'debug' | 'trace'): Promise<void> {
+    await this.waitForReady();
+    await this.invoke('set_log_level', { level });
+  }
+
+  /** Zip the backend log files for a bug report; resolves to the archive path */
+  async exportLogs(): Promise<string> {
+    await this.waitForReady();
+    return await this.invoke('export_logs');
+  }
+
   // ========================================================================
   // KEYBOARD CONTROL
   // ========================================================================
@@ -498,6 +1227,83 @@ export class TauriBridgeService implements OnDestroy {
     }
   }
 
+  /**
+   * Mute the global listener while a text input has focus
+   */
+  async setInputCaptureMode(mode: 'normal' | 'suppress_pads' | 'suppress_all'): Promise<void> {
+    try {
+      await this.waitForReady();
+      await this.invoke('set_input_capture_mode', { mode });
+    } catch (error) {
+      console.error('[TauriBridge] Failed to set input capture mode:', error);
+    }
+  }
+
+  /**
+   * Health of the background keyboard listener
+   */
+  async getListenerStatus(): Promise<ListenerStatus | null> {
+    try {
+      await this.waitForReady();
+      return await this.invoke('get_listener_status');
+    } catch (error) {
+      console.error('[TauriBridge] Failed to get listener status:', error);
+      return null;
+    }
+  }
+
+  /**
+   * Whether the OS lets global hotkeys through. The startup event may fire
+   * before this service listens, so callers should also ask once on launch.
+   */
+  async getInputPermissionStatus(): Promise<InputPermission | null> {
+    try {
+      await this.waitForReady();
+      return await this.invoke('get_input_permission_status');
+    } catch (error) {
+      console.error('[TauriBridge] Failed to get input permission status:', error);
+      return null;
+    }
+  }
+
+  /**
+   * Open System Settings at the Accessibility pane (macOS only)
+   */
+  async openInputPermissionSettings(): Promise<void> {
+    try {
+      await this.waitForReady();
+      await this.invoke('open_input_permission_settings');
+    } catch (error) {
+      console.error('[TauriBridge] Failed to open input permission settings:', error);
+    }
+  }
+
+  /**
+   * Global hotkeys, or global-shortcut registrations (Wayland)
+   */
+  async getHotkeyBackend(): Promise<HotkeyBackendInfo | null> {
+    try {
+      await this.waitForReady();
+      return await this.invoke('get_hotkey_backend');
+    } catch (error) {
+      console.error('[TauriBridge] Failed to get hotkey backend:', error);
+      return null;
+    }
+  }
+
+  /**
+   * Retry a dead keyboard listener without waiting for the backoff
+   */
+  async restartListener(): Promise<ListenerStatus | null> {
+    try {
+      await this.waitForReady();
+      return await this.invoke('restart_listener');
+    } catch (error) {
+      console.error('[TauriBridge] Failed to restart listener:', error);
+      return null;
+    }
+  }
+
   // ========================================================================
   // CONFIGURATION
   // ========================================================================
@@ -516,7 +1322,13 @@ export class TauriBridgeService implements OnDestroy {
       });
       console.log('[TauriBridge] Config applied:', config);
     } catch (error) {
-      console.error('[TauriBridge] Failed to apply config:', error);
+      const fields = Array.isArray(error) ? (error as ConfigFieldError[]) : [];
+      for (const field of fields) {
+        console.error(`[TauriBridge] Config ${field.field} rejected: ${field.message}`);
+      }
+      if (!fields.length) {
+        console.error('[TauriBridge] Failed to apply config:', error);
+      }
     }
   }
 